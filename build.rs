@@ -26,7 +26,8 @@ use regex::Regex;
 fn main() -> io::Result<()> {
     #![allow(clippy::write_with_newline)]
 
-    let usage   = include_str!("src/usage.txt");
+    let usage   = usage_for_features(include_str!("src/usage.txt"));
+    let usage   = usage.as_str();
     let tagline = "dog \\1;32m●\\0m command-line DNS client";
     let url     = "https://dns.lookup.dog/";
 
@@ -63,6 +64,18 @@ fn main() -> io::Result<()> {
 }
 
 
+/// Remove the lines for options that belong to features that aren’t being
+/// compiled in, so the help text only lists options that exist.
+fn usage_for_features(usage: &str) -> String {
+    let sqlite = env::var_os("CARGO_FEATURE_SQLITE").is_some();
+
+    usage.lines()
+         .filter(|line| sqlite || ! line.contains("--sqlite"))
+         .map(|line| format!("{}\n", line))
+         .collect()
+}
+
+
 /// Retrieve the project’s current Git hash, as a string.
 fn git_hash() -> String {
     use std::process::Command;
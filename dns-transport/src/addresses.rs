@@ -0,0 +1,182 @@
+//! Looking up the addresses of a nameserver given by name, and trying each
+//! of them in turn until one of them answers.
+//!
+//! A nameserver’s name can have several addresses, often one for each
+//! address family. If the first one can’t be reached, that shouldn’t look
+//! like the whole nameserver being down, so each one gets tried in order,
+//! with its own timeouts, and the response says which one it came from.
+
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use log::*;
+
+use super::{Error, Timeouts};
+
+
+/// How long to wait for each phase when trying an address that isn’t the
+/// last one, if that phase has no timeout of its own, so an address that
+/// never answers doesn’t stop the rest from being tried.
+pub(crate) const FALLBACK_TIMEOUT: Duration = Duration::from_secs(3);
+
+
+/// Looks up every address of a nameserver, which is an IP address or a
+/// host name, with or without a port, using the default port if it has
+/// none. The addresses are returned in the order they should be tried.
+pub(crate) async fn lookup(addr: &str, default_port: u16) -> Result<Vec<SocketAddr>, Error> {
+    if let Ok(ip) = addr.parse::<IpAddr>() {
+        return Ok(vec![ SocketAddr::new(ip, default_port) ]);
+    }
+    else if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
+        return Ok(vec![ socket_addr ]);
+    }
+
+    let found: Vec<SocketAddr> =
+        if addr.contains(':') {
+            tokio::net::lookup_host(addr).await?.collect()
+        }
+        else {
+            tokio::net::lookup_host((addr, default_port)).await?.collect()
+        };
+
+    debug!("Nameserver {} has addresses {:?}", addr, found);
+    if found.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no addresses for {}", addr)).into());
+    }
+
+    Ok(interleave(found))
+}
+
+/// Puts the addresses in the order to try them: alternating between
+/// address families, starting with the family of the one the system’s
+/// resolver put first, as with Happy Eyeballs (RFC 8305 §4), so a broken
+/// family only costs one attempt before the other gets a turn. Duplicate
+/// addresses are removed.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let mut unique = Vec::new();
+    for addr in addrs {
+        if ! unique.contains(&addr) {
+            unique.push(addr);
+        }
+    }
+
+    let Some(first) = unique.first() else { return unique };
+    let first_is_v6 = first.is_ipv6();
+    let (mut preferred, mut others): (Vec<_>, Vec<_>) = unique.into_iter().partition(|a| a.is_ipv6() == first_is_v6);
+
+    let mut ordered = Vec::with_capacity(preferred.len() + others.len());
+    preferred.reverse();
+    others.reverse();
+    while let Some(addr) = preferred.pop() {
+        ordered.push(addr);
+        ordered.extend(others.pop());
+    }
+
+    ordered
+}
+
+/// Sends to each address in turn, returning the first response. Every
+/// address but the last gets a timeout for any phase without one, so the
+/// later ones still get tried. Only failures to reach an address move on to
+/// the next one; an address that sends back something unreadable has still
+/// been reached.
+pub(crate) async fn try_each<T, F, Fut>(addrs: &[SocketAddr], timeouts: Timeouts, mut attempt: F) -> Result<T, Error>
+where F: FnMut(SocketAddr, Timeouts) -> Fut,
+      Fut: Future<Output = Result<T, Error>>,
+{
+    let mut last_error = None;
+
+    for (index, addr) in addrs.iter().enumerate() {
+        let is_last = index + 1 == addrs.len();
+        let timeouts = if is_last { timeouts } else { fallback(timeouts) };
+
+        match attempt(*addr, timeouts).await {
+            Ok(result) => {
+                return Ok(result);
+            }
+            Err(e @ (Error::NetworkError(_) | Error::Timeout(_))) if ! is_last => {
+                warn!("Address {} failed -> {:?}; trying the next one", addr, e);
+                last_error = Some(e);
+            }
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses to try").into()))
+}
+
+/// Fills in any missing timeouts with the fallback one.
+fn fallback(timeouts: Timeouts) -> Timeouts {
+    Timeouts {
+        connect:    timeouts.connect.or(Some(FALLBACK_TIMEOUT)),
+        handshake:  timeouts.handshake.or(Some(FALLBACK_TIMEOUT)),
+        response:   timeouts.response.or(Some(FALLBACK_TIMEOUT)),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Phase;
+
+    fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+        list.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ip_address() {
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        assert_eq!(rt.block_on(lookup("2001:db8::53", 53)).unwrap(), addrs(&[ "[2001:db8::53]:53" ]));
+        assert_eq!(rt.block_on(lookup("192.0.2.53:5353", 53)).unwrap(), addrs(&[ "192.0.2.53:5353" ]));
+    }
+
+    #[test]
+    fn families_alternate() {
+        let found = addrs(&[ "[2001:db8::1]:53", "[2001:db8::2]:53", "[2001:db8::3]:53", "192.0.2.1:53", "192.0.2.2:53" ]);
+        assert_eq!(interleave(found), addrs(&[ "[2001:db8::1]:53", "192.0.2.1:53", "[2001:db8::2]:53", "192.0.2.2:53", "[2001:db8::3]:53" ]));
+    }
+
+    #[test]
+    fn first_family_goes_first() {
+        let found = addrs(&[ "192.0.2.1:53", "192.0.2.1:53", "[2001:db8::1]:53" ]);
+        assert_eq!(interleave(found), addrs(&[ "192.0.2.1:53", "[2001:db8::1]:53" ]));
+    }
+
+    #[test]
+    fn second_address_answers() {
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let list = addrs(&[ "192.0.2.1:53", "192.0.2.2:53" ]);
+        let unreachable = list[0];
+        let mut tried = Vec::new();
+
+        let result = rt.block_on(try_each(&list, Timeouts::default(), |addr, timeouts| {
+            tried.push((addr, timeouts.response));
+            async move {
+                if addr == unreachable { Err(Error::Timeout(Phase::Response)) } else { Ok(addr) }
+            }
+        }));
+
+        assert_eq!(result.unwrap(), list[1]);
+        assert_eq!(tried, vec![ (list[0], Some(FALLBACK_TIMEOUT)), (list[1], None) ]);
+    }
+
+    #[test]
+    fn unreadable_response_stops() {
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let list = addrs(&[ "192.0.2.1:53", "192.0.2.2:53" ]);
+        let mut tries = 0;
+
+        let result: Result<(), Error> = rt.block_on(try_each(&list, Timeouts::default(), |_, _| {
+            tries += 1;
+            async { Err(Error::BadRequest) }
+        }));
+
+        assert!(matches!(result, Err(Error::BadRequest)));
+        assert_eq!(tries, 1);
+    }
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use log::*;
 
 use dns::{Request, Response};
-use super::{Transport, Error, UdpTransport, TcpTransport};
+use super::{Transport, Error, Details, Timeouts, UdpTransport, TcpTransport};
 
 
 /// The **automatic transport**, which uses the UDP transport, then tries
@@ -11,7 +11,7 @@ use super::{Transport, Error, UdpTransport, TcpTransport};
 /// # Examples
 ///
 /// ```no_run
-/// use dns_transport::{Transport, AutoTransport};
+/// use dns_transport::{Transport, AutoTransport, Timeouts};
 /// use dns::{Request, Flags, Query, QClass, qtype, record::NS};
 ///
 /// let query = Query {
@@ -25,40 +25,67 @@ use super::{Transport, Error, UdpTransport, TcpTransport};
 ///     flags: Flags::query(),
 ///     queries: vec![ query ],
 ///     additional: None,
+///     signature: None,
 /// };
 ///
-/// let transport = AutoTransport::new("8.8.8.8");
+/// let transport = AutoTransport::new("8.8.8.8", Timeouts::default());
 /// transport.send(&request);
 /// ```
 #[derive(Debug)]
 pub struct AutoTransport {
     addr: String,
+    timeouts: Timeouts,
+    ip_ttl: Option<u8>,
+    kernel_timestamps: bool,
 }
 
 impl AutoTransport {
 
-    /// Creates a new automatic transport that connects to the given host.
-    pub fn new(sa: impl Into<String>) -> Self {
+    /// Creates a new automatic transport that connects to the given host,
+    /// passing the timeouts on to whichever transport gets used.
+    pub fn new(sa: impl Into<String>, timeouts: Timeouts) -> Self {
         let addr = sa.into();
-        Self { addr }
+        Self { addr, timeouts, ip_ttl: None, kernel_timestamps: false }
+    }
+
+    /// Sends requests over UDP with the given IP TTL, or IPv6 hop limit.
+    /// Requests that switch to TCP are sent as normal.
+    #[must_use]
+    pub fn with_ip_ttl(mut self, ttl: u8) -> Self {
+        self.ip_ttl = Some(ttl);
+        self
+    }
+
+    /// Times requests sent over UDP with kernel timestamps, where the
+    /// system can report them.
+    #[must_use]
+    pub fn with_kernel_timestamps(mut self) -> Self {
+        self.kernel_timestamps = true;
+        self
     }
 }
 
 
 #[async_trait]
 impl Transport for AutoTransport {
-    async fn send(&self, request: &Request) -> Result<Response, Error> {
-        let udp_transport = UdpTransport::new(&self.addr);
-        let udp_response = udp_transport.send(&request).await?;
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let mut udp_transport = UdpTransport::new(&self.addr, self.timeouts);
+        if let Some(ttl) = self.ip_ttl {
+            udp_transport = udp_transport.with_ip_ttl(ttl);
+        }
+        if self.kernel_timestamps {
+            udp_transport = udp_transport.with_kernel_timestamps();
+        }
+
+        let (udp_response, udp_details) = udp_transport.send_with_details(request).await?;
 
         if ! udp_response.flags.truncated {
-            return Ok(udp_response);
+            return Ok((udp_response, udp_details));
         }
 
         debug!("Truncated flag set, so switching to TCP");
 
-        let tcp_transport = TcpTransport::new(&self.addr);
-        let tcp_response = tcp_transport.send(&request).await?;
-        Ok(tcp_response)
+        let tcp_transport = TcpTransport::new(&self.addr, self.timeouts);
+        tcp_transport.send_with_details(request).await
     }
 }
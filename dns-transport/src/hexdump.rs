@@ -0,0 +1,86 @@
+//! Logging the bytes sent and received over the wire.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use log::*;
+
+
+/// Logs the bytes of a message that’s just been sent, as a hex dump, if
+/// trace-level logging is enabled.
+pub(crate) fn log_sent(transport: &str, peer: &str, bytes: &[u8]) {
+    if log_enabled!(Level::Trace) {
+        trace!("{} sent {} bytes to {}\n{}", transport, bytes.len(), peer, hex_dump(bytes));
+    }
+}
+
+/// Logs the bytes of a message that’s just been received, as a hex dump,
+/// along with how long it took to arrive, if trace-level logging is enabled.
+pub(crate) fn log_received(transport: &str, peer: &str, bytes: &[u8], elapsed: Duration) {
+    if log_enabled!(Level::Trace) {
+        trace!("{} received {} bytes from {} after {}µs\n{}", transport, bytes.len(), peer, elapsed.as_micros(), hex_dump(bytes));
+    }
+}
+
+
+/// Formats bytes as lines of sixteen hexadecimal octets, prefixed by their
+/// offset and followed by their printable ASCII characters.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut output = String::new();
+
+    for (index, chunk) in bytes.chunks(16).enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+
+        let _ = write!(output, "{:04x} ", index * 16);
+
+        for column in 0 .. 16 {
+            if column == 8 {
+                output.push(' ');
+            }
+
+            match chunk.get(column) {
+                Some(byte)  => { let _ = write!(output, " {:02x}", byte); }
+                None        => output.push_str("   "),
+            }
+        }
+
+        output.push_str("  |");
+        for byte in chunk {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                output.push(char::from(*byte));
+            }
+            else {
+                output.push('.');
+            }
+        }
+        output.push('|');
+    }
+
+    output
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test]
+    fn one_line() {
+        assert_eq!(hex_dump(b"\x12\x34dog"),
+                   "0000  12 34 64 6f 67                                    |.4dog|");
+    }
+
+    #[test]
+    fn two_lines() {
+        assert_eq!(hex_dump(b"\x00\x01\x02\x03lookup.dog\x00\x00\x01\x00\x01"),
+                   "0000  00 01 02 03 6c 6f 6f 6b  75 70 2e 64 6f 67 00 00  |....lookup.dog..|\n\
+                    0010  01 00 01                                          |...|");
+    }
+}
@@ -0,0 +1,87 @@
+//! Limiting how many hops a UDP request can travel, and reading how many
+//! hops a response had left when it arrived.
+//!
+//! Every router a packet passes through decrements its IP TTL, or its hop
+//! limit for IPv6, and drops it once that reaches zero. Sending a request
+//! with a small TTL shows whether anything close by answers for a far-away
+//! nameserver, and the TTL a response arrives with hints at how far away
+//! whatever sent it is. On Linux, the kernel hands over the TTL of each
+//! packet it receives when asked to with `IP_RECVTTL`.
+
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+use log::*;
+
+
+/// Sets the TTL or hop limit of every packet sent on the socket, which is
+/// going to send to the given address.
+pub(crate) fn set_limit(socket: &UdpSocket, addr: SocketAddr, limit: u8) -> io::Result<()> {
+    debug!("Setting IP TTL to {}", limit);
+
+    if addr.is_ipv4() {
+        socket.set_ttl(limit.into())
+    }
+    else {
+        kernel::set_hop_limit(socket, limit)
+    }
+}
+
+/// Asks the kernel to report the TTL or hop limit of packets that arrive on
+/// the socket, which is going to receive them from the given address.
+/// Returns whether it worked.
+pub(crate) fn report(socket: &UdpSocket, addr: SocketAddr) -> bool {
+    match kernel::report(socket, addr.is_ipv6()) {
+        Ok(()) => {
+            debug!("Received TTLs are available");
+            true
+        }
+        Err(e) => {
+            debug!("Received TTLs are unavailable: {}", e);
+            false
+        }
+    }
+}
+
+
+#[cfg(target_os = "linux")]
+mod kernel {
+    use std::io;
+    use std::net::UdpSocket;
+
+    use nix::sys::socket::{setsockopt, sockopt};
+
+    /// Sets the hop limit for IPv6 packets, which the standard library has
+    /// no method for.
+    pub(super) fn set_hop_limit(socket: &UdpSocket, limit: u8) -> io::Result<()> {
+        Ok(setsockopt(socket, sockopt::Ipv6Ttl, &limit.into())?)
+    }
+
+    /// Turns on reporting of the TTL or hop limit.
+    pub(super) fn report(socket: &UdpSocket, ipv6: bool) -> io::Result<()> {
+        if ipv6 {
+            setsockopt(socket, sockopt::Ipv6RecvHopLimit, &true)?;
+        }
+        else {
+            setsockopt(socket, sockopt::Ipv4RecvTtl, &true)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod kernel {
+    use std::io;
+    use std::net::UdpSocket;
+
+    /// The IPv6 hop limit is only set on Linux.
+    pub(super) fn set_hop_limit(_socket: &UdpSocket, _limit: u8) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "setting the IPv6 hop limit is only supported on Linux"))
+    }
+
+    /// Received TTLs are only read on Linux.
+    pub(super) fn report(_socket: &UdpSocket, _ipv6: bool) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "reading received TTLs is only supported on Linux"))
+    }
+}
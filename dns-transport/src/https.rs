@@ -1,12 +1,22 @@
+use std::future::poll_fn;
+use std::io;
+use std::sync::Mutex;
+use std::time::Instant;
+
 use async_trait::async_trait;
-use hyper_tls::HttpsConnector;
+use hyper_tls::MaybeHttpsStream;
 use hyper::Body;
 use hyper::body::HttpBody as _;
-use hyper::Client;
+use hyper::client::conn::{self, SendRequest};
+use hyper::Uri;
 use log::*;
+use native_tls::TlsConnector;
+use tokio::net::TcpStream;
 
 use dns::{Request, Response};
-use super::{Transport, Error};
+use super::{Transport, Error, Details, RoundTrip, Timeouts, Phase};
+use super::timeouts::limit;
+use super::hexdump;
 
 
 /// The **HTTPS transport**, which uses Hyper.
@@ -14,7 +24,7 @@ use super::{Transport, Error};
 /// # Examples
 ///
 /// ```no_run
-/// use dns_transport::{Transport, HttpsTransport};
+/// use dns_transport::{Transport, HttpsTransport, Timeouts};
 /// use dns::{Request, Flags, Query, QClass, qtype, record::A};
 ///
 /// let query = Query {
@@ -28,58 +38,353 @@ use super::{Transport, Error};
 ///     flags: Flags::query(),
 ///     queries: vec![ query ],
 ///     additional: None,
+///     signature: None,
 /// };
 ///
-/// let transport = HttpsTransport::new("https://cloudflare-dns.com/dns-query");
+/// let transport = HttpsTransport::new("https://cloudflare-dns.com/dns-query", Timeouts::default());
 /// transport.send(&request);
 /// ```
 #[derive(Debug)]
 pub struct HttpsTransport {
     url: String,
+    sni: Option<String>,
+    http_host: Option<String>,
+    headers: Vec<(String, String)>,
+    user_agent: Option<String>,
+    path: Option<String>,
+    timeouts: Timeouts,
+
+    /// The connection, which is made for the first request and then kept
+    /// around, so later requests can reuse it instead of going through the
+    /// TCP and TLS handshakes again.
+    connection: Mutex<Option<SendRequest<Body>>>,
 }
 
 impl HttpsTransport {
 
-    /// Creates a new HTTPS transport that connects to the given URL.
-    pub fn new(url: impl Into<String>) -> Self {
-        Self { url: url.into() }
+    /// Creates a new HTTPS transport that connects to the given URL, giving
+    /// up on any phase that takes longer than its timeout.
+    pub fn new(url: impl Into<String>, timeouts: Timeouts) -> Self {
+        Self { url: url.into(), sni: None, http_host: None, headers: Vec::new(), user_agent: None, path: None, timeouts, connection: Mutex::new(None) }
+    }
+
+    /// Presents the given name to the server during the TLS handshake, and
+    /// checks the server’s certificate against it, while still connecting
+    /// to the host in the URL. This lets an endpoint be reached by its IP
+    /// address when its name can’t be looked up.
+    #[must_use]
+    pub fn with_sni(mut self, name: impl Into<String>) -> Self {
+        self.sni = Some(name.into());
+        self
+    }
+
+    /// Sends the given name in the `Host` header, instead of the host in
+    /// the URL (or the SNI name, if one is set).
+    #[must_use]
+    pub fn with_http_host(mut self, name: impl Into<String>) -> Self {
+        self.http_host = Some(name.into());
+        self
+    }
+
+    /// Sends an extra header with every request, such as one holding the
+    /// token a private endpoint wants.
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends the given `User-Agent` header with every request.
+    #[must_use]
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sends requests to the given path and query, instead of the ones in
+    /// the URL. This can be a template, as with the URL itself.
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Works out the URL to send a message to, by replacing the path if a
+    /// different one has been set, and filling in the template if there is
+    /// one. A URL containing `{?dns}` or `{dns}` is an RFC 8484 template
+    /// for GET requests, with the message going in the `dns` parameter in
+    /// unpadded `base64url`; any other URL gets it in the body of a POST.
+    /// Returns the URL, and whether to use GET.
+    fn url_for(&self, message: &[u8]) -> (String, bool) {
+        let mut url = self.url.clone();
+
+        if let Some(path) = &self.path {
+            let authority_start = url.find("://").map_or(0, |i| i + 3);
+            let path_start = url[authority_start ..].find('/').map_or(url.len(), |i| authority_start + i);
+            url.truncate(path_start);
+
+            if ! path.starts_with('/') {
+                url.push('/');
+            }
+            url.push_str(path);
+        }
+
+        let encoded = base64::encode_config(message, base64::URL_SAFE_NO_PAD);
+        if let Some(index) = url.find("{?dns}") {
+            let separator = if url[.. index].contains('?') { '&' } else { '?' };
+            let url = url.replacen("{?dns}", &format!("{}dns={}", separator, encoded), 1);
+            (url, true)
+        }
+        else if url.contains("{dns}") {
+            (url.replacen("{dns}", &encoded, 1), true)
+        }
+        else {
+            (url, false)
+        }
+    }
+
+    /// Works out the URI to send the request to, and the host to actually
+    /// connect to, if it’s different from the one in the URI. When an SNI
+    /// name is set, it takes the place of the host in the URI, as that’s
+    /// where Hyper gets the name for the TLS handshake from.
+    fn destination(&self, url: &str) -> Result<(Uri, Option<String>), Error> {
+        let invalid = || Error::NetworkError(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid URL {:?}", url)));
+        let uri = url.parse::<Uri>().map_err(|_| invalid())?;
+
+        let Some(sni) = &self.sni else {
+            return Ok((uri, None));
+        };
+
+        let Some(host) = uri.host().map(|h| h.trim_start_matches('[').trim_end_matches(']').to_owned()) else {
+            return Ok((uri, None));
+        };
+
+        let authority = match uri.port_u16() {
+            Some(port)  => format!("{}:{}", sni, port),
+            None        => sni.clone(),
+        };
+
+        let mut parts = uri.into_parts();
+        parts.authority = Some(authority.parse().map_err(|_| invalid())?);
+        let uri = Uri::from_parts(parts).map_err(|_| invalid())?;
+        Ok((uri, Some(host)))
+    }
+}
+
+
+impl HttpsTransport {
+
+    /// Returns the connection to send a request over, reusing the one from
+    /// the last request if the server hasn’t closed it, and making a new one
+    /// otherwise. Connecting and the TLS handshake are timed separately, as
+    /// with the TLS transport.
+    async fn connection(&self, uri: &Uri, connect_host: Option<&str>) -> Result<SendRequest<Body>, Error> {
+        let previous = self.connection.lock().expect("connection lock poisoned").take();
+        if let Some(mut sender) = previous {
+            if poll_fn(|cx| sender.poll_ready(cx)).await.is_ok() {
+                debug!("Reusing HTTPS connection");
+                return Ok(sender);
+            }
+
+            debug!("Previous HTTPS connection was closed");
+        }
+
+        let host = uri.host().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        let use_tls = uri.scheme_str() == Some("https");
+        let port = uri.port_u16().unwrap_or(if use_tls { 443 } else { 80 });
+
+        debug!("Opening TCP socket");
+        let stream = limit(self.timeouts.connect, Phase::Connect, TcpStream::connect((connect_host.unwrap_or(host), port))).await?;
+
+        let stream = if use_tls {
+            debug!("Performing TLS handshake with {:?}", host);
+            let connector = tokio_tls::TlsConnector::from(TlsConnector::new()?);
+            MaybeHttpsStream::from(limit(self.timeouts.handshake, Phase::Handshake, connector.connect(host, stream)).await?)
+        }
+        else {
+            MaybeHttpsStream::from(stream)
+        };
+
+        let (sender, connection) = conn::handshake(stream).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("HTTPS connection failed: {}", e);
+            }
+        });
+
+        Ok(sender)
     }
 }
 
+
+/// Details of the HTTP response that a DNS-over-HTTPS response arrived in.
+/// Proxies and caches in front of an endpoint can affect the answer, and
+/// these are the only clues that they’re there.
+#[derive(PartialEq, Debug, Clone)]
+pub struct HttpDetails {
+
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The version of HTTP negotiated with the server, such as `HTTP/2.0`.
+    pub version: String,
+
+    /// The `Server` header, naming the software that sent the response.
+    pub server: Option<String>,
+
+    /// The `Cache-Control` header, saying how long the response may be
+    /// cached for.
+    pub cache_control: Option<String>,
+
+    /// The `Age` header, which is the number of seconds the response has
+    /// spent in a cache.
+    pub age: Option<String>,
+}
+
+impl HttpDetails {
+    fn from_response(response: &hyper::Response<Body>) -> Self {
+        let header = |name| {
+            response.headers().get(name).map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        };
+
+        Self {
+            status: response.status().as_u16(),
+            version: format!("{:?}", response.version()),
+            server: header(hyper::header::SERVER),
+            cache_control: header(hyper::header::CACHE_CONTROL),
+            age: header(hyper::header::AGE),
+        }
+    }
+}
+
+
 #[async_trait]
 impl Transport for HttpsTransport {
-    async fn send(&self, request: &Request) -> Result<Response, Error> {
-        let https = HttpsConnector::new();
-        let client = Client::builder().build::<_, hyper::Body>(https);
-
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
         let bytes = request.to_bytes().expect("failed to serialise request");
-        info!("Sending {} bytes of data to {:?}", bytes.len(), self.url);
-
-        let request = hyper::Request::builder()
-            .method("POST")
-            .uri(&self.url)
-            .header("Content-Type", "application/dns-message")
-            .header("Accept",       "application/dns-message")
-            .body(Body::from(bytes))
-            .expect("Failed to build request");  // we control the request, so this should never fail
-
-        let mut response = client.request(request).await?;
-        debug!("Response: {}", response.status());
-        debug!("Headers: {:#?}", response.headers());
-
-        if response.status() != 200 {
-            return Err(Error::BadRequest);
+        let (url, use_get) = self.url_for(&bytes);
+
+        let (uri, connect_host) = self.destination(&url)?;
+        if let Some(host) = &connect_host {
+            info!("Connecting to {:?} while presenting {:?}", host, uri.host());
         }
 
-        debug!("Reading body...");
-        let mut buf = Vec::new();
-        while let Some(chunk) = response.body_mut().data().await {
-            buf.extend(&chunk?);
+        let mut sender = self.connection(&uri, connect_host.as_deref()).await?;
+
+        info!("Sending {} bytes of data to {:?}", bytes.len(), url);
+
+        hexdump::log_sent("HTTPS", &self.url, &bytes);
+
+        // The connection is already made, so the request only needs the
+        // path, with the host going in its header
+        let host = match &self.http_host {
+            Some(host)  => host.clone(),
+            None        => uri.authority().map(ToString::to_string).unwrap_or_default(),
+        };
+
+        let mut builder = hyper::Request::builder()
+            .method(if use_get { "GET" } else { "POST" })
+            .uri(uri.path_and_query().map_or("/", |p| p.as_str()))
+            .header(hyper::header::HOST, host)
+            .header("Accept", "application/dns-message");
+
+        if ! use_get {
+            builder = builder.header("Content-Type", "application/dns-message");
         }
 
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.header(hyper::header::USER_AGENT, user_agent.as_str());
+        }
+
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+
+        // The extra headers come from the user, so they might not be valid
+        let body = if use_get { Body::empty() } else { Body::from(bytes) };
+        let request = builder.body(body).map_err(|e| {
+            Error::NetworkError(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid request: {}", e)))
+        })?;
+
+        let start = Instant::now();
+        let (buf, details) = limit(self.timeouts.response, Phase::Response, async {
+            let mut response = sender.send_request(request).await?;
+            debug!("Response: {}", response.status());
+            debug!("Headers: {:#?}", response.headers());
+            let details = HttpDetails::from_response(&response);
+
+            if response.status() != 200 {
+                return Err(Error::BadRequest);
+            }
+
+            debug!("Reading body...");
+            let mut buf = Vec::new();
+            while let Some(chunk) = response.body_mut().data().await {
+                buf.extend(&chunk?);
+            }
+
+            Ok((buf, details))
+        }).await?;
+
+        // Only a connection that got a whole response is worth keeping
+        *self.connection.lock().expect("connection lock poisoned") = Some(sender);
+
         info!("Received {} bytes of data", buf.len());
+        hexdump::log_received("HTTPS", &self.url, &buf, start.elapsed());
         let response = Response::from_bytes(&buf)?;
 
-        Ok(response)
+        Ok((response, Details { size: buf.len(), over_udp: false, http: Some(details), message: buf, round_trip: Some(RoundTrip::since(start)), address: None, ip_ttl: None }))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn destination_without_sni() {
+        let transport = HttpsTransport::new("https://192.0.2.1/dns-query", Timeouts::default());
+        let (uri, host) = transport.destination(&transport.url).unwrap();
+        assert_eq!(uri, "https://192.0.2.1/dns-query");
+        assert_eq!(host, None);
+    }
+
+    #[test]
+    fn destination_with_sni() {
+        let transport = HttpsTransport::new("https://[2001:db8::1]:8443/dns-query", Timeouts::default()).with_sni("dns.example");
+        let (uri, host) = transport.destination(&transport.url).unwrap();
+        assert_eq!(uri, "https://dns.example:8443/dns-query");
+        assert_eq!(host, Some("2001:db8::1".into()));
+    }
+
+    #[test]
+    fn url_for_post() {
+        let transport = HttpsTransport::new("https://dns.example/dns-query", Timeouts::default());
+        assert_eq!(transport.url_for(&[ 0xAB, 0xCD ]), ("https://dns.example/dns-query".into(), false));
+    }
+
+    #[test]
+    fn url_for_get_template() {
+        let transport = HttpsTransport::new("https://dns.example/dns-query{?dns}", Timeouts::default());
+        assert_eq!(transport.url_for(&[ 0xAB, 0xCD, 0xEF, 0xFF ]), ("https://dns.example/dns-query?dns=q83v_w".into(), true));
+    }
+
+    #[test]
+    fn url_for_template_after_query() {
+        let transport = HttpsTransport::new("https://dns.example/resolve?token=abc{?dns}", Timeouts::default());
+        assert_eq!(transport.url_for(&[ 0xAB, 0xCD ]), ("https://dns.example/resolve?token=abc&dns=q80".into(), true));
+    }
+
+    #[test]
+    fn url_for_with_path() {
+        let transport = HttpsTransport::new("https://dns.example:8443/dns-query", Timeouts::default()).with_path("/private/q?key={dns}");
+        assert_eq!(transport.url_for(&[ 0xAB, 0xCD ]), ("https://dns.example:8443/private/q?key=q80".into(), true));
+    }
+
+    #[test]
+    fn url_for_with_path_and_no_path() {
+        let transport = HttpsTransport::new("https://192.0.2.1", Timeouts::default()).with_path("resolve");
+        assert_eq!(transport.url_for(&[]), ("https://192.0.2.1/resolve".into(), false));
     }
 }
@@ -18,13 +18,15 @@
 
 #![deny(unsafe_code)]
 
+use std::net::SocketAddr;
+
 use async_trait::async_trait;
 use derive_more::From;
 
 use dns::{Request, Response};
 
 
-// Re-export the five transport types, as well as the Tokio runtime, so that
+// Re-export the six transport types, as well as the Tokio runtime, so that
 // the dog crate can just use something called “Runtime” without worrying
 // about which runtime it actually is.
 
@@ -41,19 +43,75 @@ mod tls;
 pub use self::tls::TlsTransport;
 
 mod https;
-pub use self::https::HttpsTransport;
+pub use self::https::{HttpsTransport, HttpDetails};
+
+mod local;
+pub use self::local::LocalTransport;
 
 pub use tokio::runtime::Runtime;
 
+mod addresses;
+
+mod hexdump;
+
+mod hops;
+
+mod receive;
+
+mod timeouts;
+pub use self::timeouts::{Timeouts, Phase};
+
+mod timestamps;
+pub use self::timestamps::{RoundTrip, Timestamps};
+
 
 
 /// The trait implemented by all four transport types.
 #[async_trait]
-pub trait Transport {
+pub trait Transport: Send + Sync {
 
     /// Convert the request to bytes, send it over the network, wait for a
     /// response, deserialise it from bytes, and return it, asynchronously.
-    async fn send(&self, request: &Request) -> Result<Response, Error>;
+    async fn send(&self, request: &Request) -> Result<Response, Error> {
+        let (response, _) = self.send_with_details(request).await?;
+        Ok(response)
+    }
+
+    /// Send the request, as with `send`, but also return the details of how
+    /// the response arrived, such as its size and the protocol it used.
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error>;
+}
+
+/// Details of how a response arrived, which aren’t part of the response
+/// itself but can explain odd behaviour on the path to the nameserver.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Details {
+
+    /// The size of the DNS message that was received, in bytes, not
+    /// counting any length prefix or HTTP framing.
+    pub size: usize,
+
+    /// Whether the response arrived over UDP, which is the only protocol
+    /// where large responses can get fragmented or dropped.
+    pub over_udp: bool,
+
+    /// The details of the HTTP response, for transports that use HTTP.
+    pub http: Option<HttpDetails>,
+
+    /// The DNS message that was received, as bytes, so that it can be
+    /// written to disk when recording a session.
+    pub message: Vec<u8>,
+
+    /// How long the response took to arrive, if it was timed.
+    pub round_trip: Option<RoundTrip>,
+
+    /// The address the response came from, for transports that connect to
+    /// one, which can be any of the addresses of a nameserver given by name.
+    pub address: Option<SocketAddr>,
+
+    /// The IP TTL, or IPv6 hop limit, that the response arrived with, for
+    /// UDP responses on systems that report it.
+    pub ip_ttl: Option<u8>,
 }
 
 /// Something that can go wrong making a DNS request.
@@ -77,4 +135,8 @@ pub enum Error {
     /// The server specifically indicated that the request we sent it was
     /// malformed.
     BadRequest,
+
+    /// One of the phases of sending the request took longer than its
+    /// timeout allowed.
+    Timeout(Phase),
 }
@@ -0,0 +1,138 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use log::*;
+
+use dns::{Request, Response};
+use super::{Transport, Error, Details, RoundTrip, Timeouts};
+use super::hexdump;
+
+
+/// The **local transport**, which talks to a DNS service running on the
+/// same machine, over a Unix domain socket on Unix, or a named pipe on
+/// Windows. Messages are framed the same way as they are over TCP, with
+/// their length before them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use dns_transport::{Transport, LocalTransport, Timeouts};
+/// use dns::{Request, Flags, Query, QClass, qtype, record::A};
+///
+/// let query = Query {
+///     qname: String::from("dns.lookup.dog"),
+///     qclass: QClass::IN,
+///     qtype: qtype!(A),
+/// };
+///
+/// let request = Request {
+///     transaction_id: 0xABCD,
+///     flags: Flags::query(),
+///     queries: vec![ query ],
+///     additional: None,
+///     signature: None,
+/// };
+///
+/// let transport = LocalTransport::new("/run/resolver/dns.sock", Timeouts::default());
+/// transport.send(&request);
+/// ```
+#[derive(Debug)]
+pub struct LocalTransport {
+    path: String,
+    timeouts: Timeouts,
+}
+
+impl LocalTransport {
+
+    /// Creates a new local transport that connects to the socket or pipe at
+    /// the given path, such as `/run/resolver/dns.sock` or
+    /// `\\.\pipe\resolver`, giving up on any phase that takes longer than
+    /// its timeout.
+    pub fn new(path: impl Into<String>, timeouts: Timeouts) -> Self {
+        Self { path: path.into(), timeouts }
+    }
+}
+
+
+#[async_trait]
+impl Transport for LocalTransport {
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let mut bytes = request.to_bytes().expect("failed to serialise request");
+        let len_bytes = (bytes.len() as u16).to_be_bytes();
+        bytes.insert(0, len_bytes[0]);
+        bytes.insert(1, len_bytes[1]);
+
+        info!("Sending {} bytes of data to {} locally", bytes.len(), self.path);
+
+        let start = Instant::now();
+        hexdump::log_sent("local", &self.path, &bytes[2..]);
+        let message = exchange(&self.path, &bytes, self.timeouts).await?;
+
+        hexdump::log_received("local", &self.path, &message, start.elapsed());
+        let response = Response::from_bytes(&message)?;
+        Ok((response, Details { size: message.len(), over_udp: false, http: None, message, round_trip: Some(RoundTrip::since(start)), address: None, ip_ttl: None }))
+    }
+}
+
+
+/// Sends the length-prefixed message over a Unix domain socket, and reads
+/// the response.
+#[cfg(unix)]
+async fn exchange(path: &str, bytes: &[u8], timeouts: Timeouts) -> Result<Vec<u8>, Error> {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::UnixStream;
+
+    use super::Phase;
+    use super::tcp::read_message;
+    use super::timeouts::limit;
+
+    let mut stream = limit(timeouts.connect, Phase::Connect, UnixStream::connect(path)).await?;
+    info!("Connected to socket");
+
+    stream.write_all(bytes).await?;
+    limit(timeouts.response, Phase::Response, read_message(&mut stream)).await
+}
+
+/// Sends the length-prefixed message down a named pipe, and reads the
+/// response. Tokio can’t open named pipes itself, so the pipe is used from
+/// a blocking thread.
+#[cfg(windows)]
+async fn exchange(path: &str, bytes: &[u8], timeouts: Timeouts) -> Result<Vec<u8>, Error> {
+    use std::fs::OpenOptions;
+    use std::io::{self, Read, Write};
+
+    use super::Phase;
+    use super::timeouts::limit;
+
+    let path = path.to_owned();
+    let bytes = bytes.to_vec();
+
+    let handle = tokio::task::spawn_blocking(move || -> io::Result<Vec<u8>> {
+        let mut pipe = OpenOptions::new().read(true).write(true).open(&path)?;
+        info!("Opened pipe");
+
+        pipe.write_all(&bytes)?;
+
+        let mut len_bytes = [0; 2];
+        pipe.read_exact(&mut len_bytes)?;
+
+        let mut message = vec![0; usize::from(u16::from_be_bytes(len_bytes))];
+        pipe.read_exact(&mut message)?;
+        Ok(message)
+    });
+
+    // The pipe gets opened and read on the same thread, so there’s only
+    // the one timeout to wait on
+    let joined = async { handle.await.map_err(|e| io::Error::new(io::ErrorKind::Other, e)) };
+    let message = limit(timeouts.response, Phase::Response, joined).await?;
+    Ok(message?)
+}
+
+/// There are no local sockets to connect to on other platforms.
+#[cfg(not(any(unix, windows)))]
+async fn exchange(path: &str, _bytes: &[u8], _timeouts: Timeouts) -> Result<Vec<u8>, Error> {
+    use std::io;
+
+    warn!("Cannot connect to {} on this platform", path);
+    Err(Error::NetworkError(io::Error::new(io::ErrorKind::Other, "local sockets are not supported on this platform")))
+}
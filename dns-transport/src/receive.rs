@@ -0,0 +1,154 @@
+//! Receiving UDP packets along with what the kernel knows about them, such
+//! as the TTL they arrived with and when they arrived.
+//!
+//! The kernel hands these over as control messages, which only come with
+//! `recvmsg`, and tokio only ever calls `recv`. So when any of them have
+//! been asked for, packets get read with `recvmsg` from a duplicate of the
+//! socket instead.
+
+use std::io;
+use std::net::UdpSocket;
+use std::time::SystemTime;
+
+use log::*;
+
+
+/// A packet that has been received, and what the kernel reported about it.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub(crate) struct Received {
+
+    /// The length of the packet.
+    pub len: usize,
+
+    /// The IP TTL, or IPv6 hop limit, the packet arrived with, if the
+    /// kernel was asked to report it.
+    pub ttl: Option<u8>,
+
+    /// When the packet arrived, if the kernel was asked to timestamp it.
+    pub timestamp: Option<SystemTime>,
+}
+
+/// Receives packets from a socket, reading the kernel’s control messages
+/// if any have been asked for and the system can report them.
+pub(crate) struct Receiver {
+    reader: Option<kernel::Reader>,
+}
+
+impl Receiver {
+
+    /// Gets ready to receive packets from the socket. If `control` is set,
+    /// packets get read along with their control messages, which should
+    /// have been turned on before this is called; otherwise, they’re read
+    /// from tokio’s socket as normal.
+    pub(crate) fn start(socket: &UdpSocket, control: bool) -> Self {
+        if ! control {
+            return Self { reader: None };
+        }
+
+        match kernel::Reader::new(socket) {
+            Ok(reader) => {
+                debug!("Control messages are available");
+                Self { reader: Some(reader) }
+            }
+            Err(e) => {
+                debug!("Control messages are unavailable: {}", e);
+                Self { reader: None }
+            }
+        }
+    }
+
+    /// Receives a packet.
+    pub(crate) async fn recv(&self, socket: &mut tokio::net::UdpSocket, buf: &mut [u8]) -> io::Result<Received> {
+        match &self.reader {
+            Some(reader)  => reader.recv(buf).await,
+            None          => Ok(Received { len: socket.recv(buf).await?, ttl: None, timestamp: None }),
+        }
+    }
+}
+
+
+#[cfg(target_os = "linux")]
+mod kernel {
+    use std::convert::TryFrom;
+    use std::future::poll_fn;
+    use std::io::{self, IoSliceMut};
+    use std::net::UdpSocket;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::task::{Poll, ready};
+
+    use nix::cmsg_space;
+    use nix::sys::socket::{recvmsg, ControlMessageOwned, MsgFlags, SockaddrStorage, Timestamps};
+    use tokio::io::PollEvented;
+
+    use super::Received;
+    use crate::timestamps;
+
+    /// A duplicate of a socket, registered on its own just to find out when
+    /// it can be read from.
+    pub(super) struct Reader {
+        readiness: PollEvented<mio::net::UdpSocket>,
+    }
+
+    impl Reader {
+        pub(super) fn new(socket: &UdpSocket) -> io::Result<Self> {
+            let duplicate = mio::net::UdpSocket::from_socket(socket.try_clone()?)?;
+            Ok(Self { readiness: PollEvented::new(duplicate)? })
+        }
+
+        pub(super) async fn recv(&self, buf: &mut [u8]) -> io::Result<Received> {
+            poll_fn(|cx| {
+                ready!(self.readiness.poll_read_ready(cx, mio::Ready::readable()))?;
+
+                match recv_with_control(self.readiness.get_ref().as_raw_fd(), buf) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        self.readiness.clear_read_ready(cx, mio::Ready::readable())?;
+                        Poll::Pending
+                    }
+                    result => Poll::Ready(result),
+                }
+            }).await
+        }
+    }
+
+    fn recv_with_control(fd: RawFd, buf: &mut [u8]) -> io::Result<Received> {
+        let mut iov = [ IoSliceMut::new(buf) ];
+        let mut control = cmsg_space!(i32, Timestamps);
+        let message = recvmsg::<SockaddrStorage>(fd, &mut iov, Some(&mut control), MsgFlags::MSG_DONTWAIT)?;
+
+        let mut received = Received { len: message.bytes, ttl: None, timestamp: None };
+        for cmsg in message.cmsgs()? {
+            match cmsg {
+                ControlMessageOwned::Ipv4Ttl(ttl) | ControlMessageOwned::Ipv6HopLimit(ttl) => {
+                    received.ttl = u8::try_from(ttl).ok();
+                }
+                ControlMessageOwned::ScmTimestampsns(stamps) => {
+                    received.timestamp = timestamps::kernel::system_time(stamps.system);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(received)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod kernel {
+    use std::io;
+    use std::net::UdpSocket;
+
+    use super::Received;
+
+    /// Control messages are only read on Linux, so there’s never a reader.
+    pub(super) enum Reader {}
+
+    impl Reader {
+        pub(super) fn new(_socket: &UdpSocket) -> io::Result<Self> {
+            Err(io::Error::new(io::ErrorKind::Other, "reading control messages is only supported on Linux"))
+        }
+
+        pub(super) async fn recv(&self, _buf: &mut [u8]) -> io::Result<Received> {
+            match *self {}
+        }
+    }
+}
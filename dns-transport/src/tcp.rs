@@ -1,10 +1,16 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
 use async_trait::async_trait;
 use log::*;
 use tokio::net::TcpStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 
 use dns::{Request, Response};
-use super::{Transport, Error};
+use super::{Transport, Error, Details, RoundTrip, Timeouts, Phase};
+use super::addresses;
+use super::timeouts::limit;
+use super::hexdump;
 
 
 /// The **TCP transport**, which uses the stdlib.
@@ -12,7 +18,7 @@ use super::{Transport, Error};
 /// # Examples
 ///
 /// ```no_run
-/// use dns_transport::{Transport, TcpTransport};
+/// use dns_transport::{Transport, TcpTransport, Timeouts};
 /// use dns::{Request, Flags, Query, QClass, qtype, record::MX};
 ///
 /// let query = Query {
@@ -26,9 +32,10 @@ use super::{Transport, Error};
 ///     flags: Flags::query(),
 ///     queries: vec![ query ],
 ///     additional: None,
+///     signature: None,
 /// };
 ///
-/// let transport = TcpTransport::new("8.8.8.8");
+/// let transport = TcpTransport::new("8.8.8.8", Timeouts::default());
 /// transport.send(&request);
 /// ```
 ///
@@ -39,27 +46,32 @@ use super::{Transport, Error};
 #[derive(Debug)]
 pub struct TcpTransport {
     addr: String,
+    timeouts: Timeouts,
 }
 
 impl TcpTransport {
 
-    /// Creates a new TCP transport that connects to the given host.
-    pub fn new(sa: impl Into<String>) -> Self {
-        Self { addr: sa.into() }
+    /// Creates a new TCP transport that connects to the given host, giving
+    /// up on any phase that takes longer than its timeout.
+    pub fn new(sa: impl Into<String>, timeouts: Timeouts) -> Self {
+        Self { addr: sa.into(), timeouts }
     }
 }
 
 
 #[async_trait]
 impl Transport for TcpTransport {
-    async fn send(&self, request: &Request) -> Result<Response, Error> {
-        let mut stream =
-            if self.addr.contains(':') {
-                TcpStream::connect(&*self.addr).await?
-            }
-            else {
-                TcpStream::connect((&*self.addr, 53)).await?
-            };
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let addrs = addresses::lookup(&self.addr, 53).await?;
+        addresses::try_each(&addrs, self.timeouts, |addr, timeouts| self.send_to(request, addr, timeouts)).await
+    }
+}
+
+impl TcpTransport {
+
+    /// Sends the request to one of the nameserver’s addresses.
+    async fn send_to(&self, request: &Request, addr: SocketAddr, timeouts: Timeouts) -> Result<(Response, Details), Error> {
+        let mut stream = limit(timeouts.connect, Phase::Connect, TcpStream::connect(addr)).await?;
         info!("Created stream");
 
         // The message is prepended with the length when sent over TCP,
@@ -69,52 +81,64 @@ impl Transport for TcpTransport {
         bytes.insert(0, len_bytes[0]);
         bytes.insert(1, len_bytes[1]);
 
-        info!("Sending {} bytes of data to {} over TCP", bytes.len(), self.addr);
+        info!("Sending {} bytes of data to {} over TCP", bytes.len(), addr);
 
+        let start = Instant::now();
         let written_len = stream.write(&bytes).await?;
         debug!("Wrote {} bytes", written_len);
+        hexdump::log_sent("TCP", &self.addr, &bytes[2..]);
 
         info!("Waiting to receive...");
-        let mut buf = [0; 4096];
-        let mut read_len = stream.read(&mut buf[..]).await?;
+        let message = limit(timeouts.response, Phase::Response, read_message(&mut stream)).await?;
 
-        if read_len == 0 {
-            panic!("Received no bytes!");
-        }
-        else if read_len == 1 {
-            info!("Received one byte of data");
-            let second_read_len = stream.read(&mut buf[1..]).await?;
-            if second_read_len == 0 {
-                panic!("Received no bytes the second time!");
-            }
-
-            read_len += second_read_len;
-        }
-        else {
-            info!("Received {} bytes of data", read_len);
-        }
+        hexdump::log_received("TCP", &self.addr, &message, start.elapsed());
+        let response = Response::from_bytes(&message)?;
+        Ok((response, Details { size: message.len(), over_udp: false, http: None, message, round_trip: Some(RoundTrip::since(start)), address: Some(addr), ip_ttl: None }))
+    }
+}
+
+
+/// Reads one length-prefixed DNS message from the stream, which may arrive
+/// split across several reads, returning it without the length. The local
+/// transport frames its messages the same way, so uses this too.
+pub(crate) async fn read_message<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Vec<u8>, Error> {
+    let mut buf = vec![0; 4096];
+    let mut read_len = stream.read(&mut buf[..]).await?;
 
-        let total_len = u16::from_be_bytes([buf[0], buf[1]]);
-        if read_len - 2 == usize::from(total_len) {
-            let response = Response::from_bytes(&buf[2 .. read_len])?;
-            return Ok(response);
+    if read_len == 0 {
+        panic!("Received no bytes!");
+    }
+    else if read_len == 1 {
+        info!("Received one byte of data");
+        let second_read_len = stream.read(&mut buf[1..]).await?;
+        if second_read_len == 0 {
+            panic!("Received no bytes the second time!");
         }
 
-        debug!("We need to read {} bytes total", total_len);
-        let mut combined_buffer = buf[2..read_len].to_vec();
-        while combined_buffer.len() < usize::from(total_len) {
-            let mut buf = [0; 4096];
-            let read_len = stream.read(&mut buf[..]).await?;
-            info!("Received further {} bytes of data (of {})", read_len, total_len);
+        read_len += second_read_len;
+    }
+    else {
+        info!("Received {} bytes of data", read_len);
+    }
+
+    let total_len = u16::from_be_bytes([buf[0], buf[1]]);
+    if read_len - 2 == usize::from(total_len) {
+        return Ok(buf[2 .. read_len].to_vec());
+    }
 
-            if read_len == 0 {
-                panic!("Read zero bytes!");
-            }
+    debug!("We need to read {} bytes total", total_len);
+    let mut combined_buffer = buf[2..read_len].to_vec();
+    while combined_buffer.len() < usize::from(total_len) {
+        let mut buf = vec![0; 4096];
+        let read_len = stream.read(&mut buf[..]).await?;
+        info!("Received further {} bytes of data (of {})", read_len, total_len);
 
-            combined_buffer.extend(&buf[0 .. read_len]);
+        if read_len == 0 {
+            panic!("Read zero bytes!");
         }
 
-        let response = Response::from_bytes(&combined_buffer)?;
-        Ok(response)
+        combined_buffer.extend(&buf[0 .. read_len]);
     }
+
+    Ok(combined_buffer)
 }
@@ -0,0 +1,57 @@
+use std::future::Future;
+use std::time::Duration;
+
+use log::*;
+
+use super::Error;
+
+
+/// How long to wait for each phase of sending a request before giving up.
+/// Each phase is timed separately, so a slow connection or handshake can be
+/// told apart from a slow resolver. A phase without a timeout is waited for
+/// indefinitely.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Timeouts {
+
+    /// How long to wait for a connection to the nameserver to be made.
+    pub connect: Option<Duration>,
+
+    /// How long to wait for the TLS handshake to complete, for the
+    /// encrypted transports.
+    pub handshake: Option<Duration>,
+
+    /// How long to wait for a response after the request has been sent.
+    pub response: Option<Duration>,
+}
+
+/// One of the phases of sending a request that can time out.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Phase {
+
+    /// Connecting to the nameserver.
+    Connect,
+
+    /// Performing the TLS handshake.
+    Handshake,
+
+    /// Waiting for the response.
+    Response,
+}
+
+/// Runs a future to completion, failing with a timeout error for the given
+/// phase if it takes longer than the duration, if there is one.
+pub(crate) async fn limit<T, E>(duration: Option<Duration>, phase: Phase, future: impl Future<Output=Result<T, E>>) -> Result<T, Error>
+where Error: From<E>,
+{
+    let Some(duration) = duration else {
+        return Ok(future.await?);
+    };
+
+    if let Ok(result) = tokio::time::timeout(duration, future).await {
+        Ok(result?)
+    }
+    else {
+        warn!("Timed out after {:?} in phase {:?}", duration, phase);
+        Err(Error::Timeout(phase))
+    }
+}
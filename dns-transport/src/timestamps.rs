@@ -0,0 +1,190 @@
+//! Measuring how long a response took to arrive, using the times the kernel
+//! sent the request and received the response where possible.
+//!
+//! Timing a request from userspace includes however long it took for the
+//! process to be woken up once the response arrived, which is noise when
+//! comparing nameservers that are only a millisecond or two apart. On Linux,
+//! `SO_TIMESTAMPING` has the kernel record when each packet leaves and when
+//! each one arrives, handing over the first through the socket’s error queue
+//! and the second alongside the packet itself.
+
+use std::net::UdpSocket;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::*;
+
+
+/// How long a response took to arrive after its request was sent, and how
+/// that was measured.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct RoundTrip {
+
+    /// The time between sending the request and receiving the response.
+    pub duration: Duration,
+
+    /// Where the times the round trip was worked out from came from.
+    pub timestamps: Timestamps,
+}
+
+/// Where the times a round trip was worked out from came from.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Timestamps {
+
+    /// The kernel recorded both when the request left and when the
+    /// response arrived.
+    Kernel,
+
+    /// dog looked at the clock before sending the request and after
+    /// reading the response.
+    Userspace,
+}
+
+impl RoundTrip {
+
+    /// Measures a round trip that started at the given instant and has
+    /// just finished, using userspace timestamps.
+    pub(crate) fn since(start: Instant) -> Self {
+        Self { duration: start.elapsed(), timestamps: Timestamps::Userspace }
+    }
+}
+
+impl Timestamps {
+
+    /// A short name for where the timestamps came from, for output.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Kernel     => "kernel",
+            Self::Userspace  => "userspace",
+        }
+    }
+}
+
+
+/// Asks the kernel to record when packets leave and arrive on the socket.
+/// Returns whether it worked.
+pub(crate) fn enable(socket: &UdpSocket) -> bool {
+    match kernel::enable(socket) {
+        Ok(()) => {
+            debug!("Kernel timestamps are available");
+            true
+        }
+        Err(e) => {
+            debug!("Kernel timestamps are unavailable: {}", e);
+            false
+        }
+    }
+}
+
+/// The time a request was sent, so the round trip can be worked out once
+/// the response arrives.
+pub(crate) struct Timer {
+    sent: Instant,
+    kernel: bool,
+}
+
+impl Timer {
+
+    /// Notes the current time, along with whether the kernel has been asked
+    /// to record the times too. This should be called just before the
+    /// request is sent.
+    pub(crate) fn start(kernel: bool) -> Self {
+        Self { sent: Instant::now(), kernel }
+    }
+
+    /// Works out the round trip once the response has been read, which the
+    /// kernel may have recorded the time of. The kernel’s times only get
+    /// used if it recorded when the request left, too, as the other time
+    /// would come from a different clock.
+    pub(crate) fn stop(&self, socket: &tokio::net::UdpSocket, received: Option<SystemTime>) -> RoundTrip {
+        let userspace = RoundTrip { duration: self.sent.elapsed(), timestamps: Timestamps::Userspace };
+
+        if ! self.kernel {
+            return userspace;
+        }
+
+        let (Some(sent), Some(received)) = (kernel::last_sent(socket), received) else {
+            debug!("Kernel timestamps are missing, so using userspace ones");
+            return userspace;
+        };
+
+        match received.duration_since(sent) {
+            Ok(duration)  => RoundTrip { duration, timestamps: Timestamps::Kernel },
+            Err(_)        => userspace,
+        }
+    }
+}
+
+
+#[cfg(target_os = "linux")]
+pub(crate) mod kernel {
+    use std::convert::TryFrom;
+    use std::io::{self, IoSliceMut};
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use nix::cmsg_space;
+    use nix::sys::socket::{recvmsg, setsockopt, sockopt, ControlMessageOwned, MsgFlags, SockaddrStorage, TimestampingFlag, Timestamps};
+    use nix::sys::time::TimeSpec;
+
+    /// Turns on software timestamps for packets leaving and arriving. The
+    /// ones for packets leaving come back without the packet’s contents.
+    pub(super) fn enable(socket: &UdpSocket) -> io::Result<()> {
+        let flags = TimestampingFlag::SOF_TIMESTAMPING_SOFTWARE
+                  | TimestampingFlag::SOF_TIMESTAMPING_TX_SOFTWARE
+                  | TimestampingFlag::SOF_TIMESTAMPING_RX_SOFTWARE
+                  | TimestampingFlag::SOF_TIMESTAMPING_OPT_TSONLY;
+
+        Ok(setsockopt(socket, sockopt::Timestamping, &flags)?)
+    }
+
+    /// Returns the time the last packet sent on the socket left, reading it
+    /// from the socket’s error queue.
+    pub(super) fn last_sent(socket: &tokio::net::UdpSocket) -> Option<SystemTime> {
+        let mut buf = [0; 64];
+        let mut iov = [ IoSliceMut::new(&mut buf) ];
+
+        // The timestamps come along with an extended error saying where they
+        // came from, which has to fit too
+        let mut control = cmsg_space!(Timestamps, [u8; 64]);
+
+        let flags = MsgFlags::MSG_ERRQUEUE | MsgFlags::MSG_DONTWAIT;
+        let message = recvmsg::<SockaddrStorage>(socket.as_raw_fd(), &mut iov, Some(&mut control), flags).ok()?;
+
+        message.cmsgs().ok()?.find_map(|cmsg| match cmsg {
+            ControlMessageOwned::ScmTimestampsns(stamps)  => system_time(stamps.system),
+            _                                             => None,
+        })
+    }
+
+    /// Converts a software timestamp into a time, unless it’s zero, which
+    /// is what the kernel leaves it as when it has no timestamp.
+    pub(crate) fn system_time(time: TimeSpec) -> Option<SystemTime> {
+        let seconds = u64::try_from(time.tv_sec()).ok()?;
+        let nanos = u32::try_from(time.tv_nsec()).ok()?;
+
+        if seconds == 0 && nanos == 0 {
+            return None;
+        }
+
+        Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod kernel {
+    use std::io;
+    use std::net::UdpSocket;
+    use std::time::SystemTime;
+
+    /// Kernel timestamps are only read on Linux.
+    pub(super) fn enable(_socket: &UdpSocket) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "kernel timestamps are only supported on Linux"))
+    }
+
+    /// Kernel timestamps are only read on Linux.
+    pub(super) fn last_sent(_socket: &tokio::net::UdpSocket) -> Option<SystemTime> {
+        None
+    }
+}
@@ -1,3 +1,6 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
 use async_trait::async_trait;
 use log::*;
 use native_tls::TlsConnector;
@@ -5,7 +8,10 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 use dns::{Request, Response};
-use super::{Transport, Error};
+use super::{Transport, Error, Details, RoundTrip, Timeouts, Phase};
+use super::addresses;
+use super::timeouts::limit;
+use super::hexdump;
 
 
 /// The **TLS transport**, which uses Tokio.
@@ -13,7 +19,7 @@ use super::{Transport, Error};
 /// # Examples
 ///
 /// ```no_run
-/// use dns_transport::{Transport, TlsTransport};
+/// use dns_transport::{Transport, TlsTransport, Timeouts};
 /// use dns::{Request, Flags, Query, QClass, qtype, record::SRV};
 ///
 /// let query = Query {
@@ -27,42 +33,59 @@ use super::{Transport, Error};
 ///     flags: Flags::query(),
 ///     queries: vec![ query ],
 ///     additional: None,
+///     signature: None,
 /// };
 ///
-/// let transport = TlsTransport::new("dns.google");
+/// let transport = TlsTransport::new("dns.google", Timeouts::default());
 /// transport.send(&request);
 /// ```
 #[derive(Debug)]
 pub struct TlsTransport {
     addr: String,
+    sni: Option<String>,
+    timeouts: Timeouts,
 }
 
 impl TlsTransport {
 
-    /// Creates a new TLS transport that connects to the given host.
-    pub fn new(sa: impl Into<String>) -> Self {
+    /// Creates a new TLS transport that connects to the given host, giving
+    /// up on any phase that takes longer than its timeout.
+    pub fn new(sa: impl Into<String>, timeouts: Timeouts) -> Self {
         let addr = sa.into();
-        Self { addr }
+        Self { addr, sni: None, timeouts }
+    }
+
+    /// Presents the given name to the server during the TLS handshake,
+    /// instead of the host name in the address, and checks the server’s
+    /// certificate against it.
+    #[must_use]
+    pub fn with_sni(mut self, name: impl Into<String>) -> Self {
+        self.sni = Some(name.into());
+        self
     }
 }
 
 #[async_trait]
 impl Transport for TlsTransport {
-    async fn send(&self, request: &Request) -> Result<Response, Error> {
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let addrs = addresses::lookup(&self.addr, 853).await?;
+        addresses::try_each(&addrs, self.timeouts, |addr, timeouts| self.send_to(request, addr, timeouts)).await
+    }
+}
+
+impl TlsTransport {
+
+    /// Sends the request to one of the nameserver’s addresses, checking its
+    /// certificate against the nameserver’s name rather than the address.
+    async fn send_to(&self, request: &Request, addr: SocketAddr, timeouts: Timeouts) -> Result<(Response, Details), Error> {
         let connector = TlsConnector::new()?;
         let connector = tokio_tls::TlsConnector::from(connector);
 
-        info!("Opening TLS socket");
-        let stream =
-            if self.addr.contains(':') {
-                TcpStream::connect(&*self.addr).await?
-            }
-            else {
-                TcpStream::connect((&*self.addr, 853)).await?
-            };
+        info!("Opening TLS socket to {}", addr);
+        let stream = limit(timeouts.connect, Phase::Connect, TcpStream::connect(addr)).await?;
 
         info!("Connecting");
-        let mut stream = connector.connect(self.sni_domain(), stream).await?;
+        let mut stream = limit(timeouts.handshake, Phase::Handshake, connector.connect(self.sni_domain(), stream)).await?;
 
         // As with TCP, we need to prepend the message with its length.
         let mut bytes = request.to_bytes().expect("failed to serialise request");
@@ -72,24 +95,28 @@ impl Transport for TlsTransport {
 
         info!("Sending {} bytes of data to {}", bytes.len(), self.addr);
 
+        let start = Instant::now();
         stream.write_all(&bytes).await?;
         debug!("Sent");
+        hexdump::log_sent("TLS", &self.addr, &bytes[2..]);
 
         info!("Waiting to receive...");
         let mut buf = [0; 4096];
-        let len = stream.read(&mut buf).await?;
+        let len = limit(timeouts.response, Phase::Response, stream.read(&mut buf)).await?;
 
         // Remember to deal with the length again.
         info!("Received {} bytes of data", buf.len());
+        hexdump::log_received("TLS", &self.addr, &buf[2..len], start.elapsed());
         let response = Response::from_bytes(&buf[2..len])?;
 
-        Ok(response)
+        Ok((response, Details { size: len - 2, over_udp: false, http: None, message: buf[2..len].to_vec(), round_trip: Some(RoundTrip::since(start)), address: Some(addr), ip_ttl: None }))
     }
-}
 
-impl TlsTransport {
     fn sni_domain(&self) -> &str {
-        if let Some(colon_index) = self.addr.find(':') {
+        if let Some(name) = &self.sni {
+            name
+        }
+        else if let Some(colon_index) = self.addr.find(':') {
             &self.addr[.. colon_index]
         }
         else {
@@ -1,11 +1,18 @@
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Instant;
 
 use async_trait::async_trait;
 use log::*;
 use tokio::net::UdpSocket;
 
 use dns::{Request, Response};
-use super::{Transport, Error};
+use super::{Transport, Error, Details, Timeouts, Phase};
+use super::addresses;
+use super::hops;
+use super::receive::Receiver;
+use super::timeouts::limit;
+use super::timestamps::{self, Timer};
+use super::hexdump;
 
 
 /// The **UDP transport**, which uses the stdlib.
@@ -13,7 +20,7 @@ use super::{Transport, Error};
 /// # Examples
 ///
 /// ```no_run
-/// use dns_transport::{Transport, UdpTransport};
+/// use dns_transport::{Transport, UdpTransport, Timeouts};
 /// use dns::{Request, Flags, Query, QClass, qtype, record::NS};
 ///
 /// let query = Query {
@@ -27,52 +34,97 @@ use super::{Transport, Error};
 ///     flags: Flags::query(),
 ///     queries: vec![ query ],
 ///     additional: None,
+///     signature: None,
 /// };
 ///
-/// let transport = UdpTransport::new("8.8.8.8");
+/// let transport = UdpTransport::new("8.8.8.8", Timeouts::default());
 /// transport.send(&request);
 /// ```
 #[derive(Debug)]
 pub struct UdpTransport {
     addr: String,
+    timeouts: Timeouts,
+    ip_ttl: Option<u8>,
+    kernel_timestamps: bool,
 }
 
 impl UdpTransport {
 
-    /// Creates a new UDP transport that connects to the given host.
-    pub fn new(sa: impl Into<String>) -> Self {
+    /// Creates a new UDP transport that connects to the given host, giving
+    /// up on any phase that takes longer than its timeout.
+    pub fn new(sa: impl Into<String>, timeouts: Timeouts) -> Self {
         let addr = sa.into();
-        Self { addr }
+        Self { addr, timeouts, ip_ttl: None, kernel_timestamps: false }
+    }
+
+    /// Sends requests with the given IP TTL, or IPv6 hop limit, so they
+    /// only get so many hops away before being dropped, and reads the TTL
+    /// that responses arrive with where the system can report it.
+    #[must_use]
+    pub fn with_ip_ttl(mut self, ttl: u8) -> Self {
+        self.ip_ttl = Some(ttl);
+        self
+    }
+
+    /// Times round trips with the times the kernel sent the request and
+    /// received the response, where the system can report them.
+    #[must_use]
+    pub fn with_kernel_timestamps(mut self) -> Self {
+        self.kernel_timestamps = true;
+        self
     }
 }
 
 
 #[async_trait]
 impl Transport for UdpTransport {
-    async fn send(&self, request: &Request) -> Result<Response, Error> {
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let addrs = addresses::lookup(&self.addr, 53).await?;
+        addresses::try_each(&addrs, self.timeouts, |addr, timeouts| self.send_to(request, addr, timeouts)).await
+    }
+}
+
+impl UdpTransport {
+
+    /// Sends the request to one of the nameserver’s addresses.
+    async fn send_to(&self, request: &Request, addr: SocketAddr, timeouts: Timeouts) -> Result<(Response, Details), Error> {
         info!("Opening UDP socket");
-        let mut socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        let local: SocketAddr = if addr.is_ipv6() { (Ipv6Addr::UNSPECIFIED, 0).into() } else { (Ipv4Addr::UNSPECIFIED, 0).into() };
+        let socket = std::net::UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
 
-        if self.addr.contains(':') {
-            socket.connect(&*self.addr).await?;
-        }
-        else {
-            socket.connect((&*self.addr, 53)).await?;
+        // The socket’s options get set while it’s still a standard library
+        // socket, which nix can be given safely, before tokio takes it over
+        if let Some(ttl) = self.ip_ttl {
+            hops::set_limit(&socket, addr, ttl)?;
         }
 
+        let reporting_ttls = self.ip_ttl.is_some() && hops::report(&socket, addr);
+        let kernel_timestamps = self.kernel_timestamps && timestamps::enable(&socket);
+        let receiver = Receiver::start(&socket, reporting_ttls || kernel_timestamps);
+
+        let mut socket = UdpSocket::from_std(socket)?;
+        limit(timeouts.connect, Phase::Connect, socket.connect(addr)).await?;
+
         let bytes = request.to_bytes().expect("failed to serialise request");
-        info!("Sending {} bytes of data to {} over UDP", bytes.len(), self.addr);
+        info!("Sending {} bytes of data to {} over UDP", bytes.len(), addr);
 
+        let start = Instant::now();
+        let timer = Timer::start(kernel_timestamps);
         let len = socket.send(&bytes).await?;
         debug!("Sent {} bytes", len);
+        hexdump::log_sent("UDP", &self.addr, &bytes);
 
         info!("Waiting to receive...");
-        let mut buf = vec![0; 1024];
-        let len = socket.recv(&mut buf).await?;
+        let mut buf = vec![0; 65_535];
+        let packet = limit(timeouts.response, Phase::Response, receiver.recv(&mut socket, &mut buf)).await?;
+        let round_trip = timer.stop(&socket, packet.timestamp);
+        let (len, ip_ttl) = (packet.len, packet.ttl);
 
         info!("Received {} bytes of data", len);
+        hexdump::log_received("UDP", &self.addr, &buf[..len], start.elapsed());
         let response = Response::from_bytes(&buf[..len])?;
 
-        Ok(response)
+        Ok((response, Details { size: len, over_udp: true, http: None, message: buf[..len].to_vec(), round_trip: Some(round_trip), address: Some(addr), ip_ttl }))
     }
 }
@@ -29,6 +29,6 @@ pub use self::types::*;
 mod strings;
 
 mod wire;
-pub use self::wire::{Wire, WireError, find_qtype_number};
+pub use self::wire::{Wire, WireError, find_qtype_number, qtype_names};
 
 pub mod record;
@@ -1,4 +1,5 @@
 use crate::wire::*;
+use crate::record::validate::InvalidRecord;
 
 
 /// A **CAA** record. These allow domain names to specify which Certificate
@@ -20,6 +21,25 @@ pub struct CAA {
     pub value: String,
 }
 
+impl CAA {
+
+    /// Creates a new CAA record, checking that the tag is between 1 and 255
+    /// ASCII letters and digits, and that the tag and value fit in one
+    /// record.
+    pub fn new(critical: bool, tag: &str, value: &str) -> Result<Self, InvalidRecord> {
+        if tag.is_empty() || tag.len() > 255 || ! tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(InvalidRecord::BadTag(tag.into()));
+        }
+
+        let data_length = 2 + tag.len() + value.len();
+        if data_length > usize::from(u16::MAX) {
+            return Err(InvalidRecord::DataTooLong(data_length));
+        }
+
+        Ok(Self { critical, tag: tag.into(), value: value.into() })
+    }
+}
+
 impl Wire for CAA {
     const NAME: &'static str = "CAA";
     const RR_TYPE: u16 = 257;
@@ -69,4 +89,18 @@ mod test {
         assert_eq!(CAA::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(CAA::new(false, "issue", "letsencrypt.org"),
+                   Ok(CAA { critical: false, tag: "issue".into(), value: "letsencrypt.org".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_tag() {
+        assert_eq!(CAA::new(true, "issue wild", "letsencrypt.org"),
+                   Err(InvalidRecord::BadTag("issue wild".into())));
+        assert_eq!(CAA::new(true, "", "letsencrypt.org"),
+                   Err(InvalidRecord::BadTag(String::new())));
+    }
 }
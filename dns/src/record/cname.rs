@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name};
 
 
 /// A **CNAME** _(canonical name)_ record, which aliases one domain to another.
@@ -14,6 +15,16 @@ pub struct CNAME {
     pub domain: String,
 }
 
+impl CNAME {
+
+    /// Creates a new CNAME record, checking that the domain is a domain name
+    /// that can be written to the wire.
+    pub fn new(domain: &str) -> Result<Self, InvalidRecord> {
+        check_name(domain)?;
+        Ok(Self { domain: domain.into() })
+    }
+}
+
 impl Wire for CNAME {
     const NAME: &'static str = "CNAME";
     const RR_TYPE: u16 = 5;
@@ -44,5 +55,16 @@ mod test {
         assert_eq!(CNAME::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
-}
 
+    #[test]
+    fn new() {
+        assert_eq!(CNAME::new("bsago.me."),
+                   Ok(CNAME { domain: "bsago.me.".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_domain() {
+        assert_eq!(CNAME::new(".bsago.me."),
+                   Err(InvalidRecord::BadLabel(".bsago.me.".into())));
+    }
+}
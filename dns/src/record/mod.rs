@@ -20,13 +20,19 @@ mod ns;
 pub use self::ns::NS;
 
 mod opt;
-pub use self::opt::OPT;
+pub use self::opt::{OPT, ExtendedError};
 
 mod ptr;
 pub use self::ptr::PTR;
 
+mod rrsig;
+pub use self::rrsig::RRSIG;
+
+mod sig;
+pub use self::sig::SIG;
+
 mod soa;
-pub use self::soa::SOA;
+pub use self::soa::{SOA, TimerProblem};
 
 mod srv;
 pub use self::srv::SRV;
@@ -36,7 +42,10 @@ pub use self::txt::TXT;
 
 
 mod others;
-pub use self::others::{UnknownQtype, find_other_qtype_number};
+pub use self::others::{UnknownQtype, find_other_qtype_number, other_qtype_names};
+
+mod validate;
+pub use self::validate::{InvalidRecord, check_name};
 
 
 /// A record that’s been parsed from a byte buffer.
@@ -66,6 +75,12 @@ pub enum Record {
     /// A **PTR** record.
     PTR(PTR),
 
+    /// A **RRSIG** record.
+    RRSIG(RRSIG),
+
+    /// A **SIG** record.
+    SIG(SIG),
+
     /// A **SOA** record.
     SOA(SOA),
 
@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name};
 
 use log::{warn, debug};
 
@@ -21,6 +22,16 @@ pub struct MX {
     pub exchange: String,
 }
 
+impl MX {
+
+    /// Creates a new MX record, checking that the exchange is a domain name
+    /// that can be written to the wire.
+    pub fn new(preference: u16, exchange: &str) -> Result<Self, InvalidRecord> {
+        check_name(exchange)?;
+        Ok(Self { preference, exchange: exchange.into() })
+    }
+}
+
 impl Wire for MX {
     const NAME: &'static str = "MX";
     const RR_TYPE: u16 = 15;
@@ -62,4 +73,16 @@ mod test {
         assert_eq!(MX::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(MX::new(10, "mail.lookup.dog."),
+                   Ok(MX { preference: 10, exchange: "mail.lookup.dog.".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_exchange() {
+        assert_eq!(MX::new(10, "mail..lookup.dog."),
+                   Err(InvalidRecord::BadLabel("mail..lookup.dog.".into())));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name};
 
 use log::{warn, debug};
 
@@ -17,6 +18,16 @@ pub struct NS {
     pub nameserver: String,
 }
 
+impl NS {
+
+    /// Creates a new NS record, checking that the nameserver is a domain
+    /// name that can be written to the wire.
+    pub fn new(nameserver: &str) -> Result<Self, InvalidRecord> {
+        check_name(nameserver)?;
+        Ok(Self { nameserver: nameserver.into() })
+    }
+}
+
 impl Wire for NS {
     const NAME: &'static str = "NS";
     const RR_TYPE: u16 = 2;
@@ -57,4 +68,17 @@ mod test {
         assert_eq!(NS::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(NS::new("ns1.lookup.dog."),
+                   Ok(NS { nameserver: "ns1.lookup.dog.".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_nameserver() {
+        let nameserver = format!("{}.lookup.dog.", "a".repeat(64));
+        assert_eq!(NS::new(&nameserver),
+                   Err(InvalidRecord::BadLabel(nameserver.clone())));
+    }
 }
@@ -45,11 +45,31 @@ pub struct OPT {
     pub data: Vec<u8>,
 }
 
+/// An **Extended DNS Error**, which a nameserver can put in the OPT record
+/// of a response to say more about why it failed than the rcode can.
+///
+/// # References
+///
+/// - [RFC 8914](https://tools.ietf.org/html/rfc8914) — Extended DNS Errors (October 2020)
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExtendedError {
+
+    /// The number saying what went wrong.
+    pub info_code: u16,
+
+    /// Any text the nameserver included to explain it further.
+    pub extra_text: String,
+}
+
 impl OPT {
 
     /// The record type number associated with OPT.
     pub const RR_TYPE: u16 = 41;
 
+    /// The flag that asks for DNSSEC records, such as RRSIGs, to be
+    /// included in the response (RFC 3225 §3).
+    pub const DNSSEC_OK: u16 = 0b_1000_0000_0000_0000;
+
     /// Reads from the given cursor to parse an OPT record.
     ///
     /// The buffer will have slightly more bytes to read for an OPT record
@@ -91,6 +111,32 @@ impl OPT {
 
         Ok(bytes)
     }
+
+    /// The EDNS option code of an Extended DNS Error.
+    pub const EXTENDED_ERROR_CODE: u16 = 15;
+
+    /// Finds the first Extended DNS Error among the options in this
+    /// record’s data, if there is one. Options that run past the end of the
+    /// data are ignored.
+    pub fn extended_error(&self) -> Option<ExtendedError> {
+        let mut rest = &self.data[..];
+
+        while rest.len() >= 4 {
+            let code = u16::from_be_bytes([ rest[0], rest[1] ]);
+            let length = usize::from(u16::from_be_bytes([ rest[2], rest[3] ]));
+            let option = rest.get(4 .. 4 + length)?;
+
+            if code == Self::EXTENDED_ERROR_CODE && length >= 2 {
+                let info_code = u16::from_be_bytes([ option[0], option[1] ]);
+                let extra_text = String::from_utf8_lossy(&option[2..]).into_owned();
+                return Some(ExtendedError { info_code, extra_text });
+            }
+
+            rest = &rest[4 + length ..];
+        }
+
+        None
+    }
 }
 
 
@@ -112,6 +158,29 @@ mod test {
                    });
     }
 
+    #[test]
+    fn extended_error() {
+        let opt = OPT {
+            udp_payload_size: 1232,
+            higher_bits: 0,
+            edns0_version: 0,
+            flags: 0,
+            data: vec![
+                0x00, 0x0A, 0x00, 0x02, 0xAB, 0xCD,  // some other option first
+                0x00, 0x0F, 0x00, 0x05, 0x00, 0x09, 0x64, 0x6F, 0x67,
+            ],
+        };
+
+        assert_eq!(opt.extended_error(),
+                   Some(ExtendedError { info_code: 9, extra_text: "dog".into() }));
+    }
+
+    #[test]
+    fn extended_error_cut_short() {
+        let opt = OPT { udp_payload_size: 1232, higher_bits: 0, edns0_version: 0, flags: 0, data: vec![ 0x00, 0x0F, 0x00, 0x05, 0x00 ] };
+        assert_eq!(opt.extended_error(), None);
+    }
+
     #[test]
     fn empty() {
         assert_eq!(OPT::read(&mut Cursor::new(&[])),
@@ -36,6 +36,12 @@ pub fn find_other_qtype_number(name: &str) -> Option<u16> {
     TYPES.iter().find(|t| t.0 == name).map(|t| t.1)
 }
 
+/// Returns the names of every record type dog knows about, but doesn’t know
+/// how to parse.
+pub fn other_qtype_names() -> impl Iterator<Item=&'static str> {
+    TYPES.iter().map(|t| t.0)
+}
+
 /// Mapping of record type names to their assigned numbers.
 static TYPES: &[(&str, u16)] = &[
     ("AFSDB",      18),
@@ -49,10 +55,11 @@ static TYPES: &[(&str, u16)] = &[
     ("DHCID",      49),
     ("DLV",     32769),
     ("DNAME",      39),
-    ("DNSKEEYE",   48),
+    ("DNSKEY",     48),
     ("DS",         43),
     ("HINFO",      13),
     ("HIP",        55),
+    ("HTTPS",      65),
     ("IPSECKEY",   45),
     ("IXFR",      251),
     ("KEY",        25),
@@ -63,11 +70,10 @@ static TYPES: &[(&str, u16)] = &[
     ("NSEC3",      50),
     ("NSEC3PARAM", 51),
     ("OPENPGPKEY", 61),
-    ("RRSIG",      46),
     ("RP",         17),
-    ("SIG",        24),
     ("SMIMEA",     53),
     ("SSHFP",      44),
+    ("SVCB",       64),
     ("TA",      32768),
     ("TKEY",      249),
     ("TLSA",       52),
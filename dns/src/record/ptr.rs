@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name};
 
 
 /// A **PTR** record, which holds a _pointer_ to a canonical name. This is
@@ -20,6 +21,16 @@ pub struct PTR {
     pub cname: String,
 }
 
+impl PTR {
+
+    /// Creates a new PTR record, checking that the name it points to is a
+    /// domain name that can be written to the wire.
+    pub fn new(cname: &str) -> Result<Self, InvalidRecord> {
+        check_name(cname)?;
+        Ok(Self { cname: cname.into() })
+    }
+}
+
 impl Wire for PTR {
     const NAME: &'static str = "PTR";
     const RR_TYPE: u16 = 12;
@@ -51,4 +62,17 @@ mod test {
         assert_eq!(PTR::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(PTR::new("dns.google."),
+                   Ok(PTR { cname: "dns.google.".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_name() {
+        let cname = format!("{0}.{0}.{0}.{0}.google.", "a".repeat(63));
+        assert_eq!(PTR::new(&cname),
+                   Err(InvalidRecord::NameTooLong(cname.clone())));
+    }
 }
@@ -0,0 +1,141 @@
+use std::convert::TryFrom;
+
+use crate::strings::ReadLabels;
+use crate::wire::*;
+
+use log::{warn, debug};
+
+
+/// A **RRSIG** _(resource record signature)_ record, which contains the
+/// DNSSEC signature over a set of records with the same name, class, and type.
+///
+/// The inception and expiration times are 32-bit numbers of seconds since the
+/// Unix epoch, compared using serial number arithmetic, so they wrap around in
+/// the year 2106 rather than overflowing.
+///
+/// # References
+///
+/// - [RFC 4034 §3](https://tools.ietf.org/html/rfc4034) — Resource Records for the DNS Security Extensions (March 2005)
+#[derive(PartialEq, Debug, Clone)]
+pub struct RRSIG {
+
+    /// The type of the records being signed.
+    pub type_covered: u16,
+
+    /// The number of the algorithm used to produce the signature.
+    pub algorithm: u8,
+
+    /// The number of labels in the signed owner name, not counting the root
+    /// label or a leading wildcard label.
+    pub labels: u8,
+
+    /// The TTL of the signed records, as it appears in the zone.
+    pub original_ttl: u32,
+
+    /// The time after which this signature is no longer valid, in seconds
+    /// since the Unix epoch.
+    pub signature_expiration: u32,
+
+    /// The time before which this signature is not yet valid, in seconds
+    /// since the Unix epoch.
+    pub signature_inception: u32,
+
+    /// The key tag of the DNSKEY that produced the signature.
+    pub key_tag: u16,
+
+    /// The domain name of the zone whose key produced the signature.
+    pub signer_name: String,
+
+    /// The signature itself.
+    pub signature: Vec<u8>,
+}
+
+impl Wire for RRSIG {
+    const NAME: &'static str = "RRSIG";
+    const RR_TYPE: u16 = 46;
+
+    fn read(len: u16, c: &mut Cursor<&[u8]>) -> Result<Self, WireError> {
+        let start = c.position();
+
+        let type_covered         = c.read_u16::<BigEndian>()?;
+        let algorithm            = c.read_u8()?;
+        let labels               = c.read_u8()?;
+        let original_ttl         = c.read_u32::<BigEndian>()?;
+        let signature_expiration = c.read_u32::<BigEndian>()?;
+        let signature_inception  = c.read_u32::<BigEndian>()?;
+        let key_tag              = c.read_u16::<BigEndian>()?;
+        let signer_name          = c.read_labels()?;
+
+        let read_length = c.position() - start;
+        if read_length > u64::from(len) {
+            warn!("Expected length {} but already read {} bytes", len, read_length);
+            let got = u16::try_from(read_length).unwrap_or(u16::MAX);
+            return Err(WireError::WrongLength { expected: len, got });
+        }
+
+        let signature_length = u64::from(len) - read_length;
+        debug!("Reading {} bytes of signature", signature_length);
+
+        let mut signature = Vec::new();
+        for _ in 0 .. signature_length {
+            signature.push(c.read_u8()?);
+        }
+
+        Ok(RRSIG {
+            type_covered, algorithm, labels, original_ttl, signature_expiration,
+            signature_inception, key_tag, signer_name, signature,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let buf = &[
+            0x00, 0x01,              // type covered
+            0x0d,                    // algorithm
+            0x02,                    // labels
+            0x00, 0x00, 0x0e, 0x10,  // original TTL
+            0x5e, 0xcd, 0x01, 0x2c,  // expiration
+            0x5e, 0xcc, 0xff, 0x00,  // inception
+            0x30, 0x39,              // key tag
+            0x03, 0x64, 0x6f, 0x67, 0x00,  // signer name
+            0xAB, 0xCD, 0xEF,        // signature
+        ];
+
+        assert_eq!(RRSIG::read(26, &mut Cursor::new(buf)).unwrap(),
+                   RRSIG {
+                       type_covered: 1,
+                       algorithm: 13,
+                       labels: 2,
+                       original_ttl: 3600,
+                       signature_expiration: 1590493484,
+                       signature_inception: 1590492928,
+                       key_tag: 12345,
+                       signer_name: String::from("dog."),
+                       signature: vec![ 0xAB, 0xCD, 0xEF ],
+                   });
+    }
+
+    #[test]
+    fn name_too_long() {
+        let buf = &[
+            0x00, 0x01, 0x0d, 0x02, 0x00, 0x00, 0x0e, 0x10,
+            0x5e, 0xcd, 0x01, 0x2c, 0x5e, 0xcc, 0xff, 0x00,
+            0x30, 0x39, 0x03, 0x64, 0x6f, 0x67, 0x00,
+        ];
+
+        assert_eq!(RRSIG::read(20, &mut Cursor::new(buf)),
+                   Err(WireError::WrongLength { expected: 20, got: 23 }));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(RRSIG::read(0, &mut Cursor::new(&[])),
+                   Err(WireError::IO));
+    }
+}
@@ -0,0 +1,201 @@
+use std::convert::TryFrom;
+use std::io;
+
+use crate::strings::{ReadLabels, WriteLabels};
+use crate::wire::*;
+
+use log::{warn, debug};
+
+
+/// A **SIG** _(signature)_ record, which contains a cryptographic signature.
+///
+/// These days, the only use of SIG is for **SIG(0)** transaction signatures,
+/// where a SIG record with a type-covered field of zero is appended to the
+/// Additional section of a message and signs the entire message that comes
+/// before it. (DNSSEC signatures over record sets use RRSIG instead.)
+///
+/// # References
+///
+/// - [RFC 2535 §4.1](https://tools.ietf.org/html/rfc2535) — Domain Name System Security Extensions (March 1999)
+/// - [RFC 2931](https://tools.ietf.org/html/rfc2931) — DNS Request and Transaction Signatures (SIG(0)s) (September 2000)
+#[derive(PartialEq, Debug, Clone)]
+pub struct SIG {
+
+    /// The type of record being signed, or zero for a SIG(0) signature.
+    pub type_covered: u16,
+
+    /// The number of the algorithm used to produce the signature.
+    pub algorithm: u8,
+
+    /// The number of labels in the signed owner name, or zero for SIG(0).
+    pub labels: u8,
+
+    /// The TTL of the signed records, or zero for SIG(0).
+    pub original_ttl: u32,
+
+    /// The time after which this signature is no longer valid, in seconds
+    /// since the Unix epoch.
+    pub signature_expiration: u32,
+
+    /// The time before which this signature is not yet valid, in seconds
+    /// since the Unix epoch.
+    pub signature_inception: u32,
+
+    /// The key tag of the key that produced the signature.
+    pub key_tag: u16,
+
+    /// The domain name of the owner of the key that produced the signature.
+    pub signer_name: String,
+
+    /// The signature itself.
+    pub signature: Vec<u8>,
+}
+
+impl Wire for SIG {
+    const NAME: &'static str = "SIG";
+    const RR_TYPE: u16 = 24;
+
+    fn read(len: u16, c: &mut Cursor<&[u8]>) -> Result<Self, WireError> {
+        let start = c.position();
+
+        let type_covered         = c.read_u16::<BigEndian>()?;
+        let algorithm            = c.read_u8()?;
+        let labels               = c.read_u8()?;
+        let original_ttl         = c.read_u32::<BigEndian>()?;
+        let signature_expiration = c.read_u32::<BigEndian>()?;
+        let signature_inception  = c.read_u32::<BigEndian>()?;
+        let key_tag              = c.read_u16::<BigEndian>()?;
+        let signer_name          = c.read_labels()?;
+
+        let read_length = c.position() - start;
+        if read_length > u64::from(len) {
+            warn!("Expected length {} but already read {} bytes", len, read_length);
+            let got = u16::try_from(read_length).unwrap_or(u16::MAX);
+            return Err(WireError::WrongLength { expected: len, got });
+        }
+
+        let signature_length = u64::from(len) - read_length;
+        debug!("Reading {} bytes of signature", signature_length);
+
+        let mut signature = Vec::new();
+        for _ in 0 .. signature_length {
+            signature.push(c.read_u8()?);
+        }
+
+        Ok(SIG {
+            type_covered, algorithm, labels, original_ttl, signature_expiration,
+            signature_inception, key_tag, signer_name, signature,
+        })
+    }
+}
+
+impl SIG {
+
+    /// The class that SIG(0) records are sent with, **ANY**, as they are
+    /// not associated with any particular class of data.
+    pub const SIG0_CLASS: u16 = 255;
+
+    /// Serialises every field of this record’s data _except_ the signature.
+    ///
+    /// This is the first half of the data that gets signed when producing a
+    /// SIG(0) signature; the second half is the message itself, without the
+    /// SIG record (RFC 2931 §3.1).
+    pub fn to_unsigned_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(32);
+
+        bytes.write_u16::<BigEndian>(self.type_covered)?;
+        bytes.write_u8(self.algorithm)?;
+        bytes.write_u8(self.labels)?;
+        bytes.write_u32::<BigEndian>(self.original_ttl)?;
+        bytes.write_u32::<BigEndian>(self.signature_expiration)?;
+        bytes.write_u32::<BigEndian>(self.signature_inception)?;
+        bytes.write_u16::<BigEndian>(self.key_tag)?;
+        bytes.write_labels(&self.signer_name)?;
+
+        Ok(bytes)
+    }
+
+    /// Serialises this SIG record into a vector of bytes, including its
+    /// data length and signature, but without its name, type, class, or TTL.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut data = self.to_unsigned_bytes()?;
+        data.extend(&self.signature);
+
+        let mut bytes = Vec::with_capacity(data.len() + 2);
+        bytes.write_u16::<BigEndian>(data.len() as u16)?;
+        bytes.extend(data);
+
+        Ok(bytes)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let buf = &[
+            0x00, 0x00,              // type covered
+            0x0f,                    // algorithm
+            0x00,                    // labels
+            0x00, 0x00, 0x00, 0x00,  // original TTL
+            0x5e, 0xcd, 0x01, 0x2c,  // expiration
+            0x5e, 0xcc, 0xff, 0x00,  // inception
+            0x30, 0x39,              // key tag
+            0x03, 0x64, 0x6f, 0x67, 0x00,  // signer name
+            0xAB, 0xCD, 0xEF,        // signature
+        ];
+
+        assert_eq!(SIG::read(26, &mut Cursor::new(buf)).unwrap(),
+                   SIG {
+                       type_covered: 0,
+                       algorithm: 15,
+                       labels: 0,
+                       original_ttl: 0,
+                       signature_expiration: 1590493484,
+                       signature_inception: 1590492928,
+                       key_tag: 12345,
+                       signer_name: String::from("dog."),
+                       signature: vec![ 0xAB, 0xCD, 0xEF ],
+                   });
+    }
+
+    #[test]
+    fn round_trip() {
+        let sig = SIG {
+            type_covered: 0,
+            algorithm: 15,
+            labels: 0,
+            original_ttl: 0,
+            signature_expiration: 1590493484,
+            signature_inception: 1590492928,
+            key_tag: 12345,
+            signer_name: String::from("dog."),
+            signature: vec![ 0xAB, 0xCD, 0xEF ],
+        };
+
+        let bytes = sig.to_bytes().unwrap();
+        assert_eq!(&bytes[.. 2], &[ 0x00, 26 ]);
+        assert_eq!(SIG::read(26, &mut Cursor::new(&bytes[2 ..])).unwrap(), sig);
+    }
+
+    #[test]
+    fn name_too_long() {
+        let buf = &[
+            0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x5e, 0xcd, 0x01, 0x2c, 0x5e, 0xcc, 0xff, 0x00,
+            0x30, 0x39, 0x03, 0x64, 0x6f, 0x67, 0x00,
+        ];
+
+        assert_eq!(SIG::read(20, &mut Cursor::new(buf)),
+                   Err(WireError::WrongLength { expected: 20, got: 23 }));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(SIG::read(0, &mut Cursor::new(&[])),
+                   Err(WireError::IO));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name, check_time};
 
 use log::{warn, debug};
 
@@ -41,6 +42,72 @@ pub struct SOA {
     pub minimum_ttl: u32,
 }
 
+impl SOA {
+
+    /// Creates a new SOA record, checking that both names can be written to
+    /// the wire, and that each of the timers fits in 31 bits. Whether the
+    /// timers make sense together is up to `check_timers`, as a record with
+    /// odd timers is still legal to send.
+    ///
+    /// Every serial number is valid, as they wrap around.
+    pub fn new(mname: &str, rname: &str, serial: u32, refresh_interval: u32, retry_interval: u32, expire_limit: u32, minimum_ttl: u32) -> Result<Self, InvalidRecord> {
+        check_name(mname)?;
+        check_name(rname)?;
+        check_time("refresh", refresh_interval)?;
+        check_time("retry", retry_interval)?;
+        check_time("expire", expire_limit)?;
+        check_time("minimum", minimum_ttl)?;
+
+        Ok(Self { mname: mname.into(), rname: rname.into(), serial, refresh_interval, retry_interval, expire_limit, minimum_ttl })
+    }
+
+    /// Returns a copy of this record with a new serial number, checking
+    /// that secondaries will see it as an increase. Serial numbers are
+    /// compared using RFC 1982 arithmetic, so they can wrap around, but a
+    /// serial can’t go up by 2³¹ or more at once.
+    pub fn with_serial(&self, serial: u32) -> Result<Self, InvalidRecord> {
+        let increase = serial.wrapping_sub(self.serial);
+        if increase == 0 || increase >= 0x8000_0000 {
+            return Err(InvalidRecord::SerialNotIncreased { from: self.serial, to: serial });
+        }
+
+        Ok(Self { serial, .. self.clone() })
+    }
+
+    /// Returns anything about this record’s timers that doesn’t make sense
+    /// together: secondaries should retry more often than they refresh,
+    /// and keep answering for longer than both put together.
+    pub fn check_timers(&self) -> Vec<TimerProblem> {
+        let mut problems = Vec::new();
+
+        if self.retry_interval >= self.refresh_interval {
+            problems.push(TimerProblem::RetryNotShorterThanRefresh);
+        }
+
+        if u64::from(self.expire_limit) <= u64::from(self.refresh_interval) + u64::from(self.retry_interval) {
+            problems.push(TimerProblem::ExpiresTooSoon);
+        }
+
+        problems
+    }
+}
+
+
+/// Something about a SOA record’s timers that’s legal, but is almost
+/// certainly a mistake.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TimerProblem {
+
+    /// The retry interval isn’t shorter than the refresh interval, so a
+    /// secondary that fails to refresh won’t try again any sooner.
+    RetryNotShorterThanRefresh,
+
+    /// The expiry limit isn’t longer than the refresh and retry intervals
+    /// put together, so a secondary could stop answering for the zone
+    /// before it has had a chance to retry.
+    ExpiresTooSoon,
+}
+
 impl Wire for SOA {
     const NAME: &'static str = "SOA";
     const RR_TYPE: u16 = 6;
@@ -104,4 +171,79 @@ mod test {
         assert_eq!(SOA::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(SOA::new("ns1.lookup.dog.", "hostmaster.lookup.dog.", 2_020_052_600, 7200, 3600, 1_209_600, 300),
+                   Ok(SOA {
+                       mname: "ns1.lookup.dog.".into(),
+                       rname: "hostmaster.lookup.dog.".into(),
+                       serial: 2_020_052_600,
+                       refresh_interval: 7200,
+                       retry_interval: 3600,
+                       expire_limit: 1_209_600,
+                       minimum_ttl: 300,
+                   }));
+    }
+
+    #[test]
+    fn consistent_timers() {
+        assert_eq!(soa(1).check_timers(), Vec::new());
+    }
+
+    #[test]
+    fn retry_after_refresh() {
+        let soa = SOA::new("ns1.lookup.dog.", "hostmaster.lookup.dog.", 1, 3600, 7200, 1_209_600, 300).unwrap();
+        assert_eq!(soa.check_timers(), vec![ TimerProblem::RetryNotShorterThanRefresh ]);
+    }
+
+    #[test]
+    fn short_expiry() {
+        let soa = SOA::new("ns1.lookup.dog.", "hostmaster.lookup.dog.", 1, 7200, 3600, 7200, 300).unwrap();
+        assert_eq!(soa.check_timers(), vec![ TimerProblem::ExpiresTooSoon ]);
+    }
+
+    #[test]
+    fn huge_timers_from_the_wire() {
+        let soa = SOA { refresh_interval: u32::MAX, retry_interval: 1, expire_limit: u32::MAX, .. soa(1) };
+        assert_eq!(soa.check_timers(), vec![ TimerProblem::ExpiresTooSoon ]);
+    }
+
+    #[test]
+    fn new_with_huge_minimum() {
+        assert_eq!(SOA::new("ns1.lookup.dog.", "hostmaster.lookup.dog.", 1, 7200, 3600, 1_209_600, 0x8000_0000),
+                   Err(InvalidRecord::TimeTooLarge("minimum")));
+    }
+
+    fn soa(serial: u32) -> SOA {
+        SOA::new("ns1.lookup.dog.", "hostmaster.lookup.dog.", serial, 7200, 3600, 1_209_600, 300).unwrap()
+    }
+
+    #[test]
+    fn serial_increased() {
+        assert_eq!(soa(41).with_serial(42), Ok(soa(42)));
+    }
+
+    #[test]
+    fn serial_wraps_around() {
+        assert_eq!(soa(0xFFFF_FFF0).with_serial(5), Ok(soa(5)));
+    }
+
+    #[test]
+    fn serial_unchanged() {
+        assert_eq!(soa(42).with_serial(42),
+                   Err(InvalidRecord::SerialNotIncreased { from: 42, to: 42 }));
+    }
+
+    #[test]
+    fn serial_decreased() {
+        assert_eq!(soa(42).with_serial(41),
+                   Err(InvalidRecord::SerialNotIncreased { from: 42, to: 41 }));
+    }
+
+    #[test]
+    fn serial_increased_too_far() {
+        assert_eq!(soa(0).with_serial(0x8000_0000),
+                   Err(InvalidRecord::SerialNotIncreased { from: 0, to: 0x8000_0000 }));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::strings::ReadLabels;
 use crate::wire::*;
+use crate::record::validate::{InvalidRecord, check_name};
 
 use log::{debug, warn};
 
@@ -28,6 +29,16 @@ pub struct SRV {
     pub target: String,
 }
 
+impl SRV {
+
+    /// Creates a new SRV record, checking that the target is a domain name
+    /// that can be written to the wire.
+    pub fn new(priority: u16, weight: u16, port: u16, target: &str) -> Result<Self, InvalidRecord> {
+        check_name(target)?;
+        Ok(Self { priority, weight, port, target: target.into() })
+    }
+}
+
 impl Wire for SRV {
     const NAME: &'static str = "SRV";
     const RR_TYPE: u16 = 33;
@@ -76,4 +87,16 @@ mod test {
         assert_eq!(SRV::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(SRV::new(1, 1, 37500, "ata.local.node.dc1.consul."),
+                   Ok(SRV { priority: 1, weight: 1, port: 37500, target: "ata.local.node.dc1.consul.".into() }));
+    }
+
+    #[test]
+    fn new_with_bad_target() {
+        assert_eq!(SRV::new(1, 1, 37500, "ata..consul."),
+                   Err(InvalidRecord::BadLabel("ata..consul.".into())));
+    }
 }
@@ -1,4 +1,5 @@
 use crate::wire::*;
+use crate::record::validate::InvalidRecord;
 
 use log::*;
 
@@ -20,6 +21,22 @@ pub struct TXT {
     pub message: String,
 }
 
+impl TXT {
+
+    /// Creates a new TXT record, checking that the message fits in one
+    /// record. The message gets split into strings of 255 bytes, each of
+    /// which has a length byte in front of it, and the whole thing has to
+    /// fit in the 65,535 bytes a record’s data can be.
+    pub fn new(message: &str) -> Result<Self, InvalidRecord> {
+        let data_length = message.len() + message.len() / 255 + 1;
+        if data_length > usize::from(u16::MAX) {
+            return Err(InvalidRecord::DataTooLong(data_length));
+        }
+
+        Ok(Self { message: message.into() })
+    }
+}
+
 impl Wire for TXT {
     const NAME: &'static str = "TXT";
     const RR_TYPE: u16 = 16;
@@ -76,4 +93,25 @@ mod test {
         assert_eq!(TXT::read(0, &mut Cursor::new(&[])),
                    Err(WireError::IO));
     }
+
+    #[test]
+    fn new() {
+        assert_eq!(TXT::new("v=spf1 -all"),
+                   Ok(TXT { message: "v=spf1 -all".into() }));
+    }
+
+    #[test]
+    fn new_at_the_limit() {
+        // 65,279 bytes of text take 255 full strings and one more with 254
+        // bytes in it, so 256 length bytes, making 65,535 bytes in total
+        assert!(TXT::new(&"a".repeat(65_279)).is_ok());
+    }
+
+    #[test]
+    fn new_over_the_limit() {
+        // One more byte fills the last string, so an empty one has to go
+        // after it to end the message
+        assert_eq!(TXT::new(&"a".repeat(65_280)),
+                   Err(InvalidRecord::DataTooLong(65_537)));
+    }
 }
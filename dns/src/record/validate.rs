@@ -0,0 +1,129 @@
+//! Checking the data of records being built by hand, so that nothing
+//! illegal can be put on the wire.
+
+use crate::strings::split_labels;
+
+
+/// Something wrong with the data passed to one of the record constructors,
+/// which would make the record illegal to send.
+#[derive(PartialEq, Debug, Clone)]
+pub enum InvalidRecord {
+
+    /// A domain name would be longer than 255 bytes on the wire. Contains
+    /// the name.
+    NameTooLong(String),
+
+    /// A domain name has a label that is empty, or longer than 63 bytes.
+    /// Contains the name.
+    BadLabel(String),
+
+    /// A number of seconds has its top bit set, which RFC 2181 says has to
+    /// be treated as zero. Contains the name of the field.
+    TimeTooLarge(&'static str),
+
+    /// A new SOA serial isn’t greater than the old one, or is so much
+    /// greater that secondaries would see it as going backwards.
+    SerialNotIncreased {
+
+        /// The serial being replaced.
+        from: u32,
+
+        /// The new serial.
+        to: u32,
+    },
+
+    /// The record data would be longer than the 65,535 bytes its length
+    /// field can hold. Contains the length it would have been.
+    DataTooLong(usize),
+
+    /// A CAA tag is empty, longer than 255 bytes, or contains something
+    /// other than ASCII letters and digits. Contains the tag.
+    BadTag(String),
+}
+
+
+/// Checks that a domain name can be written to the wire: each label has to
+/// be between 1 and 63 bytes, and the whole name at most 255 bytes. A single
+/// trailing dot is allowed, as is the root name.
+pub fn check_name(name: &str) -> Result<(), InvalidRecord> {
+    if name.is_empty() || name == "." {
+        return Ok(());
+    }
+
+    let mut labels = split_labels(name);
+
+    // A trailing dot leaves an empty label at the end
+    if labels.last().is_some_and(Vec::is_empty) {
+        let _ = labels.pop();
+    }
+
+    if labels.iter().any(|label| label.is_empty() || label.len() > 63) {
+        return Err(InvalidRecord::BadLabel(name.into()));
+    }
+
+    // Each label has a length byte before it, and the name ends with an
+    // empty label
+    let wire_length = labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1;
+    if wire_length > 255 {
+        return Err(InvalidRecord::NameTooLong(name.into()));
+    }
+
+    Ok(())
+}
+
+/// Checks that a number of seconds fits in 31 bits.
+pub(crate) fn check_time(field: &'static str, seconds: u32) -> Result<(), InvalidRecord> {
+    if seconds > 0x7FFF_FFFF {
+        return Err(InvalidRecord::TimeTooLarge(field));
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normal_names() {
+        assert_eq!(check_name("lookup.dog"), Ok(()));
+        assert_eq!(check_name("lookup.dog."), Ok(()));
+        assert_eq!(check_name("."), Ok(()));
+        assert_eq!(check_name("with\\.dot.lookup.dog."), Ok(()));
+    }
+
+    #[test]
+    fn empty_label() {
+        assert_eq!(check_name("lookup..dog."),
+                   Err(InvalidRecord::BadLabel("lookup..dog.".into())));
+    }
+
+    #[test]
+    fn long_label() {
+        let name = format!("{}.lookup.dog.", "a".repeat(64));
+        assert_eq!(check_name(&name),
+                   Err(InvalidRecord::BadLabel(name.clone())));
+    }
+
+    #[test]
+    fn longest_name() {
+        // 63 + 63 + 63 + 61 bytes of labels, plus 4 length bytes and the
+        // terminating zero, makes 255
+        let name = format!("{0}.{0}.{0}.{1}.", "a".repeat(63), "b".repeat(61));
+        assert_eq!(check_name(&name), Ok(()));
+    }
+
+    #[test]
+    fn long_name() {
+        let name = format!("{0}.{0}.{0}.{1}.", "a".repeat(63), "b".repeat(62));
+        assert_eq!(check_name(&name),
+                   Err(InvalidRecord::NameTooLong(name.clone())));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(check_time("refresh", 0x7FFF_FFFF), Ok(()));
+        assert_eq!(check_time("refresh", 0x8000_0000), Err(InvalidRecord::TimeTooLarge("refresh")));
+    }
+}
@@ -13,6 +13,9 @@ use crate::wire::*;
 pub(crate) trait ReadLabels {
 
     /// Read and expand a compressed domain name.
+    ///
+    /// Any dots or backslashes _within_ a label get escaped with a
+    /// backslash, so they can be told apart from the dots between labels.
     fn read_labels(&mut self) -> Result<String, WireError>;
 }
 
@@ -36,15 +39,42 @@ pub(crate) trait WriteLabels {
     ///
     /// So “dns.lookup.dog” would be encoded as:
     /// “3, dns, 6, lookup, 3, dog, 0”.
+    ///
+    /// A dot or backslash preceded by a backslash is treated as part of the
+    /// label, rather than as a separator, and `\DDD` stands for the byte
+    /// with that decimal value.
+    ///
+    /// Returns an `InvalidInput` error if any label is empty, or longer than
+    /// the 63 bytes its length can hold.
     fn write_labels(&mut self, input: &str) -> io::Result<()>;
 }
 
 impl<W: Write> WriteLabels for W {
     fn write_labels(&mut self, input: &str) -> io::Result<()> {
-        for label in input.split('.') {
+        let mut labels = split_labels(input);
+
+        // A trailing dot leaves an empty label at the end, which gets
+        // written as the terminating zero; the root name is only that
+        if labels.last().is_some_and(Vec::is_empty) {
+            let _ = labels.pop();
+        }
+
+        if input == "." {
+            labels.clear();
+        }
+
+        for label in &labels {
+            if label.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Domain name {:?} has an empty label", input)));
+            }
+
+            if label.len() > 63 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Domain name {:?} has a label longer than 63 bytes", input)));
+            }
+
             self.write_u8(label.len() as u8)?;
 
-            for b in label.as_bytes() {
+            for b in label {
                 self.write_u8(*b)?;
             }
         }
@@ -55,6 +85,49 @@ impl<W: Write> WriteLabels for W {
 }
 
 
+/// Splits a domain name into the bytes of its labels, on every dot that
+/// isn’t escaped with a backslash. A backslash followed by three decimal
+/// digits, such as `\032`, stands for the byte with that value (RFC 1035
+/// §5.1); followed by anything else, it stands for that character.
+pub(crate) fn split_labels(input: &str) -> Vec<Vec<u8>> {
+    let mut labels = vec![ Vec::new() ];
+    let bytes = input.as_bytes();
+    let mut index = 0;
+
+    while let Some(&b) = bytes.get(index) {
+        index += 1;
+
+        let label = labels.last_mut().unwrap();
+        match b {
+            b'.'   => labels.push(Vec::new()),
+            b'\\'  => {
+                if let Some(byte) = bytes.get(index .. index + 3).and_then(decimal_escape) {
+                    label.push(byte);
+                    index += 3;
+                }
+                else {
+                    label.push(bytes.get(index).copied().unwrap_or(b'\\'));
+                    index += 1;
+                }
+            }
+            _      => label.push(b),
+        }
+    }
+
+    labels
+}
+
+/// Reads the three digits of a `\DDD` escape, if they are digits and make
+/// a number that fits in a byte.
+fn decimal_escape(digits: &[u8]) -> Option<u8> {
+    if ! digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    digits.iter().try_fold(0_u8, |n, d| n.checked_mul(10)?.checked_add(d - b'0'))
+}
+
+
 const RECURSION_LIMIT: usize = 8;
 
 fn read_string_recursive(name_buf: &mut Vec<u8>, c: &mut Cursor<&[u8]>, recursions: &mut Vec<u16>) -> Result<(), WireError> {
@@ -92,6 +165,9 @@ fn read_string_recursive(name_buf: &mut Vec<u8>, c: &mut Cursor<&[u8]>, recursio
         else {
             for _ in 0 .. byte {
                 let c = c.read_u8()?;
+                if c == b'.' || c == b'\\' {
+                    name_buf.push(b'\\');
+                }
                 name_buf.push(c);
             }
 
@@ -101,3 +177,57 @@ fn read_string_recursive(name_buf: &mut Vec<u8>, c: &mut Cursor<&[u8]>, recursio
 
     Ok(())
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_escaped_dots() {
+        let buf: &[u8] = &[ 0x05, b'a', b'.', b'b', b'\\', b'c', 0x03, b'd', b'o', b'g', 0x00 ];
+        assert_eq!(Cursor::new(buf).read_labels(), Ok(String::from("a\\.b\\\\c.dog.")));
+    }
+
+    #[test]
+    fn writes_escaped_dots() {
+        let mut bytes = Vec::new();
+        bytes.write_labels("a\\.b\\\\c.dog.").unwrap();
+        assert_eq!(bytes, &[ 0x05, b'a', b'.', b'b', b'\\', b'c', 0x03, b'd', b'o', b'g', 0x00 ]);
+    }
+
+    #[test]
+    fn writes_decimal_escapes() {
+        let mut bytes = Vec::new();
+        bytes.write_labels("a\\032b\\046.dog").unwrap();
+        assert_eq!(bytes, &[ 0x04, b'a', b' ', b'b', b'.', 0x03, b'd', b'o', b'g', 0x00 ]);
+    }
+
+    #[test]
+    fn writes_out_of_range_escapes_as_characters() {
+        let mut bytes = Vec::new();
+        bytes.write_labels("\\256").unwrap();
+        assert_eq!(bytes, &[ 0x03, b'2', b'5', b'6', 0x00 ]);
+    }
+
+    #[test]
+    fn writes_root() {
+        for root in &[ "", "." ] {
+            let mut bytes = Vec::new();
+            bytes.write_labels(root).unwrap();
+            assert_eq!(bytes, &[ 0x00 ]);
+        }
+    }
+
+    #[test]
+    fn empty_label() {
+        let error = Vec::new().write_labels("lookup..dog").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn long_label() {
+        let error = Vec::new().write_labels(&format!("{}.dog", "a".repeat(64))).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -3,7 +3,7 @@
 //! with the request packet having zero answer fields, and the response packet
 //! having at least one record in its answer fields.
 
-use crate::record::{Record, OPT};
+use crate::record::{Record, OPT, SIG};
 
 
 /// A request that gets sent out over a transport.
@@ -22,6 +22,10 @@ pub struct Request {
 
     /// An additional record that may be sent as part of the query.
     pub additional: Option<OPT>,
+
+    /// A SIG(0) record signing the request, which gets sent as the last
+    /// record in the Additional section.
+    pub signature: Option<SIG>,
 }
 
 
@@ -151,6 +155,10 @@ pub struct Flags {
     /// checking for the request’s queries.
     pub checking_disabled: bool,
 
+    /// Whether the reserved Z bit is set. This should always be zero, so a
+    /// server that sets it is either broken or up to something.
+    pub reserved: bool,
+
     /// In a response, a code indicating an error if one occurred.
     pub error_code: Option<ErrorCode>,
 }
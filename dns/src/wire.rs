@@ -4,9 +4,9 @@ pub(crate) use std::io::Cursor;
 pub(crate) use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 use std::io;
-use log::{error, info, debug};
+use log::{error, warn, info, debug};
 
-use crate::record::{Record, OPT};
+use crate::record::{Record, OPT, SIG};
 use crate::strings::{ReadLabels, WriteLabels};
 use crate::types::*;
 
@@ -15,15 +15,30 @@ impl Request {
 
     /// Converts this request to a vector of bytes.
     pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        self.to_bytes_with_signature(self.signature.as_ref())
+    }
+
+    /// Returns the data that a SIG(0) signature over this request needs to
+    /// sign: the data of the SIG record itself, minus its signature, followed
+    /// by the request as it would be sent _without_ the SIG record.
+    pub fn sig0_signed_data(&self, sig: &SIG) -> io::Result<Vec<u8>> {
+        let mut bytes = sig.to_unsigned_bytes()?;
+        bytes.extend(self.to_bytes_with_signature(None)?);
+        Ok(bytes)
+    }
+
+    fn to_bytes_with_signature(&self, signature: Option<&SIG>) -> io::Result<Vec<u8>> {
         let mut bytes = Vec::with_capacity(32);
 
+        let additional_count = u16::from(self.additional.is_some()) + u16::from(signature.is_some());
+
         bytes.write_u16::<BigEndian>(self.transaction_id)?;
         bytes.write_u16::<BigEndian>(self.flags.to_u16())?;
 
         bytes.write_u16::<BigEndian>(self.queries.len() as u16)?;
         bytes.write_u16::<BigEndian>(0)?;  // usually answers
         bytes.write_u16::<BigEndian>(0)?;  // usually authority RRs
-        bytes.write_u16::<BigEndian>(if self.additional.is_some() { 1 } else { 0 })?;  // additional RRs
+        bytes.write_u16::<BigEndian>(additional_count)?;  // additional RRs
 
         for query in &self.queries {
             bytes.write_labels(&query.qname)?;
@@ -37,6 +52,16 @@ impl Request {
             bytes.extend(opt.to_bytes()?);
         }
 
+        // The SIG(0) record has to come last, as it signs everything
+        // before it (RFC 2931 §3.1)
+        if let Some(sig) = signature {
+            bytes.write_u8(0)?;  // the root name
+            bytes.write_u16::<BigEndian>(SIG::RR_TYPE)?;
+            bytes.write_u16::<BigEndian>(SIG::SIG0_CLASS)?;
+            bytes.write_u32::<BigEndian>(0)?;  // the TTL
+            bytes.extend(sig.to_bytes()?);
+        }
+
         Ok(bytes)
     }
 
@@ -130,8 +155,18 @@ impl Answer {
             let ttl = c.read_u32::<BigEndian>()?;
 
             let len = c.read_u16::<BigEndian>()?;
+            let start = c.position();
             let record = Record::from_bytes(qtype, len, c)?;
 
+            // Carry on from the end of the record’s data, even if the record
+            // didn’t use all of it, so one odd record doesn’t throw off the
+            // parsing of every record after it.
+            let end = start + u64::from(len);
+            if c.position() != end {
+                warn!("Record data was {} bytes but {} were read", len, c.position() - start);
+                c.set_position(end);
+            }
+
             Ok(Answer::Standard { qclass, qname, record, ttl })
         }
 
@@ -165,6 +200,8 @@ impl Record {
         try_record!(NS);
         // OPT is handled separately
         try_record!(PTR);
+        try_record!(RRSIG);
+        try_record!(SIG);
         try_record!(SOA);
         try_record!(SRV);
         try_record!(TXT);
@@ -223,6 +260,8 @@ pub fn find_qtype_number(record_type: &str) -> Option<TypeInt> {
     try_record!(NS);
     // OPT is elsewhere
     try_record!(PTR);
+    try_record!(RRSIG);
+    try_record!(SIG);
     try_record!(SOA);
     try_record!(SRV);
     try_record!(TXT);
@@ -230,6 +269,17 @@ pub fn find_qtype_number(record_type: &str) -> Option<TypeInt> {
     None
 }
 
+/// Returns the names of every record type that can be parsed, for when the
+/// user needs to be told which ones are valid.
+pub fn qtype_names() -> Vec<&'static str> {
+    use crate::record::*;
+
+    vec![
+        A::NAME, AAAA::NAME, CAA::NAME, CNAME::NAME, MX::NAME, NS::NAME,
+        PTR::NAME, RRSIG::NAME, SIG::NAME, SOA::NAME, SRV::NAME, TXT::NAME,
+    ]
+}
+
 
 impl Flags {
 
@@ -238,20 +288,25 @@ impl Flags {
         Self::from_u16(0b_0000_0001_0000_0000)
     }
 
+    /// The set of flags that represents a NOTIFY packet, which a primary
+    /// sends to tell a secondary that a zone has changed (RFC 1996 §3.7).
+    pub fn notify() -> Self {
+        Self::from_u16(0b_0010_0100_0000_0000)
+    }
+
     /// Converts the flags into a two-byte number.
     pub fn to_u16(self) -> u16 {                 // 0123 4567 89AB CDEF
         let mut                          bits  = 0b_0000_0000_0000_0000;
         if self.response               { bits += 0b_1000_0000_0000_0000; }
-        match self.opcode {
-                                _ =>   { bits += 0b_0000_0000_0000_0000; }
-        }
+        bits += u16::from(self.opcode & 0b_1111) << 11;
         if self.authoritative          { bits += 0b_0000_0100_0000_0000; }
         if self.truncated              { bits += 0b_0000_0010_0000_0000; }
         if self.recursion_desired      { bits += 0b_0000_0001_0000_0000; }
         if self.recursion_available    { bits += 0b_0000_0000_1000_0000; }
-        // (the Z bit is reserved)               0b_0000_0000_0100_0000
+        if self.reserved               { bits += 0b_0000_0000_0100_0000; }
         if self.authentic_data         { bits += 0b_0000_0000_0010_0000; }
         if self.checking_disabled      { bits += 0b_0000_0000_0001_0000; }
+        bits += self.error_code.map_or(0, ErrorCode::to_bits) & 0b_1111;
 
         bits
     }
@@ -262,13 +317,14 @@ impl Flags {
 
         Flags {
             response:               has_bit(0b_1000_0000_0000_0000),
-            opcode:                 0,
+            opcode:                 (bits.to_be_bytes()[0] >> 3) & 0b_1111,
             authoritative:          has_bit(0b_0000_0100_0000_0000),
             truncated:              has_bit(0b_0000_0010_0000_0000),
             recursion_desired:      has_bit(0b_0000_0001_0000_0000),
             recursion_available:    has_bit(0b_0000_0000_1000_0000),
             authentic_data:         has_bit(0b_0000_0000_0010_0000),
             checking_disabled:      has_bit(0b_0000_0000_0001_0000),
+            reserved:               has_bit(0b_0000_0000_0100_0000),
             error_code:             ErrorCode::from_bits(bits & 0b_1111),
         }
     }
@@ -290,6 +346,20 @@ impl ErrorCode {
             n => Some(Self::Other(n)),
         }
     }
+
+    /// Converts the rcode back into a number. Only the last four bits of
+    /// this fit in the flags field.
+    fn to_bits(self) -> u16 {
+        match self {
+            Self::FormatError     => 1,
+            Self::ServerFailure   => 2,
+            Self::NXDomain        => 3,
+            Self::NotImplemented  => 4,
+            Self::QueryRefused    => 5,
+            Self::BadVersion      => 16,
+            Self::Other(n)        => n,
+        }
+    }
 }
 
 
@@ -0,0 +1,351 @@
+//! Property tests for the wire parser: it should never panic, whatever bytes
+//! it gets given, and anything written out in wire format should parse back
+//! into exactly the same thing.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use dns::{Request, Response, Query, Answer, Flags, QClass, Wire};
+use dns::record::*;
+
+use proptest::prelude::*;
+
+
+// ---- generators ----
+
+fn name() -> impl Strategy<Value = String> {
+    prop::collection::vec("[a-z0-9][a-z0-9-]{0,14}", 1 ..= 4)
+        .prop_map(|labels| format!("{}.", labels.join(".")))
+}
+
+fn qclass() -> impl Strategy<Value = QClass> {
+    prop_oneof![
+        Just(QClass::IN),
+        Just(QClass::CH),
+        Just(QClass::HS),
+        (5_u16 ..).prop_map(QClass::Other),
+    ]
+}
+
+fn flags() -> impl Strategy<Value = Flags> {
+    any::<u16>().prop_map(Flags::from_u16)
+}
+
+fn opt() -> impl Strategy<Value = OPT> {
+    (any::<u16>(), any::<u8>(), any::<u8>(), any::<u16>(), prop::collection::vec(any::<u8>(), 0 .. 64))
+        .prop_map(|(udp_payload_size, higher_bits, edns0_version, flags, data)| {
+            OPT { udp_payload_size, higher_bits, edns0_version, flags, data }
+        })
+}
+
+fn record() -> impl Strategy<Value = Record> {
+    prop_oneof![
+        any::<[u8; 4]>().prop_map(|a| Record::A(A { address: Ipv4Addr::from(a) })),
+        any::<[u8; 16]>().prop_map(|a| Record::AAAA(AAAA { address: Ipv6Addr::from(a) })),
+        (any::<bool>(), "[a-z]{1,15}", "[ -~]{0,100}")
+            .prop_map(|(critical, tag, value)| Record::CAA(CAA { critical, tag, value })),
+        name().prop_map(|domain| Record::CNAME(CNAME { domain })),
+        (any::<u16>(), name()).prop_map(|(preference, exchange)| Record::MX(MX { preference, exchange })),
+        name().prop_map(|nameserver| Record::NS(NS { nameserver })),
+        name().prop_map(|cname| Record::PTR(PTR { cname })),
+        (any::<(u16, u8, u8, u32, u32, u32, u16)>(), name(), prop::collection::vec(any::<u8>(), 0 .. 128))
+            .prop_map(|((type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag), signer_name, signature)| {
+                Record::RRSIG(RRSIG { type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag, signer_name, signature })
+            }),
+        (any::<(u16, u8, u8, u32, u32, u32, u16)>(), name(), prop::collection::vec(any::<u8>(), 0 .. 128))
+            .prop_map(|((type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag), signer_name, signature)| {
+                Record::SIG(SIG { type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag, signer_name, signature })
+            }),
+        (name(), name(), any::<(u32, u32, u32, u32, u32)>())
+            .prop_map(|(mname, rname, (serial, refresh_interval, retry_interval, expire_limit, minimum_ttl))| {
+                Record::SOA(SOA { mname, rname, serial, refresh_interval, retry_interval, expire_limit, minimum_ttl })
+            }),
+        (any::<(u16, u16, u16)>(), name())
+            .prop_map(|((priority, weight, port), target)| Record::SRV(SRV { priority, weight, port, target })),
+        "[ -~]{0,600}".prop_map(|message| Record::TXT(TXT { message })),
+        (1000_u16 .., prop::collection::vec(any::<u8>(), 0 .. 64))
+            .prop_map(|(number, bytes)| Record::Other { type_number: UnknownQtype::from(number), bytes }),
+    ]
+}
+
+fn answer() -> impl Strategy<Value = Answer> {
+    (name(), qclass(), any::<u32>(), record())
+        .prop_map(|(qname, qclass, ttl, record)| Answer::Standard { qname, qclass, ttl, record })
+}
+
+fn query() -> impl Strategy<Value = Query> {
+    (name(), any::<u16>(), qclass())
+        .prop_map(|(qname, qtype, qclass)| Query { qname, qtype, qclass })
+}
+
+fn response() -> impl Strategy<Value = Response> {
+    (any::<u16>(), flags(),
+     prop::collection::vec(query(), 0 .. 3),
+     prop::collection::vec(answer(), 0 .. 5),
+     prop::collection::vec(answer(), 0 .. 3),
+     prop::collection::vec(answer(), 0 .. 3))
+        .prop_map(|(transaction_id, flags, queries, answers, authorities, additionals)| {
+            Response { transaction_id, flags, queries, answers, authorities, additionals }
+        })
+}
+
+
+// ---- encoders ----
+
+fn write_name(bytes: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|l| ! l.is_empty()) {
+        bytes.push(label.len() as u8);
+        bytes.extend(label.as_bytes());
+    }
+    bytes.push(0);
+}
+
+fn class_number(qclass: QClass) -> u16 {
+    match qclass {
+        QClass::IN        => 1,
+        QClass::CH        => 3,
+        QClass::HS        => 4,
+        QClass::Other(n)  => n,
+    }
+}
+
+fn write_signature(bytes: &mut Vec<u8>, fields: (u16, u8, u8, u32, u32, u32, u16), signer_name: &str, signature: &[u8]) {
+    let (type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag) = fields;
+    bytes.extend(&type_covered.to_be_bytes());
+    bytes.push(algorithm);
+    bytes.push(labels);
+    bytes.extend(&original_ttl.to_be_bytes());
+    bytes.extend(&signature_expiration.to_be_bytes());
+    bytes.extend(&signature_inception.to_be_bytes());
+    bytes.extend(&key_tag.to_be_bytes());
+    write_name(bytes, signer_name);
+    bytes.extend(signature);
+}
+
+/// Returns the type number of a record, and its data in wire format.
+fn record_data(record: &Record) -> (u16, Vec<u8>) {
+    let mut bytes = Vec::new();
+
+    let rr_type = match record {
+        Record::A(a) => {
+            bytes.extend(&a.address.octets());
+            A::RR_TYPE
+        }
+        Record::AAAA(aaaa) => {
+            bytes.extend(&aaaa.address.octets());
+            AAAA::RR_TYPE
+        }
+        Record::CAA(caa) => {
+            bytes.push(if caa.critical { 0b_1000_0000 } else { 0 });
+            bytes.push(caa.tag.len() as u8);
+            bytes.extend(caa.tag.as_bytes());
+            bytes.extend(caa.value.as_bytes());
+            CAA::RR_TYPE
+        }
+        Record::CNAME(cname) => {
+            write_name(&mut bytes, &cname.domain);
+            CNAME::RR_TYPE
+        }
+        Record::MX(mx) => {
+            bytes.extend(&mx.preference.to_be_bytes());
+            write_name(&mut bytes, &mx.exchange);
+            MX::RR_TYPE
+        }
+        Record::NS(ns) => {
+            write_name(&mut bytes, &ns.nameserver);
+            NS::RR_TYPE
+        }
+        Record::PTR(ptr) => {
+            write_name(&mut bytes, &ptr.cname);
+            PTR::RR_TYPE
+        }
+        Record::RRSIG(r) => {
+            let fields = (r.type_covered, r.algorithm, r.labels, r.original_ttl, r.signature_expiration, r.signature_inception, r.key_tag);
+            write_signature(&mut bytes, fields, &r.signer_name, &r.signature);
+            RRSIG::RR_TYPE
+        }
+        Record::SIG(s) => {
+            let fields = (s.type_covered, s.algorithm, s.labels, s.original_ttl, s.signature_expiration, s.signature_inception, s.key_tag);
+            write_signature(&mut bytes, fields, &s.signer_name, &s.signature);
+            SIG::RR_TYPE
+        }
+        Record::SOA(soa) => {
+            write_name(&mut bytes, &soa.mname);
+            write_name(&mut bytes, &soa.rname);
+            for number in &[ soa.serial, soa.refresh_interval, soa.retry_interval, soa.expire_limit, soa.minimum_ttl ] {
+                bytes.extend(&number.to_be_bytes());
+            }
+            SOA::RR_TYPE
+        }
+        Record::SRV(srv) => {
+            bytes.extend(&srv.priority.to_be_bytes());
+            bytes.extend(&srv.weight.to_be_bytes());
+            bytes.extend(&srv.port.to_be_bytes());
+            write_name(&mut bytes, &srv.target);
+            SRV::RR_TYPE
+        }
+        Record::TXT(txt) => {
+            // Long messages get split into strings of 255 bytes; a message
+            // that fills its last string exactly needs an empty one after it.
+            let mut chunks = txt.message.as_bytes().chunks(255).collect::<Vec<_>>();
+            if ! matches!(chunks.last(), Some(c) if c.len() < 255) {
+                chunks.push(&[]);
+            }
+
+            for chunk in chunks {
+                bytes.push(chunk.len() as u8);
+                bytes.extend(chunk);
+            }
+            TXT::RR_TYPE
+        }
+        Record::Other { type_number, bytes: data } => {
+            bytes.extend(data);
+            match type_number {
+                UnknownQtype::HeardOf(name)  => find_other_qtype_number(name).unwrap(),
+                UnknownQtype::UnheardOf(n)   => *n,
+            }
+        }
+    };
+
+    (rr_type, bytes)
+}
+
+fn write_answer(bytes: &mut Vec<u8>, answer: &Answer) {
+    match answer {
+        Answer::Standard { qname, qclass, ttl, record } => {
+            let (rr_type, data) = record_data(record);
+            write_name(bytes, qname);
+            bytes.extend(&rr_type.to_be_bytes());
+            bytes.extend(&class_number(*qclass).to_be_bytes());
+            bytes.extend(&ttl.to_be_bytes());
+            bytes.extend(&(data.len() as u16).to_be_bytes());
+            bytes.extend(data);
+        }
+        Answer::Pseudo { qname, opt } => {
+            write_name(bytes, qname);
+            bytes.extend(&OPT::RR_TYPE.to_be_bytes());
+            bytes.extend(opt.to_bytes().unwrap());
+        }
+    }
+}
+
+fn response_bytes(response: &Response) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(&response.transaction_id.to_be_bytes());
+    bytes.extend(&response.flags.to_u16().to_be_bytes());
+    for count in &[ response.queries.len(), response.answers.len(), response.authorities.len(), response.additionals.len() ] {
+        bytes.extend(&(*count as u16).to_be_bytes());
+    }
+
+    for query in &response.queries {
+        write_name(&mut bytes, &query.qname);
+        bytes.extend(&query.qtype.to_be_bytes());
+        bytes.extend(&class_number(query.qclass).to_be_bytes());
+    }
+
+    for answer in response.answers.iter().chain(&response.authorities).chain(&response.additionals) {
+        write_answer(&mut bytes, answer);
+    }
+
+    bytes
+}
+
+
+// ---- properties ----
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0 .. 512)) {
+        let _ = Response::from_bytes(&bytes);
+    }
+
+    #[test]
+    fn arbitrary_bodies_never_panic(counts in any::<[u8; 4]>(), body in prop::collection::vec(any::<u8>(), 0 .. 512)) {
+        // A plausible header with small counts gets the parser past the
+        // header and into the records much more often than random bytes.
+        let mut bytes = vec![ 0x12, 0x34, 0x81, 0x80 ];
+        for count in &counts {
+            bytes.extend(&[ 0, count % 8 ]);
+        }
+        bytes.extend(body);
+
+        let _ = Response::from_bytes(&bytes);
+    }
+
+    #[test]
+    fn truncated_responses_never_panic(response in response(), cut in any::<prop::sample::Index>()) {
+        let bytes = response_bytes(&response);
+        let _ = Response::from_bytes(&bytes[.. cut.index(bytes.len() + 1)]);
+    }
+
+    #[test]
+    fn flags_round_trip(bits in any::<u16>()) {
+        prop_assert_eq!(Flags::from_u16(bits).to_u16(), bits);
+    }
+
+    #[test]
+    fn records_round_trip(answer in answer()) {
+        let response = Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0001_1000_0000),
+            queries: vec![],
+            answers: vec![ answer ],
+            authorities: vec![],
+            additionals: vec![],
+        };
+
+        prop_assert_eq!(Response::from_bytes(&response_bytes(&response)), Ok(response));
+    }
+
+    #[test]
+    fn responses_round_trip(response in response()) {
+        prop_assert_eq!(Response::from_bytes(&response_bytes(&response)), Ok(response));
+    }
+
+    #[test]
+    fn trailing_data_does_not_misalign(qname in name(), record in record(), extra in prop::collection::vec(any::<u8>(), 1 .. 16)) {
+        let follower = Answer::Standard {
+            qname: "lookup.dog.".into(),
+            qclass: QClass::IN,
+            ttl: 60,
+            record: Record::A(A { address: Ipv4Addr::new(192, 0, 2, 1) }),
+        };
+
+        // Pad the first record’s data with bytes it has no use for. Records
+        // of a fixed size can reject this, but if it gets accepted, the
+        // bytes should be skipped over rather than read as the next record.
+        let (rr_type, mut data) = record_data(&record);
+        data.extend(extra);
+
+        let mut bytes = vec![ 0x12, 0x34, 0x81, 0x80, 0, 0, 0, 2, 0, 0, 0, 0 ];
+        write_name(&mut bytes, &qname);
+        bytes.extend(&rr_type.to_be_bytes());
+        bytes.extend(&1_u16.to_be_bytes());
+        bytes.extend(&60_u32.to_be_bytes());
+        bytes.extend(&(data.len() as u16).to_be_bytes());
+        bytes.extend(data);
+        write_answer(&mut bytes, &follower);
+
+        if let Ok(mut parsed) = Response::from_bytes(&bytes) {
+            prop_assert_eq!(parsed.answers.pop(), Some(follower));
+        }
+    }
+
+    #[test]
+    fn requests_round_trip(transaction_id in any::<u16>(), query in query(), additional in prop::option::of(opt())) {
+        let request = Request {
+            transaction_id,
+            flags: Flags::query(),
+            queries: vec![ query.clone() ],
+            additional: additional.clone(),
+            signature: None,
+        };
+
+        let parsed = Response::from_bytes(&request.to_bytes().unwrap()).unwrap();
+        prop_assert_eq!(parsed.transaction_id, transaction_id);
+        prop_assert_eq!(parsed.flags, Flags::query());
+        prop_assert_eq!(parsed.queries, vec![ query ]);
+        prop_assert_eq!(parsed.additionals, additional.into_iter().map(|opt| Answer::Pseudo { qname: String::new(), opt }).collect::<Vec<_>>());
+    }
+}
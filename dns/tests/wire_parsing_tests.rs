@@ -1,7 +1,42 @@
-use dns::Response;
+use dns::{Response, Flags, ErrorCode};
 
 
 #[test]
 fn parse_nothing() {
     assert!(Response::from_bytes(&[]).is_err());
 }
+
+
+#[test]
+fn parse_flags() {
+    let flags = Flags::from_u16(0b_1010_0101_1000_0011);
+    assert!(flags.response);
+    assert_eq!(flags.opcode, 4);
+    assert!(flags.authoritative);
+    assert!(flags.recursion_desired);
+    assert!(flags.recursion_available);
+    assert!(! flags.reserved);
+    assert_eq!(flags.error_code, Some(ErrorCode::NXDomain));
+}
+
+#[test]
+fn flags_round_trip() {
+    let bits = 0b_1010_1101_1011_0000;
+    assert_eq!(Flags::from_u16(bits).to_u16(), bits);
+}
+
+#[test]
+fn reserved_flag() {
+    let flags = Flags::from_u16(0b_1000_0000_0100_0000);
+    assert!(flags.reserved);
+    assert_eq!(flags.to_u16(), 0b_1000_0000_0100_0000);
+}
+
+#[test]
+fn notify_flags() {
+    let flags = Flags::notify();
+    assert!(! flags.response);
+    assert_eq!(flags.opcode, 4);
+    assert!(flags.authoritative);
+    assert!(! flags.recursion_desired);
+}
@@ -0,0 +1,309 @@
+//! Annotating records with extra information that can be worked out from
+//! their contents, without sending any more queries.
+
+use std::fmt::Write;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use dns::record::{Record, UnknownQtype};
+
+use crate::ech;
+
+
+/// Works out an annotation for a record with the given name, if there’s
+/// anything useful to say about it:
+///
+/// - the reverse DNS name of the address in an **A** or **AAAA** record;
+/// - the address that a **PTR** record in a reverse zone is for;
+/// - the key tag of a **DNSKEY** record, which is how RRSIG and DS records
+///   refer to it;
+/// - the decoded parameters of an **SVCB** or **HTTPS** record, including
+///   any Encrypted Client Hello configurations.
+pub fn annotate(qname: &str, record: &Record) -> Option<String> {
+    match record {
+        Record::A(a) => {
+            let [a, b, c, d] = a.address.octets();
+            Some(format!("reverse {}.{}.{}.{}.in-addr.arpa.", d, c, b, a))
+        }
+        Record::AAAA(aaaa) => {
+            let mut name = String::from("reverse ");
+            for byte in aaaa.address.octets().iter().rev() {
+                let _ = write!(name, "{:x}.{:x}.", byte & 0xF, byte >> 4);
+            }
+            name.push_str("ip6.arpa.");
+            Some(name)
+        }
+        Record::PTR(_) => {
+            reverse_address(qname).map(|address| format!("address {}", address))
+        }
+        Record::Other { type_number: UnknownQtype::HeardOf("DNSKEY"), bytes } if bytes.len() >= 4 => {
+            Some(format!("key tag {}", key_tag(bytes)))
+        }
+        Record::Other { type_number: UnknownQtype::HeardOf("SVCB" | "HTTPS"), bytes } => {
+            service_binding(bytes)
+        }
+        _ => None,
+    }
+}
+
+
+/// Turns a name in the `in-addr.arpa` or `ip6.arpa` reverse zones back into
+/// the address it’s for.
+fn reverse_address(qname: &str) -> Option<String> {
+    let qname = qname.trim_end_matches('.').to_ascii_lowercase();
+
+    if let Some(labels) = qname.strip_suffix(".in-addr.arpa") {
+        let mut octets = labels.split('.').map(|l| l.parse::<u8>().ok()).collect::<Option<Vec<_>>>()?;
+        if octets.len() != 4 {
+            return None;
+        }
+
+        octets.reverse();
+        Some(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]).to_string())
+    }
+    else if let Some(labels) = qname.strip_suffix(".ip6.arpa") {
+        let nibbles = labels.split('.').map(|l| u8::from_str_radix(l, 16).ok().filter(|_| l.len() == 1)).collect::<Option<Vec<_>>>()?;
+        if nibbles.len() != 32 {
+            return None;
+        }
+
+        let mut octets = [0_u8; 16];
+        for (index, pair) in nibbles.rchunks(2).enumerate() {
+            octets[index] = (pair[1] << 4) | pair[0];
+        }
+
+        Some(Ipv6Addr::from(octets).to_string())
+    }
+    else {
+        None
+    }
+}
+
+/// Calculates the key tag of a DNSKEY record from its data (RFC 4034
+/// Appendix B).
+#[allow(clippy::cast_possible_truncation)]
+fn key_tag(rdata: &[u8]) -> u16 {
+
+    // Keys using the obsolete RSA/MD5 algorithm have their tag taken from
+    // the key itself
+    if rdata[3] == 1 && rdata.len() >= 7 {
+        return u16::from_be_bytes([ rdata[rdata.len() - 3], rdata[rdata.len() - 2] ]);
+    }
+
+    let mut accumulator = 0_u32;
+    for (index, byte) in rdata.iter().enumerate() {
+        if index % 2 == 0 {
+            accumulator += u32::from(*byte) << 8;
+        }
+        else {
+            accumulator += u32::from(*byte);
+        }
+    }
+
+    accumulator += (accumulator >> 16) & 0xFFFF;
+    (accumulator & 0xFFFF) as u16
+}
+
+/// Decodes the priority, target name, and service parameters of an SVCB
+/// or HTTPS record (RFC 9460), in presentation format.
+fn service_binding(rdata: &[u8]) -> Option<String> {
+    let priority = u16::from_be_bytes([ *rdata.first()?, *rdata.get(1)? ]);
+    let mut position = 2;
+
+    // The target name is never compressed
+    let mut target = String::new();
+    loop {
+        let length = usize::from(*rdata.get(position)?);
+        position += 1;
+        if length == 0 {
+            break;
+        }
+
+        let label = rdata.get(position .. position + length)?;
+        target.push_str(&String::from_utf8_lossy(label));
+        target.push('.');
+        position += length;
+    }
+
+    if target.is_empty() {
+        target.push('.');
+    }
+
+    let mut output = format!("priority {}, target {}", priority, target);
+    if priority == 0 {
+        output.push_str(" (alias)");
+    }
+
+    while position < rdata.len() {
+        let key = u16::from_be_bytes([ *rdata.get(position)?, *rdata.get(position + 1)? ]);
+        let length = usize::from(u16::from_be_bytes([ *rdata.get(position + 2)?, *rdata.get(position + 3)? ]));
+        let value = rdata.get(position + 4 .. position + 4 + length)?;
+        position += 4 + length;
+
+        output.push_str(", ");
+        output.push_str(&service_parameter(key, value)?);
+    }
+
+    Some(output)
+}
+
+/// Decodes one SVCB service parameter into its `key=value` form.
+fn service_parameter(key: u16, value: &[u8]) -> Option<String> {
+    let parameter = match key {
+        0 => {
+            let keys = value.chunks(2).map(|k| match k {
+                [a, b] => Some(parameter_name(u16::from_be_bytes([*a, *b]))),
+                _      => None,
+            }).collect::<Option<Vec<_>>>()?;
+
+            format!("mandatory={}", keys.join(","))
+        }
+        1 => {
+            let mut protocols = Vec::new();
+            let mut rest = value;
+            while let Some((length, tail)) = rest.split_first() {
+                let protocol = tail.get(.. usize::from(*length))?;
+                protocols.push(String::from_utf8_lossy(protocol).to_string());
+                rest = &tail[usize::from(*length) ..];
+            }
+
+            format!("alpn={}", protocols.join(","))
+        }
+        2 => {
+            String::from("no-default-alpn")
+        }
+        3 => {
+            match value {
+                [a, b]  => format!("port={}", u16::from_be_bytes([*a, *b])),
+                _       => return None,
+            }
+        }
+        4 => {
+            let addresses = value.chunks(4).map(|a| match a {
+                [a, b, c, d] => Some(Ipv4Addr::new(*a, *b, *c, *d).to_string()),
+                _            => None,
+            }).collect::<Option<Vec<_>>>()?;
+
+            format!("ipv4hint={}", addresses.join(","))
+        }
+        5 => {
+            ech::describe(value)
+        }
+        6 => {
+            let mut addresses = Vec::new();
+            for chunk in value.chunks(16) {
+                let mut octets = [0_u8; 16];
+                if chunk.len() != 16 {
+                    return None;
+                }
+                octets.copy_from_slice(chunk);
+                addresses.push(Ipv6Addr::from(octets).to_string());
+            }
+
+            format!("ipv6hint={}", addresses.join(","))
+        }
+        7 => {
+            format!("dohpath={}", String::from_utf8_lossy(value))
+        }
+        _ => {
+            let mut hex = String::new();
+            for byte in value {
+                let _ = write!(hex, "{:02x}", byte);
+            }
+
+            format!("{}={}", parameter_name(key), hex)
+        }
+    };
+
+    Some(parameter)
+}
+
+/// Returns the name of a service parameter key.
+fn parameter_name(key: u16) -> String {
+    match key {
+        0 => "mandatory".into(),
+        1 => "alpn".into(),
+        2 => "no-default-alpn".into(),
+        3 => "port".into(),
+        4 => "ipv4hint".into(),
+        5 => "ech".into(),
+        6 => "ipv6hint".into(),
+        7 => "dohpath".into(),
+        n => format!("key{}", n),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::*;
+
+    #[test]
+    fn a_reverse_name() {
+        let record = Record::A(A { address: Ipv4Addr::new(192, 0, 2, 1) });
+        assert_eq!(annotate("lookup.dog.", &record),
+                   Some(String::from("reverse 1.2.0.192.in-addr.arpa.")));
+    }
+
+    #[test]
+    fn aaaa_reverse_name() {
+        let record = Record::AAAA(AAAA { address: "2001:db8::1".parse().unwrap() });
+        assert_eq!(annotate("lookup.dog.", &record),
+                   Some(String::from("reverse 1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa.")));
+    }
+
+    #[test]
+    fn ptr_address() {
+        let record = Record::PTR(PTR { cname: String::from("lookup.dog.") });
+        assert_eq!(annotate("1.2.0.192.in-addr.arpa.", &record),
+                   Some(String::from("address 192.0.2.1")));
+        assert_eq!(annotate("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa.", &record),
+                   Some(String::from("address 2001:db8::1")));
+        assert_eq!(annotate("lookup.dog.", &record),
+                   None);
+    }
+
+    #[test]
+    fn dnskey_tag() {
+        // The root zone’s 2017 key-signing key
+        let key = base64::decode("AwEAAaz/tAm8yTn4Mfeh5eyI96WSVexTBAvkMgJzkKTOiW1vkIbzxeF3+/4RgWOq7HrxRixHlFlExOLAJr5emLvN7SWXgnLh4+B5xQlNVz8Og8kvArMtNROxVQuCaSnIDdD5LKyWbRd2n9WGe2R8PzgCmr3EgVLrjyBxWezF0jLHwVN8efS3rCj/EWgvIWgb9tarpVUDK/b58Da+sqqls3eNbuv7pr+eoZG+SrDK6nWeL3c6H5Apxz7LjVc1uTIdsIXxuOLYA4/ilBmSVIzuDWfdRUfhHdY6+cn8HFRm+2hM8AnXGXws9555KrUB5qihylGa8subX2Nn6UwNR1AkUTV74bU=").unwrap();
+        let mut bytes = vec![ 0x01, 0x01, 0x03, 0x08 ];
+        bytes.extend(key);
+
+        let record = Record::Other { type_number: UnknownQtype::HeardOf("DNSKEY"), bytes };
+        assert_eq!(annotate(".", &record),
+                   Some(String::from("key tag 20326")));
+    }
+
+    #[test]
+    fn https_parameters() {
+        let bytes = vec![
+            0x00, 0x01,                                // priority
+            0x00,                                      // target
+            0x00, 0x01, 0x00, 0x06, 0x02, b'h', b'2', 0x02, b'h', b'3',  // alpn
+            0x00, 0x03, 0x00, 0x02, 0x01, 0xbb,        // port
+            0x00, 0x04, 0x00, 0x04, 0xc0, 0x00, 0x02, 0x01,  // ipv4hint
+        ];
+
+        let record = Record::Other { type_number: UnknownQtype::HeardOf("HTTPS"), bytes };
+        assert_eq!(annotate("lookup.dog.", &record),
+                   Some(String::from("priority 1, target ., alpn=h2,h3, port=443, ipv4hint=192.0.2.1")));
+    }
+
+    #[test]
+    fn svcb_alias() {
+        let bytes = vec![ 0x00, 0x00, 0x03, b'd', b'o', b'g', 0x00 ];
+
+        let record = Record::Other { type_number: UnknownQtype::HeardOf("SVCB"), bytes };
+        assert_eq!(annotate("lookup.dog.", &record),
+                   Some(String::from("priority 0, target dog. (alias)")));
+    }
+
+    #[test]
+    fn truncated_parameters() {
+        let bytes = vec![ 0x00, 0x01, 0x00, 0x00, 0x03, 0x00, 0x02, 0x01 ];
+
+        let record = Record::Other { type_number: UnknownQtype::HeardOf("HTTPS"), bytes };
+        assert_eq!(annotate("lookup.dog.", &record), None);
+    }
+}
@@ -0,0 +1,214 @@
+//! Looking up the addresses of encrypted nameservers that are given as host
+//! names, using a resolver the user picks rather than the system’s.
+
+use std::net::IpAddr;
+
+use futures_util::future::join;
+
+use dns::{Request, Flags, Query, QClass, Answer, TypeInt, qtype};
+use dns::record::{Record, A, AAAA};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::Nameserver;
+
+
+/// One bootstrap lookup of a nameserver’s host name.
+#[derive(Debug)]
+pub struct Lookup {
+
+    /// The host name that was looked up.
+    pub host: String,
+
+    /// The resolver it was looked up with.
+    pub resolver: Nameserver,
+
+    /// The result of looking up each address family: IPv4 first, then IPv6.
+    pub families: Vec<Family>,
+}
+
+/// The lookup of one address family of a host.
+#[derive(Debug)]
+pub struct Family {
+
+    /// The record type that was queried, `A` or `AAAA`.
+    pub qtype: &'static str,
+
+    /// The addresses that came back, or the error that stopped them from
+    /// coming back.
+    pub result: Result<Vec<IpAddr>, TransportError>,
+}
+
+impl Lookup {
+
+    /// Every address that was found, IPv4 first.
+    pub fn addresses(&self) -> impl Iterator<Item=IpAddr> + '_ {
+        self.families.iter().filter_map(|f| f.result.as_ref().ok()).flatten().copied()
+    }
+
+    /// The address to connect to, if any were found.
+    pub fn address(&self) -> Option<IpAddr> {
+        self.addresses().next()
+    }
+
+    /// The address families that could not be looked up, with their errors.
+    pub fn failures(&self) -> impl Iterator<Item=(&'static str, &TransportError)> + '_ {
+        self.families.iter().filter_map(|f| f.result.as_ref().err().map(|e| (f.qtype, e)))
+    }
+}
+
+
+/// Returns the host name in a nameserver that needs to be looked up before
+/// it can be connected to. Only DNS-over-TLS and DNS-over-HTTPS nameservers
+/// get bootstrapped, as they’re the ones that need a name to present.
+pub fn host_to_look_up(nameserver: &str, transport_type: TransportType) -> Option<String> {
+    let endpoint = Endpoint::parse(nameserver, transport_type)?;
+    if endpoint.host.parse::<IpAddr>().is_ok() {
+        None
+    }
+    else {
+        Some(endpoint.host.to_owned())
+    }
+}
+
+/// Rewrites a nameserver to connect to the given address, while presenting
+/// the host name it used to have — or the SNI name it was given — during
+/// the TLS handshake.
+pub fn rewrite(nameserver: &str, transport_type: TransportType, address: IpAddr, names: &ServerNames) -> Nameserver {
+    let Some(endpoint) = Endpoint::parse(nameserver, transport_type) else {
+        return nameserver.into();
+    };
+
+    let address = match address {
+        IpAddr::V4(v4)  => v4.to_string(),
+        IpAddr::V6(v6)  => format!("[{}]", v6),
+    };
+
+    let sni = endpoint.sni.or(names.sni.as_deref()).unwrap_or(endpoint.host);
+
+    match (endpoint.scheme, endpoint.port) {
+        (Some(scheme), Some(port))  => format!("{}://{}:{}{}#{}", scheme, address, port, endpoint.path, sni),
+        (Some(scheme), None)        => format!("{}://{}{}#{}", scheme, address, endpoint.path, sni),
+        (None, port)                => format!("{}:{}#{}", address, port.unwrap_or("853"), sni),
+    }
+}
+
+/// Looks up the addresses of a host using the given resolver, querying for
+/// A and AAAA records at the same time.
+pub async fn lookup(host: String, resolver: Nameserver, timeouts: Timeouts) -> Lookup {
+    let (v4, v6) = join(
+        lookup_family(&host, &resolver, timeouts, qtype!(A)),
+        lookup_family(&host, &resolver, timeouts, qtype!(AAAA)),
+    ).await;
+
+    let families = vec![
+        Family { qtype: "A", result: v4 },
+        Family { qtype: "AAAA", result: v6 },
+    ];
+
+    Lookup { host, resolver, families }
+}
+
+/// Looks up the addresses of a host in one address family.
+async fn lookup_family(host: &str, resolver: &str, timeouts: Timeouts, qtype: TypeInt) -> Result<Vec<IpAddr>, TransportError> {
+    let request = Request {
+        transaction_id: rand::random(),
+        flags: Flags::query(),
+        queries: vec![ Query { qname: host.into(), qclass: QClass::IN, qtype } ],
+        additional: Some(Request::additional_record()),
+        signature: None,
+    };
+
+    let transport = TransportType::Automatic.make_transport(resolver.into(), timeouts, &ServerNames::default());
+    let response = transport.send(&request).await?;
+
+    let addresses = response.answers.into_iter().filter_map(|answer| match answer {
+        Answer::Standard { record: Record::A(a), .. }     => Some(IpAddr::V4(a.address)),
+        Answer::Standard { record: Record::AAAA(a), .. }  => Some(IpAddr::V6(a.address)),
+        _                                                 => None,
+    });
+
+    Ok(addresses.collect())
+}
+
+
+/// The parts of a DNS-over-TLS address or DNS-over-HTTPS URL.
+struct Endpoint<'a> {
+    scheme: Option<&'a str>,
+    host: &'a str,
+    port: Option<&'a str>,
+    path: &'a str,
+    sni: Option<&'a str>,
+}
+
+impl<'a> Endpoint<'a> {
+    fn parse(nameserver: &'a str, transport_type: TransportType) -> Option<Self> {
+        let (rest, sni) = match nameserver.split_once('#') {
+            Some((rest, sni))  => (rest, Some(sni)),
+            None               => (nameserver, None),
+        };
+
+        let (scheme, authority, path) = match transport_type {
+            TransportType::TLS => {
+                (None, rest, "")
+            }
+            TransportType::HTTPS => {
+                let (scheme, rest) = rest.split_once("://")?;
+                let slash = rest.find('/').unwrap_or(rest.len());
+                (Some(scheme), &rest[.. slash], &rest[slash ..])
+            }
+            _ => return None,
+        };
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, after) = bracketed.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        }
+        else {
+            match authority.split_once(':') {
+                Some((host, port))  => (host, Some(port)),
+                None                => (authority, None),
+            }
+        };
+
+        Some(Self { scheme, host, port, path, sni })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn hosts() {
+        assert_eq!(host_to_look_up("dns.example", TransportType::TLS),                        Some("dns.example".into()));
+        assert_eq!(host_to_look_up("dns.example:8853#other.example", TransportType::TLS),     Some("dns.example".into()));
+        assert_eq!(host_to_look_up("https://dns.example/dns-query", TransportType::HTTPS),    Some("dns.example".into()));
+        assert_eq!(host_to_look_up("https://dns.example:8443/dns-query", TransportType::HTTPS), Some("dns.example".into()));
+    }
+
+    #[test]
+    fn nothing_to_look_up() {
+        assert_eq!(host_to_look_up("192.0.2.1:853", TransportType::TLS),               None);
+        assert_eq!(host_to_look_up("https://[2001:db8::1]/q", TransportType::HTTPS),   None);
+        assert_eq!(host_to_look_up("dns.example", TransportType::UDP),                 None);
+    }
+
+    #[test]
+    fn rewrite_tls() {
+        let names = ServerNames::default();
+        assert_eq!(rewrite("dns.example", TransportType::TLS, IpAddr::from([192, 0, 2, 1]), &names),
+                   "192.0.2.1:853#dns.example");
+        assert_eq!(rewrite("dns.example:8853", TransportType::TLS, IpAddr::from(Ipv6Addr::LOCALHOST), &names),
+                   "[::1]:8853#dns.example");
+    }
+
+    #[test]
+    fn rewrite_https() {
+        let names = ServerNames { sni: Some("front.example".into()), .. ServerNames::default() };
+        assert_eq!(rewrite("https://dns.example/dns-query", TransportType::HTTPS, IpAddr::from([192, 0, 2, 1]), &names),
+                   "https://192.0.2.1/dns-query#front.example");
+    }
+}
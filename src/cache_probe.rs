@@ -0,0 +1,177 @@
+//! Checking that a caching resolver counts down the TTLs of the records it
+//! hands out, by asking it the same question twice, a little while apart.
+//! A resolver that clamps or stretches TTLs gives itself away by handing
+//! out TTLs that stay the same, or that go back up, between the two.
+
+use std::convert::TryFrom;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use dns::{Request, Response, Answer};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::Nameserver;
+
+
+/// How long to wait between the two queries. It has to be a few seconds,
+/// as TTLs only count in whole seconds.
+const DELAY: Duration = Duration::from_secs(3);
+
+/// How long to wait for each response if the user hasn’t given a response
+/// timeout, so a resolver that never answers doesn’t hold up the others.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How far a TTL can be from what was expected and still count as right.
+/// Each TTL gets rounded down to a whole second by the resolver, and the
+/// queries themselves take time.
+const TOLERANCE: u32 = 1;
+
+
+/// The results of probing one resolver’s cache.
+#[derive(Debug)]
+pub struct CacheProbe {
+
+    /// The resolver that was probed.
+    pub nameserver: Nameserver,
+
+    /// The lowest TTL in the answer section of the first response.
+    pub first: Result<Option<u32>, TransportError>,
+
+    /// The lowest TTL in the answer section of the second response.
+    pub second: Result<Option<u32>, TransportError>,
+
+    /// How long passed between the two responses arriving, in whole seconds.
+    pub elapsed: u32,
+}
+
+/// What the two TTLs say about how the resolver treats them.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Verdict {
+
+    /// The TTL went down by as much time as passed, as it should.
+    CountingDown,
+
+    /// The record expired between the two queries, so the second TTL is a
+    /// fresh one. This doesn’t say anything either way.
+    Refetched,
+
+    /// The TTL was the same both times, so the resolver is handing out a
+    /// fixed TTL, most likely its upper clamp limit.
+    Frozen(u32),
+
+    /// The TTL went back up before the record could have expired, so the
+    /// resolver re-fetched or stretched it. It keeps records for at least
+    /// this long.
+    Stretched(u32),
+
+    /// The TTL went down by more than the time that passed.
+    TooFast,
+
+    /// There were no answers to compare, or one of the queries failed.
+    Inconclusive,
+}
+
+
+impl CacheProbe {
+
+    /// Works out what the two TTLs say about the resolver.
+    pub fn verdict(&self) -> Verdict {
+        let (Ok(Some(first)), Ok(Some(second))) = (&self.first, &self.second) else {
+            return Verdict::Inconclusive;
+        };
+
+        let (first, second) = (*first, *second);
+        let expected = first.saturating_sub(self.elapsed);
+
+        if first <= self.elapsed + TOLERANCE && second > expected + TOLERANCE {
+            Verdict::Refetched
+        }
+        else if second + TOLERANCE < expected {
+            Verdict::TooFast
+        }
+        else if second <= expected + TOLERANCE {
+            Verdict::CountingDown
+        }
+        else if second == first {
+            Verdict::Frozen(first)
+        }
+        else {
+            Verdict::Stretched(second)
+        }
+    }
+}
+
+
+/// Sends the request to the resolver, waits, then sends it again, noting
+/// the lowest answer TTL each time.
+pub fn run(runtime: &mut dns_transport::Runtime, request: &Request, nameserver: Nameserver, transport_type: TransportType,
+           mut timeouts: Timeouts, names: &ServerNames) -> CacheProbe {
+    timeouts.response = timeouts.response.or(Some(DEFAULT_TIMEOUT));
+    let transport = transport_type.make_transport(nameserver.clone(), timeouts, names);
+
+    let first = runtime.block_on(async { transport.send(request).await }).map(|r| lowest_ttl(&r));
+    info!("First cache probe of {} -> {:?}", nameserver, first);
+    let sent = Instant::now();
+
+    thread::sleep(DELAY);
+
+    let second = runtime.block_on(async { transport.send(request).await }).map(|r| lowest_ttl(&r));
+    info!("Second cache probe of {} -> {:?}", nameserver, second);
+    let elapsed = u32::try_from(sent.elapsed().as_secs()).unwrap_or(u32::MAX);
+
+    CacheProbe { nameserver, first, second, elapsed }
+}
+
+/// Returns the lowest TTL of the records in a response’s answer section,
+/// which is when the resolver will next have to go and fetch some of them.
+fn lowest_ttl(response: &Response) -> Option<u32> {
+    response.answers.iter()
+        .filter_map(|a| match a { Answer::Standard { ttl, .. } => Some(*ttl), Answer::Pseudo { .. } => None })
+        .min()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn probe(first: u32, second: u32, elapsed: u32) -> CacheProbe {
+        CacheProbe { nameserver: "127.0.0.1".into(), first: Ok(Some(first)), second: Ok(Some(second)), elapsed }
+    }
+
+    #[test]
+    fn counting_down() {
+        assert_eq!(probe(300, 297, 3).verdict(), Verdict::CountingDown);
+        assert_eq!(probe(300, 296, 3).verdict(), Verdict::CountingDown);
+        assert_eq!(probe(300, 298, 3).verdict(), Verdict::CountingDown);
+    }
+
+    #[test]
+    fn refetched() {
+        assert_eq!(probe(2, 300, 3).verdict(), Verdict::Refetched);
+    }
+
+    #[test]
+    fn frozen() {
+        assert_eq!(probe(3600, 3600, 3).verdict(), Verdict::Frozen(3600));
+    }
+
+    #[test]
+    fn stretched() {
+        assert_eq!(probe(100, 600, 3).verdict(), Verdict::Stretched(600));
+    }
+
+    #[test]
+    fn too_fast() {
+        assert_eq!(probe(300, 250, 3).verdict(), Verdict::TooFast);
+    }
+
+    #[test]
+    fn no_answers() {
+        let probe = CacheProbe { nameserver: "127.0.0.1".into(), first: Ok(None), second: Ok(Some(300)), elapsed: 3 };
+        assert_eq!(probe.verdict(), Verdict::Inconclusive);
+    }
+}
@@ -1,13 +1,18 @@
 //! Colours, colour schemes, and terminal styling.
 
-use ansi_term::Style;
+use ansi_term::{Colour, Style};
 use ansi_term::Color::*;
+use log::*;
 
 
 /// The **colours** are used to paint the input.
 #[derive(Debug, Default)]
 pub struct Colours {
     pub qname: Style,
+    pub ttl: Style,
+    pub error: Style,
+    pub warning: Style,
+    pub annotation: Style,
 
     pub answer: Style,
     pub authority: Style,
@@ -21,10 +26,14 @@ pub struct Colours {
     pub ns: Style,
     pub opt: Style,
     pub ptr: Style,
+    pub rrsig: Style,
+    pub sig: Style,
     pub soa: Style,
     pub srv: Style,
     pub txt: Style,
     pub unknown: Style,
+
+    pub sinkhole: Style,
 }
 
 impl Colours {
@@ -34,6 +43,10 @@ impl Colours {
     pub fn pretty() -> Self {
         Self {
             qname: Blue.bold(),
+            ttl: Style::default(),
+            error: Style::default(),
+            warning: Yellow.bold(),
+            annotation: Style::default().dimmed(),
 
             answer: Style::default(),
             authority: Cyan.normal(),
@@ -47,10 +60,14 @@ impl Colours {
             ns: Red.normal(),
             opt: Purple.normal(),
             ptr: Red.normal(),
+            rrsig: Purple.normal(),
+            sig: Purple.normal(),
             soa: Purple.normal(),
             srv: Cyan.normal(),
             txt: Yellow.normal(),
             unknown: White.on(Red),
+
+            sinkhole: Red.bold(),
         }
     }
 
@@ -60,4 +77,151 @@ impl Colours {
     pub fn plain() -> Self {
         Self::default()
     }
+
+    /// Overrides styles in this palette using a string in the same format
+    /// as `LS_COLORS`: a colon-separated list of `key=codes` pairs, where the
+    /// key is the name of the thing being styled, and the codes are the ANSI
+    /// style codes to style it with, such as `mx=1;36:ttl=33`. Keys or codes
+    /// that aren’t understood are ignored.
+    pub fn customise(&mut self, spec: &str) {
+        for pair in spec.split(':').filter(|p| ! p.is_empty()) {
+            let Some((key, codes)) = pair.split_once('=') else {
+                warn!("Colour setting {:?} has no ‘=’", pair);
+                continue;
+            };
+
+            let Some(style) = parse_style(codes) else {
+                warn!("Invalid style {:?} for colour key {:?}", codes, key);
+                continue;
+            };
+
+            match self.style_mut(key) {
+                Some(slot)  => *slot = style,
+                None        => warn!("Unknown colour key {:?}", key),
+            }
+        }
+    }
+
+    fn style_mut(&mut self, key: &str) -> Option<&mut Style> {
+        match key {
+            "qname"       => Some(&mut self.qname),
+            "ttl"         => Some(&mut self.ttl),
+            "error"       => Some(&mut self.error),
+            "warning"     => Some(&mut self.warning),
+            "annotation"  => Some(&mut self.annotation),
+            "answer"      => Some(&mut self.answer),
+            "authority"   => Some(&mut self.authority),
+            "additional"  => Some(&mut self.additional),
+            "a"           => Some(&mut self.a),
+            "aaaa"        => Some(&mut self.aaaa),
+            "caa"         => Some(&mut self.caa),
+            "cname"       => Some(&mut self.cname),
+            "mx"          => Some(&mut self.mx),
+            "ns"          => Some(&mut self.ns),
+            "opt"         => Some(&mut self.opt),
+            "ptr"         => Some(&mut self.ptr),
+            "rrsig"       => Some(&mut self.rrsig),
+            "sig"         => Some(&mut self.sig),
+            "soa"         => Some(&mut self.soa),
+            "srv"         => Some(&mut self.srv),
+            "txt"         => Some(&mut self.txt),
+            "unknown"     => Some(&mut self.unknown),
+            "sinkhole"    => Some(&mut self.sinkhole),
+            _             => None,
+        }
+    }
+}
+
+
+/// Parses a semicolon-separated list of ANSI style codes, such as `1;32`,
+/// into a style.
+fn parse_style(codes: &str) -> Option<Style> {
+    let mut style = Style::default();
+    let mut codes = codes.split(';');
+
+    while let Some(code) = codes.next() {
+        match code.parse::<u8>().ok()? {
+            0   => style = Style::default(),
+            1   => style = style.bold(),
+            2   => style = style.dimmed(),
+            3   => style = style.italic(),
+            4   => style = style.underline(),
+            5   => style = style.blink(),
+            7   => style = style.reverse(),
+            8   => style = style.hidden(),
+            9   => style = style.strikethrough(),
+
+            fg @ 30 ..= 37  => style = style.fg(basic_colour(fg - 30)),
+            bg @ 40 ..= 47  => style = style.on(basic_colour(bg - 40)),
+            38              => style = style.fg(extended_colour(&mut codes)?),
+            48              => style = style.on(extended_colour(&mut codes)?),
+
+            _   => return None,
+        }
+    }
+
+    Some(style)
+}
+
+/// Returns one of the eight basic terminal colours.
+fn basic_colour(number: u8) -> Colour {
+    match number {
+        0 => Black,
+        1 => Red,
+        2 => Green,
+        3 => Yellow,
+        4 => Blue,
+        5 => Purple,
+        6 => Cyan,
+        _ => White,
+    }
+}
+
+/// Reads the rest of an extended colour code — either `5;n` for one of the
+/// 256 fixed colours, or `2;r;g;b` for an RGB colour — from the list of codes.
+fn extended_colour(codes: &mut std::str::Split<'_, char>) -> Option<Colour> {
+    let mut next = || codes.next().and_then(|c| c.parse::<u8>().ok());
+
+    match next()? {
+        5  => Some(Fixed(next()?)),
+        2  => Some(RGB(next()?, next()?, next()?)),
+        _  => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plain_codes() {
+        assert_eq!(parse_style("0"),     Some(Style::default()));
+        assert_eq!(parse_style("1;32"),  Some(Green.bold()));
+        assert_eq!(parse_style("4;37;41"), Some(White.on(Red).underline()));
+    }
+
+    #[test]
+    fn extended_codes() {
+        assert_eq!(parse_style("38;5;208"),     Some(Fixed(208).normal()));
+        assert_eq!(parse_style("48;2;1;2;3"),   Some(Style::default().on(RGB(1, 2, 3))));
+    }
+
+    #[test]
+    fn invalid_codes() {
+        assert_eq!(parse_style(""),         None);
+        assert_eq!(parse_style("1;blue"),   None);
+        assert_eq!(parse_style("38;5"),     None);
+        assert_eq!(parse_style("99"),       None);
+    }
+
+    #[test]
+    fn customise() {
+        let mut colours = Colours::pretty();
+        colours.customise("mx=1;35:ttl=2:nonsense=1:qname=nope:sinkhole");
+
+        assert_eq!(colours.mx, Purple.bold());
+        assert_eq!(colours.ttl, Style::default().dimmed());
+        assert_eq!(colours.qname, Blue.bold());
+    }
 }
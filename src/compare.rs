@@ -0,0 +1,57 @@
+//! Comparing the records in responses to the same question asked in
+//! different ways, for the modes that send a query more than once.
+
+use dns::Answer;
+
+
+/// Returns the records in one section that aren’t in the other, comparing
+/// their names without case, and ignoring their TTLs, as they count down in
+/// caches. Pseudo-records are skipped, as the OPT record is allowed to
+/// differ between requests.
+pub fn missing_from(section: &[Answer], other: &[Answer]) -> Vec<Answer> {
+    section.iter()
+           .filter(|a| a.is_standard() && ! other.iter().any(|b| same_record(a, b)))
+           .cloned()
+           .collect()
+}
+
+/// Whether two answers hold the same record, apart from their TTLs.
+fn same_record(a: &Answer, b: &Answer) -> bool {
+    match (a, b) {
+        (Answer::Standard { qname: a_name, qclass: a_class, record: a_record, .. },
+         Answer::Standard { qname: b_name, qclass: b_class, record: b_record, .. }) => {
+            a_name.eq_ignore_ascii_case(b_name) && a_class == b_class && a_record == b_record
+        }
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use dns::{Response, Flags, QClass};
+    use dns::record::{Record, A};
+
+    /// An A record for `192.0.2.last`.
+    pub fn a(qname: &str, ttl: u32, last: u8) -> Answer {
+        Answer::Standard { qname: qname.into(), qclass: QClass::IN, ttl, record: Record::A(A { address: [ 192, 0, 2, last ].into() }) }
+    }
+
+    /// A response with the given header flags and answers.
+    pub fn response(flags: u16, answers: Vec<Answer>) -> Response {
+        Response { transaction_id: 0x1234, flags: Flags::from_u16(flags), queries: vec![], answers, authorities: vec![], additionals: vec![] }
+    }
+
+    #[test]
+    fn ignores_case_and_ttl() {
+        assert_eq!(missing_from(&[ a("lookup.dog.", 300, 1) ], &[ a("LookUp.dog.", 299, 1) ]),
+                   Vec::new());
+    }
+
+    #[test]
+    fn different_data() {
+        assert_eq!(missing_from(&[ a("lookup.dog.", 300, 1) ], &[ a("lookup.dog.", 300, 2) ]),
+                   vec![ a("lookup.dog.", 300, 1) ]);
+    }
+}
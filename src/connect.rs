@@ -1,9 +1,17 @@
+use std::collections::HashMap;
+
+use log::*;
+
+use dns::{Request, Response};
 use dns_transport::*;
 
 use crate::resolve::Nameserver;
+use crate::session;
+#[cfg(feature = "sqlite")]
+use crate::transaction_log;
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub enum TransportType {
 
     /// Send packets over UDP or TCP.
@@ -25,18 +33,239 @@ pub enum TransportType {
 
     /// Send encrypted DNS-over-HTTPS packets.
     HTTPS,
+
+    /// Send packets to a service on this machine, over a Unix domain
+    /// socket or a Windows named pipe, framed as they are over TCP.
+    Local,
+}
+
+/// Names to present to encrypted nameservers in place of the ones in their
+/// addresses, so they can be reached by IP address when their own names
+/// can’t be looked up, along with anything else that private DNS-over-HTTPS
+/// endpoints expect requests to have, and how far UDP requests can travel.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct ServerNames {
+
+    /// The name to send as SNI during the TLS handshake, and to check the
+    /// server’s certificate against.
+    pub sni: Option<String>,
+
+    /// The name to send in the `Host` header of DNS-over-HTTPS requests.
+    pub http_host: Option<String>,
+
+    /// Extra headers to send with DNS-over-HTTPS requests, as pairs of
+    /// names and values.
+    pub http_headers: Vec<(String, String)>,
+
+    /// The `User-Agent` header to send with DNS-over-HTTPS requests.
+    pub user_agent: Option<String>,
+
+    /// The path and query to send DNS-over-HTTPS requests to, in place of
+    /// the ones in each nameserver’s URL.
+    pub doh_path: Option<String>,
+
+    /// The IP TTL, or IPv6 hop limit, to send UDP requests with.
+    pub ip_ttl: Option<u8>,
+
+    /// Whether to time UDP requests with kernel timestamps, which is only
+    /// worth doing when the times get shown.
+    pub kernel_timestamps: bool,
 }
 
 impl TransportType {
 
-    /// Creates a boxed `Transport` depending on the transport type.
-    pub fn make_transport(self, ns: Nameserver) -> Box<dyn Transport> {
-        match self {
-            Self::Automatic  => Box::new(AutoTransport::new(ns)),
-            Self::UDP        => Box::new(UdpTransport::new(ns)),
-            Self::TCP        => Box::new(TcpTransport::new(ns)),
-            Self::TLS        => Box::new(TlsTransport::new(ns)),
-            Self::HTTPS      => Box::new(HttpsTransport::new(ns)),
+    /// Every transport that requests can be sent over, which is all of them
+    /// except `Automatic`, as that picks between UDP and TCP.
+    pub const ALL: [Self; 5] = [ Self::UDP, Self::TCP, Self::TLS, Self::HTTPS, Self::Local ];
+
+    /// Creates a boxed `Transport` depending on the transport type. If a
+    /// session is being recorded or replayed, the transport goes through it,
+    /// and if exchanges are being logged to a database, they get logged.
+    pub fn make_transport(self, ns: Nameserver, timeouts: Timeouts, names: &ServerNames) -> Box<dyn Transport> {
+        let nameserver = ns.clone();
+
+        let transport: Box<dyn Transport> = match self {
+            Self::Automatic  => {
+                let mut transport = AutoTransport::new(ns, timeouts);
+                if let Some(ttl) = names.ip_ttl {
+                    transport = transport.with_ip_ttl(ttl);
+                }
+                if names.kernel_timestamps {
+                    transport = transport.with_kernel_timestamps();
+                }
+                Box::new(transport)
+            }
+            Self::UDP        => {
+                let mut transport = UdpTransport::new(ns, timeouts);
+                if let Some(ttl) = names.ip_ttl {
+                    transport = transport.with_ip_ttl(ttl);
+                }
+                if names.kernel_timestamps {
+                    transport = transport.with_kernel_timestamps();
+                }
+                Box::new(transport)
+            }
+            Self::TCP        => Box::new(TcpTransport::new(ns, timeouts)),
+            Self::TLS        => {
+                // An SNI name in the nameserver itself beats the one for
+                // every nameserver
+                let (addr, sni) = match ns.split_once('#') {
+                    Some((addr, sni))  => (addr.to_owned(), Some(sni.to_owned())),
+                    None               => (ns, names.sni.clone()),
+                };
+
+                let transport = TlsTransport::new(addr, timeouts);
+                match sni {
+                    Some(sni)  => Box::new(transport.with_sni(sni)),
+                    None       => Box::new(transport),
+                }
+            }
+            Self::HTTPS      => {
+                // The fragment of a URL never gets sent to the server, so it
+                // can hold an SNI name in the same way
+                let (url, sni) = match ns.split_once('#') {
+                    Some((url, sni))  => (url.to_owned(), Some(sni.to_owned())),
+                    None              => (ns, names.sni.clone()),
+                };
+
+                let mut transport = HttpsTransport::new(url, timeouts);
+                if let Some(sni) = sni {
+                    transport = transport.with_sni(sni);
+                }
+                if let Some(host) = &names.http_host {
+                    transport = transport.with_http_host(host);
+                }
+                if let Some(user_agent) = &names.user_agent {
+                    transport = transport.with_user_agent(user_agent);
+                }
+                if let Some(path) = &names.doh_path {
+                    transport = transport.with_path(path);
+                }
+                for (name, value) in &names.http_headers {
+                    transport = transport.with_header(name, value);
+                }
+                Box::new(transport)
+            }
+            Self::Local      => Box::new(LocalTransport::new(ns, timeouts)),
+        };
+
+        let transport = session::wrap(transport, nameserver.clone(), self);
+
+        #[cfg(feature = "sqlite")]
+        let transport = transaction_log::wrap(transport, nameserver, self);
+
+        transport
+    }
+
+    /// Works out the transport type and address from a nameserver written
+    /// as a URI, such as `tcp://192.0.2.1:5353`, `tls://resolver.example#sni`,
+    /// or `https://resolver.example/dns-query`. DNS-over-HTTPS nameservers
+    /// keep their scheme, as they are URLs anyway. Local services are given
+    /// as `unix:///path/to/socket` or `pipe://name`, the latter becoming the
+    /// path of a Windows named pipe. Returns `None` if the nameserver isn’t
+    /// a URI.
+    pub fn from_uri(nameserver: &str) -> Option<(Self, Nameserver)> {
+        let (scheme, rest) = nameserver.split_once("://")?;
+
+        match scheme {
+            "udp"              => Some((Self::UDP, rest.into())),
+            "tcp"              => Some((Self::TCP, rest.into())),
+            "tls"              => Some((Self::TLS, rest.into())),
+            "https" | "http"   => Some((Self::HTTPS, nameserver.into())),
+            "unix"             => Some((Self::Local, rest.into())),
+            "pipe"             => Some((Self::Local, format!(r"\\.\pipe\{}", rest))),
+            _                  => None,
+        }
+    }
+}
+
+
+/// The transports used during a run, one for each nameserver and transport
+/// type, kept for the whole run so requests to the same DNS-over-HTTPS
+/// server can share a connection.
+pub struct Transports {
+    timeouts: Timeouts,
+    names: ServerNames,
+    retry_over_tcp: bool,
+    made: HashMap<(Nameserver, TransportType), Box<dyn Transport>>,
+}
+
+impl Transports {
+
+    /// Creates an empty set of transports, which will be made with the given
+    /// timeouts and server names, retrying failed automatic requests over
+    /// TCP if `retry_over_tcp` is set.
+    pub fn new(timeouts: Timeouts, names: ServerNames, retry_over_tcp: bool) -> Self {
+        Self { timeouts, names, retry_over_tcp, made: HashMap::new() }
+    }
+
+    /// Makes the transport for the given nameserver and transport type, if
+    /// there isn’t one already. This has to be done before sending, so that
+    /// requests can be sent over several transports at once.
+    pub fn prepare(&mut self, nameserver: &Nameserver, transport_type: TransportType) {
+        let (timeouts, names) = (self.timeouts, &self.names);
+        self.made.entry((nameserver.clone(), transport_type))
+                 .or_insert_with(|| transport_type.make_transport(nameserver.clone(), timeouts, names));
+    }
+
+    /// Sends a request over the transport prepared for its nameserver and
+    /// transport type, retrying over TCP if it was sent automatically, it
+    /// failed, and retrying has been asked for.
+    pub async fn send(&self, request: &Request, nameserver: &Nameserver, transport_type: TransportType) -> Result<(Response, Details), Error> {
+        let transport = &self.made[&(nameserver.clone(), transport_type)];
+        let result = transport.send_with_details(request).await;
+
+        if self.retry_over_tcp && transport_type == TransportType::Automatic && result.is_err() {
+            info!("Request over UDP failed, so retrying over TCP");
+            let transport = TransportType::TCP.make_transport(nameserver.clone(), self.timeouts, &self.names);
+            return transport.send_with_details(request).await;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uri_schemes() {
+        assert_eq!(TransportType::from_uri("udp://192.0.2.1"),           Some((TransportType::UDP, "192.0.2.1".into())));
+        assert_eq!(TransportType::from_uri("tcp://192.0.2.1:5353"),      Some((TransportType::TCP, "192.0.2.1:5353".into())));
+        assert_eq!(TransportType::from_uri("tls://192.0.2.1:8853#dns.example"),
+                   Some((TransportType::TLS, "192.0.2.1:8853#dns.example".into())));
+        assert_eq!(TransportType::from_uri("https://dns.example/dns-query"),
+                   Some((TransportType::HTTPS, "https://dns.example/dns-query".into())));
+    }
+
+    #[test]
+    fn local_schemes() {
+        assert_eq!(TransportType::from_uri("unix:///run/resolver/dns.sock"),
+                   Some((TransportType::Local, "/run/resolver/dns.sock".into())));
+        assert_eq!(TransportType::from_uri("pipe://resolver"),
+                   Some((TransportType::Local, r"\\.\pipe\resolver".into())));
+    }
+
+    #[test]
+    fn not_uris() {
+        assert_eq!(TransportType::from_uri("192.0.2.1"),         None);
+        assert_eq!(TransportType::from_uri("ftp://192.0.2.1"),   None);
+    }
+
+    #[test]
+    fn every_transport_listed() {
+        use TransportType::*;
+
+        // There’s no wildcard, so a new transport type won’t compile until
+        // it’s been added here, and then this fails until it’s in the list
+        for transport in &[ Automatic, UDP, TCP, TLS, HTTPS, Local ] {
+            let should_be_listed = match transport {
+                Automatic                          => false,
+                UDP | TCP | TLS | HTTPS | Local    => true,
+            };
+
+            assert_eq!(TransportType::ALL.contains(transport), should_be_listed, "{:?}", transport);
         }
     }
 }
@@ -0,0 +1,210 @@
+//! Explaining what went wrong when a query fails, and what usually causes
+//! it, so that a bare `SERVFAIL` or timeout turns into something that can
+//! be acted on.
+
+use std::fmt;
+
+use dns::{Response, Answer, ErrorCode};
+use dns::record::ExtendedError;
+use dns_transport::{Error as TransportError, Phase};
+
+use crate::output;
+
+
+/// An explanation of one failure, shown with `--explain-errors`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnosis {
+
+    /// What failed, such as the domain that was being queried.
+    pub subject: String,
+
+    /// What went wrong, such as `SERVFAIL with EDE 9 (DNSKEY Missing)`.
+    pub problem: String,
+
+    /// What that means and what usually causes it.
+    pub hint: &'static str,
+}
+
+
+/// Explains why a response came back with an error, using the Extended DNS
+/// Error in its OPT record if it has one. Returns `None` if the response
+/// didn’t have an error code or an Extended DNS Error.
+pub fn of_response(response: &Response) -> Option<Diagnosis> {
+    let subject = response.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+    let ede = response.additionals.iter().find_map(|a| match a {
+        Answer::Pseudo { opt, .. }  => opt.extended_error(),
+        Answer::Standard { .. }     => None,
+    });
+
+    let rcode = response.flags.error_code;
+    let status = output::rcode_name(rcode);
+
+    match (rcode, ede) {
+        (None, None) => None,
+        (_, Some(ExtendedError { info_code, extra_text })) => {
+            let (name, hint) = extended_error(info_code);
+            let problem = if extra_text.is_empty() { format!("{} with EDE {} ({})", status, info_code, name) }
+                                                 else { format!("{} with EDE {} ({}): {:?}", status, info_code, name, extra_text) };
+
+            Some(Diagnosis { subject, problem, hint })
+        }
+        (Some(rcode), None) => {
+            Some(Diagnosis { subject, problem: status, hint: rcode_hint(rcode) })
+        }
+    }
+}
+
+/// Explains why a request to a nameserver failed before getting a response.
+pub fn of_error(nameserver: &str, error: &TransportError) -> Diagnosis {
+    let (problem, hint) = match error {
+        TransportError::NetworkError(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            ("Connection refused", "Nothing is listening on that port. Check the nameserver’s address and port, and that it supports this transport.")
+        }
+        TransportError::NetworkError(_) => {
+            ("Network error", "The nameserver couldn’t be reached. Check the address, and that a firewall isn’t blocking DNS traffic.")
+        }
+        TransportError::HttpError(_) => {
+            ("HTTP error", "The DNS-over-HTTPS request failed. Check that the URL is right, including its path, such as /dns-query.")
+        }
+        TransportError::TlsError(_) => {
+            ("TLS error", "The encrypted connection couldn’t be set up. The certificate may not match the server name, so try --sni.")
+        }
+        TransportError::BadRequest => {
+            ("HTTP 400 Bad Request", "The DNS-over-HTTPS server didn’t understand the request, so the URL may point at something that isn’t a DNS endpoint.")
+        }
+        TransportError::WireError(_) => {
+            ("Malformed response", "The response couldn’t be decoded. Something on the path may be mangling DNS packets, or the server is broken.")
+        }
+        TransportError::Timeout(Phase::Connect) => {
+            ("Timed out connecting", "The nameserver didn’t accept the connection. It may be down, or a firewall may be dropping the packets.")
+        }
+        TransportError::Timeout(Phase::Handshake) => {
+            ("Timed out during the TLS handshake", "The nameserver accepted the connection, but didn’t finish setting up encryption. It may not support TLS on that port.")
+        }
+        TransportError::Timeout(Phase::Response) => {
+            ("Timed out waiting for a response", "The nameserver never answered. It may be down, rate-limiting, or unable to reach the authoritative servers. UDP packets may also be getting dropped, so try --tcp.")
+        }
+    };
+
+    Diagnosis { subject: nameserver.into(), problem: problem.into(), hint }
+}
+
+/// Explains an error code on its own, for when there’s no Extended DNS
+/// Error to say more.
+fn rcode_hint(rcode: ErrorCode) -> &'static str {
+    match rcode {
+        ErrorCode::FormatError     => "The nameserver couldn’t understand the query. It may not support EDNS, so try --edns=disable.",
+        ErrorCode::ServerFailure   => "The resolver couldn’t get an answer. The zone’s DNSSEC may be broken, or its nameservers may be unreachable or misconfigured.",
+        ErrorCode::NXDomain        => "The domain doesn’t exist. Check it for typos, and that the zone has been delegated by its parent.",
+        ErrorCode::NotImplemented  => "The nameserver doesn’t support this kind of query.",
+        ErrorCode::QueryRefused    => "The nameserver won’t answer. It may only answer for its own zones, or only for clients on its own network.",
+        ErrorCode::BadVersion      => "The nameserver doesn’t support the EDNS version in the query, or a signature failed to verify.",
+        ErrorCode::Other(_)        => "The nameserver returned an error code that dog doesn’t know about.",
+    }
+}
+
+/// Returns the name of an Extended DNS Error info code, along with what it
+/// means and what usually causes it.
+fn extended_error(info_code: u16) -> (&'static str, &'static str) {
+    match info_code {
+        0   => ("Other Error",                   "The nameserver gave no more detail, except maybe in its extra text."),
+        1   => ("Unsupported DNSKEY Algorithm",  "The zone is signed with an algorithm the resolver can’t validate, so it was treated as unsigned."),
+        2   => ("Unsupported DS Digest Type",    "The parent’s DS record uses a digest the resolver can’t check, so the zone was treated as unsigned."),
+        3   => ("Stale Answer",                  "The resolver couldn’t refresh the record, so it answered from an expired cache entry. The zone’s nameservers may be unreachable."),
+        4   => ("Forged Answer",                 "The resolver deliberately returned a different answer, usually because of a policy or filter."),
+        5   => ("DNSSEC Indeterminate",          "The resolver couldn’t tell whether the answer should be signed."),
+        6   => ("DNSSEC Bogus",                  "The zone’s DNSSEC signatures failed to validate, so the zone’s DNSSEC chain appears broken."),
+        7   => ("Signature Expired",             "The zone’s signatures have expired. The zone probably needs to be re-signed."),
+        8   => ("Signature Not Yet Valid",       "The zone’s signatures aren’t valid yet. The signer’s clock, or the resolver’s, may be wrong."),
+        9   => ("DNSKEY Missing",                "A DS record points at a DNSKEY that the zone doesn’t have, so the zone’s DNSSEC chain appears broken. A key rollover may have gone wrong."),
+        10  => ("RRSIGs Missing",                "The zone should be signed, but the answer came without signatures."),
+        11  => ("No Zone Key Bit Set",           "The zone’s DNSKEY doesn’t have the Zone Key bit set, so it can’t be used to validate the zone."),
+        12  => ("NSEC Missing",                  "The zone didn’t prove that the name doesn’t exist, which a signed zone has to."),
+        13  => ("Cached Error",                  "The resolver is repeating an error it got earlier. Wait for it to expire, or ask another resolver."),
+        14  => ("Not Ready",                     "The nameserver hasn’t finished starting up, or hasn’t loaded the zone yet."),
+        15  => ("Blocked",                       "The resolver’s operator has blocked this domain."),
+        16  => ("Censored",                      "The domain is blocked because of an outside requirement, such as a court order."),
+        17  => ("Filtered",                      "The domain is blocked because of a filter the client asked for, such as a malware or adult content filter."),
+        18  => ("Prohibited",                    "The nameserver won’t answer this client. It may only serve clients on its own network."),
+        19  => ("Stale NXDomain Answer",         "The resolver couldn’t refresh the record, so it answered from an expired NXDOMAIN in its cache."),
+        20  => ("Not Authoritative",             "The nameserver isn’t authoritative for this zone and doesn’t do recursion."),
+        21  => ("Not Supported",                 "The nameserver doesn’t support the kind of query that was sent."),
+        22  => ("No Reachable Authority",        "The resolver couldn’t reach any of the zone’s nameservers. They may be down, or the delegation may be wrong."),
+        23  => ("Network Error",                 "The resolver ran into a network error talking to the zone’s nameservers."),
+        24  => ("Invalid Data",                  "The zone’s nameservers returned data that the resolver couldn’t use."),
+        25  => ("Signature Expired before Valid", "The zone’s signatures expire before they become valid, so the signer is misconfigured."),
+        26  => ("Too Early",                     "The request was sent too early in a TLS session to be answered."),
+        27  => ("Unsupported NSEC3 Iterations Value", "The zone uses more NSEC3 iterations than the resolver will do, so it was treated as unsigned."),
+        28  => ("Unable to Conform to Policy",   "The nameserver couldn’t apply its policy to the query."),
+        29  => ("Synthesized",                   "The answer was made up by the resolver, such as from DNS64, rather than coming from the zone."),
+        30  => ("Invalid Query Type",            "The query type can’t be asked for, such as a meta-type."),
+        _   => ("Unknown",                       "The nameserver used an Extended DNS Error code that dog doesn’t know about."),
+    }
+}
+
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} for {}: {}", self.problem, self.subject, self.hint)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+    use dns::record::OPT;
+
+    fn response(flags: u16, additionals: Vec<Answer>) -> Response {
+        Response {
+            transaction_id: 0xABCD,
+            flags: Flags::from_u16(flags),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: QClass::IN, qtype: 1 } ],
+            answers: vec![],
+            authorities: vec![],
+            additionals,
+        }
+    }
+
+    fn ede(data: Vec<u8>) -> Answer {
+        Answer::Pseudo { qname: String::new(), opt: OPT { udp_payload_size: 1232, higher_bits: 0, edns0_version: 0, flags: 0, data } }
+    }
+
+    #[test]
+    fn no_error() {
+        assert_eq!(of_response(&response(0x8180, vec![])), None);
+    }
+
+    #[test]
+    fn rcode_only() {
+        let diagnosis = of_response(&response(0x8183, vec![])).unwrap();
+        assert_eq!(diagnosis.subject, "lookup.dog.");
+        assert_eq!(diagnosis.problem, "NXDOMAIN");
+    }
+
+    #[test]
+    fn extended_error() {
+        let diagnosis = of_response(&response(0x8182, vec![ ede(vec![ 0x00, 0x0F, 0x00, 0x02, 0x00, 0x09 ]) ])).unwrap();
+        assert_eq!(diagnosis.problem, "SERVFAIL with EDE 9 (DNSKEY Missing)");
+        assert!(diagnosis.hint.contains("DNSSEC chain appears broken"));
+    }
+
+    #[test]
+    fn extended_error_with_text() {
+        let diagnosis = of_response(&response(0x8182, vec![ ede(vec![ 0x00, 0x0F, 0x00, 0x05, 0x00, 0x16, 0x64, 0x6F, 0x67 ]) ])).unwrap();
+        assert_eq!(diagnosis.problem, "SERVFAIL with EDE 22 (No Reachable Authority): \"dog\"");
+    }
+
+    #[test]
+    fn extended_error_without_rcode() {
+        let diagnosis = of_response(&response(0x8180, vec![ ede(vec![ 0x00, 0x0F, 0x00, 0x02, 0x00, 0x03 ]) ])).unwrap();
+        assert_eq!(diagnosis.problem, "NOERROR with EDE 3 (Stale Answer)");
+    }
+
+    #[test]
+    fn timeout() {
+        let diagnosis = of_error("192.0.2.1", &TransportError::Timeout(Phase::Response));
+        assert_eq!(diagnosis.to_string(), format!("Timed out waiting for a response for 192.0.2.1: {}", diagnosis.hint));
+    }
+}
@@ -0,0 +1,286 @@
+//! Sending the same query with and without asking for DNSSEC records, and
+//! with and without validation, then comparing the responses. A query that
+//! only fails when the resolver validates it points at broken signatures,
+//! rather than a broken zone or network.
+
+use futures_util::future::join3;
+use log::*;
+
+use dns::{Request, Response, Answer, ErrorCode, Wire};
+use dns::record::{Record, OPT, RRSIG};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::compare;
+use crate::connect::{TransportType, ServerNames};
+use crate::order;
+use crate::resolve::Nameserver;
+
+
+/// One way of asking a resolver the same question.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum View {
+
+    /// Without the DNSSEC OK bit, so the response has no DNSSEC records.
+    /// A validating resolver still validates the answer.
+    Unsigned,
+
+    /// With the DNSSEC OK bit, so the response comes with its signatures.
+    Signed,
+
+    /// With the DNSSEC OK and Checking Disabled bits, so the resolver hands
+    /// out the records without validating them.
+    Unchecked,
+}
+
+/// The responses to one query asked in every view.
+#[derive(Debug)]
+pub struct Comparison {
+
+    /// The nameserver the requests were sent to.
+    pub nameserver: Nameserver,
+
+    /// The name that was queried.
+    pub qname: String,
+
+    /// The response to each view, starting with the unsigned one, which the
+    /// others get compared against.
+    pub views: Vec<(View, Result<Response, TransportError>)>,
+}
+
+/// One way in which a view’s response differs from the unsigned one.
+#[derive(PartialEq, Debug)]
+pub enum Difference {
+
+    /// The view’s response had a different response code.
+    Status {
+        view: View,
+        unsigned: Option<ErrorCode>,
+        status: Option<ErrorCode>,
+    },
+
+    /// An answer in the unsigned response was missing from the view’s.
+    Missing {
+        view: View,
+        answer: Answer,
+    },
+
+    /// An answer in the view’s response wasn’t in the unsigned one.
+    Extra {
+        view: View,
+        answer: Answer,
+    },
+}
+
+
+impl View {
+
+    /// Every view, with the unsigned one that gets compared against first.
+    pub const ALL: [Self; 3] = [ Self::Unsigned, Self::Signed, Self::Unchecked ];
+
+    /// The name of the view, for output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unsigned   => "unsigned",
+            Self::Signed     => "signed",
+            Self::Unchecked  => "unchecked",
+        }
+    }
+
+    /// Whether requests in this view set the DNSSEC OK bit.
+    pub fn dnssec_ok(self) -> bool {
+        self != Self::Unsigned
+    }
+
+    /// Whether requests in this view set the Checking Disabled bit.
+    pub fn checking_disabled(self) -> bool {
+        self == Self::Unchecked
+    }
+
+    /// Returns a copy of the request with its bits set for this view. A
+    /// request without an OPT record gets one, as the DNSSEC OK bit goes in
+    /// it. Any signature gets removed, as it wouldn’t match the new bits.
+    fn apply(self, request: &Request) -> Request {
+        let mut request = request.clone();
+        request.signature = None;
+        request.flags.checking_disabled = self.checking_disabled();
+
+        if self.dnssec_ok() {
+            let opt = request.additional.get_or_insert_with(Request::additional_record);
+            opt.flags |= OPT::DNSSEC_OK;
+        }
+        else if let Some(opt) = &mut request.additional {
+            opt.flags &= ! OPT::DNSSEC_OK;
+        }
+
+        request
+    }
+}
+
+
+/// Sends the request to the nameserver in every view at the same time, and
+/// waits for all the responses.
+pub async fn run(request: &Request, nameserver: Nameserver, transport_type: TransportType, timeouts: Timeouts, names: &ServerNames) -> Comparison {
+    let transport = transport_type.make_transport(nameserver.clone(), timeouts, names);
+    let [ unsigned, signed, unchecked ] = View::ALL.map(|view| view.apply(request));
+
+    let (unsigned, signed, unchecked) = join3(transport.send(&unsigned), transport.send(&signed), transport.send(&unchecked)).await;
+    info!("DNSSEC views from {} -> {:?}, {:?}, and {:?}", nameserver, unsigned, signed, unchecked);
+
+    let qname = request.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+    let views = vec![ (View::Unsigned, unsigned), (View::Signed, signed), (View::Unchecked, unchecked) ];
+    Comparison { nameserver, qname, views }
+}
+
+
+impl Comparison {
+
+    /// Returns every difference between each view’s response and the
+    /// unsigned one, skipping any views whose requests failed.
+    pub fn differences(&self) -> Vec<Difference> {
+        let Some((_, Ok(unsigned))) = self.views.first() else {
+            return Vec::new();
+        };
+
+        let mut differences = Vec::new();
+        for (view, response) in self.views.iter().skip(1) {
+            let Ok(response) = response else { continue };
+
+            if response.flags.error_code != unsigned.flags.error_code {
+                differences.push(Difference::Status { view: *view, unsigned: unsigned.flags.error_code, status: response.flags.error_code });
+            }
+
+            for answer in missing_from(&unsigned.answers, &response.answers) {
+                differences.push(Difference::Missing { view: *view, answer });
+            }
+
+            for answer in missing_from(&response.answers, &unsigned.answers) {
+                differences.push(Difference::Extra { view: *view, answer });
+            }
+        }
+
+        differences
+    }
+
+    /// Whether the query fails in a view that gets validated, but not in
+    /// the one that doesn’t, which is what broken signatures look like.
+    pub fn fails_only_when_validating(&self) -> bool {
+        let servfail = |response: &Result<Response, TransportError>| {
+            matches!(response, Ok(r) if r.flags.error_code == Some(ErrorCode::ServerFailure))
+        };
+
+        let validated_failed = self.views.iter().any(|(view, response)| ! view.checking_disabled() && servfail(response));
+        let unchecked_passed = self.views.iter().any(|(view, response)| view.checking_disabled() && response.is_ok() && ! servfail(response));
+        validated_failed && unchecked_passed
+    }
+}
+
+/// Returns the answers in one response that aren’t in the other, like
+/// `compare::missing_from`, but also skipping DNSSEC records, as they only
+/// get sent in some views.
+fn missing_from(answers: &[Answer], other: &[Answer]) -> Vec<Answer> {
+    compare::missing_from(answers, other).into_iter()
+        .filter(|a| matches!(a, Answer::Standard { record, .. } if ! is_dnssec_record(record)))
+        .collect()
+}
+
+/// The type numbers of NSEC and NSEC3 records, which dog doesn’t decode.
+const NSEC: u16 = 47;
+const NSEC3: u16 = 50;
+
+/// Whether the record is one that only gets sent to clients that ask for
+/// DNSSEC records.
+fn is_dnssec_record(record: &Record) -> bool {
+    matches!(order::type_number(record), RRSIG::RR_TYPE | NSEC | NSEC3)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+    use dns::record::UnknownQtype;
+    use crate::compare::test::{a, response};
+
+    fn comparison(unsigned: Response, signed: Response, unchecked: Response) -> Comparison {
+        let views = vec![ (View::Unsigned, Ok(unsigned)), (View::Signed, Ok(signed)), (View::Unchecked, Ok(unchecked)) ];
+        Comparison { nameserver: "192.0.2.53".into(), qname: "lookup.dog.".into(), views }
+    }
+
+    #[test]
+    fn views_agree() {
+        let comparison = comparison(response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]),
+                                    response(0b_1000_0001_1010_0000, vec![ a("LookUp.dog.", 299, 1) ]),
+                                    response(0b_1000_0001_1001_0000, vec![ a("lookup.dog.", 300, 1) ]));
+
+        assert_eq!(comparison.differences(), Vec::new());
+        assert!(! comparison.fails_only_when_validating());
+    }
+
+    #[test]
+    fn validation_failure() {
+        let comparison = comparison(response(0b_1000_0001_1000_0010, vec![]),
+                                    response(0b_1000_0001_1000_0010, vec![]),
+                                    response(0b_1000_0001_1001_0000, vec![ a("lookup.dog.", 300, 1) ]));
+
+        assert_eq!(comparison.differences(), vec![
+            Difference::Status { view: View::Unchecked, unsigned: Some(ErrorCode::ServerFailure), status: None },
+            Difference::Extra { view: View::Unchecked, answer: a("lookup.dog.", 300, 1) },
+        ]);
+        assert!(comparison.fails_only_when_validating());
+    }
+
+    #[test]
+    fn different_answers() {
+        let comparison = comparison(response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]),
+                                    response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 2) ]),
+                                    response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]));
+
+        assert_eq!(comparison.differences(), vec![
+            Difference::Missing { view: View::Signed, answer: a("lookup.dog.", 300, 1) },
+            Difference::Extra { view: View::Signed, answer: a("lookup.dog.", 300, 2) },
+        ]);
+    }
+
+    #[test]
+    fn dnssec_records_skipped() {
+        let nsec = Answer::Standard { qname: "lookup.dog.".into(), qclass: QClass::IN, ttl: 300, record: Record::Other { type_number: UnknownQtype::HeardOf("NSEC"), bytes: vec![] } };
+        let comparison = comparison(response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]),
+                                    response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1), nsec.clone() ]),
+                                    response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1), nsec ]));
+
+        assert_eq!(comparison.differences(), Vec::new());
+    }
+
+    #[test]
+    fn failed_baseline() {
+        let mut comparison = comparison(response(0b_1000_0001_1000_0000, vec![]),
+                                        response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]),
+                                        response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]));
+        comparison.views[0].1 = Err(TransportError::Timeout(dns_transport::Phase::Response));
+
+        assert_eq!(comparison.differences(), Vec::new());
+    }
+
+    #[test]
+    fn bits_for_each_view() {
+        let request = Request {
+            transaction_id: 0xABCD,
+            flags: Flags::query(),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: QClass::IN, qtype: 1 } ],
+            additional: None,
+            signature: None,
+        };
+
+        let unsigned = View::Unsigned.apply(&request);
+        assert!(! unsigned.flags.checking_disabled);
+        assert_eq!(unsigned.additional, None);
+
+        let signed = View::Signed.apply(&request);
+        assert!(! signed.flags.checking_disabled);
+        assert_eq!(signed.additional.map(|opt| opt.flags & OPT::DNSSEC_OK), Some(OPT::DNSSEC_OK));
+
+        let unchecked = View::Unchecked.apply(&request);
+        assert!(unchecked.flags.checking_disabled);
+        assert_eq!(unchecked.additional.map(|opt| opt.flags & OPT::DNSSEC_OK), Some(OPT::DNSSEC_OK));
+    }
+}
@@ -0,0 +1,368 @@
+//! Decoding the Encrypted Client Hello configurations in the `ech`
+//! parameter of SVCB and HTTPS records, so a broken one can be spotted
+//! without having to pick apart the base64 by hand.
+//!
+//! The parameter holds an `ECHConfigList`: a length-prefixed list of
+//! configurations, each with a version and a length, so versions that
+//! aren’t understood can be skipped over. Each configuration for the
+//! current version says which public name to send in the outer Client
+//! Hello, and which key and cipher suites to encrypt the inner one with.
+//!
+//! # Reference
+//!
+//! - [draft-ietf-tls-esni-22](https://datatracker.ietf.org/doc/draft-ietf-tls-esni/) — TLS Encrypted Client Hello (September 2024)
+//! - [RFC 9180 §7](https://tools.ietf.org/html/rfc9180) — Hybrid Public Key Encryption, Algorithm Identifiers (February 2022)
+
+use std::fmt;
+
+
+/// The version of configuration that gets decoded. Earlier drafts used
+/// other numbers, which get reported but not decoded.
+const CURRENT_VERSION: u16 = 0xfe0d;
+
+/// One configuration in the list.
+#[derive(PartialEq, Debug)]
+pub enum Config {
+
+    /// A configuration in the current version.
+    Current {
+        config_id: u8,
+        kem_id: u16,
+        public_key_length: usize,
+        cipher_suites: Vec<(u16, u16)>,
+        maximum_name_length: u8,
+        public_name: String,
+        extensions: Vec<u16>,
+    },
+
+    /// A configuration in a version that isn’t understood, which clients
+    /// skip over.
+    Unsupported {
+        version: u16,
+    },
+}
+
+/// Something wrong with the structure of a list of configurations.
+#[derive(PartialEq, Debug)]
+pub enum Invalid {
+
+    /// The data ended before a field that should have been there.
+    Truncated(&'static str),
+
+    /// There were bytes left over after the end of a field.
+    TrailingBytes(&'static str),
+
+    /// The list had no configurations in it.
+    NoConfigs,
+
+    /// A configuration had no cipher suites in it.
+    NoCipherSuites,
+
+    /// A configuration’s public key is the wrong length for its KEM.
+    PublicKeyLength {
+        kem_id: u16,
+        length: usize,
+    },
+
+    /// A configuration’s public name isn’t a valid host name.
+    PublicName(String),
+}
+
+
+/// Describes the value of an `ech` parameter, decoding each configuration
+/// in it, or saying what’s wrong with it alongside the raw value if it
+/// can’t be decoded.
+pub fn describe(value: &[u8]) -> String {
+    match parse(value) {
+        Ok(configs) => {
+            let configs = configs.iter().map(Config::to_string).collect::<Vec<_>>();
+            format!("ech=[{}]", configs.join("; "))
+        }
+        Err(invalid) => {
+            format!("ech={} (invalid: {})", base64::encode(value), invalid)
+        }
+    }
+}
+
+/// Parses an `ECHConfigList`, checking that every length within it adds
+/// up and that each configuration could be used.
+pub fn parse(value: &[u8]) -> Result<Vec<Config>, Invalid> {
+    let mut list = Reader(value);
+    let mut configs_data = Reader(list.vector16("list")?);
+    list.finish("list")?;
+
+    let mut configs = Vec::new();
+    while ! configs_data.0.is_empty() {
+        let version = configs_data.u16("config version")?;
+        let contents = configs_data.vector16("config")?;
+
+        if version == CURRENT_VERSION {
+            configs.push(parse_contents(contents)?);
+        }
+        else {
+            configs.push(Config::Unsupported { version });
+        }
+    }
+
+    if configs.is_empty() {
+        return Err(Invalid::NoConfigs);
+    }
+
+    Ok(configs)
+}
+
+fn parse_contents(contents: &[u8]) -> Result<Config, Invalid> {
+    let mut contents = Reader(contents);
+
+    let config_id = contents.u8("config ID")?;
+    let kem_id = contents.u16("KEM")?;
+    let public_key_length = contents.vector16("public key")?.len();
+    if let Some(expected) = public_key_size(kem_id) {
+        if public_key_length != expected {
+            return Err(Invalid::PublicKeyLength { kem_id, length: public_key_length });
+        }
+    }
+
+    let mut suites = Reader(contents.vector16("cipher suites")?);
+    let mut cipher_suites = Vec::new();
+    while ! suites.0.is_empty() {
+        cipher_suites.push((suites.u16("KDF")?, suites.u16("AEAD")?));
+    }
+
+    if cipher_suites.is_empty() {
+        return Err(Invalid::NoCipherSuites);
+    }
+
+    let maximum_name_length = contents.u8("maximum name length")?;
+    let public_name = String::from_utf8_lossy(contents.vector8("public name")?).to_string();
+    if ! is_host_name(&public_name) {
+        return Err(Invalid::PublicName(public_name));
+    }
+
+    let mut extensions_data = Reader(contents.vector16("extensions")?);
+    let mut extensions = Vec::new();
+    while ! extensions_data.0.is_empty() {
+        extensions.push(extensions_data.u16("extension type")?);
+        extensions_data.vector16("extension")?;
+    }
+
+    contents.finish("config")?;
+    Ok(Config::Current { config_id, kem_id, public_key_length, cipher_suites, maximum_name_length, public_name, extensions })
+}
+
+/// Whether the name could be sent as the server name in a Client Hello:
+/// letters, digits, and hyphens, in dot-separated labels that aren’t empty.
+fn is_host_name(name: &str) -> bool {
+    ! name.is_empty() && name.trim_end_matches('.').split('.').all(|label| {
+        ! label.is_empty() && label.len() <= 63 && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+
+/// Reads fields from the front of a slice of bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize, field: &'static str) -> Result<&'a [u8], Invalid> {
+        if self.0.len() < length {
+            return Err(Invalid::Truncated(field));
+        }
+
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self, field: &'static str) -> Result<u8, Invalid> {
+        Ok(self.take(1, field)?[0])
+    }
+
+    fn u16(&mut self, field: &'static str) -> Result<u16, Invalid> {
+        let bytes = self.take(2, field)?;
+        Ok(u16::from_be_bytes([ bytes[0], bytes[1] ]))
+    }
+
+    fn vector8(&mut self, field: &'static str) -> Result<&'a [u8], Invalid> {
+        let length = self.u8(field)?;
+        self.take(usize::from(length), field)
+    }
+
+    fn vector16(&mut self, field: &'static str) -> Result<&'a [u8], Invalid> {
+        let length = self.u16(field)?;
+        self.take(usize::from(length), field)
+    }
+
+    fn finish(&self, field: &'static str) -> Result<(), Invalid> {
+        if self.0.is_empty() { Ok(()) } else { Err(Invalid::TrailingBytes(field)) }
+    }
+}
+
+
+/// Returns the size of an encoded public key for the KEM, if it’s known.
+fn public_key_size(kem_id: u16) -> Option<usize> {
+    match kem_id {
+        0x0010  => Some(65),
+        0x0011  => Some(97),
+        0x0012  => Some(133),
+        0x0020  => Some(32),
+        0x0021  => Some(56),
+        _       => None,
+    }
+}
+
+/// Returns the name of a key encapsulation mechanism.
+fn kem_name(kem_id: u16) -> String {
+    match kem_id {
+        0x0010  => "P-256".into(),
+        0x0011  => "P-384".into(),
+        0x0012  => "P-521".into(),
+        0x0020  => "X25519".into(),
+        0x0021  => "X448".into(),
+        n       => format!("KEM {:#06x}", n),
+    }
+}
+
+/// Returns the name of a key derivation function.
+fn kdf_name(kdf_id: u16) -> String {
+    match kdf_id {
+        0x0001  => "HKDF-SHA256".into(),
+        0x0002  => "HKDF-SHA384".into(),
+        0x0003  => "HKDF-SHA512".into(),
+        n       => format!("KDF {:#06x}", n),
+    }
+}
+
+/// Returns the name of an AEAD.
+fn aead_name(aead_id: u16) -> String {
+    match aead_id {
+        0x0001  => "AES-128-GCM".into(),
+        0x0002  => "AES-256-GCM".into(),
+        0x0003  => "ChaCha20Poly1305".into(),
+        0xFFFF  => "export-only".into(),
+        n       => format!("AEAD {:#06x}", n),
+    }
+}
+
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Current { config_id, kem_id, cipher_suites, maximum_name_length, public_name, extensions, .. } => {
+                let suites = cipher_suites.iter().map(|(kdf, aead)| format!("{}/{}", kdf_name(*kdf), aead_name(*aead))).collect::<Vec<_>>();
+                write!(f, "config {}, public name {}, {} with {}", config_id, public_name, kem_name(*kem_id), suites.join(" or "))?;
+
+                if *maximum_name_length > 0 {
+                    write!(f, ", max name length {}", maximum_name_length)?;
+                }
+
+                if ! extensions.is_empty() {
+                    let extensions = extensions.iter().map(|e| format!("{:#06x}", e)).collect::<Vec<_>>();
+                    write!(f, ", extensions {}", extensions.join(","))?;
+                }
+
+                Ok(())
+            }
+            Self::Unsupported { version } => {
+                write!(f, "unsupported version {:#06x}", version)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(field)      => write!(f, "{} is truncated", field),
+            Self::TrailingBytes(field)  => write!(f, "{} has trailing bytes", field),
+            Self::NoConfigs             => write!(f, "no configs"),
+            Self::NoCipherSuites        => write!(f, "no cipher suites"),
+            Self::PublicKeyLength { kem_id, length } => {
+                write!(f, "{}-byte public key is the wrong size for {}", length, kem_name(*kem_id))
+            }
+            Self::PublicName(name)      => write!(f, "public name {:?} is not a host name", name),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Builds a current-version configuration with an X25519 key and the
+    /// given cipher suites and public name.
+    #[allow(clippy::cast_possible_truncation)]
+    fn config(suites: &[(u16, u16)], public_name: &str) -> Vec<u8> {
+        let mut contents = vec![ 0x2a, 0x00, 0x20, 0x00, 0x20 ];
+        contents.extend([ 0xAB; 32 ]);
+        contents.extend(((suites.len() * 4) as u16).to_be_bytes());
+        for (kdf, aead) in suites {
+            contents.extend(kdf.to_be_bytes());
+            contents.extend(aead.to_be_bytes());
+        }
+        contents.push(0);
+        contents.push(public_name.len() as u8);
+        contents.extend(public_name.bytes());
+        contents.extend([ 0x00, 0x00 ]);
+
+        let mut config = vec![ 0xfe, 0x0d ];
+        config.extend((contents.len() as u16).to_be_bytes());
+        config.extend(contents);
+        config
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn list(configs: &[Vec<u8>]) -> Vec<u8> {
+        let configs = configs.concat();
+        let mut list = (configs.len() as u16).to_be_bytes().to_vec();
+        list.extend(configs);
+        list
+    }
+
+    #[test]
+    fn current_config() {
+        let value = list(&[ config(&[ (1, 1), (1, 3) ], "public.example") ]);
+        assert_eq!(describe(&value),
+                   "ech=[config 42, public name public.example, X25519 with HKDF-SHA256/AES-128-GCM or HKDF-SHA256/ChaCha20Poly1305]");
+    }
+
+    #[test]
+    fn unsupported_version_skipped() {
+        let old = vec![ 0xfe, 0x0a, 0x00, 0x02, 0xFF, 0xFF ];
+        let value = list(&[ old, config(&[ (1, 1) ], "public.example") ]);
+        assert_eq!(describe(&value),
+                   "ech=[unsupported version 0xfe0a; config 42, public name public.example, X25519 with HKDF-SHA256/AES-128-GCM]");
+    }
+
+    #[test]
+    fn truncated_list() {
+        let mut value = list(&[ config(&[ (1, 1) ], "public.example") ]);
+        value.truncate(20);
+        assert_eq!(parse(&value), Err(Invalid::Truncated("list")));
+    }
+
+    #[test]
+    fn empty_list() {
+        assert_eq!(parse(&[ 0x00, 0x00 ]), Err(Invalid::NoConfigs));
+    }
+
+    #[test]
+    fn no_cipher_suites() {
+        let value = list(&[ config(&[], "public.example") ]);
+        assert_eq!(parse(&value), Err(Invalid::NoCipherSuites));
+    }
+
+    #[test]
+    fn bad_public_name() {
+        let value = list(&[ config(&[ (1, 1) ], "not a name") ]);
+        assert_eq!(describe(&value),
+                   format!("ech={} (invalid: public name \"not a name\" is not a host name)", base64::encode(&value)));
+    }
+
+    #[test]
+    fn wrong_key_size() {
+        let mut value = list(&[ config(&[ (1, 1) ], "public.example") ]);
+        value[8] = 0x10;
+        assert_eq!(parse(&value), Err(Invalid::PublicKeyLength { kem_id: 0x0010, length: 32 }));
+    }
+}
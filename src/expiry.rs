@@ -0,0 +1,150 @@
+//! Monitoring how long the DNSSEC signatures on a set of records have left
+//! before they expire, so a signing pipeline that has stopped re-signing a
+//! zone gets noticed before resolvers start rejecting it.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use dns::{Response, Answer};
+use dns::record::Record;
+
+use crate::warnings::Warning;
+
+
+/// A signature over some records, and when it expires.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Expiry {
+
+    /// The owner name of the signed records.
+    pub qname: String,
+
+    /// The type of the records the signature covers.
+    pub type_covered: u16,
+
+    /// The key tag of the key that made the signature.
+    pub key_tag: u16,
+
+    /// When the signature expires, in seconds since the Unix epoch.
+    pub expiration: u32,
+}
+
+impl Expiry {
+
+    /// How long is left until the signature expires, given the current
+    /// time as a number of seconds since the Unix epoch. Returns `None` if
+    /// it has already expired.
+    pub fn remaining(&self, now: u64) -> Option<Duration> {
+        u64::from(self.expiration).checked_sub(now).map(Duration::from_secs)
+    }
+}
+
+
+/// Returns every RRSIG record in every section of a response, along with
+/// when each one expires.
+pub fn signatures(response: &Response) -> Vec<Expiry> {
+    let records = response.answers.iter().chain(&response.authorities).chain(&response.additionals);
+    records.filter_map(|answer| match answer {
+        Answer::Standard { qname, record: Record::RRSIG(rrsig), .. } => Some(Expiry {
+            qname: qname.clone(),
+            type_covered: rrsig.type_covered,
+            key_tag: rrsig.key_tag,
+            expiration: rrsig.signature_expiration,
+        }),
+        _ => None,
+    }).collect()
+}
+
+/// Checks the signature that expires the soonest against the threshold,
+/// returning a warning if it expires within it, or if there were no
+/// signatures to check at all, as a zone that has lost its signatures is
+/// just as worth alerting about.
+pub fn check(signatures: &[Expiry], threshold: Duration, now: u64) -> Option<Warning> {
+    let Some(soonest) = signatures.iter().min_by_key(|expiry| expiry.expiration) else {
+        return Some(Warning::NoSignatures);
+    };
+
+    match soonest.remaining(now) {
+        Some(remaining) if remaining >= threshold => None,
+        remaining => Some(Warning::ExpiringSignature { qname: soonest.qname.clone(), type_covered: soonest.type_covered, remaining }),
+    }
+}
+
+/// Returns the current time as a number of seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, QClass};
+    use dns::record::{RRSIG, A};
+
+    fn rrsig(qname: &str, type_covered: u16, expiration: u32) -> Answer {
+        Answer::Standard {
+            qname: qname.into(),
+            qclass: QClass::IN,
+            ttl: 3600,
+            record: Record::RRSIG(RRSIG {
+                type_covered, algorithm: 13, labels: 2, original_ttl: 3600,
+                signature_expiration: expiration, signature_inception: expiration - 1_209_600,
+                key_tag: 12345, signer_name: "lookup.dog.".into(), signature: vec![ 0xAB ],
+            }),
+        }
+    }
+
+    fn response(answers: Vec<Answer>) -> Response {
+        Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0001_1010_0000),
+            queries: vec![],
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    fn expiry(type_covered: u16, expiration: u32) -> Expiry {
+        Expiry { qname: "lookup.dog.".into(), type_covered, key_tag: 12345, expiration }
+    }
+
+    const DAY: Duration = Duration::from_hours(24);
+
+    #[test]
+    fn finds_signatures() {
+        let mut response = response(vec![ rrsig("lookup.dog.", 1, 1_600_000_000) ]);
+        response.authorities.push(rrsig("lookup.dog.", 6, 1_590_000_000));
+
+        assert_eq!(signatures(&response), vec![ expiry(1, 1_600_000_000), expiry(6, 1_590_000_000) ]);
+    }
+
+    #[test]
+    fn unsigned() {
+        let a = Answer::Standard { qname: "lookup.dog.".into(), qclass: QClass::IN, ttl: 60, record: Record::A(A { address: [ 192, 0, 2, 1 ].into() }) };
+        assert_eq!(signatures(&response(vec![ a ])), Vec::new());
+    }
+
+    #[test]
+    fn plenty_of_time() {
+        let signatures = vec![ expiry(1, 1_600_000_000), expiry(15, 1_590_000_000) ];
+        assert_eq!(check(&signatures, DAY, 1_590_000_000 - 2 * 86_400), None);
+    }
+
+    #[test]
+    fn soonest_expiring() {
+        let signatures = vec![ expiry(1, 1_600_000_000), expiry(15, 1_590_000_000) ];
+        assert_eq!(check(&signatures, DAY, 1_590_000_000 - 3600),
+                   Some(Warning::ExpiringSignature { qname: "lookup.dog.".into(), type_covered: 15, remaining: Some(Duration::from_hours(1)) }));
+    }
+
+    #[test]
+    fn already_expired() {
+        assert_eq!(check(&[ expiry(1, 1_590_000_000) ], DAY, 1_590_000_001),
+                   Some(Warning::ExpiringSignature { qname: "lookup.dog.".into(), type_covered: 1, remaining: None }));
+    }
+
+    #[test]
+    fn no_signatures() {
+        assert_eq!(check(&[], DAY, 1_590_000_000), Some(Warning::NoSignatures));
+    }
+}
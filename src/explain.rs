@@ -0,0 +1,167 @@
+//! Explaining what each field of a structured record means, for people who
+//! are still learning how DNS fits together.
+
+use dns::record::{Record, RRSIG, SIG};
+
+use crate::output::TextFormat;
+
+
+/// One labelled field of a record, with a description of what it’s for.
+#[derive(PartialEq, Debug)]
+pub struct Field {
+
+    /// The name of the field.
+    pub label: &'static str,
+
+    /// The field’s value, formatted for display.
+    pub value: String,
+
+    /// What the field means.
+    pub meaning: &'static str,
+}
+
+impl Field {
+    fn new(label: &'static str, value: impl Into<String>, meaning: &'static str) -> Self {
+        Self { label, value: value.into(), meaning }
+    }
+}
+
+
+/// Breaks a record down into its labelled fields, if it’s one of the record
+/// types with enough structure to be worth explaining.
+pub fn explain(record: &Record, tf: TextFormat) -> Vec<Field> {
+    match record {
+        Record::SOA(soa) => vec![
+            Field::new("primary nameserver", tf.display_name(&soa.mname), "the server holding the original copy of the zone"),
+            Field::new("responsible mailbox", mailbox(&soa.rname), "who to contact about problems with the zone"),
+            Field::new("serial", soa.serial.to_string(), "the zone’s version number, which goes up whenever it changes"),
+            Field::new("refresh", duration(tf, soa.refresh_interval), "how often secondary servers check the primary for changes"),
+            Field::new("retry", duration(tf, soa.retry_interval), "how long secondary servers wait before retrying a failed check"),
+            Field::new("expire", duration(tf, soa.expire_limit), "how long secondary servers keep answering without reaching the primary"),
+            Field::new("minimum", duration(tf, soa.minimum_ttl), "how long resolvers cache answers saying a name or record doesn’t exist"),
+        ],
+        Record::MX(mx) => vec![
+            Field::new("preference", mx.preference.to_string(), "servers with lower numbers are tried first"),
+            Field::new("exchange", tf.display_name(&mx.exchange), "the server that accepts mail for the domain"),
+        ],
+        Record::SRV(srv) => vec![
+            Field::new("priority", srv.priority.to_string(), "servers with lower numbers are tried first"),
+            Field::new("weight", srv.weight.to_string(), "how often to pick this server over others with the same priority"),
+            Field::new("port", srv.port.to_string(), "the port the service runs on"),
+            Field::new("target", tf.display_name(&srv.target), "the server that runs the service"),
+        ],
+        Record::CAA(caa) => vec![
+            Field::new("critical", caa.critical.to_string(), "whether a certificate authority that doesn’t understand the tag must refuse to issue"),
+            Field::new("tag", caa.tag.clone(), caa_tag_meaning(&caa.tag)),
+            Field::new("value", caa.value.clone(), "the certificate authority or address the tag applies to"),
+        ],
+        Record::RRSIG(RRSIG { type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag, signer_name, .. }) |
+        Record::SIG(SIG { type_covered, algorithm, labels, original_ttl, signature_expiration, signature_inception, key_tag, signer_name, .. }) => vec![
+            Field::new("type covered", type_covered.to_string(), "the type of the records this signature is for"),
+            Field::new("algorithm", algorithm.to_string(), "the cryptographic algorithm used to make the signature"),
+            Field::new("labels", labels.to_string(), "how many labels the signed name has, to detect wildcards"),
+            Field::new("original TTL", duration(tf, *original_ttl), "the TTL of the records when they were signed"),
+            Field::new("expiration", tf.format_timestamp(*signature_expiration), "when the signature stops being valid"),
+            Field::new("inception", tf.format_timestamp(*signature_inception), "when the signature starts being valid"),
+            Field::new("key tag", key_tag.to_string(), "which of the signer’s keys made the signature"),
+            Field::new("signer", tf.display_name(signer_name), "the zone that signed the records"),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+
+/// Formats a duration from a record, followed by a spelt-out version of it.
+fn duration(tf: TextFormat, seconds: u32) -> String {
+    format!("{} ({})", tf.format_duration(seconds), humanize(seconds))
+}
+
+/// Spells out a number of seconds in words, such as “1 day, 2 hours”.
+fn humanize(seconds: u32) -> String {
+    if seconds == 0 {
+        return "0 seconds".into();
+    }
+
+    let units = [
+        (seconds / 604_800,          "week"),
+        (seconds % 604_800 / 86400,  "day"),
+        (seconds % 86400 / 3600,     "hour"),
+        (seconds % 3600 / 60,        "minute"),
+        (seconds % 60,               "second"),
+    ];
+
+    units.iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, unit)| format!("{} {}{}", count, unit, if *count == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Turns the mailbox in an SOA record back into an email address, by
+/// replacing its first unescaped dot with an at sign.
+fn mailbox(rname: &str) -> String {
+    let rname = rname.trim_end_matches('.');
+    let mut escaped = false;
+
+    for (index, c) in rname.char_indices() {
+        match c {
+            '\\'                => escaped = ! escaped,
+            '.' if ! escaped    => return format!("{}@{}", rname[.. index].replace('\\', ""), &rname[index + 1 ..]),
+            _                   => escaped = false,
+        }
+    }
+
+    rname.into()
+}
+
+fn caa_tag_meaning(tag: &str) -> &'static str {
+    match tag {
+        "issue"      => "which certificate authorities may issue certificates for the domain",
+        "issuewild"  => "which certificate authorities may issue wildcard certificates for the domain",
+        "iodef"      => "where certificate authorities should report invalid certificate requests",
+        _            => "a property that certificate authorities check before issuing",
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::{A, MX};
+    use crate::output::TimeFormat;
+
+    #[test]
+    fn humanize_durations() {
+        assert_eq!(humanize(0),       "0 seconds");
+        assert_eq!(humanize(1),       "1 second");
+        assert_eq!(humanize(3600),    "1 hour");
+        assert_eq!(humanize(93784),   "1 day, 2 hours, 3 minutes, 4 seconds");
+        assert_eq!(humanize(1_209_600), "2 weeks");
+    }
+
+    #[test]
+    fn mailboxes() {
+        assert_eq!(mailbox("hostmaster.example.com."),     "hostmaster@example.com");
+        assert_eq!(mailbox("john\\.smith.example.com."),   "john.smith@example.com");
+        assert_eq!(mailbox("root"),                        "root");
+    }
+
+    fn text_format() -> TextFormat {
+        TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Absolute, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: true }
+    }
+
+    #[test]
+    fn explain_mx() {
+        let record = Record::MX(MX { preference: 10, exchange: "mail.example.com.".into() });
+        assert_eq!(explain(&record, text_format()), vec![
+            Field::new("preference", "10", "servers with lower numbers are tried first"),
+            Field::new("exchange", "mail.example.com.", "the server that accepts mail for the domain"),
+        ]);
+    }
+
+    #[test]
+    fn nothing_to_explain() {
+        let record = Record::A(A { address: std::net::Ipv4Addr::LOCALHOST });
+        assert_eq!(explain(&record, text_format()), Vec::new());
+    }
+}
@@ -0,0 +1,67 @@
+//! The features this build of dog has, so scripts can check for them with
+//! `dog --version --json` before relying on them.
+
+
+/// A feature, along with the command-line options and commands that use
+/// it. Some features, such as decoding internationalised names, are always
+/// on, so have no arguments of their own.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Feature {
+
+    /// The name of the feature, as it appears in the JSON.
+    pub name: &'static str,
+
+    /// The long options, without their leading dashes, and commands that
+    /// belong to this feature.
+    pub arguments: &'static [&'static str],
+}
+
+/// Every feature, in alphabetical order. An option that isn’t part of the
+/// basic query options has to be in here, or the tests fail.
+pub static FEATURES: &[Feature] = &[
+    Feature { name: "annotate",        arguments: &[ "annotate" ] },
+    Feature { name: "authoritative",   arguments: &[ "authoritative" ] },
+    Feature { name: "bootstrap",       arguments: &[ "bootstrap" ] },
+    Feature { name: "both-protocols",  arguments: &[ "both-protocols" ] },
+    Feature { name: "cache-probe",     arguments: &[ "cache-probe" ] },
+    Feature { name: "check-glue",      arguments: &[ "check-glue" ] },
+    Feature { name: "columns",         arguments: &[ "columns", "full" ] },
+    Feature { name: "display",         arguments: &[ "display" ] },
+    Feature { name: "dnsflagday2020",  arguments: &[ "dnsflagday2020" ] },
+    Feature { name: "dnssec-views",    arguments: &[ "dnssec-views" ] },
+    Feature { name: "ech",             arguments: &[] },
+    Feature { name: "expiry-alert",    arguments: &[ "expiry-alert" ] },
+    Feature { name: "explain",         arguments: &[ "explain" ] },
+    Feature { name: "explain-errors",  arguments: &[ "explain-errors" ] },
+    Feature { name: "filter",          arguments: &[ "grep", "filter" ] },
+    Feature { name: "flood",           arguments: &[ "flood", "qps", "i-own-this-server" ] },
+    Feature { name: "header",          arguments: &[ "header" ] },
+    Feature { name: "history",         arguments: &[ "history", "replay" ] },
+    Feature { name: "http-details",    arguments: &[ "http-details" ] },
+    Feature { name: "http-headers",    arguments: &[ "http-header", "user-agent", "doh-path" ] },
+    Feature { name: "idn",             arguments: &[ "punycode" ] },
+    Feature { name: "ip-ttl",          arguments: &[ "ip-ttl" ] },
+    Feature { name: "log-file",        arguments: &[ "log-file" ] },
+    Feature { name: "paging",          arguments: &[ "limit", "page", "summary" ] },
+    Feature { name: "probe-max-udp",   arguments: &[ "probe-max-udp" ] },
+    Feature { name: "push-zone",       arguments: &[ "push-zone", "primary" ] },
+    Feature { name: "race",            arguments: &[ "race" ] },
+    Feature { name: "server-list",     arguments: &[ "server-list" ] },
+    Feature { name: "server-names",    arguments: &[ "sni", "http-host" ] },
+    Feature { name: "session",         arguments: &[ "record-session", "replay-session" ] },
+    Feature { name: "sig0",            arguments: &[ "sig0" ] },
+    Feature { name: "sinkhole",        arguments: &[] },
+    Feature { name: "sort",            arguments: &[ "sort" ] },
+    #[cfg(feature = "sqlite")]
+    Feature { name: "sqlite",          arguments: &[ "sqlite" ] },
+    Feature { name: "time-format",     arguments: &[ "time-format" ] },
+    Feature { name: "timeouts",        arguments: &[ "timeout", "connect-timeout", "handshake-timeout", "response-timeout" ] },
+    Feature { name: "trust",           arguments: &[ "trust" ] },
+    Feature { name: "type-groups",     arguments: &[] },
+    Feature { name: "zone-format",     arguments: &[ "zone-format" ] },
+];
+
+/// Returns the names of every feature, for the JSON.
+pub fn names() -> Vec<&'static str> {
+    FEATURES.iter().map(|f| f.name).collect()
+}
@@ -0,0 +1,257 @@
+//! Narrowing down the records in a response before they get displayed, so
+//! that only the interesting few out of a huge response are shown.
+
+use dns::{Response, Answer, QClass};
+use dns::record::Record;
+
+use crate::order;
+use crate::zone;
+
+
+/// A test that a record has to pass to be displayed.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Filter {
+
+    /// The record, written out as a line of a zone file, has to contain
+    /// this text. This is the `--grep` option.
+    Grep(String),
+
+    /// One of the record’s fields has to compare with a value in some way.
+    /// This is the `--filter` option.
+    Field(Field, Comparison, String),
+}
+
+/// A field of a record that can be filtered on.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Field {
+
+    /// The owner name, ignoring case.
+    Name,
+
+    /// The name of the record type, ignoring case.
+    Type,
+
+    /// The name of the class, ignoring case.
+    Class,
+
+    /// The TTL, in seconds.
+    TTL,
+
+    /// The record data, as it would be written in a zone file.
+    Data,
+}
+
+/// How a field gets compared with the value it’s filtered on.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Comparison {
+
+    /// The field is exactly the value.
+    Is,
+
+    /// The field is anything but the value.
+    IsNot,
+
+    /// The value appears somewhere in the field.
+    Contains,
+
+    /// The field begins with the value.
+    StartsWith,
+
+    /// The field ends with the value.
+    EndsWith,
+
+    /// The field is a smaller number than the value.
+    LessThan,
+
+    /// The field is a larger number than the value.
+    GreaterThan,
+}
+
+
+impl Filter {
+
+    /// Parses a filter written in the form `FIELD COMPARISON VALUE`, such as
+    /// `rdata contains 192.0.2.` or `ttl < 300`. Returns `None` if it isn’t
+    /// valid, including if a number is compared with something that isn’t
+    /// a number.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut words = input.trim().splitn(3, ' ');
+        let field = match words.next()? {
+            "name"            => Field::Name,
+            "type"            => Field::Type,
+            "class"           => Field::Class,
+            "ttl"             => Field::TTL,
+            "rdata" | "data"  => Field::Data,
+            _                 => return None,
+        };
+
+        let comparison = match words.next()? {
+            "is" | "=" | "=="  => Comparison::Is,
+            "is-not" | "!="    => Comparison::IsNot,
+            "contains"         => Comparison::Contains,
+            "starts-with"      => Comparison::StartsWith,
+            "ends-with"        => Comparison::EndsWith,
+            "<"                => Comparison::LessThan,
+            ">"                => Comparison::GreaterThan,
+            _                  => return None,
+        };
+
+        let value = words.next()?.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        let numeric = matches!(comparison, Comparison::LessThan | Comparison::GreaterThan);
+        let valid = match field {
+            Field::TTL  => (numeric || matches!(comparison, Comparison::Is | Comparison::IsNot)) && value.parse::<u32>().is_ok(),
+            _           => ! numeric,
+        };
+
+        if ! valid {
+            return None;
+        }
+
+        Some(Self::Field(field, comparison, value.into()))
+    }
+
+    /// Whether the record with the given owner name, class, and TTL passes
+    /// this filter.
+    fn matches(&self, qname: &str, qclass: QClass, ttl: u32, record: &Record) -> bool {
+        let type_name = zone::type_name(order::type_number(record));
+        let class_name = format!("{:?}", qclass);
+
+        match self {
+            Self::Grep(text) => {
+                let line = format!("{} {} {} {} {}", qname, ttl, class_name, type_name, zone::presentation(record));
+                line.contains(text.as_str())
+            }
+            Self::Field(Field::TTL, comparison, value) => {
+                let value = value.parse::<u32>().unwrap_or_default();
+                match comparison {
+                    Comparison::Is           => ttl == value,
+                    Comparison::IsNot        => ttl != value,
+                    Comparison::LessThan     => ttl < value,
+                    Comparison::GreaterThan  => ttl > value,
+                    _                        => false,
+                }
+            }
+            Self::Field(field, comparison, value) => {
+                let (text, value) = match field {
+                    Field::Name   => (qname.to_ascii_lowercase(), value.to_ascii_lowercase()),
+                    Field::Type   => (type_name.to_ascii_lowercase(), value.to_ascii_lowercase()),
+                    Field::Class  => (class_name.to_ascii_lowercase(), value.to_ascii_lowercase()),
+                    Field::TTL    => unreachable!(),
+                    Field::Data   => (zone::presentation(record), value.clone()),
+                };
+
+                match comparison {
+                    Comparison::Is           => text == value,
+                    Comparison::IsNot        => text != value,
+                    Comparison::Contains     => text.contains(&value),
+                    Comparison::StartsWith   => text.starts_with(&value),
+                    Comparison::EndsWith     => text.ends_with(&value),
+                    _                        => false,
+                }
+            }
+        }
+    }
+}
+
+
+/// Removes the records in every section of a response that don’t pass all
+/// of the filters. Pseudo-records are left alone.
+pub fn apply(filters: &[Filter], response: &mut Response) {
+    let passes = |answer: &Answer| match answer {
+        Answer::Standard { qname, qclass, ttl, record } => filters.iter().all(|f| f.matches(qname, *qclass, *ttl, record)),
+        Answer::Pseudo { .. }                           => true,
+    };
+
+    response.answers.retain(passes);
+    response.authorities.retain(passes);
+    response.additionals.retain(passes);
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::*;
+    use std::net::Ipv4Addr;
+
+    fn a(last: u8) -> Record {
+        Record::A(A { address: Ipv4Addr::new(192, 0, 2, last) })
+    }
+
+    #[test]
+    fn parse_contains() {
+        assert_eq!(Filter::parse("rdata contains 192.0.2."),
+                   Some(Filter::Field(Field::Data, Comparison::Contains, "192.0.2.".into())));
+    }
+
+    #[test]
+    fn parse_value_with_spaces() {
+        assert_eq!(Filter::parse("data is 10 mail.lookup.dog."),
+                   Some(Filter::Field(Field::Data, Comparison::Is, "10 mail.lookup.dog.".into())));
+    }
+
+    #[test]
+    fn parse_ttl() {
+        assert_eq!(Filter::parse("ttl < 300"),
+                   Some(Filter::Field(Field::TTL, Comparison::LessThan, "300".into())));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Filter::parse("rdata"),                None);
+        assert_eq!(Filter::parse("rdata contains"),       None);
+        assert_eq!(Filter::parse("colour is red"),        None);
+        assert_eq!(Filter::parse("rdata matches x"),      None);
+        assert_eq!(Filter::parse("ttl < soon"),           None);
+        assert_eq!(Filter::parse("ttl contains 3"),       None);
+        assert_eq!(Filter::parse("name > lookup.dog"),    None);
+    }
+
+    #[test]
+    fn grep() {
+        let filter = Filter::Grep("IN A 192.0.2.1".into());
+        assert!(filter.matches("lookup.dog.", QClass::IN, 60, &a(1)));
+        assert!(filter.matches("lookup.dog.", QClass::IN, 60, &a(12)));
+        assert!(! filter.matches("lookup.dog.", QClass::IN, 60, &a(2)));
+    }
+
+    #[test]
+    fn names_ignore_case() {
+        let filter = Filter::parse("name ends-with .DOG.").unwrap();
+        assert!(filter.matches("lookup.dog.", QClass::IN, 60, &a(1)));
+    }
+
+    #[test]
+    fn types() {
+        let filter = Filter::parse("type is a").unwrap();
+        assert!(filter.matches("lookup.dog.", QClass::IN, 60, &a(1)));
+        assert!(! filter.matches("lookup.dog.", QClass::IN, 60, &Record::TXT(TXT { message: "a".into() })));
+    }
+
+    #[test]
+    fn ttls() {
+        let filter = Filter::parse("ttl > 60").unwrap();
+        assert!(filter.matches("lookup.dog.", QClass::IN, 61, &a(1)));
+        assert!(! filter.matches("lookup.dog.", QClass::IN, 60, &a(1)));
+    }
+
+    #[test]
+    fn every_filter_has_to_pass() {
+        let filters = vec![ Filter::parse("rdata starts-with 192.0.2.").unwrap(), Filter::parse("rdata is-not 192.0.2.2").unwrap() ];
+        let mut response = Response {
+            transaction_id: 0x1234,
+            flags: dns::Flags::from_u16(0b_1000_0001_1000_0000),
+            queries: vec![],
+            answers: (1 ..= 3).map(|n| Answer::Standard { qname: "lookup.dog.".into(), qclass: QClass::IN, ttl: 60, record: a(n) }).collect(),
+            authorities: vec![],
+            additionals: vec![],
+        };
+
+        apply(&filters, &mut response);
+        assert_eq!(response.answers.len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+//! Load-testing a nameserver by sending it queries at a fixed rate, and
+//! measuring how many of them it keeps up with.
+//!
+//! Each query gets a random label put in front of its name, so none of them
+//! can be answered from a cache, and the nameserver has to do the work of
+//! looking every one up. This is only meant for nameservers the user runs,
+//! so it has to be confirmed on the command-line.
+
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+use futures_util::stream::{self, StreamExt};
+use log::*;
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+
+use dns::{Request, ErrorCode};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::Nameserver;
+
+
+/// How many queries can be waiting for a response at once. A nameserver
+/// that falls behind will have fewer queries sent to it than were asked
+/// for, which shows up in the achieved rate.
+const MAX_IN_FLIGHT: usize = 1000;
+
+/// How long to wait for each response if the user hasn’t given a response
+/// timeout, so a nameserver that drops queries doesn’t stall the flood.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many characters long the random labels are.
+const LABEL_LENGTH: usize = 12;
+
+
+/// How many queries to send, and how quickly.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Load {
+
+    /// The number of queries to send.
+    pub count: u32,
+
+    /// The number of queries to send each second.
+    pub qps: u32,
+}
+
+impl Load {
+
+    /// The rate to send queries at if the user doesn’t give one.
+    pub const DEFAULT_QPS: u32 = 100;
+
+    /// How long after the start of the flood the query with the given index
+    /// should be sent.
+    fn send_time(self, index: u32) -> Duration {
+        Duration::from_nanos(1_000_000_000 * u64::from(index) / u64::from(self.qps))
+    }
+}
+
+
+/// The results of flooding one nameserver.
+#[derive(Debug)]
+pub struct Flood {
+
+    /// The nameserver that was flooded.
+    pub nameserver: Nameserver,
+
+    /// The name that the random labels were put in front of.
+    pub qname: String,
+
+    /// How many queries were asked for, and how quickly.
+    pub load: Load,
+
+    /// How long it took from the first query being sent to the last one
+    /// finishing.
+    pub elapsed: Duration,
+
+    /// What happened to each query, in the order they finished: either the
+    /// response code and how long it took, or why it failed.
+    pub results: Vec<Result<(Option<ErrorCode>, Duration), TransportError>>,
+}
+
+impl Flood {
+
+    /// The number of queries that got a response of any kind.
+    pub fn responses(&self) -> u32 {
+        u32::try_from(self.results.iter().filter(|r| r.is_ok()).count()).unwrap_or(u32::MAX)
+    }
+
+    /// How many responses arrived each second, on average.
+    pub fn achieved_qps(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { f64::from(self.responses()) / seconds } else { 0.0 }
+    }
+
+    /// The time it took for the given percentage of responses to arrive,
+    /// or `None` if there weren’t any.
+    pub fn percentile(&self, percent: u32) -> Option<Duration> {
+        let mut latencies = self.results.iter()
+            .filter_map(|r| r.as_ref().ok().map(|(_, latency)| *latency))
+            .collect::<Vec<_>>();
+
+        if latencies.is_empty() {
+            return None;
+        }
+
+        latencies.sort_unstable();
+        let rank = (latencies.len() * percent.min(100) as usize).div_ceil(100);
+        Some(latencies[rank.saturating_sub(1)])
+    }
+}
+
+
+/// Sends the request to the nameserver as many times as the load asks for,
+/// each time with a different random label in front of its name, at the
+/// rate it asks for.
+pub async fn run(request: &Request, nameserver: Nameserver, transport_type: TransportType, load: Load,
+                 mut timeouts: Timeouts, names: &ServerNames) -> Flood {
+    timeouts.response = timeouts.response.or(Some(DEFAULT_TIMEOUT));
+    let transport = transport_type.make_transport(nameserver.clone(), timeouts, names);
+    let qname = request.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+
+    info!("Flooding {} with {} queries at {} a second", nameserver, load.count, load.qps);
+    let start = Instant::now();
+
+    let results = stream::iter(0 .. load.count).map(|index| {
+        let request = randomise(request, &random_label());
+        let transport = &transport;
+
+        async move {
+            let send_at = start + load.send_time(index);
+            tokio::time::delay_until(send_at.into()).await;
+
+            let sent = Instant::now();
+            let result = transport.send(&request).await;
+            result.map(|response| (response.flags.error_code, sent.elapsed()))
+        }
+    }).buffer_unordered(MAX_IN_FLIGHT).collect::<Vec<_>>().await;
+
+    let elapsed = start.elapsed();
+    debug!("Flood of {} finished after {:?}", nameserver, elapsed);
+    Flood { nameserver, qname, load, elapsed, results }
+}
+
+/// Returns a copy of the request with the label in front of the name it
+/// asks for, and a new transaction ID. Any signature gets removed, as it
+/// wouldn’t match the new name.
+fn randomise(request: &Request, label: &str) -> Request {
+    let mut request = request.clone();
+    request.transaction_id = rand::random();
+    request.signature = None;
+
+    for query in &mut request.queries {
+        query.qname = if query.qname.trim_matches('.').is_empty() { format!("{}.", label) }
+                                                              else { format!("{}.{}", label, query.qname) };
+    }
+
+    request
+}
+
+/// Generates a random label made of lowercase letters and digits.
+fn random_label() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric)
+        .take(LABEL_LENGTH)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+
+    fn flood(latencies: &[u64], failures: usize) -> Flood {
+        let mut results = latencies.iter().map(|ms| Ok((None, Duration::from_millis(*ms)))).collect::<Vec<_>>();
+        for _ in 0 .. failures {
+            results.push(Err(TransportError::Timeout(dns_transport::Phase::Response)));
+        }
+
+        Flood {
+            nameserver: "192.0.2.53".into(),
+            qname: "lookup.dog".into(),
+            load: Load { count: 10, qps: 10 },
+            elapsed: Duration::from_secs(2),
+            results,
+        }
+    }
+
+    #[test]
+    fn send_times() {
+        let load = Load { count: 10, qps: 4 };
+        assert_eq!(load.send_time(0), Duration::from_secs(0));
+        assert_eq!(load.send_time(1), Duration::from_millis(250));
+        assert_eq!(load.send_time(10), Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn achieved_qps() {
+        let flood = flood(&[ 1, 2, 3, 4, 5, 6, 7, 8 ], 2);
+        assert_eq!(flood.responses(), 8);
+        assert!((flood.achieved_qps() - 4.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn percentiles() {
+        let flood = flood(&[ 10, 1, 9, 2, 8, 3, 7, 4, 6, 5 ], 3);
+        assert_eq!(flood.percentile(50), Some(Duration::from_millis(5)));
+        assert_eq!(flood.percentile(90), Some(Duration::from_millis(9)));
+        assert_eq!(flood.percentile(99), Some(Duration::from_millis(10)));
+        assert_eq!(flood.percentile(100), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn no_percentiles_without_responses() {
+        assert_eq!(flood(&[], 5).percentile(50), None);
+    }
+
+    #[test]
+    fn randomised_names() {
+        let request = Request {
+            transaction_id: 0xABCD,
+            flags: Flags::query(),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: QClass::IN, qtype: 1 } ],
+            additional: None,
+            signature: None,
+        };
+
+        assert_eq!(randomise(&request, "x7k2").queries[0].qname, "x7k2.lookup.dog.");
+    }
+
+    #[test]
+    fn random_labels() {
+        let label = random_label();
+        assert_eq!(label.len(), LABEL_LENGTH);
+        assert!(label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_ne!(label, random_label());
+    }
+}
@@ -0,0 +1,222 @@
+//! Checking the glue records in a referral against what the nameservers
+//! they’re for say their own addresses are.
+//!
+//! When a parent zone delegates to nameservers inside the child zone, it has
+//! to include their addresses as glue, or nobody could find them. But the
+//! glue is a copy, kept in the parent, and it goes stale when the child’s
+//! nameservers move without the parent being told. Resolvers then only
+//! reach the nameservers some of the time, depending on which addresses
+//! they happen to have cached.
+
+use std::net::IpAddr;
+
+use futures_util::future::join_all;
+use log::*;
+
+use dns::{Request, Response, Query, Answer, QClass, Flags, TypeInt, qtype};
+use dns::record::{Record, A, AAAA};
+use dns_transport::Timeouts;
+
+use crate::connect::{TransportType, ServerNames};
+use crate::warnings::Warning;
+
+
+/// Finds the glue in a referral: the address records in the additional
+/// section for the nameservers named in the authority section. Returns the
+/// name of each nameserver that has glue, along with its glue addresses.
+/// Responses that aren’t referrals have no glue.
+pub fn referral_glue(response: &Response) -> Vec<(String, Vec<IpAddr>)> {
+    if response.answers.iter().any(Answer::is_standard) {
+        return Vec::new();
+    }
+
+    let nameservers = response.authorities.iter().filter_map(|answer| match answer {
+        Answer::Standard { record: Record::NS(ns), .. }  => Some(&ns.nameserver),
+        _                                                => None,
+    });
+
+    let mut glue: Vec<(String, Vec<IpAddr>)> = Vec::new();
+    for nameserver in nameservers {
+        let addresses = response.additionals.iter().filter_map(|answer| match answer {
+            Answer::Standard { qname, record, .. } if qname.eq_ignore_ascii_case(nameserver) => address_of(record),
+            _ => None,
+        }).collect::<Vec<_>>();
+
+        if ! addresses.is_empty() && ! glue.iter().any(|(name, _)| name.eq_ignore_ascii_case(nameserver)) {
+            glue.push((nameserver.clone(), addresses));
+        }
+    }
+
+    glue
+}
+
+/// Asks each nameserver with glue in a referral for its own A and AAAA
+/// records, returning a warning for each family of addresses whose glue
+/// doesn’t match.
+pub async fn check(response: &Response, timeouts: Timeouts) -> Vec<Warning> {
+    let checks = referral_glue(response).into_iter().map(|(nameserver, glue)| async move {
+        let mut warnings = Vec::new();
+
+        for qtype in &[ qtype!(A), qtype!(AAAA) ] {
+            // A family without glue has nothing to be stale
+            let family = glue.iter().copied().filter(|a| family_of(*a) == *qtype).collect::<Vec<_>>();
+            if family.is_empty() {
+                continue;
+            }
+
+            match authoritative_addresses(&nameserver, &glue, *qtype, timeouts).await {
+                Some(authoritative) => warnings.extend(compare(&nameserver, family, authoritative)),
+                None                => info!("Could not check {} glue for {}", zone_type(*qtype), nameserver),
+            }
+        }
+
+        warnings
+    });
+
+    join_all(checks).await.into_iter().flatten().collect()
+}
+
+/// Compares the glue addresses of one family with the ones the nameserver
+/// gave for itself, ignoring their order, and returns a warning if they
+/// differ.
+fn compare(nameserver: &str, mut glue: Vec<IpAddr>, mut authoritative: Vec<IpAddr>) -> Option<Warning> {
+    glue.sort_unstable();
+    glue.dedup();
+    authoritative.sort_unstable();
+    authoritative.dedup();
+
+    if glue == authoritative {
+        None
+    }
+    else {
+        Some(Warning::StaleGlue { nameserver: nameserver.into(), glue, authoritative })
+    }
+}
+
+/// Asks a nameserver, at one of its glue addresses, for its own addresses of
+/// one type, with recursion turned off. Returns `None` if the request fails
+/// or the answer isn’t authoritative, as then it can’t be compared.
+async fn authoritative_addresses(nameserver: &str, glue: &[IpAddr], qtype: TypeInt, timeouts: Timeouts) -> Option<Vec<IpAddr>> {
+    // Plenty of networks still have no IPv6 route, so prefer an IPv4
+    // address to ask, if the nameserver has one
+    let address = glue.iter().find(|a| a.is_ipv4()).or_else(|| glue.first())?;
+    let address = match address {
+        IpAddr::V4(v4)  => v4.to_string(),
+        IpAddr::V6(v6)  => format!("[{}]:53", v6),
+    };
+
+    let request = Request {
+        transaction_id: rand::random(),
+        flags: Flags { recursion_desired: false, .. Flags::query() },
+        queries: vec![ Query { qname: nameserver.into(), qclass: QClass::IN, qtype } ],
+        additional: Some(Request::additional_record()),
+        signature: None,
+    };
+
+    let transport = TransportType::Automatic.make_transport(address.clone(), timeouts, &ServerNames::default());
+    let response = match transport.send(&request).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Asking {} at {} for its own addresses failed -> {:?}", nameserver, address, e);
+            return None;
+        }
+    };
+
+    if ! response.flags.authoritative || response.flags.error_code.is_some() {
+        return None;
+    }
+
+    let addresses = response.answers.iter().filter_map(|answer| match answer {
+        Answer::Standard { qname, record, .. } if qname.eq_ignore_ascii_case(nameserver) => address_of(record),
+        _ => None,
+    });
+
+    Some(addresses.collect())
+}
+
+/// Returns the address in an A or AAAA record.
+fn address_of(record: &Record) -> Option<IpAddr> {
+    match record {
+        Record::A(a)     => Some(IpAddr::V4(a.address)),
+        Record::AAAA(a)  => Some(IpAddr::V6(a.address)),
+        _                => None,
+    }
+}
+
+/// Returns the type number of the record an address would be in.
+fn family_of(address: IpAddr) -> TypeInt {
+    if address.is_ipv4() { qtype!(A) } else { qtype!(AAAA) }
+}
+
+/// Returns the name of an address record type.
+fn zone_type(qtype: TypeInt) -> &'static str {
+    if qtype == qtype!(A) { "A" } else { "AAAA" }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::NS;
+
+    fn answer(qname: &str, record: Record) -> Answer {
+        Answer::Standard { qname: qname.into(), qclass: QClass::IN, ttl: 172_800, record }
+    }
+
+    fn ns(nameserver: &str) -> Record {
+        Record::NS(NS { nameserver: nameserver.into() })
+    }
+
+    fn a(address: &str) -> Record {
+        Record::A(A { address: address.parse().unwrap() })
+    }
+
+    fn referral() -> Response {
+        Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0000_0000_0000),
+            queries: vec![],
+            answers: vec![],
+            authorities: vec![
+                answer("lookup.dog.", ns("ns1.lookup.dog.")),
+                answer("lookup.dog.", ns("ns2.lookup.dog.")),
+                answer("lookup.dog.", ns("ns.elsewhere.example.")),
+            ],
+            additionals: vec![
+                answer("ns1.lookup.dog.", a("192.0.2.1")),
+                answer("NS1.lookup.dog.", a("192.0.2.11")),
+                answer("ns2.lookup.dog.", a("192.0.2.2")),
+            ],
+        }
+    }
+
+    #[test]
+    fn finds_glue() {
+        assert_eq!(referral_glue(&referral()), vec![
+            ("ns1.lookup.dog.".into(), vec![ "192.0.2.1".parse().unwrap(), "192.0.2.11".parse().unwrap() ]),
+            ("ns2.lookup.dog.".into(), vec![ "192.0.2.2".parse().unwrap() ]),
+        ]);
+    }
+
+    #[test]
+    fn answers_are_not_referrals() {
+        let mut response = referral();
+        response.answers.push(answer("lookup.dog.", a("192.0.2.80")));
+        assert_eq!(referral_glue(&response), Vec::new());
+    }
+
+    #[test]
+    fn matching_glue() {
+        let glue = vec![ "192.0.2.11".parse().unwrap(), "192.0.2.1".parse().unwrap() ];
+        let authoritative = vec![ "192.0.2.1".parse().unwrap(), "192.0.2.11".parse().unwrap() ];
+        assert_eq!(compare("ns1.lookup.dog.", glue, authoritative), None);
+    }
+
+    #[test]
+    fn stale_glue() {
+        let glue = vec![ "192.0.2.1".parse().unwrap() ];
+        let authoritative = vec![ "198.51.100.1".parse().unwrap() ];
+        assert_eq!(compare("ns1.lookup.dog.", glue.clone(), authoritative.clone()),
+                   Some(Warning::StaleGlue { nameserver: "ns1.lookup.dog.".into(), glue, authoritative }));
+    }
+}
@@ -0,0 +1,143 @@
+//! Decoding internationalised domain names from their ASCII-compatible
+//! `xn--` form, so they can be displayed as the characters they stand for.
+
+use std::convert::TryFrom;
+
+
+/// Decodes every label of a domain name that is in ASCII-compatible form,
+/// leaving any other labels — and any that fail to decode — as they are.
+pub fn decode_name(name: &str) -> String {
+    name.split('.').map(decode_label).collect::<Vec<_>>().join(".")
+}
+
+/// Decodes one label if it starts with the `xn--` prefix. Labels that would
+/// decode to something that can’t appear in a hostname, such as a dot or a
+/// control character, are left encoded, as displaying them would be
+/// misleading.
+fn decode_label(label: &str) -> String {
+    let encoded = match label.get(.. 4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("xn--")  => &label[4 ..],
+        _                                                    => return label.into(),
+    };
+
+    match punycode_decode(encoded) {
+        Some(decoded) if decoded.chars().all(|c| c != '.' && ! c.is_control()) => decoded,
+        _ => label.into(),
+    }
+}
+
+
+// Parameters for Punycode, from RFC 3492 §5.
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// Decodes a Punycode string into Unicode, following the algorithm in
+/// RFC 3492 §6.2, returning `None` if the input is malformed.
+fn punycode_decode(input: &str) -> Option<String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(index)  => (&input[.. index], &input[index + 1 ..]),
+        None         => ("", input),
+    };
+
+    if ! basic.is_ascii() {
+        return None;
+    }
+
+    let mut output = basic.chars().collect::<Vec<_>>();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut weight = 1_u32;
+        let mut k = BASE;
+
+        loop {
+            let digit = decode_digit(digits.next()?)?;
+            i = i.checked_add(digit.checked_mul(weight)?)?;
+
+            let threshold = if k <= bias { T_MIN } else if k >= bias + T_MAX { T_MAX } else { k - bias };
+            if digit < threshold {
+                break;
+            }
+
+            weight = weight.checked_mul(BASE - threshold)?;
+            k += BASE;
+        }
+
+        let length = u32::try_from(output.len() + 1).ok()?;
+        bias = adapt(i - old_i, length, old_i == 0);
+        n = n.checked_add(i / length)?;
+        i %= length;
+
+        output.insert(usize::try_from(i).ok()?, char::from_u32(n)?);
+        i += 1;
+    }
+
+    Some(output.into_iter().collect())
+}
+
+/// Works out the new bias after decoding a character (RFC 3492 §6.1).
+fn adapt(delta: u32, points: u32, first_time: bool) -> u32 {
+    let mut delta = if first_time { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+
+    k + (((BASE - T_MIN + 1) * delta) / (delta + SKEW))
+}
+
+/// Returns the value of one Punycode digit, which is case-insensitive.
+fn decode_digit(byte: u8) -> Option<u32> {
+    match byte {
+        b'a' ..= b'z'  => Some(u32::from(byte - b'a')),
+        b'A' ..= b'Z'  => Some(u32::from(byte - b'A')),
+        b'0' ..= b'9'  => Some(u32::from(byte - b'0') + 26),
+        _              => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn latin() {
+        assert_eq!(decode_name("xn--bcher-kva"),   "bücher");
+        assert_eq!(decode_name("xn--mnchen-3ya"),  "münchen");
+    }
+
+    #[test]
+    fn chinese() {
+        assert_eq!(decode_name("xn--ihqwcrb4cv8a8dqg056pqjye"), "他们为什么不说中文");
+    }
+
+    #[test]
+    fn whole_name() {
+        assert_eq!(decode_name("mail.XN--bcher-kva.test."), "mail.bücher.test.");
+    }
+
+    #[test]
+    fn plain_labels() {
+        assert_eq!(decode_name("lookup.dog."), "lookup.dog.");
+        assert_eq!(decode_name("xn-.dog."),    "xn-.dog.");
+    }
+
+    #[test]
+    fn invalid_labels() {
+        assert_eq!(decode_name("xn--bcher-kv!.dog."),  "xn--bcher-kv!.dog.");
+        assert_eq!(decode_name("xn--99999999999a."),   "xn--99999999999a.");
+    }
+}
@@ -0,0 +1,95 @@
+//! Debug logging, to the terminal and to a log file.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{Log, LevelFilter, Metadata, Record};
+use serde_json::json;
+
+
+/// The **logger** writes human-readable messages to standard error when the
+/// `DOG_DEBUG` environment variable is set, and machine-readable messages
+/// to the log file when one has been given.
+struct Logger {
+    stderr: env_logger::Logger,
+}
+
+/// The file to write log lines to, if the user has specified one. This only
+/// gets set after the command-line options have been parsed, which happens
+/// after the logger has already been installed.
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// The level of messages written to the log file. This includes connection
+/// events and retries, which get logged at the debug level.
+const LOG_FILE_LEVEL: LevelFilter = LevelFilter::Debug;
+
+
+/// Checks the `DOG_DEBUG` environment variable, enabling debug logging if
+/// it’s non-empty. Setting it to `trace` enables trace logging as well,
+/// which includes a hex dump of every message sent and received.
+pub fn configure() {
+    let level = match env::var_os("DOG_DEBUG") {
+        Some(debug) if debug == "trace"    => LevelFilter::Trace,
+        Some(debug) if ! debug.is_empty()  => LevelFilter::Debug,
+        _                                  => LevelFilter::Off,
+    };
+
+    let stderr = env_logger::Builder::new().filter(None, level).build();
+
+    log::set_max_level(stderr.filter());
+    log::set_boxed_logger(Box::new(Logger { stderr }))
+        .expect("Failed to install logger");
+}
+
+/// Opens the file at the given path for appending, and starts writing log
+/// lines to it.
+pub fn log_to_file(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *LOG_FILE.lock().unwrap() = Some(file);
+
+    if log::max_level() < LOG_FILE_LEVEL {
+        log::set_max_level(LOG_FILE_LEVEL);
+    }
+
+    Ok(())
+}
+
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.stderr.enabled(metadata)
+            || (metadata.level() <= LOG_FILE_LEVEL && LOG_FILE.lock().unwrap().is_some())
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.stderr.matches(record) {
+            self.stderr.log(record);
+        }
+
+        if record.level() <= LOG_FILE_LEVEL {
+            if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+                let line = json!({
+                    "time": humantime::format_rfc3339_micros(SystemTime::now()).to_string(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": record.args().to_string(),
+                });
+
+                // There’s nowhere left to report an error writing the log
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+
+        if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
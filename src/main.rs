@@ -27,13 +27,50 @@ use std::time::Instant;
 
 use log::*;
 
+use self::connect::TransportType;
+
+mod annotations;
+mod bootstrap;
+mod cache_probe;
 mod colours;
+mod compare;
 mod connect;
+mod diagnosis;
+mod dnssec_views;
+mod ech;
+mod expiry;
+mod explain;
+mod features;
+mod filter;
+mod flood;
+mod glue;
+mod history;
+mod idna;
+mod logger;
+mod order;
 mod output;
+mod paging;
+mod probe;
+mod progress;
+mod protocols;
+mod provenance;
+mod push_zone;
+mod race;
 mod requests;
 mod resolve;
+mod server_list;
+mod session;
+mod sig0;
+mod sinkhole;
+mod suggestions;
 mod table;
+#[cfg(feature = "sqlite")]
+mod transaction_log;
+mod trust;
 mod txid;
+mod type_groups;
+mod warnings;
+mod zone;
 
 mod options;
 use self::options::*;
@@ -42,15 +79,63 @@ use self::options::*;
 /// Configures logging, parses the command-line options, and handles any
 /// errors before passing control over to the Dog type.
 fn main() {
-    configure_logger();
+    logger::configure();
+
+    let args = env::args_os().skip(1).map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>();
 
     match Options::getopts(env::args_os().skip(1)) {
         OptionsResult::Ok(options) => {
+            start(&options, &args);
             info!("Running with options -> {:#?}", options);
-            let dog = Dog::init(options);
+            let dog = Dog::init(options, args);
             exit(dog.run());
         }
 
+        OptionsResult::History => {
+            let entries = match history::path().map(|path| history::read(&path)) {
+                Some(Ok(entries)) => entries,
+                Some(Err(e)) => {
+                    eprintln!("Failed to read history: {}", e);
+                    exit(exits::OPTIONS_ERROR);
+                }
+                None => {
+                    eprintln!("No history is kept unless DOG_HISTORY is set to a file path");
+                    exit(exits::OPTIONS_ERROR);
+                }
+            };
+
+            for (index, entry) in entries.iter().enumerate() {
+                println!("{}", entry.summary(index + 1));
+            }
+
+            exit(exits::SUCCESS);
+        }
+
+        OptionsResult::Replay(number) => {
+            let entry = history::path().and_then(|path| history::read(&path).ok())
+                                       .and_then(|mut entries| if number <= entries.len() { Some(entries.remove(number - 1)) } else { None });
+
+            let Some(entry) = entry else {
+                eprintln!("There is no query number {} in the history", number);
+                exit(exits::OPTIONS_ERROR);
+            };
+
+            if let OptionsResult::Ok(options) = Options::getopts(&entry.args) {
+                start(&options, &entry.args);
+                info!("Replaying with options -> {:#?}", options);
+                let dog = Dog::init(options, entry.args);
+                exit(dog.run());
+            }
+
+            eprintln!("Query number {} cannot be run again", number);
+            exit(exits::OPTIONS_ERROR);
+        }
+
+        OptionsResult::VersionJSON => {
+            println!("{}", output::version_json());
+            exit(exits::SUCCESS);
+        }
+
         OptionsResult::Help(help_reason, use_colours) => {
             if use_colours.should_use_colours() {
                 print!("{}", include_str!(concat!(env!("OUT_DIR"), "/usage.pretty.txt")));
@@ -79,7 +164,14 @@ fn main() {
         }
 
         OptionsResult::InvalidOptionsFormat(oe) => {
-            eprintln!("Invalid options: {:?}", oe);
+            if let getopts::Fail::UnrecognizedOption(ref name) = oe {
+                let suggestions = Options::suggest_options(name);
+                eprintln!("Invalid options: {}{}", oe, suggestions::did_you_mean(&suggestions));
+            }
+            else {
+                eprintln!("Invalid options: {}", oe);
+            }
+
             exit(exits::OPTIONS_ERROR);
         }
 
@@ -90,79 +182,469 @@ fn main() {
     }
 }
 
-
-/// Checks the `DOG_DEBUG` environment variable, enabling debug logging if
-/// it’s non-empty.
-fn configure_logger() {
-    let present = match env::var_os("DOG_DEBUG") {
-        Some(debug)  => debug.len() > 0,
-        None         => false,
-    };
-
-    let mut logs = env_logger::Builder::new();
-    if present {
-        let _ = logs.filter(None, log::LevelFilter::Debug);
+/// Sets up everything the options ask for before any queries get sent:
+/// logging to a file, recording or replaying a session, and logging
+/// exchanges to a database. A query replayed from the history gets set up
+/// the same way as one from the command line.
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+fn start(options: &Options, args: &[String]) {
+    if let Some(path) = &options.log_file {
+        if let Err(e) = logger::log_to_file(path) {
+            eprintln!("Failed to open log file {}: {}", path.display(), e);
+            exit(exits::OPTIONS_ERROR);
+        }
     }
-    else {
-        let _ = logs.filter(None, log::LevelFilter::Off);
+
+    if let Some(mode) = &options.session {
+        if let Err(e) = session::start(mode) {
+            eprintln!("Failed to start session: {}", e);
+            exit(exits::OPTIONS_ERROR);
+        }
     }
 
-    logs.init()
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &options.sqlite {
+        if let Err(e) = transaction_log::start(path, args) {
+            eprintln!("Failed to open database {}: {}", path.display(), e);
+            exit(exits::OPTIONS_ERROR);
+        }
+    }
 }
 
+
 struct Dog {
     options: Options,
+
+    /// The arguments dog was run with, for recording in the history.
+    args: Vec<String>,
 }
 
 impl Dog {
-    fn init(options: Options) -> Self {
-        Self { options }
+    fn init(options: Options, args: Vec<String>) -> Self {
+        Self { options, args }
     }
 
     fn run(self) -> i32 {
-        let Options { requests, format, measure_time } = self.options;
+        let format = &self.options.format;
+        if self.options.probe_max_udp {
+            Self::probe(self.options.requests, format)
+        }
+        else if self.options.cache_probe {
+            Self::cache_probe(self.options.requests, format)
+        }
+        else if self.options.both_protocols {
+            Self::both_protocols(self.options.requests, format)
+        }
+        else if self.options.dnssec_views {
+            Self::dnssec_views(self.options.requests, format)
+        }
+        else if self.options.push_zone {
+            Self::push_zone(self.options.requests, self.options.primary, format)
+        }
+        else if let Some(load) = self.options.flood {
+            Self::flood(self.options.requests, load, format)
+        }
+        else {
+            self.lookup()
+        }
+    }
+
+    /// Sends every request and displays the responses, which is what dog
+    /// does unless it’s been told to do something else.
+    fn lookup(self) -> i32 {
+        let Options { requests, format, measure_time, race, filters, sort, page, summarise, explain_errors, check_glue, authoritative, expiry_alert, .. } = self.options;
         let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timer = if measure_time { Some(Instant::now()) } else { None };
+
+        let mut gathered = Gathered::default();
+        let mut timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+        let retry_over_tcp = requests.protocol_tweaks.retry_over_tcp;
+        let bootstrap_resolver = requests.bootstrap.clone();
         let should_show_opt = requests.edns.should_show();
+        let mut requests = requests.generate();
 
-        let mut responses = Vec::new();
-        let timer = if measure_time { Some(Instant::now()) } else { None };
+        // A race can’t be won while it waits forever for a nameserver that
+        // never answers
+        if race {
+            timeouts.response = timeouts.response.or(Some(race::DEFAULT_TIMEOUT));
+        }
 
-        let mut errored = false;
-        for (request, transport) in requests.generate() {
-            let result = runtime.block_on(async { transport.send(&request).await });
+        let handling = Handling { should_show_opt, filters, sort, page, summarise, explain_errors, check_glue, authoritative, expiry_alert, timeouts };
 
-            match result {
-                Ok(mut response) => {
-                    if ! should_show_opt {
-                        response.answers.retain(dns::Answer::is_standard);
-                        response.authorities.retain(dns::Answer::is_standard);
-                        response.additionals.retain(dns::Answer::is_standard);
-                    }
+        let mut lookups = Vec::new();
+        if let Some(resolver) = bootstrap_resolver {
+            lookups = Self::bootstrap(&mut runtime, &mut requests, &resolver, timeouts, &server_names);
+            gathered.errored |= lookups.iter().any(|l| l.address().is_none());
+        }
+
+        // When racing, requests that only differ by nameserver get sent at
+        // the same time; otherwise, each one gets sent on its own.
+        let batches = if race { race::group(requests) }
+                           else { requests.into_iter().map(|r| vec![ r ]).collect() };
+
+        let mut transports = connect::Transports::new(timeouts, server_names, retry_over_tcp);
+        let mut progress = progress::Progress::new(batches.len());
+        let mut history_queries = Vec::new();
+        for mut batch in batches {
+            for (request, nameserver, transport_type) in &batch {
+                history_queries.push(history::describe_query(request, nameserver, *transport_type));
+                transports.prepare(nameserver, *transport_type);
+            }
+
+            let (request, result, nameserver) = if race {
+                let (race, finish) = runtime.block_on(race::run(batch, &transports));
+                gathered.races.push(race);
+                progress.tick();
 
-                    responses.push(response);
+                // The race results explain why nobody answered
+                let Some((request, response, source, details)) = finish else {
+                    gathered.errored = true;
+                    continue;
+                };
+
+                let nameserver = source.nameserver.clone();
+                (request, Ok((response, source, details)), nameserver)
+            }
+            else {
+                let (request, nameserver, transport_type) = batch.remove(0);
+                let result = runtime.block_on(transports.send(&request, &nameserver, transport_type));
+                progress.tick();
+
+                let result = result.map(|(response, details)| {
+                    let source = provenance::Source::arrived(nameserver.clone(), transport_type, &details);
+                    (response, source, details)
+                });
+
+                (request, result, nameserver)
+            };
+
+            match result {
+                Ok((response, source, details)) => {
+                    gathered.collect(&mut runtime, &handling, &request, response, source, details);
                 }
                 Err(e) => {
-                    format.print_error(e);
-                    errored = true;
+                    warn!("Request failed -> {:?}", e);
+                    progress.clear();
+                    format.print_error(&e);
+                    if explain_errors {
+                        gathered.diagnoses.push(diagnosis::of_error(&nameserver, &e));
+                    }
+                    gathered.errored = true;
                 }
             }
         }
 
-        let duration = timer.map(|t| t.elapsed());
-        if format.print(responses, duration) {
-            if errored {
-                exits::NETWORK_ERROR
+        progress.clear();
+
+        if let Some(path) = history::path() {
+            if let Err(e) = history::record(&path, &self.args, &history_queries, &gathered.responses) {
+                warn!("Failed to record history in {}: {}", path.display(), e);
             }
-            else {
-                exits::SUCCESS
+        }
+
+        gathered.finish(&handling, &format, &lookups, timer)
+    }
+}
+
+
+/// Everything gathered from the responses to a run’s requests, ready to be
+/// displayed once they have all been sent.
+#[derive(Default)]
+struct Gathered {
+    responses: Vec<(dns::Response, provenance::Source, dns_transport::Details)>,
+    warnings: Vec<warnings::Warning>,
+    diagnoses: Vec<diagnosis::Diagnosis>,
+    summaries: Vec<paging::Summary>,
+    signatures: Vec<expiry::Expiry>,
+    races: Vec<race::Race>,
+
+    /// Whether any request failed, or any nameserver couldn’t be found.
+    errored: bool,
+
+    /// Whether any response was left out for not being authoritative.
+    refused: bool,
+}
+
+/// How the responses get checked and trimmed down once they arrive.
+#[allow(clippy::struct_excessive_bools)]
+struct Handling {
+    should_show_opt: bool,
+    filters: Vec<filter::Filter>,
+    sort: bool,
+    page: Option<paging::Page>,
+    summarise: bool,
+    explain_errors: bool,
+    check_glue: bool,
+    authoritative: bool,
+    expiry_alert: Option<std::time::Duration>,
+    timeouts: dns_transport::Timeouts,
+}
+
+impl Gathered {
+
+    /// Checks a response that arrived, then filters, sorts, and pages it,
+    /// before keeping it to be displayed once every response is in.
+    fn collect(&mut self, runtime: &mut dns_transport::Runtime, handling: &Handling, request: &dns::Request,
+               mut response: dns::Response, source: provenance::Source, details: dns_transport::Details) {
+        self.warnings.extend(warnings::check(request, &response, &details));
+
+        // A response from a cache or a referral from a parent zone could be
+        // out of date, so it doesn’t get shown
+        if handling.authoritative && ! response.flags.authoritative {
+            let qname = response.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+            self.warnings.push(warnings::Warning::NotAuthoritative { qname, nameserver: source.nameserver });
+            self.refused = true;
+            return;
+        }
+
+        if handling.check_glue {
+            self.warnings.extend(runtime.block_on(glue::check(&response, handling.timeouts)));
+        }
+
+        // Signatures get collected before filtering, so hiding the RRSIG
+        // records doesn’t hide them from the check
+        if handling.expiry_alert.is_some() {
+            self.signatures.extend(expiry::signatures(&response));
+        }
+
+        // The Extended DNS Error is in the OPT record, so this has to happen
+        // before it gets hidden
+        if handling.explain_errors {
+            self.diagnoses.extend(diagnosis::of_response(&response));
+        }
+
+        if ! handling.should_show_opt {
+            response.answers.retain(dns::Answer::is_standard);
+            response.authorities.retain(dns::Answer::is_standard);
+            response.additionals.retain(dns::Answer::is_standard);
+        }
+
+        if ! handling.filters.is_empty() {
+            filter::apply(&handling.filters, &mut response);
+        }
+
+        if handling.sort {
+            order::sort_response(&mut response);
+        }
+
+        // Summarised responses don’t get kept around, so a huge zone
+        // transfer only needs to fit in memory once
+        if handling.summarise {
+            self.summaries.push(paging::Summary::of(&response, &source.nameserver));
+            return;
+        }
+
+        if let Some(page) = handling.page {
+            self.warnings.extend(page.apply(&mut response));
+        }
+
+        self.responses.push((response, source, details));
+    }
+
+    /// Displays everything that was gathered, and works out the exit code
+    /// from what went wrong, if anything.
+    fn finish(mut self, handling: &Handling, format: &output::OutputFormat, lookups: &[bootstrap::Lookup], timer: Option<Instant>) -> i32 {
+        let mut expiring = false;
+        if let Some(threshold) = handling.expiry_alert {
+            if let Some(warning) = expiry::check(&self.signatures, threshold, expiry::now()) {
+                self.warnings.push(warning);
+                expiring = true;
             }
         }
+
+        // A network error means some signatures may not have been checked,
+        // so it takes priority over an expiring one
+        let exit_code = if self.errored { exits::NETWORK_ERROR }
+                    else if self.refused { exits::NOT_AUTHORITATIVE }
+                       else if expiring { exits::SIGNATURE_EXPIRING }
+                                   else { exits::SUCCESS };
+
+        if handling.summarise {
+            format.print_summaries(&self.summaries, &self.warnings);
+            return exit_code;
+        }
+
+        let duration = timer.map(|t| t.elapsed());
+        let results = output::Results { responses: self.responses, lookups, races: &self.races, warnings: &self.warnings, diagnoses: &self.diagnoses, duration };
+        if format.print(results) {
+            exit_code
+        }
         else {
             exits::NO_SHORT_RESULTS
         }
     }
 }
 
+impl Dog {
+
+    /// Looks up the addresses of any encrypted nameservers given as host
+    /// names using the bootstrap resolver, then points their requests at
+    /// those addresses. Requests to nameservers whose addresses can’t be
+    /// found get dropped.
+    fn bootstrap(runtime: &mut dns_transport::Runtime, requests: &mut Vec<(dns::Request, resolve::Nameserver, TransportType)>,
+                 resolver: &str, timeouts: dns_transport::Timeouts, names: &connect::ServerNames) -> Vec<bootstrap::Lookup> {
+        let mut lookups: Vec<bootstrap::Lookup> = Vec::new();
+
+        for (_, nameserver, transport_type) in requests.iter() {
+            if let Some(host) = bootstrap::host_to_look_up(nameserver, *transport_type) {
+                if ! lookups.iter().any(|l| l.host == host) {
+                    info!("Bootstrapping {:?} via {:?}", host, resolver);
+                    lookups.push(runtime.block_on(bootstrap::lookup(host, resolver.into(), timeouts)));
+                }
+            }
+        }
+
+        requests.retain_mut(|(_, nameserver, transport_type)| {
+            let Some(host) = bootstrap::host_to_look_up(nameserver, *transport_type) else { return true };
+            let Some(address) = lookups.iter().find(|l| l.host == host).and_then(bootstrap::Lookup::address) else { return false };
+            *nameserver = bootstrap::rewrite(nameserver, *transport_type, address, names);
+            true
+        });
+
+        lookups
+    }
+
+    /// Probes each nameserver for the largest UDP response that makes it
+    /// back, instead of displaying the responses themselves.
+    fn probe(requests: requests::RequestGenerator, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+
+        let mut probes = Vec::new();
+        for (request, nameserver, _) in requests.generate() {
+            probes.push(runtime.block_on(probe::run(request, nameserver, timeouts)));
+        }
+
+        format.print_probes(&probes);
+
+        if probes.iter().all(|p| p.largest().is_some()) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+
+    /// Asks each resolver the same question twice, a few seconds apart,
+    /// to check that it counts down the TTLs of the records in its cache.
+    fn cache_probe(requests: requests::RequestGenerator, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+
+        let mut probes = Vec::new();
+        for (request, nameserver, transport_type) in requests.generate() {
+            probes.push(cache_probe::run(&mut runtime, &request, nameserver, transport_type, timeouts, &server_names));
+        }
+
+        format.print_cache_probes(&probes);
+
+        if probes.iter().all(|p| p.first.is_ok() && p.second.is_ok()) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+
+    /// Sends each query over UDP and TCP at the same time, and compares the
+    /// responses, to catch nameservers that answer differently over each.
+    fn both_protocols(requests: requests::RequestGenerator, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+
+        let mut comparisons: Vec<protocols::Comparison> = Vec::new();
+        for (request, nameserver, _) in requests.generate() {
+            // Picking more than one transport would send the same query
+            // twice, but the transport makes no difference here
+            let qname = request.queries.first().map(|q| q.qname.as_str()).unwrap_or_default();
+            if comparisons.iter().any(|c| c.nameserver == nameserver && c.qname == qname) {
+                continue;
+            }
+
+            comparisons.push(runtime.block_on(protocols::run(&request, nameserver, timeouts, &server_names)));
+        }
+
+        format.print_protocol_comparisons(&comparisons);
+
+        if comparisons.iter().all(|c| c.udp.is_ok() && c.tcp.is_ok()) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+
+    /// Sends each query with and without asking for DNSSEC records, and
+    /// without validation, and compares the responses, to catch queries
+    /// that only fail when they get validated.
+    fn dnssec_views(requests: requests::RequestGenerator, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+
+        let mut comparisons = Vec::new();
+        for (request, nameserver, transport_type) in requests.generate() {
+            comparisons.push(runtime.block_on(dnssec_views::run(&request, nameserver, transport_type, timeouts, &server_names)));
+        }
+
+        format.print_dnssec_views(&comparisons);
+
+        if comparisons.iter().all(|c| c.views.iter().all(|(_, response)| response.is_ok())) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+
+    /// Notifies each secondary that the zone has changed, then waits for
+    /// them to catch up with the primary.
+    fn push_zone(requests: requests::RequestGenerator, primary: Option<resolve::Nameserver>, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+
+        let mut pushes = Vec::new();
+        for (request, nameserver, transport_type) in requests.generate() {
+            pushes.push(push_zone::run(&mut runtime, &request, nameserver, transport_type, primary.clone(), timeouts, &server_names));
+        }
+
+        format.print_pushes(&pushes);
+
+        if pushes.iter().all(|p| p.converged.is_some()) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+
+    /// Floods each nameserver with queries for random names at a fixed
+    /// rate, to see how many of them it can keep up with.
+    fn flood(requests: requests::RequestGenerator, load: flood::Load, format: &output::OutputFormat) -> i32 {
+        let mut runtime = dns_transport::Runtime::new().expect("Failed to create runtime");
+        let timeouts = requests.timeouts;
+        let server_names = requests.server_names.clone();
+
+        let mut floods = Vec::new();
+        for (request, nameserver, transport_type) in requests.generate() {
+            floods.push(runtime.block_on(flood::run(&request, nameserver, transport_type, load, timeouts, &server_names)));
+        }
+
+        format.print_floods(&floods);
+
+        if floods.iter().all(|f| f.responses() > 0) {
+            exits::SUCCESS
+        }
+        else {
+            exits::NETWORK_ERROR
+        }
+    }
+}
+
 
 mod exits {
     #![allow(unused)]
@@ -179,4 +661,12 @@ mod exits {
 
     /// Exit code for when the command-line options are invalid.
     pub const OPTIONS_ERROR: i32 = 3;
+
+    /// Exit code for when `--expiry-alert` was given, and a DNSSEC signature
+    /// expires within it, or there were no signatures at all.
+    pub const SIGNATURE_EXPIRING: i32 = 4;
+
+    /// Exit code for when `--authoritative` was given, and a response was
+    /// refused for not being authoritative.
+    pub const NOT_AUTHORITATIVE: i32 = 5;
 }
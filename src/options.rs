@@ -1,16 +1,29 @@
 use std::ffi::OsStr;
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use log::*;
 
-use dns::{QClass, find_qtype_number, qtype};
-use dns::record::{A, find_other_qtype_number};
-
-use crate::connect::TransportType;
-use crate::output::{OutputFormat, UseColours, TextFormat};
-use crate::requests::{RequestGenerator, Inputs, ProtocolTweaks, UseEDNS};
-use crate::resolve::Resolver;
+use dns::{QClass, find_qtype_number, qtype, qtype_names};
+use dns::record::{A, SOA, check_name, find_other_qtype_number, other_qtype_names};
+use dns_transport::Timeouts;
+
+use crate::connect::{TransportType, ServerNames};
+use crate::filter::Filter;
+use crate::flood::Load;
+use crate::history;
+use crate::paging::Page;
+use crate::output::{OutputFormat, UseColours, TextFormat, TimeFormat, BUILT_IN_DISPLAYS};
+use crate::requests::{RequestGenerator, Inputs, ProtocolTweaks, UseEDNS, FLAG_DAY_PAYLOAD_SIZE};
+use crate::resolve::{Resolver, Nameserver};
+use crate::server_list::{self, ServerListError};
+use crate::session;
+use crate::sig0::{Sig0Key, Sig0KeyError};
+use crate::suggestions;
+use crate::table::Column;
 use crate::txid::TxidGenerator;
+use crate::type_groups;
 
 
 /// The command-line options used when running dog.
@@ -23,8 +36,83 @@ pub struct Options {
     /// Whether to display the time taken after every query.
     pub measure_time: bool,
 
+    /// Whether to send each query to every nameserver at once, and only
+    /// show the first answer.
+    pub race: bool,
+
+    /// Whether to probe for the largest UDP response that makes it back
+    /// from each nameserver, rather than displaying the responses.
+    pub probe_max_udp: bool,
+
+    /// Whether to query each resolver twice, a few seconds apart, to check
+    /// that it counts down the TTLs in its cache, rather than displaying
+    /// the responses.
+    pub cache_probe: bool,
+
+    /// Whether to send each query over UDP and TCP at the same time, and
+    /// compare the two responses, rather than displaying them.
+    pub both_protocols: bool,
+
+    /// Whether to send each query with and without DNSSEC records and
+    /// validation, and compare the responses, rather than displaying them.
+    pub dnssec_views: bool,
+
+    /// Whether to send a NOTIFY for the zone to each nameserver, then wait
+    /// for them to catch up with the primary, rather than displaying the
+    /// responses. This is the `push-zone` command.
+    pub push_zone: bool,
+
+    /// The primary nameserver to compare the serial numbers of secondaries
+    /// against when pushing a zone, if not the one named in its SOA record.
+    pub primary: Option<Nameserver>,
+
+    /// How many queries to flood each nameserver with, and how quickly,
+    /// rather than displaying the responses.
+    pub flood: Option<Load>,
+
+    /// Whether to explain what each error means and what usually causes it.
+    pub explain_errors: bool,
+
+    /// Whether to ask the nameservers in each referral for their own
+    /// addresses, and warn if they don’t match the glue.
+    pub check_glue: bool,
+
+    /// Whether to refuse responses that don’t have the authoritative flag
+    /// set, as the nameserver being queried is meant to be the one for the
+    /// zone.
+    pub authoritative: bool,
+
+    /// How long the signatures in the responses have to have left before
+    /// they expire, if they’re being monitored.
+    pub expiry_alert: Option<Duration>,
+
+    /// The filters that records have to pass to be displayed.
+    pub filters: Vec<Filter>,
+
+    /// Whether to sort the records in each section of every response into
+    /// a canonical order, rather than leaving them as they arrived.
+    pub sort: bool,
+
+    /// Which page of each response’s records to display, if they shouldn’t
+    /// all be displayed.
+    pub page: Option<Page>,
+
+    /// Whether to display a count of each response’s records by type,
+    /// rather than the records themselves.
+    pub summarise: bool,
+
     /// How to format the output data.
     pub format: OutputFormat,
+
+    /// The file to write log lines to, if any.
+    pub log_file: Option<PathBuf>,
+
+    /// The directory to record exchanges to, or replay them from, if any.
+    pub session: Option<session::Mode>,
+
+    /// The database file to log every exchange to, if any.
+    #[cfg(feature = "sqlite")]
+    pub sqlite: Option<PathBuf>,
 }
 
 impl Options {
@@ -36,78 +124,250 @@ impl Options {
     /// normally, a `Help` or `Version` variant if one of those options is
     /// specified, or an error variant if there’s an invalid option or
     /// inconsistency within the options after they were parsed.
-    #[allow(unused_results)]
     pub fn getopts<C>(args: C) -> OptionsResult
     where C: IntoIterator,
           C::Item: AsRef<OsStr>,
     {
+        let opts = Self::command_line_options();
+
+        let matches = match opts.parse(args) {
+            Ok(m)  => m,
+            Err(e) => return OptionsResult::InvalidOptionsFormat(e),
+        };
+
+        let uc = UseColours::deduce(&matches);
+
+        if matches.opt_present("version") && matches.opt_present("json") {
+            OptionsResult::VersionJSON
+        }
+        else if matches.opt_present("version") {
+            OptionsResult::Version(uc)
+        }
+        else if matches.opt_present("help") {
+            OptionsResult::Help(HelpReason::Flag, uc)
+        }
+        else if let Some(result) = Self::history_command(&matches.free, history::path().is_some()) {
+            result
+        }
+        else {
+            match Self::deduce(matches) {
+                Ok(opts) => {
+                    if opts.requests.inputs.domains.is_empty() {
+                        OptionsResult::Help(HelpReason::NoDomains, uc)
+                    }
+                    else {
+                        OptionsResult::Ok(opts)
+                    }
+                }
+                Err(e) => {
+                    OptionsResult::InvalidOptions(e)
+                }
+            }
+        }
+    }
+
+    /// Defines every command-line option dog accepts.
+    #[allow(unused_results)]
+    fn command_line_options() -> getopts::Options {
         let mut opts = getopts::Options::new();
 
         // Query options
         opts.optmulti("q", "query",       "Host name or IP address to query", "HOST");
         opts.optmulti("t", "type",        "Type of the DNS record being queried (A, MX, NS...)", "TYPE");
         opts.optmulti("n", "nameserver",  "Address of the nameserver to send packets to", "ADDR");
+        opts.optmulti("",  "server-list", "Send packets to every nameserver in a file (dnscrypt-proxy, TOML, or CSV)", "PATH");
         opts.optmulti("",  "class",       "Network class of the DNS record being queried (IN, CH, HS)", "CLASS");
 
         // Sending options
         opts.optopt ("",  "edns",         "Whether to OPT in to EDNS (disable, hide, show)", "SETTING");
         opts.optopt ("",  "txid",         "Set the transaction ID to a specific value", "NUMBER");
         opts.optopt ("Z", "",             "Uncommon protocol tweaks", "TWEAKS");
+        opts.optflag("",  "dnsflagday2020", "Use a 1232-byte UDP buffer, and retry failures over TCP");
+        opts.optopt ("",  "sig0",         "Sign requests with SIG(0) using a private key file", "KEYFILE");
+        opts.optflag("",  "race",         "Send to every nameserver at once, and show the first answer");
+        opts.optflag("",  "probe-max-udp", "Search for the largest UDP response that makes it back");
+        opts.optflag("",  "cache-probe",  "Query twice to check that a resolver counts down TTLs");
+        opts.optflag("",  "both-protocols", "Query over UDP and TCP at once, and compare the responses");
+        opts.optflag("",  "dnssec-views", "Query with and without DNSSEC and validation, and compare the responses");
+        opts.optopt ("",  "primary",      "Primary to compare serials against when pushing a zone", "ADDR");
+        opts.optopt ("",  "flood",        "Load-test a nameserver you run with this many uncacheable queries", "NUMBER");
+        opts.optopt ("",  "qps",          "How many queries to send each second when flooding", "NUMBER");
+        opts.optflag("",  "i-own-this-server", "Confirm that the nameserver being flooded is yours");
 
         // Protocol options
         opts.optflag("U", "udp",          "Use the DNS protocol over UDP");
         opts.optflag("T", "tcp",          "Use the DNS protocol over TCP");
         opts.optflag("S", "tls",          "Use the DNS-over-TLS protocol");
         opts.optflag("H", "https",        "Use the DNS-over-HTTPS protocol");
+        opts.optopt ("",  "timeout",           "How long to wait for each phase of a request", "DURATION");
+        opts.optopt ("",  "connect-timeout",   "How long to wait to connect to the nameserver", "DURATION");
+        opts.optopt ("",  "handshake-timeout", "How long to wait for the TLS handshake", "DURATION");
+        opts.optopt ("",  "response-timeout",  "How long to wait for a response once sent", "DURATION");
+        opts.optopt ("",  "sni",               "Server name to present during the TLS handshake", "NAME");
+        opts.optopt ("",  "http-host",         "Host header to send with DNS-over-HTTPS requests", "NAME");
+        opts.optmulti("", "http-header",       "Extra header to send with DNS-over-HTTPS requests", "'NAME: VALUE'");
+        opts.optopt ("",  "user-agent",        "User-Agent header to send with DNS-over-HTTPS requests", "TEXT");
+        opts.optopt ("",  "doh-path",          "Path and query to send DNS-over-HTTPS requests to, such as /dns-query{?dns}", "PATH");
+        opts.optopt ("",  "bootstrap",         "Resolver to look up the addresses of encrypted nameservers with", "ADDR");
+        opts.optopt ("",  "ip-ttl",            "IP TTL or hop limit to send UDP requests with", "NUMBER");
 
         // Output options
         opts.optopt ("",  "color",        "When to use terminal colors",  "WHEN");
         opts.optopt ("",  "colour",       "When to use terminal colours", "WHEN");
         opts.optflag("J", "json",         "Display the output as JSON");
+        opts.optopt ("",  "display",      "Which output format to use (text, short, json)", "FORMAT");
         opts.optflag("",  "seconds",      "Do not format durations, display them as seconds");
+        opts.optopt ("",  "time-format",  "How to display timestamps (absolute, relative, both)", "FORMAT");
+        opts.optopt ("",  "columns",      "Which columns of the table to display, in order", "COLUMNS");
+        opts.optflag("",  "full",         "Display long record data in full, rather than truncating it");
+        opts.optflag("",  "header",       "Display a summary of each response’s header");
+        opts.optflag("",  "http-details", "Display the HTTP status and headers of DNS-over-HTTPS responses");
+        opts.optflag("",  "trust",        "Display a verdict on how far each response can be trusted");
+        opts.optflag("",  "zone-format",  "Display record data as it would be written in a zone file");
+        opts.optflag("",  "annotate",     "Annotate records with information worked out from their data");
+        opts.optflag("",  "explain",      "Label each field of structured records, such as SOA, with what it means");
+        opts.optflag("",  "explain-errors", "Explain what error codes and failures mean, and what usually causes them");
+        opts.optflag("",  "check-glue",   "Warn when the glue in a referral doesn’t match the nameservers’ own addresses");
+        opts.optflag("",  "authoritative", "Ask without recursion, and refuse responses that aren’t authoritative");
+        opts.optopt ("",  "expiry-alert", "Exit with an error if a signature expires sooner than this", "DURATION");
+        opts.optmulti("",  "grep",        "Only display records containing the text", "TEXT");
+        opts.optmulti("",  "filter",      "Only display records that pass a test, such as 'rdata contains 192.0.2.'", "FILTER");
+        opts.optflag("",  "sort",         "Sort the records in each section into a canonical order");
+        opts.optopt ("",  "limit",        "Display at most this many records from each response", "NUMBER");
+        opts.optopt ("",  "page",         "Which page of records to display, when there are too many", "NUMBER");
+        opts.optflag("",  "summary",      "Display how many records of each type there are, rather than the records");
+        opts.optflag("",  "punycode",     "Display internationalised domain names in their raw xn-- form");
         opts.optflag("1", "short",        "Short mode: display nothing but the first result");
         opts.optflag("",  "time",         "Print how long the response took to arrive");
+        opts.optopt ("",  "log-file",     "Write timestamped log lines to a file", "PATH");
+        opts.optopt ("",  "record-session", "Write every request and response to files in a directory", "DIR");
+        opts.optopt ("",  "replay-session", "Answer requests from a recorded session, without using the network", "DIR");
+        #[cfg(feature = "sqlite")]
+        opts.optopt ("",  "sqlite",       "Append every query and response to a SQLite database", "PATH");
 
         // Meta options
         opts.optflag("v", "version",      "Print version information");
         opts.optflag("?", "help",         "Print list of command-line options");
 
-        let matches = match opts.parse(args) {
-            Ok(m)  => m,
-            Err(e) => return OptionsResult::InvalidOptionsFormat(e),
-        };
+        opts
+    }
 
-        let uc = UseColours::deduce(&matches);
+    /// Returns the names of every long option, without their dashes.
+    /// getopts doesn’t expose the options it knows about, so they get
+    /// picked out of the usage text.
+    fn long_option_names() -> Vec<String> {
+        let usage = Self::command_line_options().usage("");
+        usage.split_whitespace()
+             .filter_map(|word| word.strip_prefix("--"))
+             .filter(|name| ! name.is_empty())
+             .map(String::from)
+             .collect()
+    }
 
-        if matches.opt_present("version") {
-            OptionsResult::Version(uc)
-        }
-        else if matches.opt_present("help") {
-            OptionsResult::Help(HelpReason::Flag, uc)
+    /// Returns the long options closest to an option the user typed that
+    /// wasn’t recognised, each with its leading dashes.
+    pub fn suggest_options(input: &str) -> Vec<String> {
+        let names = Self::long_option_names();
+        suggestions::closest(input.trim_start_matches('-'), names.iter().map(String::as_str)).into_iter()
+            .map(|name| format!("--{}", name))
+            .collect()
+    }
+
+    /// A leading `history` argument lists the history, and a leading
+    /// `replay` argument runs a query from it again, much like `push-zone`.
+    /// These are only commands when a history is being kept; otherwise, they
+    /// get looked up like any other domain. Either way, giving the name with
+    /// a trailing dot, such as `history.`, always looks it up.
+    fn history_command(free: &[String], history_kept: bool) -> Option<OptionsResult> {
+        if ! history_kept {
+            return None;
         }
-        else {
-            match Self::deduce(matches) {
-                Ok(opts) => {
-                    if opts.requests.inputs.domains.is_empty() {
-                        OptionsResult::Help(HelpReason::NoDomains, uc)
-                    }
-                    else {
-                        OptionsResult::Ok(opts)
-                    }
-                }
-                Err(e) => {
-                    OptionsResult::InvalidOptions(e)
+
+        match free {
+            [ command ] if command == "history" => {
+                Some(OptionsResult::History)
+            }
+            [ command, number @ .. ] if command == "replay" => {
+                let number = number.join(" ");
+                match number.parse() {
+                    Ok(n) if n > 0  => Some(OptionsResult::Replay(n)),
+                    _               => Some(OptionsResult::InvalidOptions(OptionsError::InvalidReplay(number))),
                 }
             }
+            _ => None,
         }
     }
 
-    fn deduce(matches: getopts::Matches) -> Result<Self, OptionsError> {
+    fn deduce(mut matches: getopts::Matches) -> Result<Self, OptionsError> {
+
+        // A leading `push-zone` argument is a command, not a domain to query
+        let push_zone = matches.free.first().map_or(false, |a| a == "push-zone");
+        if push_zone {
+            let _ = matches.free.remove(0);
+        }
+
         let measure_time = matches.opt_present("time");
-        let format = OutputFormat::deduce(&matches);
-        let requests = RequestGenerator::deduce(matches)?;
+        let race = matches.opt_present("race");
+        let probe_max_udp = matches.opt_present("probe-max-udp");
+        let cache_probe = matches.opt_present("cache-probe");
+        let both_protocols = matches.opt_present("both-protocols");
+        let dnssec_views = matches.opt_present("dnssec-views");
+        let sort = matches.opt_present("sort");
+        let page = Page::deduce(&matches)?;
+        let summarise = matches.opt_present("summary");
+        let primary = matches.opt_str("primary");
+        let flood = deduce_flood(&matches)?;
+        let filters = Filter::deduce(&matches)?;
+        let explain_errors = matches.opt_present("explain-errors");
+        let check_glue = matches.opt_present("check-glue");
+        let authoritative = matches.opt_present("authoritative");
+        let expiry_alert = deduce_expiry_alert(&matches)?;
+        let format = OutputFormat::deduce(&matches)?;
+        let log_file = matches.opt_str("log-file").map(PathBuf::from);
+        let session = deduce_session(&matches)?;
+        #[cfg(feature = "sqlite")]
+        let sqlite = matches.opt_str("sqlite").map(PathBuf::from);
+        let mut requests = RequestGenerator::deduce(matches)?;
+        requests.server_names.kernel_timestamps = measure_time;
+
+        // Each of these replaces the usual lookup with something else, so
+        // only one of them can run at once
+        let modes = [ (race, "--race"), (probe_max_udp, "--probe-max-udp"), (cache_probe, "--cache-probe"), (both_protocols, "--both-protocols"),
+                      (dnssec_views, "--dnssec-views"), (push_zone, "push-zone"), (flood.is_some(), "--flood") ];
+        let active_modes = modes.iter().filter(|(present, _)| *present).collect::<Vec<_>>();
+        if let [ (_, first), (_, second), .. ] = active_modes[..] {
+            return Err(OptionsError::ConflictingModes(first, second));
+        }
+
+        // These check the responses to a lookup, which the other modes
+        // don’t go through, so they’d be silently ignored; racing is still
+        // a lookup, just a faster one
+        let lookup_checks = [ (authoritative, "--authoritative"), (check_glue, "--check-glue"), (expiry_alert.is_some(), "--expiry-alert") ];
+        if let Some(&&(_, mode)) = active_modes.iter().find(|(_, mode)| *mode != "--race") {
+            if let Some((_, check)) = lookup_checks.iter().find(|(present, _)| *present) {
+                return Err(OptionsError::LookupOnly(check, mode));
+            }
+        }
+
+        // The DO bit that asks for signatures lives in the OPT record, so
+        // without EDNS there would never be any signatures to check
+        if expiry_alert.is_some() && requests.edns == UseEDNS::Disable {
+            return Err(OptionsError::ExpiryAlertWithoutEDNS);
+        }
+
+        // Pushing a zone is all about its SOA record
+        if push_zone {
+            requests.inputs.types = vec![ qtype!(SOA) ];
+        }
 
-        Ok(Self { requests, measure_time, format })
+        // Flooding whichever resolver the system happens to use could mean
+        // flooding someone else’s
+        if flood.is_some() && requests.inputs.resolvers.contains(&Resolver::SystemDefault) {
+            return Err(OptionsError::FloodWithoutNameserver);
+        }
+
+        Ok(Self { requests, measure_time, race, probe_max_udp, cache_probe, both_protocols, dnssec_views, push_zone, primary, flood, explain_errors, check_glue, authoritative, expiry_alert, filters, sort, page, summarise, format, log_file, session,
+                  #[cfg(feature = "sqlite")] sqlite })
     }
 }
 
@@ -117,9 +377,127 @@ impl RequestGenerator {
         let edns = UseEDNS::deduce(&matches)?;
         let txid_generator = TxidGenerator::deduce(&matches)?;
         let protocol_tweaks = ProtocolTweaks::deduce(&matches)?;
+        let sig0_key = Sig0Key::deduce(&matches)?;
+        let timeouts = deduce_timeouts(&matches)?;
+        let server_names = ServerNames::deduce(&matches)?;
+        let bootstrap = matches.opt_str("bootstrap").map(|ns| ns.trim_start_matches('@').to_owned());
         let inputs = Inputs::deduce(matches)?;
 
-        Ok(Self { inputs, txid_generator, edns, protocol_tweaks })
+        Ok(Self { inputs, txid_generator, edns, protocol_tweaks, sig0_key, timeouts, server_names, bootstrap })
+    }
+}
+
+
+impl ServerNames {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        let sni = matches.opt_str("sni");
+        let http_host = matches.opt_str("http-host");
+        let user_agent = matches.opt_str("user-agent");
+        let doh_path = matches.opt_str("doh-path");
+
+        let http_headers = matches.opt_strs("http-header").into_iter()
+                                  .map(parse_http_header)
+                                  .collect::<Result<_, _>>()?;
+
+        let ip_ttl = match matches.opt_str("ip-ttl") {
+            Some(input) => match input.parse() {
+                Ok(0) | Err(_)  => return Err(OptionsError::InvalidIpTtl(input)),
+                Ok(ttl)         => Some(ttl),
+            },
+            None => None,
+        };
+
+        Ok(Self { sni, http_host, http_headers, user_agent, doh_path, ip_ttl, kernel_timestamps: false })
+    }
+}
+
+/// Splits a header written as `Name: value` into its name and value. The
+/// name has to be an HTTP token, and the value can’t span lines.
+fn parse_http_header(input: String) -> Result<(String, String), OptionsError> {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+
+    match input.split_once(':') {
+        Some((name, value)) if ! name.is_empty() && name.chars().all(is_token_char) && ! value.contains(['\r', '\n']) => {
+            Ok((name.to_owned(), value.trim().to_owned()))
+        }
+        _ => Err(OptionsError::InvalidHttpHeader(input)),
+    }
+}
+
+
+/// Works out the timeout for each phase of a request. `--timeout` sets all
+/// of them at once, and the options for each phase override it.
+fn deduce_timeouts(matches: &getopts::Matches) -> Result<Timeouts, OptionsError> {
+    let all = parse_timeout(matches, "timeout")?;
+
+    Ok(Timeouts {
+        connect:    parse_timeout(matches, "connect-timeout")?.or(all),
+        handshake:  parse_timeout(matches, "handshake-timeout")?.or(all),
+        response:   parse_timeout(matches, "response-timeout")?.or(all),
+    })
+}
+
+/// Parses a timeout option, which is either a number of seconds or a
+/// duration with units, such as `500ms` or `2s`.
+fn parse_timeout(matches: &getopts::Matches, name: &str) -> Result<Option<Duration>, OptionsError> {
+    let Some(input) = matches.opt_str(name) else {
+        return Ok(None);
+    };
+
+    let duration = input.parse().map(Duration::from_secs)
+                        .or_else(|_| humantime::parse_duration(&input));
+
+    match duration {
+        Ok(d) if d > Duration::from_secs(0)  => Ok(Some(d)),
+        _                                    => Err(OptionsError::InvalidTimeout(input)),
+    }
+}
+
+
+/// Parses the `--expiry-alert` option, which, like a timeout, is either a
+/// number of seconds or a duration with units, such as `3d`.
+fn deduce_expiry_alert(matches: &getopts::Matches) -> Result<Option<Duration>, OptionsError> {
+    let Some(input) = matches.opt_str("expiry-alert") else {
+        return Ok(None);
+    };
+
+    match input.parse().map(Duration::from_secs).or_else(|_| humantime::parse_duration(&input)) {
+        Ok(duration)  => Ok(Some(duration)),
+        Err(_)        => Err(OptionsError::InvalidExpiryAlert(input)),
+    }
+}
+
+fn deduce_flood(matches: &getopts::Matches) -> Result<Option<Load>, OptionsError> {
+    let Some(input) = matches.opt_str("flood") else {
+        return Ok(None);
+    };
+
+    if ! matches.opt_present("i-own-this-server") {
+        return Err(OptionsError::FloodWithoutOwnership);
+    }
+
+    let count = match input.parse() {
+        Ok(0) | Err(_)  => return Err(OptionsError::InvalidFlood(input)),
+        Ok(count)       => count,
+    };
+
+    let qps = match matches.opt_str("qps") {
+        Some(input) => match input.parse() {
+            Ok(0) | Err(_)  => return Err(OptionsError::InvalidQps(input)),
+            Ok(qps)         => qps,
+        },
+        None => Load::DEFAULT_QPS,
+    };
+
+    Ok(Some(Load { count, qps }))
+}
+
+fn deduce_session(matches: &getopts::Matches) -> Result<Option<session::Mode>, OptionsError> {
+    match (matches.opt_str("record-session"), matches.opt_str("replay-session")) {
+        (Some(_), Some(_))        => Err(OptionsError::RecordAndReplay),
+        (Some(directory), None)   => Ok(Some(session::Mode::Record(directory.into()))),
+        (None, Some(directory))   => Ok(Some(session::Mode::Replay(directory.into()))),
+        (None, None)              => Ok(None),
     }
 }
 
@@ -131,9 +509,19 @@ impl Inputs {
         inputs.load_named_args(&matches)?;
         inputs.load_free_args(matches)?;
         inputs.load_fallbacks();
+        inputs.check_domains()?;
         Ok(inputs)
     }
 
+    /// Checks that every domain can be written into a request, so a typo
+    /// such as a doubled dot gets reported rather than sent.
+    fn check_domains(&self) -> Result<(), OptionsError> {
+        match self.domains.iter().find(|domain| check_name(domain).is_err()) {
+            Some(domain)  => Err(OptionsError::InvalidDomain(domain.clone())),
+            None          => Ok(()),
+        }
+    }
+
     fn load_transport_types(&mut self, matches: &getopts::Matches) {
         if matches.opt_present("https") {
             self.transport_types.push(TransportType::HTTPS);
@@ -165,6 +553,10 @@ impl Inputs {
             self.add_nameserver(&ns)?;
         }
 
+        for path in matches.opt_strs("server-list") {
+            self.add_server_list(path)?;
+        }
+
         for qclass in matches.opt_strs("class") {
             self.add_class(&qclass)?;
         }
@@ -173,6 +565,19 @@ impl Inputs {
     }
 
     fn add_type(&mut self, input: &str) -> Result<(), OptionsError> {
+        if let Some(group) = type_groups::find(input) {
+            trace!("Expanding type group {:?} -> {:?}", input, group);
+            for qtype in group {
+                self.add_single_type(&qtype)?;
+            }
+
+            return Ok(());
+        }
+
+        self.add_single_type(input)
+    }
+
+    fn add_single_type(&mut self, input: &str) -> Result<(), OptionsError> {
         if input == "OPT" {
             return Err(OptionsError::QueryTypeOPT);
         }
@@ -192,6 +597,20 @@ impl Inputs {
         Ok(())
     }
 
+    fn add_server_list(&mut self, path: String) -> Result<(), OptionsError> {
+        let servers = match server_list::load(Path::new(&path)) {
+            Ok(servers)  => servers,
+            Err(e)       => return Err(OptionsError::InvalidServerList(path, e)),
+        };
+
+        for server in servers {
+            trace!("Got nameserver {:?} from list -> {:?}", server.name, server.nameserver);
+            self.add_nameserver(&server.nameserver)?;
+        }
+
+        Ok(())
+    }
+
     fn parse_class_name(&self, input: &str) -> Option<QClass> {
         match input {
             "IN"  => Some(QClass::IN),
@@ -299,18 +718,29 @@ fn parse_dec_or_hex(input: &str) -> Option<u16> {
 
 
 impl OutputFormat {
-    fn deduce(matches: &getopts::Matches) -> Self {
-        if matches.opt_present("short") {
-            let summary_format = TextFormat::deduce(matches);
-            Self::Short(summary_format)
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        let display = match matches.opt_str("display") {
+            Some(name)                            => name,
+            None if matches.opt_present("short")  => "short".into(),
+            None if matches.opt_present("json")   => "json".into(),
+            None                                  => "text".into(),
+        };
+
+        if display == "short" {
+            let summary_format = TextFormat::deduce(matches)?;
+            Ok(Self::Short(summary_format))
         }
-        else if matches.opt_present("json") {
-            Self::JSON
+        else if display == "json" {
+            Ok(Self::JSON(matches.opt_present("annotate")))
         }
-        else {
+        else if display == "text" {
             let use_colours = UseColours::deduce(matches);
-            let summary_format = TextFormat::deduce(matches);
-            Self::Text(use_colours, summary_format)
+            let summary_format = TextFormat::deduce(matches)?;
+            let columns = Column::deduce(matches)?;
+            Ok(Self::Text(use_colours, summary_format, columns))
+        }
+        else {
+            Err(OptionsError::InvalidDisplay(display))
         }
     }
 }
@@ -332,9 +762,88 @@ impl UseColours {
 
 
 impl TextFormat {
-    fn deduce(matches: &getopts::Matches) -> Self {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         let format_durations = ! matches.opt_present("seconds");
-        Self { format_durations }
+        let zone_format = matches.opt_present("zone-format");
+        let truncate_data = ! matches.opt_present("full") && ! zone_format;
+        let time_format = TimeFormat::deduce(matches)?;
+        let show_header = matches.opt_present("header");
+        let annotate = matches.opt_present("annotate");
+        let decode_idn = ! matches.opt_present("punycode") && ! zone_format;
+        let show_http_details = matches.opt_present("http-details");
+        let show_trust = matches.opt_present("trust");
+        let explain = matches.opt_present("explain");
+        Ok(Self { format_durations, truncate_data, time_format, show_header, zone_format, annotate, decode_idn, show_http_details, show_trust, explain })
+    }
+}
+
+
+impl TimeFormat {
+    fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
+        match matches.opt_str("time-format").as_deref() {
+            Some("absolute")       => Ok(Self::Absolute),
+            Some("relative")       => Ok(Self::Relative),
+            Some("both") | None    => Ok(Self::Both),
+            Some(otherwise)        => Err(OptionsError::InvalidTimeFormat(otherwise.into())),
+        }
+    }
+}
+
+
+impl Filter {
+    fn deduce(matches: &getopts::Matches) -> Result<Vec<Self>, OptionsError> {
+        let mut filters = matches.opt_strs("grep").into_iter().map(Self::Grep).collect::<Vec<_>>();
+
+        for input in matches.opt_strs("filter") {
+            match Self::parse(&input) {
+                Some(filter)  => filters.push(filter),
+                None          => return Err(OptionsError::InvalidFilter(input)),
+            }
+        }
+
+        Ok(filters)
+    }
+}
+
+
+impl Page {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        let number = |option| -> Result<Option<usize>, OptionsError> {
+            match matches.opt_str(option) {
+                Some(input) => match input.parse() {
+                    Ok(0) | Err(_)  => Err(OptionsError::InvalidPage(input)),
+                    Ok(number)      => Ok(Some(number)),
+                },
+                None => Ok(None),
+            }
+        };
+
+        match (number("limit")?, number("page")?) {
+            (None, None)      => Ok(None),
+            (limit, number)   => Ok(Some(Self { limit: limit.unwrap_or(Self::DEFAULT_LIMIT), number: number.unwrap_or(1) })),
+        }
+    }
+}
+
+
+impl Column {
+    fn deduce(matches: &getopts::Matches) -> Result<Vec<Self>, OptionsError> {
+        if let Some(columns) = matches.opt_str("columns") {
+            columns.split(',').map(|column| {
+                match column {
+                    "type"               => Ok(Self::Type),
+                    "name"               => Ok(Self::Name),
+                    "class"              => Ok(Self::Class),
+                    "ttl"                => Ok(Self::TTL),
+                    "section"            => Ok(Self::Section),
+                    "rdata" | "data"     => Ok(Self::Data),
+                    otherwise            => Err(OptionsError::InvalidColumn(otherwise.into())),
+                }
+            }).collect()
+        }
+        else {
+            Ok(Self::defaults())
+        }
     }
 }
 
@@ -360,11 +869,40 @@ impl ProtocolTweaks {
     fn deduce(matches: &getopts::Matches) -> Result<Self, OptionsError> {
         let mut tweaks = Self::default();
 
+        // The DNS Flag Day 2020 preset avoids fragmentation; any tweaks
+        // given explicitly take precedence over it
+        if matches.opt_present("dnsflagday2020") {
+            tweaks.udp_payload_size = Some(FLAG_DAY_PAYLOAD_SIZE);
+            tweaks.retry_over_tcp = true;
+        }
+
+        // Authoritative nameservers answer from their own data, so there’s
+        // no need to ask them to recurse
+        if matches.opt_present("authoritative") {
+            tweaks.no_recursion = true;
+        }
+
+        // Signatures only get sent back when they’re asked for
+        if matches.opt_present("expiry-alert") {
+            tweaks.dnssec_ok = true;
+        }
+
+        // Resolvers only set the AD bit for clients that show they
+        // understand it (RFC 6840 §5.7)
+        if matches.opt_present("trust") {
+            tweaks.set_authentic_flag = true;
+        }
+
         if let Some(tweak_strs) = matches.opt_str("Z") {
             for tweak_str in tweak_strs.split(',') {
-                match &*tweak_str {
-                    "authentic"  => { tweaks.set_authentic_flag = true; },
-                    otherwise    => return Err(OptionsError::InvalidTweak(otherwise.into())),
+                match tweak_str.split_once('=') {
+                    None if tweak_str == "authentic"     => { tweaks.set_authentic_flag = true; },
+                    None if tweak_str == "tcp-retry"     => { tweaks.retry_over_tcp = true; },
+                    None if tweak_str == "no-tcp-retry"  => { tweaks.retry_over_tcp = false; },
+                    None if tweak_str == "dnssec-ok"     => { tweaks.dnssec_ok = true; },
+                    None if tweak_str == "no-recurse"    => { tweaks.no_recursion = true; },
+                    Some(("bufsize", size))              => { tweaks.udp_payload_size = Some(parse_bufsize(size)?); },
+                    _                                    => return Err(OptionsError::InvalidTweak(tweak_str.into())),
                 }
             }
         }
@@ -374,8 +912,34 @@ impl ProtocolTweaks {
 }
 
 
+/// Parses a UDP payload size, which can’t be smaller than the 512 bytes
+/// every nameserver has to support.
+fn parse_bufsize(input: &str) -> Result<u16, OptionsError> {
+    match input.parse() {
+        Ok(size) if size >= 512  => Ok(size),
+        _                        => Err(OptionsError::InvalidTweak(format!("bufsize={}", input))),
+    }
+}
+
+
+impl Sig0Key {
+    fn deduce(matches: &getopts::Matches) -> Result<Option<Self>, OptionsError> {
+        if let Some(path) = matches.opt_str("sig0") {
+            match Self::load(Path::new(&path)) {
+                Ok(key)  => Ok(Some(key)),
+                Err(e)   => Err(OptionsError::InvalidSig0Key(path, e)),
+            }
+        }
+        else {
+            Ok(None)
+        }
+    }
+}
+
+
 /// The result of the `Options::getopts` function.
 #[derive(PartialEq, Debug)]
+#[allow(clippy::large_enum_variant)]  // only ever constructed once
 pub enum OptionsResult {
 
     /// The options were parsed successfully.
@@ -392,6 +956,18 @@ pub enum OptionsResult {
 
     /// One of the arguments was `--version`, to display the version number.
     Version(UseColours),
+
+    /// The arguments were `--version` and `--json`, to display the version
+    /// number and the capabilities of this build as JSON.
+    VersionJSON,
+
+    /// The only argument was the `history` command, to list the queries
+    /// that have been run before.
+    History,
+
+    /// The arguments were the `replay` command, to run the query with the
+    /// given number from the history again.
+    Replay(usize),
 }
 
 /// The reason that help is being displayed. If it’s for the `--help` flag,
@@ -412,10 +988,31 @@ pub enum HelpReason {
 pub enum OptionsError {
     TooManyProtocols,
     InvalidEDNS(String),
+    InvalidDomain(String),
     InvalidQueryType(String),
     InvalidQueryClass(String),
     InvalidTxid(String),
     InvalidTweak(String),
+    InvalidSig0Key(String, Sig0KeyError),
+    InvalidServerList(String, ServerListError),
+    InvalidColumn(String),
+    InvalidFilter(String),
+    InvalidPage(String),
+    InvalidTimeFormat(String),
+    InvalidReplay(String),
+    InvalidTimeout(String),
+    InvalidExpiryAlert(String),
+    InvalidDisplay(String),
+    InvalidHttpHeader(String),
+    InvalidIpTtl(String),
+    InvalidFlood(String),
+    InvalidQps(String),
+    FloodWithoutOwnership,
+    FloodWithoutNameserver,
+    RecordAndReplay,
+    ExpiryAlertWithoutEDNS,
+    ConflictingModes(&'static str, &'static str),
+    LookupOnly(&'static str, &'static str),
     QueryTypeOPT,
 }
 
@@ -424,10 +1021,36 @@ impl fmt::Display for OptionsError {
         match self {
             Self::TooManyProtocols       => write!(f, "Too many protocols"),
             Self::InvalidEDNS(edns)      => write!(f, "Invalid EDNS setting {:?}", edns),
-            Self::InvalidQueryType(qt)   => write!(f, "Invalid query type {:?}", qt),
+            Self::InvalidDomain(name)    => write!(f, "Invalid domain {:?} (each label has to be 1 to 63 bytes, and the whole name at most 255)", name),
+            Self::InvalidQueryType(qt)   => {
+                let names = qtype_names().into_iter().chain(other_qtype_names());
+                write!(f, "Invalid query type {:?}{}", qt, suggestions::did_you_mean(&suggestions::closest(qt, names)))
+            }
             Self::InvalidQueryClass(qc)  => write!(f, "Invalid query class {:?}", qc),
             Self::InvalidTxid(txid)      => write!(f, "Invalid transaction ID {:?}", txid),
             Self::InvalidTweak(tweak)    => write!(f, "Invalid protocol tweak {:?}", tweak),
+            Self::InvalidSig0Key(p, e)   => write!(f, "Invalid SIG(0) key {:?}: {}", p, e),
+            Self::InvalidServerList(p, e) => write!(f, "Invalid server list {:?}: {}", p, e),
+            Self::InvalidColumn(col)     => write!(f, "Invalid column {:?}", col),
+            Self::InvalidFilter(filter)  => write!(f, "Invalid filter {:?}", filter),
+            Self::InvalidPage(number)    => write!(f, "Invalid number of records or page {:?}", number),
+            Self::InvalidTimeFormat(tf)  => write!(f, "Invalid time format {:?}", tf),
+            Self::InvalidReplay(num)     => write!(f, "Invalid history number {:?}", num),
+            Self::InvalidTimeout(to)     => write!(f, "Invalid timeout {:?}", to),
+            Self::InvalidExpiryAlert(d)  => write!(f, "Invalid expiry alert duration {:?}", d),
+            Self::InvalidDisplay(d)      => {
+                write!(f, "Invalid display {:?}{}", d, suggestions::did_you_mean(&suggestions::closest(d, BUILT_IN_DISPLAYS.iter().copied())))
+            }
+            Self::InvalidHttpHeader(h)   => write!(f, "Invalid HTTP header {:?} (it should look like 'Name: value')", h),
+            Self::InvalidIpTtl(ttl)      => write!(f, "Invalid IP TTL {:?} (it should be from 1 to 255)", ttl),
+            Self::InvalidFlood(count)    => write!(f, "Invalid number of queries to flood with {:?}", count),
+            Self::InvalidQps(qps)        => write!(f, "Invalid number of queries per second {:?}", qps),
+            Self::FloodWithoutOwnership  => write!(f, "Flooding is only for nameservers you run (confirm this with --i-own-this-server)"),
+            Self::FloodWithoutNameserver => write!(f, "Flooding needs the nameserver to be given explicitly"),
+            Self::RecordAndReplay        => write!(f, "Cannot record and replay a session at the same time"),
+            Self::ExpiryAlertWithoutEDNS => write!(f, "Cannot check signature expiry with EDNS disabled, as signatures are only sent when asked for with EDNS"),
+            Self::ConflictingModes(a, b) => write!(f, "Cannot use {} and {} at the same time", a, b),
+            Self::LookupOnly(check, mode) => write!(f, "Cannot use {} with {}, as it only checks the responses to a lookup", check, mode),
             Self::QueryTypeOPT           => write!(f, "OPT request is sent by default (see -Z flag)"),
         }
     }
@@ -481,6 +1104,12 @@ mod test {
                    OptionsResult::Version(UseColours::Automatic));
     }
 
+    #[test]
+    fn version_json() {
+        assert_eq!(Options::getopts(&[ "--version", "--json" ]),
+                   OptionsResult::VersionJSON);
+    }
+
     #[test]
     fn version_yes_color() {
         assert_eq!(Options::getopts(&[ "--version", "--color", "always" ]),
@@ -590,6 +1219,26 @@ mod test {
         });
     }
 
+    #[test]
+    fn type_group() {
+        let options = Options::getopts(&[ "-q", "lookup.dog", "--type", "mail" ]).unwrap();
+        assert_eq!(options.requests.inputs, Inputs {
+            domains:    vec![ String::from("lookup.dog") ],
+            types:      vec![ qtype!(MX), qtype!(TXT) ],
+            .. Inputs::fallbacks()
+        });
+    }
+
+    #[test]
+    fn type_group_free_arg() {
+        let options = Options::getopts(&[ "lookup.dog", "DNSSEC" ]).unwrap();
+        assert_eq!(options.requests.inputs, Inputs {
+            domains:    vec![ String::from("lookup.dog") ],
+            types:      vec![ 48, 43, qtype!(RRSIG), 47 ],
+            .. Inputs::fallbacks()
+        });
+    }
+
     #[test]
     fn two_classes() {
         let options = Options::getopts(&[ "-q", "lookup.dog", "--class", "IN", "--class", "CH" ]).unwrap();
@@ -645,6 +1294,139 @@ mod test {
         });
     }
 
+    #[test]
+    fn default_columns() {
+        let options = Options::getopts(&[ "lookup.dog" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn picked_columns() {
+        let options = Options::getopts(&[ "lookup.dog", "--columns", "ttl,name,rdata" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }, vec![ Column::TTL, Column::Name, Column::Data ]));
+    }
+
+    #[test]
+    fn full_data() {
+        let options = Options::getopts(&[ "lookup.dog", "--full" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: false, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn relative_times() {
+        let options = Options::getopts(&[ "lookup.dog", "--short", "--time-format=relative" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Short(TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Relative, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }));
+    }
+
+    #[test]
+    fn header() {
+        let options = Options::getopts(&[ "lookup.dog", "--header" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: true, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn zone_format() {
+        let options = Options::getopts(&[ "lookup.dog", "--short", "--zone-format" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Short(TextFormat { format_durations: true, truncate_data: false, time_format: TimeFormat::Both, show_header: false, zone_format: true, annotate: false, decode_idn: false, show_http_details: false, show_trust: false, explain: false }));
+    }
+
+    #[test]
+    fn annotate_json() {
+        let options = Options::getopts(&[ "lookup.dog", "--json", "--annotate" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::JSON(true));
+    }
+
+    #[test]
+    fn display_json() {
+        let options = Options::getopts(&[ "lookup.dog", "--display", "json" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::JSON(false));
+    }
+
+    #[test]
+    fn display_overrides_short() {
+        let options = Options::getopts(&[ "lookup.dog", "--short", "--display=text" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn invalid_display() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--display", "jsn" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidDisplay("jsn".into())));
+    }
+
+    #[test]
+    fn invalid_display_message() {
+        let error = OptionsError::InvalidDisplay("jsn".into());
+        assert_eq!(error.to_string(), "Invalid display \"jsn\" (did you mean json?)");
+    }
+
+    fn free(args: &[&str]) -> Vec<String> {
+        args.iter().copied().map(String::from).collect()
+    }
+
+    #[test]
+    fn history() {
+        assert_eq!(Options::history_command(&free(&[ "history" ]), true),
+                   Some(OptionsResult::History));
+    }
+
+    #[test]
+    fn replay() {
+        assert_eq!(Options::history_command(&free(&[ "replay", "3" ]), true),
+                   Some(OptionsResult::Replay(3)));
+    }
+
+    #[test]
+    fn history_is_only_a_command_alone() {
+        assert_eq!(Options::history_command(&free(&[ "history", "MX" ]), true),
+                   None);
+    }
+
+    #[test]
+    fn history_is_a_domain_without_history() {
+        assert_eq!(Options::history_command(&free(&[ "history" ]), false),
+                   None);
+        assert_eq!(Options::history_command(&free(&[ "replay", "3" ]), false),
+                   None);
+    }
+
+    #[test]
+    fn history_with_a_dot_is_a_domain() {
+        assert_eq!(Options::history_command(&free(&[ "history." ]), true),
+                   None);
+    }
+
+    #[test]
+    fn history_domain_with_a_type() {
+        let options = Options::getopts(&[ "history", "MX" ]).unwrap();
+        assert_eq!(options.requests.inputs.domains, vec![ String::from("history") ]);
+    }
+
+    #[test]
+    fn log_file() {
+        let options = Options::getopts(&[ "lookup.dog", "--log-file", "dog.log" ]).unwrap();
+        assert_eq!(options.log_file, Some(PathBuf::from("dog.log")));
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn sqlite() {
+        let options = Options::getopts(&[ "lookup.dog", "--sqlite", "results.db" ]).unwrap();
+        assert_eq!(options.sqlite, Some(PathBuf::from("results.db")));
+    }
+
+    #[test]
+    fn record_session() {
+        let options = Options::getopts(&[ "lookup.dog", "--record-session", "fixtures" ]).unwrap();
+        assert_eq!(options.session, Some(session::Mode::Record(PathBuf::from("fixtures"))));
+    }
+
+    #[test]
+    fn replay_session() {
+        let options = Options::getopts(&[ "lookup.dog", "--replay-session", "fixtures" ]).unwrap();
+        assert_eq!(options.session, Some(session::Mode::Replay(PathBuf::from("fixtures"))));
+    }
+
     // invalid options tests
 
     #[test]
@@ -653,6 +1435,18 @@ mod test {
                    OptionsResult::InvalidOptions(OptionsError::InvalidQueryClass("tubes".into())));
     }
 
+    #[test]
+    fn record_and_replay() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--record-session", "a", "--replay-session", "b" ]),
+                   OptionsResult::InvalidOptions(OptionsError::RecordAndReplay));
+    }
+
+    #[test]
+    fn missing_server_list() {
+        assert!(matches!(Options::getopts(&[ "lookup.dog", "--server-list", "/does/not/exist.toml" ]),
+                         OptionsResult::InvalidOptions(OptionsError::InvalidServerList(_, ServerListError::IO(_)))));
+    }
+
     #[test]
     fn invalid_named_type() {
         assert_eq!(Options::getopts(&[ "lookup.dog", "--type", "tubes" ]),
@@ -671,6 +1465,428 @@ mod test {
                    OptionsResult::InvalidOptions(OptionsError::InvalidTxid("0x10000".into())));
     }
 
+    #[test]
+    fn invalid_sig0_key_name() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--sig0", "dog.key" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidSig0Key("dog.key".into(), Sig0KeyError::FileName)));
+    }
+
+    #[test]
+    fn unsupported_sig0_key() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--sig0", "Kdog.+008+12345.private" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidSig0Key("Kdog.+008+12345.private".into(), Sig0KeyError::UnsupportedAlgorithm(8))));
+    }
+
+    #[test]
+    fn invalid_column() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--columns", "ttl,colour" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidColumn("colour".into())));
+    }
+
+    #[test]
+    fn invalid_time_format() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--time-format", "soon" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidTimeFormat("soon".into())));
+    }
+
+    #[test]
+    fn race() {
+        let options = Options::getopts(&[ "lookup.dog", "@1.1.1.1", "@8.8.8.8", "--race" ]).unwrap();
+        assert!(options.race);
+    }
+
+    #[test]
+    fn http_details() {
+        let options = Options::getopts(&[ "lookup.dog", "--https", "--http-details" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: true, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn trust() {
+        let options = Options::getopts(&[ "lookup.dog", "--tls", "@dns.example", "--trust" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: true, explain: false }, Column::defaults()));
+        assert!(options.requests.protocol_tweaks.set_authentic_flag);
+    }
+
+    #[test]
+    fn explain() {
+        let options = Options::getopts(&[ "lookup.dog", "SOA", "--explain" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: true }, Column::defaults()));
+    }
+
+    #[test]
+    fn punycode() {
+        let options = Options::getopts(&[ "lookup.dog", "--punycode" ]).unwrap();
+        assert_eq!(options.format, OutputFormat::Text(UseColours::Automatic, TextFormat { format_durations: true, truncate_data: true, time_format: TimeFormat::Both, show_header: false, zone_format: false, annotate: false, decode_idn: false, show_http_details: false, show_trust: false, explain: false }, Column::defaults()));
+    }
+
+    #[test]
+    fn probe_max_udp() {
+        let options = Options::getopts(&[ "lookup.dog", "--probe-max-udp" ]).unwrap();
+        assert!(options.probe_max_udp);
+    }
+
+    #[test]
+    fn cache_probe() {
+        let options = Options::getopts(&[ "lookup.dog", "@1.1.1.1", "--cache-probe" ]).unwrap();
+        assert!(options.cache_probe);
+    }
+
+    #[test]
+    fn both_protocols() {
+        let options = Options::getopts(&[ "lookup.dog", "@1.1.1.1", "--both-protocols" ]).unwrap();
+        assert!(options.both_protocols);
+    }
+
+    #[test]
+    fn dnssec_views() {
+        let options = Options::getopts(&[ "lookup.dog", "@1.1.1.1", "--dnssec-views" ]).unwrap();
+        assert!(options.dnssec_views);
+    }
+
+    #[test]
+    fn push_zone() {
+        let options = Options::getopts(&[ "push-zone", "lookup.dog", "@192.0.2.53", "--primary", "192.0.2.1" ]).unwrap();
+        assert!(options.push_zone);
+        assert_eq!(options.primary, Some("192.0.2.1".into()));
+        assert_eq!(options.requests.inputs, Inputs {
+            domains:          vec![ String::from("lookup.dog") ],
+            types:            vec![ qtype!(SOA) ],
+            classes:          vec![ QClass::IN ],
+            resolvers:        vec![ Resolver::Specified("192.0.2.53".into()) ],
+            transport_types:  vec![ TransportType::Automatic ],
+        });
+    }
+
+    #[test]
+    fn conflicting_modes() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--probe-max-udp", "--dnssec-views" ]),
+                   OptionsResult::InvalidOptions(OptionsError::ConflictingModes("--probe-max-udp", "--dnssec-views")));
+    }
+
+    #[test]
+    fn conflicting_modes_with_command() {
+        assert_eq!(Options::getopts(&[ "push-zone", "lookup.dog", "@192.0.2.53", "--flood", "10", "--i-own-this-server" ]),
+                   OptionsResult::InvalidOptions(OptionsError::ConflictingModes("push-zone", "--flood")));
+    }
+
+    #[test]
+    fn conflicting_modes_with_race() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--race", "--both-protocols" ]),
+                   OptionsResult::InvalidOptions(OptionsError::ConflictingModes("--race", "--both-protocols")));
+    }
+
+    #[test]
+    fn lookup_checks_with_modes() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--authoritative", "--cache-probe" ]),
+                   OptionsResult::InvalidOptions(OptionsError::LookupOnly("--authoritative", "--cache-probe")));
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--check-glue", "--dnssec-views" ]),
+                   OptionsResult::InvalidOptions(OptionsError::LookupOnly("--check-glue", "--dnssec-views")));
+        assert_eq!(Options::getopts(&[ "push-zone", "lookup.dog", "@192.0.2.53", "--expiry-alert", "3d" ]),
+                   OptionsResult::InvalidOptions(OptionsError::LookupOnly("--expiry-alert", "push-zone")));
+    }
+
+    #[test]
+    fn lookup_checks_with_race() {
+        let options = Options::getopts(&[ "lookup.dog", "@192.0.2.53", "@192.0.2.54", "--race", "--check-glue", "--authoritative", "--expiry-alert", "3d" ]).unwrap();
+        assert!(options.race && options.check_glue && options.authoritative);
+    }
+
+    #[test]
+    fn lookup_only_message() {
+        let error = OptionsError::LookupOnly("--check-glue", "--flood");
+        assert_eq!(error.to_string(), "Cannot use --check-glue with --flood, as it only checks the responses to a lookup");
+    }
+
+    #[test]
+    fn conflicting_modes_message() {
+        let error = OptionsError::ConflictingModes("--cache-probe", "--both-protocols");
+        assert_eq!(error.to_string(), "Cannot use --cache-probe and --both-protocols at the same time");
+    }
+
+    #[test]
+    fn push_zone_is_only_a_command_first() {
+        let options = Options::getopts(&[ "lookup.dog", "push-zone" ]).unwrap();
+        assert!(! options.push_zone);
+    }
+
+    #[test]
+    fn flood() {
+        let options = Options::getopts(&[ "lookup.dog", "@192.0.2.53", "--flood", "1000", "--qps", "250", "--i-own-this-server" ]).unwrap();
+        assert_eq!(options.flood, Some(Load { count: 1000, qps: 250 }));
+    }
+
+    #[test]
+    fn flood_default_qps() {
+        let options = Options::getopts(&[ "lookup.dog", "@192.0.2.53", "--flood", "1000", "--i-own-this-server" ]).unwrap();
+        assert_eq!(options.flood, Some(Load { count: 1000, qps: Load::DEFAULT_QPS }));
+    }
+
+    #[test]
+    fn flood_without_ownership() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "@192.0.2.53", "--flood", "1000" ]),
+                   OptionsResult::InvalidOptions(OptionsError::FloodWithoutOwnership));
+    }
+
+    #[test]
+    fn flood_without_nameserver() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--flood", "1000", "--i-own-this-server" ]),
+                   OptionsResult::InvalidOptions(OptionsError::FloodWithoutNameserver));
+    }
+
+    #[test]
+    fn invalid_flood() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "@192.0.2.53", "--flood", "0", "--i-own-this-server" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidFlood("0".into())));
+        assert_eq!(Options::getopts(&[ "lookup.dog", "@192.0.2.53", "--flood", "10", "--qps", "fast", "--i-own-this-server" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidQps("fast".into())));
+    }
+
+    #[test]
+    fn explain_errors() {
+        let options = Options::getopts(&[ "lookup.dog", "--explain-errors" ]).unwrap();
+        assert!(options.explain_errors);
+    }
+
+    #[test]
+    fn check_glue() {
+        let options = Options::getopts(&[ "lookup.dog", "--check-glue" ]).unwrap();
+        assert!(options.check_glue);
+    }
+
+    #[test]
+    fn authoritative() {
+        let options = Options::getopts(&[ "lookup.dog", "--authoritative" ]).unwrap();
+        assert!(options.authoritative);
+        assert!(options.requests.protocol_tweaks.no_recursion);
+    }
+
+    #[test]
+    fn no_recurse_tweak() {
+        let options = Options::getopts(&[ "lookup.dog", "-Z", "no-recurse" ]).unwrap();
+        assert!(! options.authoritative);
+        assert!(options.requests.protocol_tweaks.no_recursion);
+    }
+
+    #[test]
+    fn grep_and_filter() {
+        let options = Options::getopts(&[ "lookup.dog", "--grep", "v=spf1", "--filter", "ttl > 60" ]).unwrap();
+        assert_eq!(options.filters, vec![
+            Filter::Grep("v=spf1".into()),
+            Filter::parse("ttl > 60").unwrap(),
+        ]);
+    }
+
+    #[test]
+    fn invalid_filter() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--filter", "rdata resembles 192.0.2.1" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidFilter("rdata resembles 192.0.2.1".into())));
+    }
+
+    #[test]
+    fn sort() {
+        let options = Options::getopts(&[ "lookup.dog", "--sort" ]).unwrap();
+        assert!(options.sort);
+    }
+
+    #[test]
+    fn limit() {
+        let options = Options::getopts(&[ "lookup.dog", "--limit", "20" ]).unwrap();
+        assert_eq!(options.page, Some(Page { limit: 20, number: 1 }));
+    }
+
+    #[test]
+    fn page_without_limit() {
+        let options = Options::getopts(&[ "lookup.dog", "--page", "3" ]).unwrap();
+        assert_eq!(options.page, Some(Page { limit: Page::DEFAULT_LIMIT, number: 3 }));
+    }
+
+    #[test]
+    fn invalid_page() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--page", "0" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidPage("0".into())));
+    }
+
+    #[test]
+    fn summary() {
+        let options = Options::getopts(&[ "lookup.dog", "--summary" ]).unwrap();
+        assert!(options.summarise);
+        assert_eq!(options.page, None);
+    }
+
+    #[test]
+    fn flag_day() {
+        let options = Options::getopts(&[ "lookup.dog", "--dnsflagday2020" ]).unwrap();
+        assert_eq!(options.requests.protocol_tweaks, ProtocolTweaks {
+            set_authentic_flag: false,
+            udp_payload_size: Some(1232),
+            retry_over_tcp: true,
+            dnssec_ok: false,
+            no_recursion: false,
+        });
+    }
+
+    #[test]
+    fn flag_day_deviations() {
+        let options = Options::getopts(&[ "lookup.dog", "--dnsflagday2020", "-Z", "bufsize=4096,no-tcp-retry" ]).unwrap();
+        assert_eq!(options.requests.protocol_tweaks, ProtocolTweaks {
+            set_authentic_flag: false,
+            udp_payload_size: Some(4096),
+            retry_over_tcp: false,
+            dnssec_ok: false,
+            no_recursion: false,
+        });
+    }
+
+    #[test]
+    fn expiry_alert() {
+        let options = Options::getopts(&[ "lookup.dog", "--expiry-alert", "3d" ]).unwrap();
+        assert_eq!(options.expiry_alert, Some(Duration::from_hours(3 * 24)));
+        assert!(options.requests.protocol_tweaks.dnssec_ok);
+    }
+
+    #[test]
+    fn invalid_expiry_alert() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--expiry-alert", "soon" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidExpiryAlert("soon".into())));
+    }
+
+    #[test]
+    fn expiry_alert_without_edns() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--expiry-alert", "3d", "--edns", "disable" ]),
+                   OptionsResult::InvalidOptions(OptionsError::ExpiryAlertWithoutEDNS));
+    }
+
+    #[test]
+    fn dnssec_ok_tweak() {
+        let options = Options::getopts(&[ "lookup.dog", "-Z", "dnssec-ok" ]).unwrap();
+        assert!(options.requests.protocol_tweaks.dnssec_ok);
+        assert_eq!(options.expiry_alert, None);
+    }
+
+    #[test]
+    fn invalid_bufsize() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "-Z", "bufsize=100" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidTweak("bufsize=100".into())));
+    }
+
+    #[test]
+    fn no_timeouts() {
+        let options = Options::getopts(&[ "lookup.dog" ]).unwrap();
+        assert_eq!(options.requests.timeouts, Timeouts::default());
+    }
+
+    #[test]
+    fn timeouts() {
+        let options = Options::getopts(&[ "lookup.dog", "--timeout", "5", "--handshake-timeout", "500ms" ]).unwrap();
+        assert_eq!(options.requests.timeouts, Timeouts {
+            connect:    Some(Duration::from_secs(5)),
+            handshake:  Some(Duration::from_millis(500)),
+            response:   Some(Duration::from_secs(5)),
+        });
+    }
+
+    #[test]
+    fn server_names() {
+        let options = Options::getopts(&[ "lookup.dog", "@https://192.0.2.1/dns-query", "--sni", "dns.example", "--http-host", "doh.example" ]).unwrap();
+        assert_eq!(options.requests.server_names, ServerNames {
+            sni:        Some("dns.example".into()),
+            http_host:  Some("doh.example".into()),
+            .. ServerNames::default()
+        });
+    }
+
+    #[test]
+    fn doh_settings() {
+        let options = Options::getopts(&[ "lookup.dog", "@https://dns.example/dns-query", "--http-header", "Authorization: Bearer abc",
+                                          "--http-header", "X-Tenant:42", "--user-agent", "dog-test", "--doh-path", "/private{?dns}" ]).unwrap();
+        assert_eq!(options.requests.server_names, ServerNames {
+            http_headers:  vec![ ("Authorization".into(), "Bearer abc".into()), ("X-Tenant".into(), "42".into()) ],
+            user_agent:    Some("dog-test".into()),
+            doh_path:      Some("/private{?dns}".into()),
+            .. ServerNames::default()
+        });
+    }
+
+    #[test]
+    fn invalid_http_header() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--http-header", "No colon here" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidHttpHeader("No colon here".into())));
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--http-header", "Bad name: value" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidHttpHeader("Bad name: value".into())));
+    }
+
+    #[test]
+    fn ip_ttl() {
+        let options = Options::getopts(&[ "lookup.dog", "@192.0.2.1", "--ip-ttl", "3" ]).unwrap();
+        assert_eq!(options.requests.server_names.ip_ttl, Some(3));
+    }
+
+    #[test]
+    fn invalid_ip_ttl() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--ip-ttl", "0" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidIpTtl("0".into())));
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--ip-ttl", "256" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidIpTtl("256".into())));
+    }
+
+    #[test]
+    fn bootstrap() {
+        let options = Options::getopts(&[ "lookup.dog", "-S", "@dns.example", "--bootstrap", "@9.9.9.9" ]).unwrap();
+        assert_eq!(options.requests.bootstrap, Some("9.9.9.9".into()));
+    }
+
+    #[test]
+    fn invalid_timeout() {
+        assert_eq!(Options::getopts(&[ "lookup.dog", "--response-timeout", "soon" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidTimeout("soon".into())));
+    }
+
+    #[test]
+    fn invalid_type_suggestion() {
+        assert_eq!(OptionsError::InvalidQueryType("AAA".into()).to_string(),
+                   "Invalid query type \"AAA\" (did you mean AAAA or CAA?)");
+    }
+
+    #[test]
+    fn option_suggestions() {
+        assert_eq!(Options::suggest_options("jsno"),      vec![ "--json" ]);
+        assert_eq!(Options::suggest_options("--nameservr"), vec![ "--nameserver" ]);
+        assert_eq!(Options::suggest_options("pear"),      Vec::<String>::new());
+    }
+
+    #[test]
+    fn every_option_is_a_feature() {
+        use crate::features::FEATURES;
+
+        // These make up a plain query, so don’t get listed as features
+        let basics = [ "query", "type", "nameserver", "class", "edns", "txid", "udp", "tcp", "tls", "https",
+                       "color", "colour", "json", "seconds", "short", "time", "version", "help" ];
+
+        for name in Options::long_option_names() {
+            assert!(basics.contains(&name.as_str()) || FEATURES.iter().any(|f| f.arguments.contains(&name.as_str())),
+                    "--{} is missing from the features", name);
+        }
+    }
+
+    #[test]
+    fn invalid_replay() {
+        assert_eq!(Options::history_command(&free(&[ "replay", "0" ]), true),
+                   Some(OptionsResult::InvalidOptions(OptionsError::InvalidReplay("0".into()))));
+    }
+
+    #[test]
+    fn replay_without_number() {
+        assert_eq!(Options::history_command(&free(&[ "replay" ]), true),
+                   Some(OptionsResult::InvalidOptions(OptionsError::InvalidReplay(String::new()))));
+    }
+
+    #[test]
+    fn invalid_domain() {
+        assert_eq!(Options::getopts(&[ "lookup..dog" ]),
+                   OptionsResult::InvalidOptions(OptionsError::InvalidDomain("lookup..dog".into())));
+    }
+
     #[test]
     fn opt() {
         assert_eq!(Options::getopts(&[ "OPT", "lookup.dog" ]),
@@ -0,0 +1,105 @@
+//! Putting the records in a response into a canonical order, so that the
+//! same set of records always comes out the same way, no matter which order
+//! the nameserver happened to send them in.
+
+use dns::{Response, Answer, Wire};
+use dns::record::*;
+
+use crate::zone;
+
+
+/// Sorts the records in every section of a response into canonical order.
+pub fn sort_response(response: &mut Response) {
+    sort_section(&mut response.answers);
+    sort_section(&mut response.authorities);
+    sort_section(&mut response.additionals);
+}
+
+/// Sorts one section’s records by owner name (ignoring case), then by type
+/// number, then by their data as it would be written in a zone file. TTLs
+/// are left out, as they count down between queries. Pseudo-records go at
+/// the end, in the order they arrived.
+fn sort_section(answers: &mut [Answer]) {
+    answers.sort_by_cached_key(|answer| {
+        match answer {
+            Answer::Standard { qname, record, .. } => {
+                (false, qname.to_ascii_lowercase(), type_number(record), zone::presentation(record))
+            }
+            Answer::Pseudo { .. } => {
+                (true, String::new(), OPT::RR_TYPE, String::new())
+            }
+        }
+    });
+}
+
+/// Returns the number of a record’s type.
+pub fn type_number(record: &Record) -> u16 {
+    match record {
+        Record::A(_)      => A::RR_TYPE,
+        Record::AAAA(_)   => AAAA::RR_TYPE,
+        Record::CAA(_)    => CAA::RR_TYPE,
+        Record::CNAME(_)  => CNAME::RR_TYPE,
+        Record::MX(_)     => MX::RR_TYPE,
+        Record::NS(_)     => NS::RR_TYPE,
+        Record::PTR(_)    => PTR::RR_TYPE,
+        Record::RRSIG(_)  => RRSIG::RR_TYPE,
+        Record::SIG(_)    => SIG::RR_TYPE,
+        Record::SOA(_)    => SOA::RR_TYPE,
+        Record::SRV(_)    => SRV::RR_TYPE,
+        Record::TXT(_)    => TXT::RR_TYPE,
+        Record::Other { type_number: UnknownQtype::HeardOf(name), .. }  => find_other_qtype_number(name).unwrap_or(0),
+        Record::Other { type_number: UnknownQtype::UnheardOf(num), .. } => *num,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::QClass;
+    use std::net::Ipv4Addr;
+
+    fn answer(name: &str, ttl: u32, record: Record) -> Answer {
+        Answer::Standard { qname: name.into(), qclass: QClass::IN, ttl, record }
+    }
+
+    fn a(address: [u8; 4]) -> Record {
+        let [a, b, c, d] = address;
+        Record::A(A { address: Ipv4Addr::new(a, b, c, d) })
+    }
+
+    #[test]
+    fn by_name_then_type_then_data() {
+        let mut answers = vec![
+            answer("www.lookup.dog.", 60, a([ 127, 0, 0, 2 ])),
+            answer("lookup.dog.",     60, Record::MX(MX { preference: 10, exchange: "mail.lookup.dog.".into() })),
+            answer("www.lookup.dog.", 30, a([ 127, 0, 0, 1 ])),
+            answer("Lookup.dog.",     60, a([ 127, 0, 0, 3 ])),
+        ];
+
+        sort_section(&mut answers);
+
+        assert_eq!(answers, vec![
+            answer("Lookup.dog.",     60, a([ 127, 0, 0, 3 ])),
+            answer("lookup.dog.",     60, Record::MX(MX { preference: 10, exchange: "mail.lookup.dog.".into() })),
+            answer("www.lookup.dog.", 30, a([ 127, 0, 0, 1 ])),
+            answer("www.lookup.dog.", 60, a([ 127, 0, 0, 2 ])),
+        ]);
+    }
+
+    #[test]
+    fn pseudo_records_last() {
+        let opt = Answer::Pseudo { qname: String::new(), opt: OPT { udp_payload_size: 512, higher_bits: 0, edns0_version: 0, flags: 0, data: vec![] } };
+        let mut answers = vec![ opt.clone(), answer("lookup.dog.", 60, a([ 127, 0, 0, 1 ])) ];
+
+        sort_section(&mut answers);
+
+        assert_eq!(answers, vec![ answer("lookup.dog.", 60, a([ 127, 0, 0, 1 ])), opt ]);
+    }
+
+    #[test]
+    fn unknown_types_by_number() {
+        assert_eq!(type_number(&Record::Other { type_number: UnknownQtype::UnheardOf(1234), bytes: vec![] }), 1234);
+        assert_eq!(type_number(&Record::Other { type_number: UnknownQtype::HeardOf("DS"), bytes: vec![] }), 43);
+    }
+}
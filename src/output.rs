@@ -1,28 +1,60 @@
-//! Text and JSON output.
+//! Text and JSON output, and the renderers that produce them.
 
-use std::time::Duration;
+use std::env;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use dns::{Response, Query, Answer, ErrorCode, WireError};
+use ansi_term::Style;
+
+use dns::{Response, Query, Answer, Flags, ErrorCode, WireError};
 use dns::record::{Record, OPT, UnknownQtype};
-use dns_transport::Error as TransportError;
+use dns_transport::{Error as TransportError, Phase, Details, HttpDetails, RoundTrip};
 use serde_json::{json, Value as JsonValue};
 
+use crate::annotations::annotate;
+use crate::bootstrap::Lookup;
+use crate::cache_probe::{CacheProbe, Verdict};
+use crate::idna;
 use crate::colours::Colours;
-use crate::table::{Table, Section};
+use crate::connect::TransportType;
+use crate::diagnosis::Diagnosis;
+use crate::features;
+use crate::dnssec_views::{self, View, Difference as DnssecDifference};
+use crate::flood::Flood;
+use crate::paging::Summary;
+use crate::order;
+use crate::probe::{Probe, Attempt};
+use crate::protocols::{Comparison, Difference};
+use crate::provenance::Source;
+use crate::push_zone::Push;
+use crate::race::{Race, Outcome};
+use crate::sinkhole::Sinkhole;
+use crate::table::{Table, Column, Section};
+use crate::trust;
+use crate::warnings::{Warning, hops_away};
+use crate::zone;
+
+
+/// The identifier of the layout of dog’s JSON documents, which is included
+/// in every one of them. The number goes up whenever a change is made that
+/// could break programs reading the output.
+pub const JSON_SCHEMA: &str = "dog/1";
 
 
 /// How to format the output data.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum OutputFormat {
 
-    /// Format the output as plain text, optionally adding ANSI colours.
-    Text(UseColours, TextFormat),
+    /// Format the output as plain text, optionally adding ANSI colours, in a
+    /// table with the given columns.
+    Text(UseColours, TextFormat, Vec<Column>),
 
     /// Format the output as one line of plain text.
     Short(TextFormat),
 
-    /// Format the entries as JSON.
-    JSON,
+    /// Format the entries as JSON, adding an annotation to each record
+    /// that has one if the flag is set.
+    JSON(bool),
 }
 
 
@@ -42,12 +74,150 @@ pub enum UseColours {
 
 /// Options that govern how text should be rendered in record summaries.
 #[derive(PartialEq, Debug, Copy, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct TextFormat {
 
     /// Whether to format TTLs as hours, minutes, and seconds.
     pub format_durations: bool,
+
+    /// Whether to truncate long record data so that each line of the table
+    /// fits in the terminal, rather than displaying it in full.
+    pub truncate_data: bool,
+
+    /// How to display timestamps, such as when a signature expires.
+    pub time_format: TimeFormat,
+
+    /// Whether to print a summary of each response’s header, with its
+    /// opcode, status, flags, and section counts, before the records.
+    pub show_header: bool,
+
+    /// Whether to display record data exactly as it would be written in a
+    /// zone file, so it can be copied and pasted into one.
+    pub zone_format: bool,
+
+    /// Whether to add annotations worked out from each record’s data, such
+    /// as the reverse name of an address, after the record.
+    pub annotate: bool,
+
+    /// Whether to decode internationalised domain names from their `xn--`
+    /// form, both in owner names and in names inside record data.
+    pub decode_idn: bool,
+
+    /// Whether to print the details of the HTTP response that each
+    /// DNS-over-HTTPS response arrived in.
+    pub show_http_details: bool,
+
+    /// Whether to print a verdict on how far each response can be trusted,
+    /// from its AD bit and the transport it arrived over.
+    pub show_trust: bool,
+
+    /// Whether to label each field of structured records, such as SOA
+    /// records, with what it means.
+    pub explain: bool,
 }
 
+/// How to display timestamps in record summaries.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TimeFormat {
+
+    /// Display timestamps as dates and times in UTC.
+    Absolute,
+
+    /// Display timestamps as how long ago they were, or how long until they
+    /// will be, such as `in 3d4h`.
+    Relative,
+
+    /// Display timestamps both ways, with the relative time in brackets.
+    Both,
+}
+
+
+/// Everything a run of dog has to show for itself once its requests have
+/// been sent, for a renderer to display.
+pub struct Results<'a> {
+
+    /// The responses that arrived, each paired with where it came from and
+    /// the details of how it arrived.
+    pub responses: Vec<(Response, Source, Details)>,
+
+    /// The lookups of encrypted nameservers’ addresses, if bootstrapping.
+    pub lookups: &'a [Lookup],
+
+    /// The results of any races between nameservers.
+    pub races: &'a [Race],
+
+    /// Anything odd about the responses worth pointing out.
+    pub warnings: &'a [Warning],
+
+    /// Explanations of any errors, if they were asked for.
+    pub diagnoses: &'a [Diagnosis],
+
+    /// How long the run took, if it was being timed.
+    pub duration: Option<Duration>,
+}
+
+/// A **renderer** displays the results of a run in one output format, and
+/// each output format has one. Only the results of a normal run have to be
+/// rendered; the other kinds of output, such as probe results, get printed
+/// as plain text unless a renderer prints them itself.
+pub trait Renderer {
+
+    /// Prints the results. Returns whether there was anything to print,
+    /// which is used to pick the exit code in short mode.
+    fn render(&self, results: Results<'_>) -> bool;
+
+    /// Prints the results of probing nameservers for the largest UDP
+    /// response that makes it back from them.
+    fn render_probes(&self, probes: &[Probe]) {
+        print_text_probes(probes);
+    }
+
+    /// Prints the results of probing resolvers’ caches, and what they say
+    /// about how each resolver treats TTLs.
+    fn render_cache_probes(&self, probes: &[CacheProbe]) {
+        print_text_cache_probes(probes);
+    }
+
+    /// Prints how the responses to each query sent over both UDP and TCP
+    /// differ from each other.
+    fn render_protocol_comparisons(&self, comparisons: &[Comparison]) {
+        print_text_protocol_comparisons(comparisons);
+    }
+
+    /// Prints how the responses to each query asked with and without DNSSEC
+    /// differ from each other, and whether it only fails when validated.
+    fn render_dnssec_views(&self, comparisons: &[dnssec_views::Comparison]) {
+        print_text_dnssec_views(comparisons);
+    }
+
+    /// Prints the results of pushing a zone to secondaries, and how long
+    /// each one took to catch up with the primary.
+    fn render_pushes(&self, pushes: &[Push]) {
+        print_text_pushes(pushes);
+    }
+
+    /// Prints how well each nameserver kept up with being flooded: the rate
+    /// it answered at, what it answered with, and how long it took.
+    fn render_floods(&self, floods: &[Flood]) {
+        print_text_floods(floods);
+    }
+
+    /// Prints a count of the records in each response by type, in place of
+    /// the records themselves, along with any warnings about them.
+    fn render_summaries(&self, summaries: &[Summary], warnings: &[Warning]) {
+        print_text_summaries(summaries, warnings, &Colours::plain());
+    }
+
+    /// Prints an error that stopped a request from getting a response.
+    fn render_error(&self, error: &TransportError) {
+        eprintln!("Error [{}]: {}", erroneous_phase(error), error_message(error));
+    }
+}
+
+/// The names of the output formats that are always available.
+pub const BUILT_IN_DISPLAYS: &[&str] = &[ "text", "short", "json" ];
+
+
 impl UseColours {
 
     /// Whether we should use colours or not. This checks whether the user has
@@ -58,10 +228,16 @@ impl UseColours {
     }
 
     /// Creates a palette of colours depending on the user’s wishes or whether
-    /// output is to a terminal.
+    /// output is to a terminal, applying any overrides from the `DOG_COLORS`
+    /// environment variable.
     pub fn palette(self) -> Colours {
         if self.should_use_colours() {
-            Colours::pretty()
+            let mut colours = Colours::pretty();
+            if let Some(spec) = env::var_os("DOG_COLORS") {
+                colours.customise(&spec.to_string_lossy());
+            }
+
+            colours
         }
         else {
             Colours::plain()
@@ -71,109 +247,728 @@ impl UseColours {
 
 
 impl OutputFormat {
-    pub fn print(self, responses: Vec<Response>, duration: Option<Duration>) -> bool {
+
+    /// Returns the renderer that prints responses in this format.
+    pub fn renderer(&self) -> Box<dyn Renderer> {
         match self {
-            Self::Short(tf) => {
-                let all_answers = responses.into_iter().flat_map(|r| r.answers).collect::<Vec<_>>();
+            Self::Text(uc, tf, columns)    => Box::new(TextRenderer { colours: *uc, format: *tf, columns: columns.clone() }),
+            Self::Short(tf)                => Box::new(ShortRenderer { format: *tf }),
+            Self::JSON(annotate)           => Box::new(JsonRenderer { annotate: *annotate }),
+        }
+    }
 
-                if all_answers.is_empty() {
-                    eprintln!("No results");
-                    return false;
-                }
+    /// Prints the results of a run using this format’s renderer. Returns
+    /// whether there was anything to print in short mode.
+    pub fn print(&self, results: Results<'_>) -> bool {
+        self.renderer().render(results)
+    }
 
-                for answer in all_answers {
-                    match answer {
-                        Answer::Standard { record, .. } => {
-                            println!("{}", tf.record_payload_summary(&record))
-                        }
-                        Answer::Pseudo { opt, .. } => {
-                            println!("{}", tf.pseudo_record_payload_summary(&opt))
-                        }
-                    }
+    /// Prints UDP probe results with this format’s renderer.
+    pub fn print_probes(&self, probes: &[Probe]) {
+        self.renderer().render_probes(probes);
+    }
 
+    /// Prints cache probe results with this format’s renderer.
+    pub fn print_cache_probes(&self, probes: &[CacheProbe]) {
+        self.renderer().render_cache_probes(probes);
+    }
+
+    /// Prints UDP and TCP comparisons with this format’s renderer.
+    pub fn print_protocol_comparisons(&self, comparisons: &[Comparison]) {
+        self.renderer().render_protocol_comparisons(comparisons);
+    }
+
+    /// Prints DNSSEC view comparisons with this format’s renderer.
+    pub fn print_dnssec_views(&self, comparisons: &[dnssec_views::Comparison]) {
+        self.renderer().render_dnssec_views(comparisons);
+    }
+
+    /// Prints zone push results with this format’s renderer.
+    pub fn print_pushes(&self, pushes: &[Push]) {
+        self.renderer().render_pushes(pushes);
+    }
+
+    /// Prints flood results with this format’s renderer.
+    pub fn print_floods(&self, floods: &[Flood]) {
+        self.renderer().render_floods(floods);
+    }
+
+    /// Prints response summaries with this format’s renderer.
+    pub fn print_summaries(&self, summaries: &[Summary], warnings: &[Warning]) {
+        self.renderer().render_summaries(summaries, warnings);
+    }
+
+    /// Prints a request’s error with this format’s renderer.
+    pub fn print_error(&self, error: &TransportError) {
+        self.renderer().render_error(error);
+    }
+}
+
+/// The built-in renderer that prints records in a table.
+struct TextRenderer {
+    colours: UseColours,
+    format: TextFormat,
+    columns: Vec<Column>,
+}
+
+/// The built-in renderer that prints just the data of each answer.
+struct ShortRenderer {
+    format: TextFormat,
+}
+
+/// The built-in renderer that prints one JSON document.
+struct JsonRenderer {
+    annotate: bool,
+}
+
+impl Renderer for ShortRenderer {
+    fn render(&self, results: Results<'_>) -> bool {
+        let Results { responses, lookups, races, warnings, diagnoses, .. } = results;
+        let tf = self.format;
+
+        for lookup in lookups {
+            eprintln!("{}", bootstrap_line(lookup));
+        }
+
+        for race in races {
+            eprintln!("Race for {}:", race.query);
+            for line in race_lines(race) {
+                eprintln!("  {}", line);
+            }
+        }
+
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+
+        for diagnosis in diagnoses {
+            eprintln!("Error: {}", diagnosis);
+        }
+
+        let all_answers = responses.into_iter().flat_map(|(r, ..)| r.answers).collect::<Vec<_>>();
+
+        if all_answers.is_empty() {
+            eprintln!("No results");
+            return false;
+        }
+
+        for answer in all_answers {
+            match answer {
+                Answer::Standard { record, qname, .. } => {
+                    match annotate(&qname, &record).filter(|_| tf.annotate) {
+                        Some(note)  => println!("{} ; {}", tf.record_payload_summary(&record), note),
+                        None        => println!("{}", tf.record_payload_summary(&record)),
+                    }
+                }
+                Answer::Pseudo { opt, .. } => {
+                    println!("{}", tf.pseudo_record_payload_summary(&opt))
                 }
             }
-            Self::JSON => {
-                let mut rs = Vec::new();
 
-                for response in responses {
-                    let json = json!({
-                        "queries": self.json_queries(&response.queries),
-                        "answers": self.json_answers(&response.answers),
-                        "authorities": self.json_answers(&response.authorities),
-                        "additionals": self.json_answers(&response.additionals),
-                    });
+        }
+
+        true
+    }
+
+    fn render_probes(&self, probes: &[Probe]) {
+        for probe in probes {
+            match probe.largest().and_then(|a| a.result.as_ref().ok()) {
+                Some((size, _))  => println!("{}", size),
+                None             => eprintln!("No response from {}", probe.nameserver),
+            }
+        }
+    }
+
+    fn render_cache_probes(&self, probes: &[CacheProbe]) {
+        for probe in probes {
+            println!("{}", verdict_summary(probe.verdict()));
+        }
+    }
+
+    fn render_protocol_comparisons(&self, comparisons: &[Comparison]) {
+        for comparison in comparisons {
+            match (&comparison.udp, &comparison.tcp) {
+                (Err(e), _)  => eprintln!("No response from {} over UDP ({})", comparison.nameserver, error_message(e)),
+                (_, Err(e))  => eprintln!("No response from {} over TCP ({})", comparison.nameserver, error_message(e)),
+                _            => println!("{}", comparison.differences().len()),
+            }
+        }
+    }
+
+    fn render_dnssec_views(&self, comparisons: &[dnssec_views::Comparison]) {
+        for comparison in comparisons {
+            match comparison.views.iter().find_map(|(view, r)| r.as_ref().err().map(|e| (view, e))) {
+                Some((view, e))  => eprintln!("No response from {} in the {} view ({})", comparison.nameserver, view.name(), error_message(e)),
+                None             => println!("{}", comparison.differences().len()),
+            }
+        }
+    }
+
+    fn render_pushes(&self, pushes: &[Push]) {
+        for push in pushes {
+            match push.converged {
+                Some(elapsed)  => println!("{}ms", elapsed.as_millis()),
+                None           => eprintln!("{} did not catch up", push.secondary),
+            }
+        }
+    }
+
+    fn render_floods(&self, floods: &[Flood]) {
+        for flood in floods {
+            println!("{:.1}", flood.achieved_qps());
+        }
+    }
 
-                    rs.push(json);
+    fn render_summaries(&self, summaries: &[Summary], warnings: &[Warning]) {
+        for warning in warnings {
+            eprintln!("Warning: {}", warning);
+        }
+
+        for summary in summaries {
+            println!("{}", summary);
+        }
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&self, results: Results<'_>) -> bool {
+        let Results { responses, lookups, races, warnings, diagnoses, duration } = results;
+
+        let mut rs = Vec::new();
+
+        for (response, source, details) in responses {
+            let round_trip = details.round_trip.filter(|_| duration.is_some());
+            let mut object = self.json_response(&response, &source, details);
+
+            if let Some(round_trip) = round_trip {
+                object["round_trip"] = json!({ "duration": round_trip.duration, "timestamps": round_trip.timestamps.name() });
+            }
+
+            rs.push(object);
+        }
+
+        let warnings = warnings.iter().map(|w| {
+            json!({ "type": w.kind(), "message": w.to_string() })
+        }).collect::<Vec<_>>();
+
+        let mut object = json!({ "schema": JSON_SCHEMA, "responses": rs, "warnings": warnings });
+
+        if let Some(duration) = duration {
+            object["duration"] = json!(duration);
+        }
+
+        if ! lookups.is_empty() {
+            object["bootstrap"] = lookups.iter().map(json_bootstrap).collect();
+        }
+
+        if ! races.is_empty() {
+            object["races"] = races.iter().map(json_race).collect();
+        }
+
+        if ! diagnoses.is_empty() {
+            object["diagnoses"] = diagnoses.iter().map(|d| {
+                json!({ "subject": d.subject, "problem": d.problem, "hint": d.hint })
+            }).collect();
+        }
+
+        println!("{}", object);
+        true
+    }
+
+    fn render_probes(&self, probes: &[Probe]) {
+        let probes = probes.iter().map(|probe| {
+            let attempts = probe.attempts.iter().map(|attempt| {
+                match &attempt.result {
+                    Ok((size, truncated)) => json!({
+                        "advertised": attempt.advertised,
+                        "size": size,
+                        "truncated": truncated,
+                    }),
+                    Err(e) => json!({
+                        "advertised": attempt.advertised,
+                        "error_phase": erroneous_phase(e),
+                        "error_message": error_message(e),
+                    }),
                 }
+            }).collect::<Vec<_>>();
+
+            let largest = probe.largest().and_then(|a| a.result.as_ref().ok()).map(|(size, _)| size);
+            json!({ "nameserver": probe.nameserver, "attempts": attempts, "largest": largest })
+        }).collect::<Vec<_>>();
+
+        println!("{}", json!({ "schema": JSON_SCHEMA, "probes": probes }));
+    }
+
+    fn render_cache_probes(&self, probes: &[CacheProbe]) {
+        let probes = probes.iter().map(|probe| {
+            let ttl = |result: &Result<Option<u32>, TransportError>| match result {
+                Ok(ttl)  => json!({ "ttl": ttl }),
+                Err(e)   => json!({ "error_phase": erroneous_phase(e), "error_message": error_message(e) }),
+            };
+
+            let (verdict, clamp) = match probe.verdict() {
+                Verdict::CountingDown   => ("counting_down", None),
+                Verdict::Refetched      => ("refetched", None),
+                Verdict::Frozen(ttl)    => ("frozen", Some(ttl)),
+                Verdict::Stretched(ttl) => ("stretched", Some(ttl)),
+                Verdict::TooFast        => ("too_fast", None),
+                Verdict::Inconclusive   => ("inconclusive", None),
+            };
+
+            json!({
+                "nameserver": probe.nameserver,
+                "first": ttl(&probe.first),
+                "second": ttl(&probe.second),
+                "elapsed": probe.elapsed,
+                "verdict": verdict,
+                "clamp": clamp,
+            })
+        }).collect::<Vec<_>>();
 
-                if let Some(duration) = duration {
-                    let object = json!({ "responses": rs, "duration": duration });
-                    println!("{}", object);
+        println!("{}", json!({ "schema": JSON_SCHEMA, "cache_probes": probes }));
+    }
+
+    fn render_protocol_comparisons(&self, comparisons: &[Comparison]) {
+        let comparisons = comparisons.iter().map(|comparison| {
+            let response = |result: &Result<Response, TransportError>| match result {
+                Ok(r)   => json!({ "status": rcode_name(r.flags.error_code), "flags": flag_names(r.flags) }),
+                Err(e)  => json!({ "error_phase": erroneous_phase(e), "error_message": error_message(e) }),
+            };
+
+            let differences = comparison.differences().iter().map(|difference| match difference {
+                Difference::Flag { name, udp, tcp } => {
+                    json!({ "type": "flag", "flag": name, "udp": udp, "tcp": tcp })
                 }
-                else {
-                    let object = json!({ "responses": rs });
-                    println!("{}", object);
+                Difference::Status { udp, tcp } => {
+                    json!({ "type": "status", "udp": rcode_name(*udp), "tcp": rcode_name(*tcp) })
                 }
-            }
-            Self::Text(uc, tf) => {
-                let mut table = Table::new(uc.palette(), tf);
+                Difference::Record { only_over, section, answer } => {
+                    json!({ "type": "record", "only_over": transport_name(*only_over), "section": section, "record": record_line(answer) })
+                }
+            }).collect::<Vec<_>>();
 
-                for response in responses {
-                    if let Some(rcode) = response.flags.error_code {
-                        print_error_code(rcode);
-                    }
+            json!({
+                "nameserver": comparison.nameserver,
+                "name": comparison.qname,
+                "udp": response(&comparison.udp),
+                "tcp": response(&comparison.tcp),
+                "differences": differences,
+            })
+        }).collect::<Vec<_>>();
 
-                    for a in response.answers {
-                        table.add_row(a, Section::Answer);
-                    }
+        println!("{}", json!({ "schema": JSON_SCHEMA, "comparisons": comparisons }));
+    }
 
-                    for a in response.authorities {
-                        table.add_row(a, Section::Authority);
-                    }
+    fn render_dnssec_views(&self, comparisons: &[dnssec_views::Comparison]) {
+        let comparisons = comparisons.iter().map(|comparison| {
+            let views = comparison.views.iter().map(|(view, response)| {
+                let mut object = match response {
+                    Ok(r)   => json!({ "status": rcode_name(r.flags.error_code), "flags": flag_names(r.flags), "answers": r.answers.len() }),
+                    Err(e)  => json!({ "error_phase": erroneous_phase(e), "error_message": error_message(e) }),
+                };
 
-                    for a in response.additionals {
-                        table.add_row(a, Section::Additional);
-                    }
+                object["view"] = json!(view.name());
+                object["dnssec_ok"] = json!(view.dnssec_ok());
+                object["checking_disabled"] = json!(view.checking_disabled());
+                object
+            }).collect::<Vec<_>>();
+
+            let differences = comparison.differences().iter().map(|difference| match difference {
+                DnssecDifference::Status { view, unsigned, status } => {
+                    json!({ "type": "status", "view": view.name(), "unsigned": rcode_name(*unsigned), "status": rcode_name(*status) })
+                }
+                DnssecDifference::Missing { view, answer } => {
+                    json!({ "type": "missing", "view": view.name(), "record": record_line(answer) })
+                }
+                DnssecDifference::Extra { view, answer } => {
+                    json!({ "type": "extra", "view": view.name(), "record": record_line(answer) })
+                }
+            }).collect::<Vec<_>>();
+
+            json!({
+                "nameserver": comparison.nameserver,
+                "name": comparison.qname,
+                "views": views,
+                "differences": differences,
+                "fails_only_when_validating": comparison.fails_only_when_validating(),
+            })
+        }).collect::<Vec<_>>();
+
+        println!("{}", json!({ "schema": JSON_SCHEMA, "dnssec_views": comparisons }));
+    }
+
+    fn render_pushes(&self, pushes: &[Push]) {
+        let pushes = pushes.iter().map(|push| {
+            let serial = |result: &Result<Option<u32>, TransportError>| match result {
+                Ok(serial)  => json!({ "serial": serial }),
+                Err(e)      => json!({ "error_phase": erroneous_phase(e), "error_message": error_message(e) }),
+            };
+
+            let notify = match &push.notify {
+                Ok(rcode)  => json!({ "status": rcode_name(*rcode) }),
+                Err(e)     => json!({ "error_phase": erroneous_phase(e), "error_message": error_message(e) }),
+            };
+
+            let polls = push.polls.iter().map(|(elapsed, result)| {
+                let mut object = serial(result);
+                object["duration"] = json!(elapsed);
+                object
+            }).collect::<Vec<_>>();
+
+            json!({
+                "zone": push.zone,
+                "secondary": push.secondary,
+                "primary": push.primary,
+                "primary_serial": serial(&push.primary_serial),
+                "notify": notify,
+                "polls": polls,
+                "converged": push.converged,
+            })
+        }).collect::<Vec<_>>();
+
+        println!("{}", json!({ "schema": JSON_SCHEMA, "pushes": pushes }));
+    }
+
+    fn render_floods(&self, floods: &[Flood]) {
+        let floods = floods.iter().map(|flood| {
+            let outcomes = flood_outcomes(flood).into_iter()
+                .map(|(outcome, count)| (outcome, json!(count)))
+                .collect::<serde_json::Map<_, _>>();
+
+            let latency = |percent| flood.percentile(percent).map(|d| d.as_secs_f64());
+
+            json!({
+                "nameserver": flood.nameserver,
+                "name": flood.qname,
+                "queries": flood.load.count,
+                "target_qps": flood.load.qps,
+                "responses": flood.responses(),
+                "duration": flood.elapsed.as_secs_f64(),
+                "achieved_qps": flood.achieved_qps(),
+                "outcomes": outcomes,
+                "latency": { "p50": latency(50), "p90": latency(90), "p99": latency(99), "max": latency(100) },
+            })
+        }).collect::<Vec<_>>();
+
+        println!("{}", json!({ "schema": JSON_SCHEMA, "floods": floods }));
+    }
+
+    fn render_summaries(&self, summaries: &[Summary], warnings: &[Warning]) {
+        let summaries = summaries.iter().map(|summary| {
+            let types = summary.counts.iter().map(|(name, count)| (name.clone(), json!(count))).collect::<serde_json::Map<_, _>>();
+            json!({ "name": summary.qname, "nameserver": summary.nameserver, "total": summary.total, "types": types })
+        }).collect::<Vec<_>>();
+
+        let warnings = warnings.iter().map(|w| {
+            json!({ "type": w.kind(), "message": w.to_string() })
+        }).collect::<Vec<_>>();
+
+        println!("{}", json!({ "schema": JSON_SCHEMA, "summaries": summaries, "warnings": warnings }));
+    }
+
+    fn render_error(&self, error: &TransportError) {
+        let object = json!({
+            "schema": JSON_SCHEMA,
+            "error": true,
+            "error_phase": erroneous_phase(error),
+            "error_message": error_message(error),
+        });
+
+        eprintln!("{}", object);
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, results: Results<'_>) -> bool {
+        let Results { responses, lookups, races, warnings, diagnoses, duration } = results;
+        let tf = self.format;
+
+        let palette = self.colours.palette();
+
+        // The responses get used up making the tables, so the round trips
+        // need to be taken out first
+        let round_trips = responses.iter()
+            .filter_map(|(_, source, details)| details.round_trip.map(|rt| (source.nameserver.clone(), rt)))
+            .collect::<Vec<_>>();
+
+        if ! lookups.is_empty() {
+            for lookup in lookups {
+                println!("{}", bootstrap_line(lookup));
+            }
+
+            println!();
+        }
+
+        // When more than one request was sent, each response gets its
+        // own heading and table, so it’s clear which name, type, and
+        // nameserver each record came from.
+        if responses.len() > 1 {
+            for (index, (response, source, details)) in responses.into_iter().enumerate() {
+                if index > 0 {
+                    println!();
                 }
 
-                table.print(duration);
+                println!("{}", palette.qname.paint(tf.display_name(&group_heading(&response, &source.nameserver))));
+                let mut table = Table::new(self.colours.palette(), tf, self.columns.clone());
+                add_response_rows(&mut table, response, &source, details, tf, palette.error);
+                table.print();
+            }
+        }
+        else {
+            let mut table = Table::new(self.colours.palette(), tf, self.columns.clone());
+            for (response, source, details) in responses {
+                add_response_rows(&mut table, response, &source, details, tf, palette.error);
+            }
+
+            table.print();
+        }
+
+        if let Some(dur) = duration {
+            for (nameserver, round_trip) in &round_trips {
+                println!("{}", round_trip_line(nameserver, round_trip));
+            }
+
+            println!("Ran in {}ms", dur.as_millis());
+        }
+
+        for race in races {
+            println!();
+            println!("Race for {}:", palette.qname.paint(&race.query));
+            for line in race_lines(race) {
+                println!("  {}", line);
+            }
+        }
+
+        if ! warnings.is_empty() {
+            println!();
+            println!("{}", palette.warning.paint("Warnings:"));
+            for warning in warnings {
+                println!("  {}", warning);
+            }
+        }
+
+        if ! diagnoses.is_empty() {
+            println!();
+            println!("{}", palette.error.paint("Errors explained:"));
+            for diagnosis in diagnoses {
+                println!("  {} for {}", diagnosis.problem, diagnosis.subject);
+                println!("    {}", diagnosis.hint);
             }
         }
 
         true
     }
 
-    pub fn print_error(self, error: TransportError) {
-    	match self {
-    		Self::Short(..) | Self::Text(..) => {
-    			eprintln!("Error [{}]: {}", erroneous_phase(&error), error_message(error));
-    		}
+    fn render_summaries(&self, summaries: &[Summary], warnings: &[Warning]) {
+        print_text_summaries(summaries, warnings, &self.colours.palette());
+    }
+}
+
+
+/// Prints UDP probe results as plain text, for `Renderer::render_probes`.
+fn print_text_probes(probes: &[Probe]) {
+    for (index, probe) in probes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("Probing {}:", probe.nameserver);
+        for attempt in &probe.attempts {
+            match &attempt.result {
+                Ok((size, false))  => println!("  advertised {:>4}: {} bytes", attempt.advertised, size),
+                Ok((size, true))   => println!("  advertised {:>4}: {} bytes, truncated", attempt.advertised, size),
+                Err(e)             => println!("  advertised {:>4}: no response ({})", attempt.advertised, error_message(e)),
+            }
+        }
+
+        match probe.largest() {
+            Some(Attempt { advertised, result: Ok((size, _)) }) => {
+                println!("Largest response: {} bytes, advertising {}", size, advertised);
+            }
+            _ => {
+                println!("No responses made it back");
+            }
+        }
+    }
+}
+
+/// Prints cache probe results as plain text, for
+/// `Renderer::render_cache_probes`.
+fn print_text_cache_probes(probes: &[CacheProbe]) {
+    for (index, probe) in probes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
 
-    		Self::JSON => {
-    			let object = json!({
-    				"error": true,
-    				"error_phase": erroneous_phase(&error),
-    				"error_message": error_message(error),
-    			});
+        println!("Probing the cache of {}:", probe.nameserver);
+        for (label, result) in &[ ("first", &probe.first), ("second", &probe.second) ] {
+            match result {
+                Ok(Some(ttl))  => println!("  {:>6} query: TTL {}", label, ttl),
+                Ok(None)       => println!("  {:>6} query: no answers", label),
+                Err(e)         => println!("  {:>6} query: no response ({})", label, error_message(e)),
+            }
+        }
 
-    			eprintln!("{}", object);
-    		}
-    	}
+        println!("{} seconds apart: {}", probe.elapsed, verdict_summary(probe.verdict()));
     }
 }
 
-fn erroneous_phase(error: &TransportError) -> &'static str {
+/// Prints UDP and TCP comparisons as plain text, for
+/// `Renderer::render_protocol_comparisons`.
+fn print_text_protocol_comparisons(comparisons: &[Comparison]) {
+    for (index, comparison) in comparisons.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("Comparing UDP and TCP for {} from {}:", comparison.qname, comparison.nameserver);
+        match (&comparison.udp, &comparison.tcp) {
+            (Err(e), _)  => println!("  no response over UDP ({})", error_message(e)),
+            (_, Err(e))  => println!("  no response over TCP ({})", error_message(e)),
+            _            => {
+                let differences = comparison.differences();
+                if differences.is_empty() {
+                    println!("  the responses are the same");
+                }
+
+                for difference in &differences {
+                    println!("  {}", difference_summary(difference));
+                }
+            }
+        }
+    }
+}
+
+/// Prints DNSSEC view comparisons as plain text, for
+/// `Renderer::render_dnssec_views`.
+fn print_text_dnssec_views(comparisons: &[dnssec_views::Comparison]) {
+    for (index, comparison) in comparisons.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("Comparing DNSSEC views of {} from {}:", comparison.qname, comparison.nameserver);
+        for (view, response) in &comparison.views {
+            println!("  {:<9} {:<10} {}", view.name(), view_bits(*view), view_summary(response));
+        }
+
+        let differences = comparison.differences();
+        if differences.is_empty() {
+            println!("  the views agree");
+        }
+
+        for difference in &differences {
+            println!("  {}", dnssec_difference_summary(difference));
+        }
+
+        if comparison.fails_only_when_validating() {
+            println!("Fails only when validated, so the zone’s signatures are probably broken");
+        }
+    }
+}
+
+/// Prints zone push results as plain text, for `Renderer::render_pushes`.
+fn print_text_pushes(pushes: &[Push]) {
+    for (index, push) in pushes.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("Pushing {} to {}:", push.zone, push.secondary);
+        match (&push.primary, &push.primary_serial) {
+            (None, _)                     => println!("  primary unknown: no SOA record to find it from"),
+            (Some(p), Ok(Some(serial)))   => println!("  primary {} has serial {}", p, serial),
+            (Some(p), Ok(None))           => println!("  primary {} has no SOA record", p),
+            (Some(p), Err(e))             => println!("  primary {} did not respond ({})", p, error_message(e)),
+        }
+
+        match &push.notify {
+            Ok(rcode)  => println!("  NOTIFY: {}", rcode_name(*rcode)),
+            Err(e)     => println!("  NOTIFY: no response ({})", error_message(e)),
+        }
+
+        for (elapsed, result) in &push.polls {
+            match result {
+                Ok(Some(serial))  => println!("  {:>6}ms: serial {}", elapsed.as_millis(), serial),
+                Ok(None)          => println!("  {:>6}ms: no SOA record", elapsed.as_millis()),
+                Err(e)            => println!("  {:>6}ms: no response ({})", elapsed.as_millis(), error_message(e)),
+            }
+        }
+
+        match push.converged {
+            Some(elapsed)  => println!("Caught up after {}ms", elapsed.as_millis()),
+            None           => println!("Did not catch up"),
+        }
+    }
+}
+
+/// Prints flood results as plain text, for `Renderer::render_floods`.
+fn print_text_floods(floods: &[Flood]) {
+    for (index, flood) in floods.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!("Flooding {} with random names under {} at {} queries a second:", flood.nameserver, flood.qname, flood.load.qps);
+        println!("  sent {} queries in {:.2}s, and got {} responses", flood.load.count, flood.elapsed.as_secs_f64(), flood.responses());
+        println!("  achieved {:.1} queries a second", flood.achieved_qps());
+
+        let outcomes = flood_outcomes(flood).iter()
+            .map(|(outcome, count)| format!("{} {}", outcome, count))
+            .collect::<Vec<_>>();
+        println!("  outcomes: {}", outcomes.join(", "));
+
+        let latency = |percent| flood.percentile(percent).map_or_else(|| "-".into(), |d| format!("{:.3}ms", d.as_secs_f64() * 1000.0));
+        println!("  latency: p50 {}, p90 {}, p99 {}, max {}", latency(50), latency(90), latency(99), latency(100));
+    }
+}
+
+/// Prints response summaries as text in the given colours, for
+/// `Renderer::render_summaries`.
+fn print_text_summaries(summaries: &[Summary], warnings: &[Warning], palette: &Colours) {
+    for summary in summaries {
+        println!("{} @{}: {}", palette.qname.paint(&summary.qname), summary.nameserver, summary);
+    }
+
+    if ! warnings.is_empty() {
+        println!();
+        println!("{}", palette.warning.paint("Warnings:"));
+        for warning in warnings {
+            println!("  {}", warning);
+        }
+    }
+}
+
+
+/// Describes this build of dog as JSON, for `--version --json`: its
+/// version, and the record types, transports, and features it supports, so
+/// scripts can check what the installed dog can do.
+pub fn version_json() -> JsonValue {
+    let record_types = dns::qtype_names().into_iter()
+        .chain(dns::record::other_qtype_names())
+        .collect::<Vec<_>>();
+
+    json!({
+        "schema": JSON_SCHEMA,
+        "version": env!("CARGO_PKG_VERSION"),
+        "record_types": record_types,
+        "transports": TransportType::ALL.iter().map(|t| format!("{:?}", t).to_lowercase()).collect::<Vec<_>>(),
+        "features": features::names(),
+    })
+}
+
+pub fn erroneous_phase(error: &TransportError) -> &'static str {
 	match error {
 		TransportError::NetworkError(_)  => "network",
 		TransportError::HttpError(_)     => "http",
 		TransportError::TlsError(_)      => "tls",
 		TransportError::BadRequest       => "http-status",
 		TransportError::WireError(_)     => "protocol",
+		TransportError::Timeout(_)       => "timeout",
 	}
 }
 
-fn error_message(error: TransportError) -> String {
+pub fn error_message(error: &TransportError) -> String {
 	match error {
 		TransportError::NetworkError(e)  => e.to_string(),
 		TransportError::HttpError(e)     => e.to_string(),
@@ -187,12 +982,23 @@ fn error_message(error: TransportError) -> String {
 				WireError::OutOfBounds(index)             => format!("Malformed packet: out of bounds ({})", index),
 			}
 		}
+		TransportError::Timeout(phase)   => {
+			match phase {
+				Phase::Connect    => "Timed out connecting to the nameserver".into(),
+				Phase::Handshake  => "Timed out during the TLS handshake".into(),
+				Phase::Response   => "Timed out waiting for a response".into(),
+			}
+		}
 	}
 }
 
 
 impl TextFormat {
     pub fn record_payload_summary(self, record: &Record) -> String {
+        if self.zone_format {
+            return zone::presentation(record);
+        }
+
         match *record {
             Record::A(ref a) => {
                 format!("{}", a.address)
@@ -209,20 +1015,38 @@ impl TextFormat {
                 }
             }
             Record::CNAME(ref cname) => {
-                format!("{:?}", cname.domain)
+                format!("{:?}", self.display_name(&cname.domain))
             }
             Record::MX(ref mx) => {
-                format!("{} {:?}", mx.preference, mx.exchange)
+                format!("{} {:?}", mx.preference, self.display_name(&mx.exchange))
             }
             Record::NS(ref ns) => {
-                format!("{:?}", ns.nameserver)
+                format!("{:?}", self.display_name(&ns.nameserver))
             }
             Record::PTR(ref ptr) => {
-                format!("{:?}", ptr.cname)
+                format!("{:?}", self.display_name(&ptr.cname))
+            }
+            Record::RRSIG(ref rrsig) => {
+                format!("{} {} {} {} {} {} {} {:?} {}",
+                    rrsig.type_covered, rrsig.algorithm, rrsig.labels,
+                    self.format_duration(rrsig.original_ttl),
+                    self.format_timestamp(rrsig.signature_expiration),
+                    self.format_timestamp(rrsig.signature_inception),
+                    rrsig.key_tag, self.display_name(&rrsig.signer_name), base64::encode(&rrsig.signature),
+                )
+            }
+            Record::SIG(ref sig) => {
+                format!("{} {} {} {} {} {} {} {:?} {}",
+                    sig.type_covered, sig.algorithm, sig.labels,
+                    self.format_duration(sig.original_ttl),
+                    self.format_timestamp(sig.signature_expiration),
+                    self.format_timestamp(sig.signature_inception),
+                    sig.key_tag, self.display_name(&sig.signer_name), base64::encode(&sig.signature),
+                )
             }
             Record::SOA(ref soa) => {
                 format!("{:?} {:?} {} {} {} {} {}",
-                    soa.mname, soa.rname, soa.serial,
+                    self.display_name(&soa.mname), self.display_name(&soa.rname), soa.serial,
                     self.format_duration(soa.refresh_interval),
                     self.format_duration(soa.retry_interval),
                     self.format_duration(soa.expire_limit),
@@ -230,7 +1054,7 @@ impl TextFormat {
                 )
             }
             Record::SRV(ref srv) => {
-                format!("{} {} {:?}:{}", srv.priority, srv.weight, srv.target, srv.port)
+                format!("{} {} {:?}:{}", srv.priority, srv.weight, self.display_name(&srv.target), srv.port)
             }
             Record::TXT(ref txt) => {
                 format!("{:?}", txt.message)
@@ -241,6 +1065,17 @@ impl TextFormat {
         }
     }
 
+    /// Returns a domain name as it should be displayed, decoding any
+    /// internationalised labels if the user wants them decoded.
+    pub fn display_name(self, name: &str) -> String {
+        if self.decode_idn {
+            idna::decode_name(name)
+        }
+        else {
+            name.into()
+        }
+    }
+
     pub fn pseudo_record_payload_summary(self, opt: &OPT) -> String {
         format!("{} {} {} {} {:?}",
             opt.udp_payload_size,
@@ -267,10 +1102,91 @@ impl TextFormat {
             format!("{}d{}h{:02}m{:02}s", seconds / 86400, (seconds % 86400) / 3600, (seconds % 3600) / 60, seconds % 60)
         }
     }
+
+    /// Formats a timestamp from a record, which is a number of seconds since
+    /// the Unix epoch, depending on the time format.
+    pub fn format_timestamp(self, timestamp: u32) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.format_timestamp_at(timestamp, now)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn format_timestamp_at(self, timestamp: u32, now: u64) -> String {
+
+        // Record timestamps are compared using serial number arithmetic
+        // (RFC 4034 §3.1.5), so they refer to whichever time is closest to
+        // now, even after they wrap around in 2106.
+        let offset = i64::from(timestamp.wrapping_sub(now as u32) as i32);
+
+        let absolute = || {
+            let seconds = u64::try_from(now as i64 + offset).unwrap_or_default();
+            humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(seconds)).to_string()
+        };
+
+        let relative = || {
+            if offset < 0 {
+                format!("{} ago", format_rough_duration(offset.unsigned_abs()))
+            }
+            else {
+                format!("in {}", format_rough_duration(offset.unsigned_abs()))
+            }
+        };
+
+        match self.time_format {
+            TimeFormat::Absolute  => absolute(),
+            TimeFormat::Relative  => relative(),
+            TimeFormat::Both      => format!("{} ({})", absolute(), relative()),
+        }
+    }
 }
 
-impl OutputFormat {
-    fn json_queries(self, queries: &[Query]) -> JsonValue {
+/// Formats a duration using only its two largest units, as the exact number
+/// of seconds doesn’t matter when it’s days away.
+fn format_rough_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}s", seconds)
+    }
+    else if seconds < 60 * 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    }
+    else if seconds < 60 * 60 * 24 {
+        format!("{}h{:02}m", seconds / 3600, (seconds % 3600) / 60)
+    }
+    else {
+        format!("{}d{}h", seconds / 86400, (seconds % 86400) / 3600)
+    }
+}
+
+impl JsonRenderer {
+    fn json_response(&self, response: &Response, source: &Source, details: Details) -> JsonValue {
+        let trust = trust::assess(response, source, &details);
+        let source = source.to_json();
+        let mut json = json!({
+            "nameserver": source["nameserver"],
+            "address": details.address.map(|a| a.to_string()),
+            "ip_ttl": details.ip_ttl,
+            "trust": { "verdict": trust.verdict.name(), "validated": trust.validated, "channel": trust.channel.name() },
+            "size": details.size,
+            "queries": self.json_queries(&response.queries),
+            "answers": self.json_answers(&response.answers, &source),
+            "authorities": self.json_answers(&response.authorities, &source),
+            "additionals": self.json_answers(&response.additionals, &source),
+        });
+
+        if let Some(details) = details.http {
+            json["http"] = json!({
+                "status": details.status,
+                "version": details.version,
+                "server": details.server,
+                "cache_control": details.cache_control,
+                "age": details.age,
+            });
+        }
+
+        json
+    }
+
+    fn json_queries(&self, queries: &[Query]) -> JsonValue {
         let queries = queries.iter().map(|q| {
             json!({
                 "name": q.qname,
@@ -282,7 +1198,10 @@ impl OutputFormat {
         json!(queries)
     }
 
-    fn json_answers(self, answers: &[Answer]) -> JsonValue {
+    /// Describes the records in one section of a response as JSON, tagging
+    /// each with the source of the response, so they can still be traced
+    /// back once the sections of several responses have been merged.
+    fn json_answers(&self, answers: &[Answer], source: &JsonValue) -> JsonValue {
         let answers = answers.iter().map(|a| {
             match a {
                 Answer::Standard { qname, qclass, ttl, record } => {
@@ -291,6 +1210,13 @@ impl OutputFormat {
                     omut.insert("name".into(), qname.as_str().into());
                     omut.insert("class".into(), format!("{:?}", qclass).into());
                     omut.insert("ttl".into(), (*ttl).into());
+                    omut.insert("source".into(), source.clone());
+                    if let Some(sinkhole) = Sinkhole::detect(record) {
+                        omut.insert("sinkhole".into(), sinkhole.to_string().into());
+                    }
+                    if let Some(note) = annotate(qname, record).filter(|_| self.annotate) {
+                        omut.insert("annotation".into(), note.into());
+                    }
                     json!(object)
                 }
                 Answer::Pseudo { qname, opt } => {
@@ -299,6 +1225,7 @@ impl OutputFormat {
                         "type": "OPT",
                         "version": opt.edns0_version,
                         "data": opt.data,
+                        "source": source,
                     });
 
                     object
@@ -309,7 +1236,7 @@ impl OutputFormat {
         json!(answers)
     }
 
-    fn json_record(self, record: &Record) -> JsonValue {
+    fn json_record(&self, record: &Record) -> JsonValue {
         match record {
             Record::A(rec)      => json!({ "type": "A",     "address": rec.address.to_string() }),
             Record::AAAA(rec)   => json!({ "type": "AAAA",  "address": rec.address.to_string() }),
@@ -318,6 +1245,8 @@ impl OutputFormat {
             Record::MX(rec)     => json!({ "type": "MX",    "preference": rec.preference, "exchange": rec.exchange }),
             Record::NS(rec)     => json!({ "type": "NS",    "nameserver": rec.nameserver }),
             Record::PTR(rec)    => json!({ "type": "PTR",   "cname": rec.cname }),
+            Record::RRSIG(rec)  => json!({ "type": "RRSIG", "type_covered": rec.type_covered, "algorithm": rec.algorithm, "labels": rec.labels, "original_ttl": rec.original_ttl, "signature_expiration": rec.signature_expiration, "signature_inception": rec.signature_inception, "key_tag": rec.key_tag, "signer_name": rec.signer_name, "signature": base64::encode(&rec.signature) }),
+            Record::SIG(rec)    => json!({ "type": "SIG",   "type_covered": rec.type_covered, "algorithm": rec.algorithm, "labels": rec.labels, "original_ttl": rec.original_ttl, "signature_expiration": rec.signature_expiration, "signature_inception": rec.signature_inception, "key_tag": rec.key_tag, "signer_name": rec.signer_name, "signature": base64::encode(&rec.signature) }),
             Record::SOA(rec)    => json!({ "type": "SOA",   "mname": rec.mname }),
             Record::SRV(rec)    => json!({ "type": "SRV",   "priority": rec.priority, "weight": rec.weight, "port": rec.port, "target": rec.target, }),
             Record::TXT(rec)    => json!({ "type": "TXT",   "message": rec.message }),
@@ -332,14 +1261,487 @@ impl OutputFormat {
     }
 }
 
-pub fn print_error_code(rcode: ErrorCode) {
+/// Describes what a cache probe found, in a sentence.
+fn verdict_summary(verdict: Verdict) -> String {
+    match verdict {
+        Verdict::CountingDown   => "TTLs count down properly".into(),
+        Verdict::Refetched      => "the record expired in between, so try again".into(),
+        Verdict::Frozen(ttl)    => format!("TTLs do not count down, and look clamped to {} seconds", ttl),
+        Verdict::Stretched(ttl) => format!("TTLs went back up early, so are stretched to at least {} seconds", ttl),
+        Verdict::TooFast        => "TTLs count down faster than time passes".into(),
+        Verdict::Inconclusive   => "nothing to compare".into(),
+    }
+}
+
+/// Describes one way in which the responses over UDP and TCP differ, in a
+/// sentence.
+fn difference_summary(difference: &Difference) -> String {
+    match difference {
+        Difference::Flag { name, udp: true, .. }  => format!("{} flag set over UDP, but not over TCP", name),
+        Difference::Flag { name, .. }             => format!("{} flag set over TCP, but not over UDP", name),
+        Difference::Status { udp, tcp }           => format!("status {} over UDP, but {} over TCP", rcode_name(*udp), rcode_name(*tcp)),
+        Difference::Record { only_over, section, answer } => {
+            format!("only over {}, in the {} section: {}", transport_name(*only_over), section, record_line(answer))
+        }
+    }
+}
+
+/// Lists the bits that requests in a view set, such as `(DO, CD)`.
+fn view_bits(view: View) -> String {
+    match (view.dnssec_ok(), view.checking_disabled()) {
+        (true, true)   => "(DO, CD)".into(),
+        (true, false)  => "(DO)".into(),
+        (false, true)  => "(CD)".into(),
+        (false, false) => "(no bits)".into(),
+    }
+}
+
+/// Summarises the response to one view: its status, how many answers it
+/// had, and whether they were authenticated.
+fn view_summary(response: &Result<Response, TransportError>) -> String {
+    match response {
+        Ok(r) if r.flags.authentic_data  => format!("{}, {} answers, authenticated", rcode_name(r.flags.error_code), r.answers.len()),
+        Ok(r)                            => format!("{}, {} answers", rcode_name(r.flags.error_code), r.answers.len()),
+        Err(e)                           => format!("no response ({})", error_message(e)),
+    }
+}
+
+/// Describes one way a view’s response differs from the unsigned one.
+fn dnssec_difference_summary(difference: &DnssecDifference) -> String {
+    match difference {
+        DnssecDifference::Status { view, unsigned, status } => {
+            format!("status {} when {}, but {} when unsigned", rcode_name(*status), view.name(), rcode_name(*unsigned))
+        }
+        DnssecDifference::Missing { view, answer } => {
+            format!("missing when {}: {}", view.name(), record_line(answer))
+        }
+        DnssecDifference::Extra { view, answer } => {
+            format!("only when {}: {}", view.name(), record_line(answer))
+        }
+    }
+}
+
+/// Returns the short name of a transport, as used in JSON output.
+fn transport_name(transport: TransportType) -> String {
+    format!("{:?}", transport).to_lowercase()
+}
+
+/// Writes out a record as it would appear in a zone file, without its TTL.
+fn record_line(answer: &Answer) -> String {
+    match answer {
+        Answer::Standard { qname, qclass, record, .. } => {
+            format!("{} {:?} {} {}", qname, qclass, zone::type_name(order::type_number(record)), zone::presentation(record))
+        }
+        Answer::Pseudo { qname, .. } => {
+            format!("{} OPT", qname)
+        }
+    }
+}
+
+/// Adds the records in every section of a response to a table, printing
+/// the response’s header, HTTP details, trust verdict, and status first if
+/// there’s anything to say.
+fn add_response_rows(table: &mut Table, response: Response, source: &Source, details: Details, tf: TextFormat, error_style: Style) {
+    if tf.show_header {
+        print_header(&response, &details);
+    }
+
+    if tf.show_trust {
+        println!("Trust: {}", trust::assess(&response, source, &details));
+    }
+
+    if let Some(http) = details.http.filter(|_| tf.show_http_details) {
+        println!("{}", http_summary(&http));
+    }
+
+    if let Some(rcode) = response.flags.error_code {
+        print_error_code(rcode, error_style);
+    }
+
+    for a in response.answers {
+        table.add_row(a, Section::Answer);
+    }
+
+    for a in response.authorities {
+        table.add_row(a, Section::Authority);
+    }
+
+    for a in response.additionals {
+        table.add_row(a, Section::Additional);
+    }
+}
+
+/// Formats one line for each nameserver that took part in a race, in the
+/// order they finished, with their names and times lined up.
+fn race_lines(race: &Race) -> Vec<String> {
+    let width = race.entrants.iter().map(|e| e.nameserver.len()).max().unwrap_or_default();
+
+    race.entrants.iter().map(|entrant| {
+        let (outcome, detail) = match &entrant.outcome {
+            Outcome::Won            => ("won",       String::new()),
+            Outcome::Answered       => ("answered",  String::new()),
+            Outcome::Rejected(rc)   => ("rejected",  rcode_name(Some(*rc))),
+            Outcome::Failed(e)      => ("failed",    error_message(e)),
+            Outcome::Abandoned      => ("abandoned", String::new()),
+        };
+
+        let line = format!("{:<9}  {:<width$}  {:>5}ms  {}", outcome, entrant.nameserver, entrant.elapsed.as_millis(), detail, width = width);
+        line.trim_end().into()
+    }).collect()
+}
+
+/// Counts what happened to the queries in a flood, by response code for
+/// those that got a response, and by the phase they failed in for those that
+/// didn’t, most common first.
+fn flood_outcomes(flood: &Flood) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+
+    for result in &flood.results {
+        let outcome = match result {
+            Ok((rcode, _))  => rcode_name(*rcode),
+            Err(e)          => erroneous_phase(e).into(),
+        };
+
+        match counts.iter_mut().find(|(o, _)| *o == outcome) {
+            Some((_, count))  => *count += 1,
+            None              => counts.push((outcome, 1)),
+        }
+    }
+
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Describes how long a response took to arrive, to the microsecond, and
+/// which clock the time came from.
+fn round_trip_line(nameserver: &str, round_trip: &RoundTrip) -> String {
+    let millis = round_trip.duration.as_secs_f64() * 1000.0;
+    format!("Round trip to {} took {:.3}ms, timed with {} timestamps", nameserver, millis, round_trip.timestamps.name())
+}
+
+/// Describes the bootstrap lookup of a nameserver’s host name, including
+/// any address families that failed to be looked up.
+fn bootstrap_line(lookup: &Lookup) -> String {
+    let addresses = lookup.addresses().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    let mut line = if addresses.is_empty() {
+        format!("Bootstrap: no addresses found for {} via {}", lookup.host, lookup.resolver)
+    }
+    else {
+        format!("Bootstrap: {} is {} via {}", lookup.host, addresses.join(", "), lookup.resolver)
+    };
+
+    for (qtype, e) in lookup.failures() {
+        line = format!("{} ({} lookup failed: {})", line, qtype, error_message(e));
+    }
+
+    line
+}
+
+/// Describes the bootstrap lookup of a nameserver’s host name as JSON.
+fn json_bootstrap(lookup: &Lookup) -> JsonValue {
+    let addresses = lookup.addresses().map(|a| a.to_string()).collect::<Vec<_>>();
+    let errors = lookup.failures().map(|(qtype, e)| {
+        json!({ "type": qtype, "error_phase": erroneous_phase(e), "error_message": error_message(e) })
+    }).collect::<Vec<_>>();
+
+    json!({ "host": lookup.host, "resolver": lookup.resolver, "addresses": addresses, "errors": errors })
+}
+
+/// Describes a race as JSON, with each nameserver that took part in it.
+fn json_race(race: &Race) -> JsonValue {
+    let entrants = race.entrants.iter().map(|entrant| {
+        let mut object = json!({
+            "nameserver": entrant.nameserver,
+            "duration": entrant.elapsed,
+        });
+
+        match &entrant.outcome {
+            Outcome::Won           => object["outcome"] = "won".into(),
+            Outcome::Answered      => object["outcome"] = "answered".into(),
+            Outcome::Rejected(rc)  => {
+                object["outcome"] = "rejected".into();
+                object["status"] = rcode_name(Some(*rc)).into();
+            }
+            Outcome::Failed(e)     => {
+                object["outcome"] = "failed".into();
+                object["error_phase"] = erroneous_phase(e).into();
+                object["error_message"] = error_message(e).into();
+            }
+            Outcome::Abandoned     => object["outcome"] = "abandoned".into(),
+        }
+
+        object
+    }).collect::<Vec<_>>();
+
+    json!({ "query": race.query, "entrants": entrants })
+}
+
+/// Describes the query a response is answering and the nameserver it came
+/// from, such as `example.net. MX @1.1.1.1`, to head its group of records.
+fn group_heading(response: &Response, nameserver: &str) -> String {
+    match response.queries.first() {
+        Some(query)  => format!("{} {} @{}", query.qname, zone::type_name(query.qtype), nameserver),
+        None         => format!("@{}", nameserver),
+    }
+}
+
+/// Summarises the HTTP response a DNS-over-HTTPS response arrived in, such
+/// as `HTTP: 200 HTTP/2.0, server cloudflare, cache-control max-age=300`.
+fn http_summary(details: &HttpDetails) -> String {
+    let mut parts = vec![ format!("HTTP: {} {}", details.status, details.version) ];
+
+    let headers = [ ("server", &details.server), ("cache-control", &details.cache_control), ("age", &details.age) ];
+    for (name, value) in &headers {
+        if let Some(value) = value {
+            parts.push(format!("{} {}", name, value));
+        }
+    }
+
+    parts.join(", ")
+}
+
+/// Prints a summary of a response’s header in the style of dig, so its shape
+/// can be seen at a glance: what kind of response it is, which flags are
+/// set, and how many records are in each section.
+fn print_header(response: &Response, details: &Details) {
+    println!("Header: opcode {}, status {}, id {}",
+             opcode_name(response.flags.opcode),
+             rcode_name(response.flags.error_code),
+             response.transaction_id);
+
+    println!("Flags: {}; query {}, answer {}, authority {}, additional {}",
+             flag_names(response.flags).join(" "),
+             response.queries.len(),
+             response.answers.len(),
+             response.authorities.len(),
+             response.additionals.len());
+
+    println!("Size: {} bytes{}", details.size, if details.over_udp { " over UDP" } else { "" });
+
+    if let Some(address) = details.address {
+        println!("Address: {}", address);
+    }
+
+    if let Some(ttl) = details.ip_ttl {
+        let hops = hops_away(ttl);
+        println!("IP TTL: {} (about {} hop{} away)", ttl, hops, if hops == 1 { "" } else { "s" });
+    }
+}
+
+/// Returns the mnemonic for an opcode, or its number if it doesn’t have one.
+fn opcode_name(opcode: u8) -> String {
+    match opcode {
+        0  => "QUERY".into(),
+        1  => "IQUERY".into(),
+        2  => "STATUS".into(),
+        4  => "NOTIFY".into(),
+        5  => "UPDATE".into(),
+        6  => "DSO".into(),
+        n  => n.to_string(),
+    }
+}
+
+/// Returns the mnemonic for a response code, as it appears in dig’s output.
+pub fn rcode_name(rcode: Option<ErrorCode>) -> String {
     match rcode {
-        ErrorCode::FormatError     => println!("Status: Format Error"),
-        ErrorCode::ServerFailure   => println!("Status: Server Failure"),
-        ErrorCode::NXDomain        => println!("Status: NXDomain"),
-        ErrorCode::NotImplemented  => println!("Status: Not Implemented"),
-        ErrorCode::QueryRefused    => println!("Status: Query Refused"),
-        ErrorCode::BadVersion      => println!("Status: Bad Version"),
-        ErrorCode::Other(num)      => println!("Status: Other Failure ({})", num),
+        None                             => "NOERROR".into(),
+        Some(ErrorCode::FormatError)     => "FORMERR".into(),
+        Some(ErrorCode::ServerFailure)   => "SERVFAIL".into(),
+        Some(ErrorCode::NXDomain)        => "NXDOMAIN".into(),
+        Some(ErrorCode::NotImplemented)  => "NOTIMP".into(),
+        Some(ErrorCode::QueryRefused)    => "REFUSED".into(),
+        Some(ErrorCode::BadVersion)      => "BADVERS".into(),
+        Some(ErrorCode::Other(num))      => num.to_string(),
+    }
+}
+
+/// Returns the short names of the flags that are set, in the order they
+/// appear in the header.
+fn flag_names(flags: Flags) -> Vec<&'static str> {
+    let mut names = Vec::new();
+    if flags.response             { names.push("qr"); }
+    if flags.authoritative        { names.push("aa"); }
+    if flags.truncated            { names.push("tc"); }
+    if flags.recursion_desired    { names.push("rd"); }
+    if flags.recursion_available  { names.push("ra"); }
+    if flags.authentic_data       { names.push("ad"); }
+    if flags.checking_disabled    { names.push("cd"); }
+    names
+}
+
+pub fn print_error_code(rcode: ErrorCode, style: Style) {
+    let status = match rcode {
+        ErrorCode::FormatError     => "Format Error".into(),
+        ErrorCode::ServerFailure   => "Server Failure".into(),
+        ErrorCode::NXDomain        => "NXDomain".into(),
+        ErrorCode::NotImplemented  => "Not Implemented".into(),
+        ErrorCode::QueryRefused    => "Query Refused".into(),
+        ErrorCode::BadVersion      => "Bad Version".into(),
+        ErrorCode::Other(num)      => format!("Other Failure ({})", num),
+    };
+
+    println!("{}", style.paint(format!("Status: {}", status)));
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOW: u64 = 1_590_000_000;
+
+    fn text_format(time_format: TimeFormat) -> TextFormat {
+        TextFormat { format_durations: true, truncate_data: true, time_format, show_header: false, zone_format: false, annotate: false, decode_idn: true, show_http_details: false, show_trust: false, explain: false }
+    }
+
+    #[test]
+    fn version_json_capabilities() {
+        let json = version_json();
+        assert_eq!(json["schema"], json!(JSON_SCHEMA));
+        assert_eq!(json["version"], json!(env!("CARGO_PKG_VERSION")));
+        assert!(json["record_types"].as_array().unwrap().contains(&json!("SOA")));
+        assert!(json["record_types"].as_array().unwrap().contains(&json!("DNSKEY")));
+        assert_eq!(json["transports"], json!([ "udp", "tcp", "tls", "https", "local" ]));
+        assert_eq!(json["features"].as_array().unwrap().len(), features::FEATURES.len());
+        assert!(json["features"].as_array().unwrap().contains(&json!("trust")));
+    }
+
+    #[test]
+    fn json_fields_in_alphabetical_order() {
+        let answer = Answer::Standard { qname: "lookup.dog.".into(), qclass: dns::QClass::IN, ttl: 60, record: Record::MX(dns::record::MX { preference: 10, exchange: "mail.lookup.dog.".into() }) };
+        let source = json!({ "nameserver": "192.0.2.1", "transport": "udp" });
+        let json = JsonRenderer { annotate: false }.json_answers(&[ answer ], &source).to_string();
+        assert_eq!(json, r#"[{"class":"IN","exchange":"mail.lookup.dog.","name":"lookup.dog.","preference":10,"source":{"nameserver":"192.0.2.1","transport":"udp"},"ttl":60,"type":"MX"}]"#);
+    }
+
+    #[test]
+    fn protocol_differences() {
+        let answer = Answer::Standard { qname: "lookup.dog.".into(), qclass: dns::QClass::IN, ttl: 60, record: Record::A(dns::record::A { address: [ 192, 0, 2, 1 ].into() }) };
+
+        assert_eq!(difference_summary(&Difference::Flag { name: "aa", udp: false, tcp: true }),
+                   "aa flag set over TCP, but not over UDP");
+        assert_eq!(difference_summary(&Difference::Status { udp: None, tcp: Some(ErrorCode::ServerFailure) }),
+                   "status NOERROR over UDP, but SERVFAIL over TCP");
+        assert_eq!(difference_summary(&Difference::Record { only_over: TransportType::UDP, section: "answer", answer }),
+                   "only over udp, in the answer section: lookup.dog. IN A 192.0.2.1");
+    }
+
+    #[test]
+    fn bootstrap_with_one_family_failing() {
+        use crate::bootstrap::Family;
+
+        let lookup = Lookup {
+            host: "dns.example".into(),
+            resolver: "9.9.9.9".into(),
+            families: vec![
+                Family { qtype: "A", result: Ok(vec![ "192.0.2.1".parse().unwrap() ]) },
+                Family { qtype: "AAAA", result: Err(TransportError::Timeout(Phase::Response)) },
+            ],
+        };
+
+        assert_eq!(bootstrap_line(&lookup),
+                   "Bootstrap: dns.example is 192.0.2.1 via 9.9.9.9 (AAAA lookup failed: Timed out waiting for a response)");
+    }
+
+    #[test]
+    fn round_trip() {
+        use dns_transport::Timestamps;
+
+        let round_trip = RoundTrip { duration: Duration::from_micros(1_234), timestamps: Timestamps::Kernel };
+        assert_eq!(round_trip_line("1.1.1.1", &round_trip),
+                   "Round trip to 1.1.1.1 took 1.234ms, timed with kernel timestamps");
+    }
+
+    #[test]
+    fn flood_outcome_counts() {
+        use crate::flood::Load;
+        use dns_transport::Phase;
+
+        let latency = Duration::from_millis(1);
+        let flood = Flood {
+            nameserver: "192.0.2.53".into(),
+            qname: "lookup.dog".into(),
+            load: Load { count: 5, qps: 10 },
+            elapsed: Duration::from_secs(1),
+            results: vec![
+                Ok((Some(ErrorCode::NXDomain), latency)),
+                Err(TransportError::Timeout(Phase::Response)),
+                Ok((Some(ErrorCode::NXDomain), latency)),
+                Ok((Some(ErrorCode::ServerFailure), latency)),
+                Ok((Some(ErrorCode::NXDomain), latency)),
+            ],
+        };
+
+        assert_eq!(flood_outcomes(&flood), vec![
+            (String::from("NXDOMAIN"), 3),
+            (String::from("SERVFAIL"), 1),
+            (String::from("timeout"), 1),
+        ]);
+    }
+
+    #[test]
+    fn heading() {
+        let response = Response {
+            transaction_id: 0xABCD,
+            flags: Flags::from_u16(0x8180),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: dns::QClass::IN, qtype: 15 } ],
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        };
+
+        assert_eq!(group_heading(&response, "1.1.1.1"), "lookup.dog. MX @1.1.1.1");
+    }
+
+    #[test]
+    fn http_details() {
+        let details = HttpDetails {
+            status: 200,
+            version: "HTTP/2.0".into(),
+            server: Some("cloudflare".into()),
+            cache_control: Some("max-age=300".into()),
+            age: None,
+        };
+
+        assert_eq!(http_summary(&details), "HTTP: 200 HTTP/2.0, server cloudflare, cache-control max-age=300");
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(flag_names(Flags::from_u16(0b_1000_0101_1010_0000)), vec![ "qr", "aa", "rd", "ra", "ad" ]);
+        assert_eq!(flag_names(Flags::from_u16(0)), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn absolute_timestamp() {
+        assert_eq!(text_format(TimeFormat::Absolute).format_timestamp_at(1_590_493_484, NOW),
+                   "2020-05-26T11:44:44Z");
+    }
+
+    #[test]
+    fn future_timestamp() {
+        assert_eq!(text_format(TimeFormat::Relative).format_timestamp_at(1_590_493_484, NOW),
+                   "in 5d17h");
+    }
+
+    #[test]
+    fn past_timestamp() {
+        assert_eq!(text_format(TimeFormat::Relative).format_timestamp_at(1_589_999_100, NOW),
+                   "15m00s ago");
+    }
+
+    #[test]
+    fn both_timestamps() {
+        assert_eq!(text_format(TimeFormat::Both).format_timestamp_at(1_590_003_720, NOW),
+                   "2020-05-20T19:42:00Z (in 1h02m)");
+    }
+
+    #[test]
+    fn wrapped_timestamp() {
+        // 2106-02-07T06:28:16Z has wrapped back around to zero
+        let now = u64::from(u32::MAX) - 59;
+        assert_eq!(text_format(TimeFormat::Both).format_timestamp_at(40, now),
+                   "2106-02-07T06:28:56Z (in 1m40s)");
     }
 }
@@ -0,0 +1,227 @@
+//! Keeping very large responses, such as zone transfers or huge record sets,
+//! from flooding the terminal, either by only showing one page of records
+//! at a time, or by summarising them instead of showing them at all.
+
+use std::fmt;
+
+use dns::{Response, Answer};
+
+use crate::order;
+use crate::warnings::Warning;
+use crate::zone;
+
+
+/// Which records of each response to show.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Page {
+
+    /// The most records to show from each response.
+    pub limit: usize,
+
+    /// Which page of records to show, counting from 1.
+    pub number: usize,
+}
+
+impl Page {
+
+    /// The number of records on a page when `--page` is given without
+    /// `--limit`.
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    /// Removes every record that isn’t on this page from the response,
+    /// counting through its sections in order. Pseudo-records are left
+    /// alone. Returns a warning if any records were removed, so the user
+    /// knows there are more to see.
+    pub fn apply(self, response: &mut Response) -> Option<Warning> {
+        let first = self.limit.saturating_mul(self.number - 1);
+        let last = first.saturating_add(self.limit);
+
+        let mut index = 0;
+        let mut on_page = |answer: &Answer| {
+            if ! answer.is_standard() {
+                return true;
+            }
+
+            index += 1;
+            index > first && index <= last
+        };
+
+        response.answers.retain(&mut on_page);
+        response.authorities.retain(&mut on_page);
+        response.additionals.retain(&mut on_page);
+
+        let total = index;
+        if first == 0 && total <= last {
+            return None;
+        }
+
+        let qname = response.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+        let next_page = if total > last { Some(self.number + 1) } else { None };
+        Some(Warning::Paged { qname, first: first + 1, last: last.min(total), total, next_page })
+    }
+}
+
+
+/// A count of the records in a response, by type, to display in place of
+/// the records themselves.
+#[derive(PartialEq, Debug)]
+pub struct Summary {
+
+    /// The name that was queried.
+    pub qname: String,
+
+    /// The nameserver the response came from.
+    pub nameserver: String,
+
+    /// The number of records in every section of the response.
+    pub total: usize,
+
+    /// The name of each record type, along with how many records of that
+    /// type there were, most common first.
+    pub counts: Vec<(String, usize)>,
+}
+
+impl Summary {
+
+    /// Counts the records in a response. Pseudo-records are left out.
+    pub fn of(response: &Response, nameserver: &str) -> Self {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+
+        let records = response.answers.iter().chain(&response.authorities).chain(&response.additionals);
+        for answer in records {
+            if let Answer::Standard { record, .. } = answer {
+                let type_name = zone::type_name(order::type_number(record));
+                match counts.iter_mut().find(|(name, _)| *name == type_name) {
+                    Some((_, count))  => *count += 1,
+                    None              => counts.push((type_name, 1)),
+                }
+            }
+        }
+
+        counts.sort_by(|(a_name, a_count), (b_name, b_count)| b_count.cmp(a_count).then_with(|| a_name.cmp(b_name)));
+
+        Self {
+            qname: response.queries.first().map(|q| q.qname.clone()).unwrap_or_default(),
+            nameserver: nameserver.into(),
+            total: counts.iter().map(|(_, count)| count).sum(),
+            counts,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", thousands(self.total), if self.total == 1 { "record" } else { "records" })?;
+
+        for (index, (type_name, count)) in self.counts.iter().enumerate() {
+            write!(f, "{} {} {}", if index == 0 { ":" } else { "," }, thousands(*count), type_name)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Writes out a number with commas between each group of three digits,
+/// such as `15,023`.
+pub fn thousands(number: usize) -> String {
+    let digits = number.to_string();
+    let groups = digits.as_bytes().rchunks(3).rev()
+                       .map(|group| String::from_utf8_lossy(group))
+                       .collect::<Vec<_>>();
+
+    groups.join(",")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+    use dns::record::{Record, A, AAAA, OPT};
+
+    fn a(last: u8) -> Answer {
+        Answer::Standard { qname: "lookup.dog.".into(), qclass: QClass::IN, ttl: 60, record: Record::A(A { address: [ 192, 0, 2, last ].into() }) }
+    }
+
+    fn aaaa() -> Answer {
+        Answer::Standard { qname: "lookup.dog.".into(), qclass: QClass::IN, ttl: 60, record: Record::AAAA(AAAA { address: "2001:db8::1".parse().unwrap() }) }
+    }
+
+    fn response(answers: Vec<Answer>) -> Response {
+        Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0001_1000_0000),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: QClass::IN, qtype: 1 } ],
+            answers,
+            authorities: vec![],
+            additionals: vec![],
+        }
+    }
+
+    #[test]
+    fn first_page() {
+        let mut response = response((1 ..= 5).map(a).collect());
+        let warning = Page { limit: 2, number: 1 }.apply(&mut response);
+
+        assert_eq!(response.answers, vec![ a(1), a(2) ]);
+        assert_eq!(warning, Some(Warning::Paged { qname: "lookup.dog.".into(), first: 1, last: 2, total: 5, next_page: Some(2) }));
+    }
+
+    #[test]
+    fn last_page() {
+        let mut response = response((1 ..= 5).map(a).collect());
+        let warning = Page { limit: 2, number: 3 }.apply(&mut response);
+
+        assert_eq!(response.answers, vec![ a(5) ]);
+        assert_eq!(warning, Some(Warning::Paged { qname: "lookup.dog.".into(), first: 5, last: 5, total: 5, next_page: None }));
+    }
+
+    #[test]
+    fn everything_fits() {
+        let mut response = response((1 ..= 2).map(a).collect());
+        assert_eq!(Page { limit: 2, number: 1 }.apply(&mut response), None);
+        assert_eq!(response.answers.len(), 2);
+    }
+
+    #[test]
+    fn pages_across_sections() {
+        let mut response = response(vec![ a(1), a(2) ]);
+        response.additionals = vec![ a(3), a(4) ];
+        Page { limit: 3, number: 1 }.apply(&mut response);
+
+        assert_eq!(response.answers, vec![ a(1), a(2) ]);
+        assert_eq!(response.additionals, vec![ a(3) ]);
+    }
+
+    #[test]
+    fn pseudo_records_kept() {
+        let opt = Answer::Pseudo { qname: String::new(), opt: OPT { udp_payload_size: 512, higher_bits: 0, edns0_version: 0, flags: 0, data: vec![] } };
+        let mut response = response(vec![ a(1), a(2) ]);
+        response.additionals = vec![ opt.clone() ];
+        Page { limit: 1, number: 2 }.apply(&mut response);
+
+        assert_eq!(response.answers, vec![ a(2) ]);
+        assert_eq!(response.additionals, vec![ opt ]);
+    }
+
+    #[test]
+    fn summary() {
+        let summary = Summary::of(&response(vec![ aaaa(), a(1), a(2) ]), "192.0.2.53");
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.to_string(), "3 records: 2 A, 1 AAAA");
+    }
+
+    #[test]
+    fn empty_summary() {
+        assert_eq!(Summary::of(&response(vec![]), "192.0.2.53").to_string(), "0 records");
+    }
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(thousands(0),         "0");
+        assert_eq!(thousands(999),       "999");
+        assert_eq!(thousands(15_023),    "15,023");
+        assert_eq!(thousands(1_234_567), "1,234,567");
+    }
+}
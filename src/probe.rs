@@ -0,0 +1,150 @@
+//! Probing for the largest UDP response that can make it back from a
+//! nameserver, by advertising different buffer sizes until responses stop
+//! arriving.
+
+use std::time::Duration;
+
+use log::*;
+
+use dns::Request;
+use dns_transport::{Transport, UdpTransport, Error as TransportError, Timeouts};
+
+use crate::resolve::Nameserver;
+
+
+/// The smallest buffer size to advertise, which every DNS implementation
+/// has to be able to handle.
+const SMALLEST: u16 = 512;
+
+/// The largest buffer size to advertise, which is the largest in common use.
+const LARGEST: u16 = 4096;
+
+/// The search stops when the sizes that work and the sizes that don’t are
+/// this close together.
+const PRECISION: u16 = 16;
+
+/// How long to wait for each probe if the user hasn’t given a response
+/// timeout. A response that gets dropped on the way never arrives, so
+/// without a timeout the probe would wait forever.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+
+/// The results of probing one nameserver.
+#[derive(Debug)]
+pub struct Probe {
+
+    /// The nameserver that was probed.
+    pub nameserver: Nameserver,
+
+    /// Each buffer size that was advertised, in the order they were tried.
+    pub attempts: Vec<Attempt>,
+}
+
+/// One request sent as part of a probe.
+#[derive(Debug)]
+pub struct Attempt {
+
+    /// The UDP buffer size advertised in the request.
+    pub advertised: u16,
+
+    /// The size of the response and whether it was truncated, or what went
+    /// wrong if it never arrived.
+    pub result: Result<(usize, bool), TransportError>,
+}
+
+impl Probe {
+
+    /// The attempt that got back the largest response, if any did.
+    pub fn largest(&self) -> Option<&Attempt> {
+        self.attempts.iter()
+            .filter(|a| a.result.is_ok())
+            .max_by_key(|a| a.result.as_ref().map_or(0, |(size, _)| *size))
+    }
+}
+
+
+/// Returns a copy of the request that advertises the given buffer size,
+/// keeping the rest of its OPT record, such as the DNSSEC OK bit, or adding
+/// one if it has none. Any signature gets removed, as it wouldn’t match the
+/// new size.
+fn with_payload_size(request: &Request, advertised: u16) -> Request {
+    let mut request = request.clone();
+    request.signature = None;
+    request.additional.get_or_insert_with(Request::additional_record).udp_payload_size = advertised;
+    request
+}
+
+/// Sends the request over UDP again and again, advertising different buffer
+/// sizes, and binary-searching for the largest size that still gets a
+/// response back.
+pub async fn run(request: Request, nameserver: Nameserver, mut timeouts: Timeouts) -> Probe {
+    timeouts.response = timeouts.response.or(Some(DEFAULT_TIMEOUT));
+    let transport = UdpTransport::new(nameserver.clone(), timeouts);
+    let mut attempts = Vec::new();
+
+    let attempt = |advertised: u16| {
+        let request = with_payload_size(&request, advertised);
+        let transport = &transport;
+
+        async move {
+            let result = transport.send_with_details(&request).await
+                                  .map(|(response, details)| (details.size, response.flags.truncated));
+            info!("Probe advertising {} bytes -> {:?}", advertised, result);
+            Attempt { advertised, result }
+        }
+    };
+
+    // If the largest size works, or the smallest doesn’t, there’s nothing
+    // in between to search for.
+    for size in &[ LARGEST, SMALLEST ] {
+        let first = attempt(*size).await;
+        let done = (*size == LARGEST) == first.result.is_ok();
+        attempts.push(first);
+
+        if done {
+            return Probe { nameserver, attempts };
+        }
+    }
+
+    let (mut working, mut failing) = (SMALLEST, LARGEST);
+    while failing - working > PRECISION {
+        let middle = working + (failing - working) / 2;
+        let next = attempt(middle).await;
+
+        if next.result.is_ok() {
+            working = middle;
+        }
+        else {
+            failing = middle;
+        }
+
+        attempts.push(next);
+    }
+
+    Probe { nameserver, attempts }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::Flags;
+    use dns::record::OPT;
+
+    fn request(additional: Option<OPT>) -> Request {
+        Request { transaction_id: 0x1234, flags: Flags::query(), queries: vec![], additional, signature: None }
+    }
+
+    #[test]
+    fn keeps_the_rest_of_the_opt_record() {
+        let opt = OPT { flags: OPT::DNSSEC_OK, data: vec![ 0, 10, 0, 0 ], .. Request::additional_record() };
+        let probe = with_payload_size(&request(Some(opt.clone())), 1232);
+        assert_eq!(probe.additional, Some(OPT { udp_payload_size: 1232, .. opt }));
+    }
+
+    #[test]
+    fn adds_an_opt_record() {
+        let probe = with_payload_size(&request(None), 4096);
+        assert_eq!(probe.additional, Some(OPT { udp_payload_size: 4096, .. Request::additional_record() }));
+    }
+}
@@ -0,0 +1,136 @@
+//! Reporting progress while lots of requests are being sent.
+
+use std::time::{Duration, Instant};
+
+
+/// A **progress** counter that gets drawn on standard error while a batch of
+/// requests is being sent, so that long runs aren’t silent until they
+/// finish. It only gets drawn if standard error is a terminal, and only
+/// once the run has been going for long enough that it’s worth showing.
+#[derive(Debug)]
+pub struct Progress {
+
+    /// The number of requests in the batch.
+    total: usize,
+
+    /// The number of requests that have finished, successfully or not.
+    done: usize,
+
+    /// When the batch started.
+    started: Instant,
+
+    /// Whether to draw anything at all.
+    enabled: bool,
+
+    /// When the counter was last drawn, if it has been.
+    last_drawn: Option<Instant>,
+}
+
+/// How long to wait before drawing the counter, so that quick runs don’t
+/// flash it on the screen.
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// How long to wait between redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How many characters wide the bar is.
+const BAR_WIDTH: usize = 20;
+
+
+impl Progress {
+
+    /// Creates a new progress counter for a batch of the given number of
+    /// requests.
+    pub fn new(total: usize) -> Self {
+        let enabled = total > 1 && atty::is(atty::Stream::Stderr);
+        Self { total, done: 0, started: Instant::now(), enabled, last_drawn: None }
+    }
+
+    /// Marks one more request as finished, redrawing the counter if it’s
+    /// time to.
+    pub fn tick(&mut self) {
+        self.done += 1;
+
+        if ! self.enabled || self.started.elapsed() < INITIAL_DELAY {
+            return;
+        }
+
+        if self.last_drawn.is_none_or(|t| t.elapsed() >= REDRAW_INTERVAL) {
+            eprint!("\r{}\x1B[K", render(self.done, self.total, self.started.elapsed()));
+            self.last_drawn = Some(Instant::now());
+        }
+    }
+
+    /// Erases the counter, if it has been drawn, so something else can be
+    /// printed on standard error. It gets drawn again on the next tick.
+    pub fn clear(&mut self) {
+        if self.last_drawn.take().is_some() {
+            eprint!("\r\x1B[K");
+        }
+    }
+}
+
+
+/// Renders the bar, the counts, the rate at which requests are finishing,
+/// and how long until they all will have.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn render(done: usize, total: usize, elapsed: Duration) -> String {
+    let filled = (BAR_WIDTH * done / total.max(1)).min(BAR_WIDTH);
+    let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled));
+
+    let seconds = elapsed.as_secs_f64();
+    let rate = if seconds > 0.0 { done as f64 / seconds } else { 0.0 };
+
+    let eta = if rate > 0.0 {
+            format_eta(((total - done) as f64 / rate).ceil() as u64)
+        }
+        else {
+            String::from("?")
+        };
+
+    let width = total.to_string().len();
+    format!("[{}] {:>width$}/{} {:.1}/s, ETA {}", bar, done, total, rate, eta, width = width)
+}
+
+/// Formats the estimated number of seconds remaining.
+fn format_eta(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}s", seconds)
+    }
+    else if seconds < 60 * 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    }
+    else {
+        format!("{}h{:02}m", seconds / 3600, (seconds % 3600) / 60)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn starting() {
+        assert_eq!(render(0, 500, Duration::from_secs(0)),
+                   "[--------------------]   0/500 0.0/s, ETA ?");
+    }
+
+    #[test]
+    fn halfway() {
+        assert_eq!(render(250, 500, Duration::from_secs(10)),
+                   "[##########----------] 250/500 25.0/s, ETA 10s");
+    }
+
+    #[test]
+    fn long_way_to_go() {
+        assert_eq!(render(10, 5000, Duration::from_secs(5)),
+                   "[--------------------]   10/5000 2.0/s, ETA 41m35s");
+    }
+
+    #[test]
+    fn finished() {
+        assert_eq!(render(500, 500, Duration::from_secs(20)),
+                   "[####################] 500/500 25.0/s, ETA 0s");
+    }
+}
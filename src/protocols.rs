@@ -0,0 +1,174 @@
+//! Sending the same query over UDP and TCP at the same time, and comparing
+//! the two responses. A nameserver should answer the same way over both,
+//! but ones behind broken load balancers or middleboxes often don’t, as the
+//! two protocols can end up going to different backends.
+
+use futures_util::future::join;
+use log::*;
+
+use dns::{Request, Response, Answer, Flags, ErrorCode};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::compare::missing_from;
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::Nameserver;
+
+
+/// The responses to one query sent over both protocols.
+#[derive(Debug)]
+pub struct Comparison {
+
+    /// The nameserver both requests were sent to.
+    pub nameserver: Nameserver,
+
+    /// The name that was queried.
+    pub qname: String,
+
+    /// The response that came back over UDP.
+    pub udp: Result<Response, TransportError>,
+
+    /// The response that came back over TCP.
+    pub tcp: Result<Response, TransportError>,
+}
+
+/// One way in which the two responses differ.
+#[derive(PartialEq, Debug)]
+pub enum Difference {
+
+    /// A header flag was set in one response but not the other.
+    Flag {
+        name: &'static str,
+        udp: bool,
+        tcp: bool,
+    },
+
+    /// The two responses had different response codes.
+    Status {
+        udp: Option<ErrorCode>,
+        tcp: Option<ErrorCode>,
+    },
+
+    /// A record was in one section of one response, but not the other.
+    /// TTLs are ignored, as they count down in caches.
+    Record {
+        only_over: TransportType,
+        section: &'static str,
+        answer: Answer,
+    },
+}
+
+
+/// Sends the request to the nameserver over UDP and TCP at the same time,
+/// and waits for both responses.
+pub async fn run(request: &Request, nameserver: Nameserver, timeouts: Timeouts, names: &ServerNames) -> Comparison {
+    let udp_transport = TransportType::UDP.make_transport(nameserver.clone(), timeouts, names);
+    let tcp_transport = TransportType::TCP.make_transport(nameserver.clone(), timeouts, names);
+
+    let (udp, tcp) = join(udp_transport.send(request), tcp_transport.send(request)).await;
+    info!("Both protocols to {} -> {:?} and {:?}", nameserver, udp, tcp);
+
+    let qname = request.queries.first().map(|q| q.qname.clone()).unwrap_or_default();
+    Comparison { nameserver, qname, udp, tcp }
+}
+
+
+impl Comparison {
+
+    /// Returns every difference between the two responses, or nothing if
+    /// either of the requests failed.
+    pub fn differences(&self) -> Vec<Difference> {
+        let (Ok(udp), Ok(tcp)) = (&self.udp, &self.tcp) else {
+            return Vec::new();
+        };
+
+        let mut differences = flag_differences(udp.flags, tcp.flags);
+
+        if udp.flags.error_code != tcp.flags.error_code {
+            differences.push(Difference::Status { udp: udp.flags.error_code, tcp: tcp.flags.error_code });
+        }
+
+        // A truncated response is missing records on purpose, so comparing
+        // them would only say what the truncated flag already does
+        if ! udp.flags.truncated {
+            let sections = [
+                ("answer",     &udp.answers,     &tcp.answers),
+                ("authority",  &udp.authorities, &tcp.authorities),
+                ("additional", &udp.additionals, &tcp.additionals),
+            ];
+
+            for (section, udp_answers, tcp_answers) in &sections {
+                for answer in missing_from(udp_answers, tcp_answers) {
+                    differences.push(Difference::Record { only_over: TransportType::UDP, section, answer });
+                }
+
+                for answer in missing_from(tcp_answers, udp_answers) {
+                    differences.push(Difference::Record { only_over: TransportType::TCP, section, answer });
+                }
+            }
+        }
+
+        differences
+    }
+}
+
+/// Compares the header flags that a nameserver sets, returning a difference
+/// for each one that was only set in one of the responses.
+fn flag_differences(udp: Flags, tcp: Flags) -> Vec<Difference> {
+    let flags = [
+        ("aa", udp.authoritative,        tcp.authoritative),
+        ("tc", udp.truncated,            tcp.truncated),
+        ("ra", udp.recursion_available,  tcp.recursion_available),
+        ("ad", udp.authentic_data,       tcp.authentic_data),
+    ];
+
+    flags.iter()
+         .filter(|(_, udp, tcp)| udp != tcp)
+         .map(|&(name, udp, tcp)| Difference::Flag { name, udp, tcp })
+         .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compare::test::{a, response};
+
+    fn comparison(udp: Response, tcp: Response) -> Comparison {
+        Comparison { nameserver: "192.0.2.53".into(), qname: "lookup.dog.".into(), udp: Ok(udp), tcp: Ok(tcp) }
+    }
+
+    #[test]
+    fn identical() {
+        let udp = response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]);
+        let tcp = response(0b_1000_0001_1000_0000, vec![ a("LookUp.dog.", 299, 1) ]);
+        assert_eq!(comparison(udp, tcp).differences(), Vec::new());
+    }
+
+    #[test]
+    fn different_records() {
+        let udp = response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]);
+        let tcp = response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 2) ]);
+        assert_eq!(comparison(udp, tcp).differences(), vec![
+            Difference::Record { only_over: TransportType::UDP, section: "answer", answer: a("lookup.dog.", 300, 1) },
+            Difference::Record { only_over: TransportType::TCP, section: "answer", answer: a("lookup.dog.", 300, 2) },
+        ]);
+    }
+
+    #[test]
+    fn different_flags_and_status() {
+        let udp = response(0b_1000_0100_1000_0000, vec![]);
+        let tcp = response(0b_1000_0000_1000_0011, vec![]);
+        assert_eq!(comparison(udp, tcp).differences(), vec![
+            Difference::Flag { name: "aa", udp: true, tcp: false },
+            Difference::Status { udp: None, tcp: Some(ErrorCode::NXDomain) },
+        ]);
+    }
+
+    #[test]
+    fn truncated_records_skipped() {
+        let udp = response(0b_1000_0011_1000_0000, vec![]);
+        let tcp = response(0b_1000_0001_1000_0000, vec![ a("lookup.dog.", 300, 1) ]);
+        assert_eq!(comparison(udp, tcp).differences(), vec![
+            Difference::Flag { name: "tc", udp: true, tcp: false },
+        ]);
+    }
+}
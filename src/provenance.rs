@@ -0,0 +1,91 @@
+//! Keeping track of where each response came from, so that records from
+//! several nameservers can be told apart once they’ve been merged together.
+
+use std::time::SystemTime;
+
+use dns_transport::Details;
+use serde_json::{json, Value as JsonValue};
+
+use crate::connect::TransportType;
+use crate::resolve::Nameserver;
+
+
+/// The nameserver a response came from, how it got here, and when.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Source {
+
+    /// The nameserver that sent the response.
+    pub nameserver: Nameserver,
+
+    /// The transport the response arrived over. This is never `Automatic`,
+    /// as by the time a response has arrived, it’s known whether it came
+    /// over UDP or TCP.
+    pub transport: TransportType,
+
+    /// When the response arrived.
+    pub received: SystemTime,
+}
+
+impl Source {
+
+    /// Notes that a response has just arrived from the given nameserver,
+    /// using the details of how it arrived to say which protocol an
+    /// automatic transport ended up using.
+    pub fn arrived(nameserver: Nameserver, transport: TransportType, details: &Details) -> Self {
+        let transport = match transport {
+            TransportType::Automatic if details.over_udp  => TransportType::UDP,
+            TransportType::Automatic                      => TransportType::TCP,
+            other                                         => other,
+        };
+
+        Self { nameserver, transport, received: SystemTime::now() }
+    }
+
+    /// Describes this source as JSON, to be attached to every record that
+    /// came from it.
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "nameserver": self.nameserver,
+            "transport": format!("{:?}", self.transport).to_lowercase(),
+            "received": humantime::format_rfc3339_millis(self.received).to_string(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn details(over_udp: bool) -> Details {
+        Details { size: 64, over_udp, http: None, message: vec![], round_trip: None, address: None, ip_ttl: None }
+    }
+
+    #[test]
+    fn automatic_over_udp() {
+        let source = Source::arrived("192.0.2.1".into(), TransportType::Automatic, &details(true));
+        assert_eq!(source.transport, TransportType::UDP);
+    }
+
+    #[test]
+    fn automatic_over_tcp() {
+        let source = Source::arrived("192.0.2.1".into(), TransportType::Automatic, &details(false));
+        assert_eq!(source.transport, TransportType::TCP);
+    }
+
+    #[test]
+    fn json() {
+        let source = Source {
+            nameserver: "https://dns.example/dns-query".into(),
+            transport: TransportType::HTTPS,
+            received: UNIX_EPOCH + Duration::from_millis(1_590_493_484_250),
+        };
+
+        assert_eq!(source.to_json(), json!({
+            "nameserver": "https://dns.example/dns-query",
+            "transport": "https",
+            "received": "2020-05-26T11:44:44.250Z",
+        }));
+    }
+}
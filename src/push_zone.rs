@@ -0,0 +1,172 @@
+//! Telling a secondary nameserver that a zone has changed, then watching it
+//! until it has caught up with the primary.
+//!
+//! This sends a NOTIFY message to the secondary, as the primary itself would
+//! have done, then keeps asking the secondary for the zone’s SOA record
+//! until its serial number matches the one on the primary.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::*;
+
+use dns::{Request, Response, Answer, Flags, ErrorCode};
+use dns::record::{Record, SOA};
+use dns_transport::{Error as TransportError, Timeouts};
+
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::Nameserver;
+
+
+/// How long to wait between each time the secondary is asked for its SOA.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to keep asking the secondary before giving up on it.
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for each response if the user hasn’t given a response
+/// timeout, so one lost packet doesn’t hold up the whole run.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// The results of pushing a zone to one secondary.
+#[derive(Debug)]
+pub struct Push {
+
+    /// The zone that was pushed.
+    pub zone: String,
+
+    /// The secondary that was notified.
+    pub secondary: Nameserver,
+
+    /// The primary that the secondary’s serial was compared against, if it
+    /// was given or could be worked out.
+    pub primary: Option<Nameserver>,
+
+    /// The zone’s serial number on the primary.
+    pub primary_serial: Result<Option<u32>, TransportError>,
+
+    /// The status of the secondary’s reply to the NOTIFY message.
+    pub notify: Result<Option<ErrorCode>, TransportError>,
+
+    /// The serial the secondary had each time it was asked, along with how
+    /// long after the NOTIFY it was asked.
+    pub polls: Vec<(Duration, Result<Option<u32>, TransportError>)>,
+
+    /// How long after the NOTIFY the secondary’s serial matched the
+    /// primary’s, if it did before the time ran out.
+    pub converged: Option<Duration>,
+}
+
+
+/// Notifies the secondary that the zone in the request has changed, then
+/// polls it until its serial matches the primary’s. If no primary is given,
+/// the one named in the secondary’s SOA record gets asked.
+pub fn run(runtime: &mut dns_transport::Runtime, request: &Request, secondary: Nameserver, transport_type: TransportType,
+           primary: Option<Nameserver>, mut timeouts: Timeouts, names: &ServerNames) -> Push {
+    timeouts.response = timeouts.response.or(Some(DEFAULT_TIMEOUT));
+    let transport = transport_type.make_transport(secondary.clone(), timeouts, names);
+    let zone = request.queries[0].qname.clone();
+
+    let primary = primary.or_else(|| {
+        let soa = runtime.block_on(async { transport.send(request).await }).ok()?;
+        zone_soa(&soa).map(|soa| soa.mname.trim_end_matches('.').to_owned())
+    });
+
+    let primary_serial = match &primary {
+        Some(primary) => {
+            let transport = TransportType::Automatic.make_transport(primary.clone(), timeouts, names);
+            runtime.block_on(async { transport.send(request).await }).map(|r| zone_soa(&r).map(|soa| soa.serial))
+        }
+        None => Ok(None),
+    };
+    info!("Primary {:?} has serial {:?}", primary, primary_serial);
+
+    let notify_request = Request {
+        transaction_id: request.transaction_id,
+        flags: Flags::notify(),
+        queries: request.queries.clone(),
+        additional: None,
+        signature: None,
+    };
+
+    let notified = Instant::now();
+    let notify = runtime.block_on(async { transport.send(&notify_request).await }).map(|r| r.flags.error_code);
+    info!("Notified {} -> {:?}", secondary, notify);
+
+    let mut polls = Vec::new();
+    let mut converged = None;
+    if let (Ok(_), Ok(Some(wanted))) = (&notify, &primary_serial) {
+        while notified.elapsed() < CONVERGENCE_TIMEOUT {
+            let serial = runtime.block_on(async { transport.send(request).await }).map(|r| zone_soa(&r).map(|soa| soa.serial));
+            let elapsed = notified.elapsed();
+            debug!("Secondary {} has serial {:?} after {:?}", secondary, serial, elapsed);
+
+            let matched = matches!(serial, Ok(Some(s)) if s == *wanted);
+            polls.push((elapsed, serial));
+            if matched {
+                converged = Some(elapsed);
+                break;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    Push { zone, secondary, primary, primary_serial, notify, polls, converged }
+}
+
+/// Finds the SOA record in the answer section of a response.
+fn zone_soa(response: &Response) -> Option<&SOA> {
+    response.answers.iter().find_map(|a| match a {
+        Answer::Standard { record: Record::SOA(soa), .. }  => Some(soa),
+        _                                                   => None,
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::QClass;
+
+    fn soa(serial: u32) -> Answer {
+        Answer::Standard {
+            qname: "lookup.dog.".into(),
+            qclass: QClass::IN,
+            ttl: 3600,
+            record: Record::SOA(SOA {
+                mname: "primary.lookup.dog.".into(), rname: "hostmaster.lookup.dog.".into(), serial,
+                refresh_interval: 3600, retry_interval: 600, expire_limit: 86400, minimum_ttl: 300,
+            }),
+        }
+    }
+
+    #[test]
+    fn finds_soa() {
+        let response = Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0100_0000_0000),
+            queries: vec![],
+            answers: vec![ soa(2_020_052_601) ],
+            authorities: vec![],
+            additionals: vec![],
+        };
+
+        assert_eq!(zone_soa(&response).map(|soa| soa.serial), Some(2_020_052_601));
+    }
+
+    #[test]
+    fn no_soa() {
+        let response = Response {
+            transaction_id: 0x1234,
+            flags: Flags::from_u16(0b_1000_0100_0000_0011),
+            queries: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        };
+
+        assert_eq!(zone_soa(&response), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Racing a query against several nameservers at once.
+
+use std::time::{Duration, Instant};
+
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use log::*;
+
+use dns::{Request, Response, ErrorCode};
+use dns_transport::{Error as TransportError, Details};
+
+use crate::connect::{TransportType, Transports};
+use crate::provenance::Source;
+use crate::resolve::Nameserver;
+use crate::zone;
+
+
+/// How long to wait for each nameserver’s response, when no response
+/// timeout has been given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to keep waiting for the other nameservers once one has won, so
+/// their times can still be reported without holding everything up.
+const GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// The results of sending the same query to several nameservers at once.
+#[derive(Debug)]
+pub struct Race {
+
+    /// The query that was raced, such as `example.net. A`.
+    pub query: String,
+
+    /// Every nameserver the query was sent to, in the order they finished.
+    pub entrants: Vec<Entrant>,
+}
+
+/// One of the nameservers taking part in a race.
+#[derive(Debug)]
+pub struct Entrant {
+
+    /// The nameserver the query was sent to.
+    pub nameserver: Nameserver,
+
+    /// How long after the start of the race this nameserver finished.
+    pub elapsed: Duration,
+
+    /// How the nameserver fared.
+    pub outcome: Outcome,
+}
+
+/// How a nameserver fared in a race.
+#[derive(Debug)]
+pub enum Outcome {
+
+    /// This nameserver’s response was the first valid one to arrive.
+    Won,
+
+    /// A valid response arrived, but after the winner’s.
+    Answered,
+
+    /// The nameserver responded with an error, such as `SERVFAIL` or
+    /// `REFUSED`, which doesn’t count as an answer.
+    Rejected(ErrorCode),
+
+    /// The request failed before any response arrived.
+    Failed(TransportError),
+
+    /// The race had already been won, and no response arrived before the
+    /// grace period ran out.
+    Abandoned,
+}
+
+/// A request that has been sent, the response to it, where it came from,
+/// and the details of how it arrived.
+pub type Finish = (Request, Response, Source, Details);
+
+
+/// Groups requests that differ only in their nameserver, so each group can
+/// be raced against each other, keeping them in their original order.
+pub fn group(requests: Vec<(Request, Nameserver, TransportType)>) -> Vec<Vec<(Request, Nameserver, TransportType)>> {
+    let mut groups: Vec<Vec<(Request, Nameserver, TransportType)>> = Vec::new();
+
+    for entrant in requests {
+        let existing = groups.iter_mut().find(|g| g[0].0.queries == entrant.0.queries && g[0].2 == entrant.2);
+        match existing {
+            Some(group)  => group.push(entrant),
+            None         => groups.push(vec![ entrant ]),
+        }
+    }
+
+    groups
+}
+
+/// Sends every request in a group at once, over the transports prepared for
+/// them, waiting for the others to finish after the winner so the losers’
+/// times can be reported too, until the grace period runs out. Returns the
+/// race, along with the winning response — or, if nobody gave a valid
+/// answer, the first response that arrived at all.
+pub async fn run(entrants: Vec<(Request, Nameserver, TransportType)>, transports: &Transports) -> (Race, Option<Finish>) {
+    let query = entrants.first().map(|(request, ..)| describe(request)).unwrap_or_default();
+    let mut running = entrants.iter().map(|(_, nameserver, _)| nameserver.clone()).collect::<Vec<_>>();
+    let start = Instant::now();
+
+    let mut pending = entrants.into_iter().map(|(request, nameserver, transport_type)| async move {
+        let result = transports.send(&request, &nameserver, transport_type).await;
+        (request, nameserver, transport_type, start.elapsed(), result)
+    }).collect::<FuturesUnordered<_>>();
+
+    let mut finishers = Vec::new();
+    let mut winner = None;
+    let mut fallback = None;
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let next = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if let Ok(next) = tokio::time::timeout(remaining, pending.next()).await {
+                    next
+                }
+                else {
+                    info!("Race for {} over, so giving up on {} nameservers", query, running.len());
+                    break;
+                }
+            }
+            None => pending.next().await,
+        };
+
+        let Some((request, nameserver, transport_type, elapsed, result)) = next else {
+            break;
+        };
+
+        if let Some(index) = running.iter().position(|n| *n == nameserver) {
+            running.remove(index);
+        }
+
+        let outcome = match result {
+            Ok((response, details)) => {
+                match response.flags.error_code {
+                    None | Some(ErrorCode::NXDomain) if winner.is_none() => {
+                        info!("Race for {} won by {} in {:?}", query, nameserver, elapsed);
+                        let source = Source::arrived(nameserver.clone(), transport_type, &details);
+                        winner = Some((request, response, source, details));
+                        deadline = Some(Instant::now() + GRACE_PERIOD);
+                        Outcome::Won
+                    }
+                    None | Some(ErrorCode::NXDomain) => {
+                        Outcome::Answered
+                    }
+                    Some(rcode) => {
+                        if fallback.is_none() {
+                            let source = Source::arrived(nameserver.clone(), transport_type, &details);
+                            fallback = Some((request, response, source, details));
+                        }
+
+                        Outcome::Rejected(rcode)
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Request to {} failed during race -> {:?}", nameserver, e);
+                Outcome::Failed(e)
+            }
+        };
+
+        finishers.push(Entrant { nameserver, elapsed, outcome });
+    }
+
+    let elapsed = start.elapsed();
+    finishers.extend(running.into_iter().map(|nameserver| Entrant { nameserver, elapsed, outcome: Outcome::Abandoned }));
+
+    (Race { query, entrants: finishers }, winner.or(fallback))
+}
+
+/// Describes the query a request is making, such as `example.net. A`.
+fn describe(request: &Request) -> String {
+    match request.queries.first() {
+        Some(query)  => format!("{} {}", query.qname, zone::type_name(query.qtype)),
+        None         => String::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+
+    fn request(qname: &str, qtype: u16) -> Request {
+        Request {
+            transaction_id: 0xABCD,
+            flags: Flags::query(),
+            queries: vec![ Query { qname: qname.into(), qclass: QClass::IN, qtype } ],
+            additional: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn groups_by_query() {
+        let requests = vec![
+            (request("lookup.dog.", 1),  "1.1.1.1".into(), TransportType::UDP),
+            (request("lookup.dog.", 1),  "8.8.8.8".into(), TransportType::UDP),
+            (request("lookup.dog.", 15), "1.1.1.1".into(), TransportType::UDP),
+            (request("lookup.dog.", 15), "8.8.8.8".into(), TransportType::UDP),
+        ];
+
+        let groups = group(requests);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].iter().map(|e| e.1.as_str()).collect::<Vec<_>>(), vec![ "1.1.1.1", "8.8.8.8" ]);
+        assert_eq!(groups[1][0].0.queries[0].qtype, 15);
+    }
+
+    #[test]
+    fn keeps_transports_apart() {
+        let requests = vec![
+            (request("lookup.dog.", 1), "1.1.1.1".into(), TransportType::UDP),
+            (request("lookup.dog.", 1), "1.1.1.1".into(), TransportType::TCP),
+        ];
+
+        assert_eq!(group(requests).len(), 2);
+    }
+
+    #[test]
+    fn description() {
+        assert_eq!(describe(&request("lookup.dog.", 15)), "lookup.dog. MX");
+    }
+}
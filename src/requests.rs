@@ -1,5 +1,8 @@
-use crate::connect::TransportType;
-use crate::resolve::Resolver;
+use dns_transport::Timeouts;
+
+use crate::connect::{TransportType, ServerNames};
+use crate::resolve::{Resolver, Nameserver};
+use crate::sig0::Sig0Key;
 use crate::txid::TxidGenerator;
 
 
@@ -19,6 +22,19 @@ pub struct RequestGenerator {
 
     /// Other weird protocol options.
     pub protocol_tweaks: ProtocolTweaks,
+
+    /// The key to sign requests with, if they should be signed.
+    pub sig0_key: Option<Sig0Key>,
+
+    /// How long to wait for each phase of sending a request.
+    pub timeouts: Timeouts,
+
+    /// The names to present to encrypted nameservers.
+    pub server_names: ServerNames,
+
+    /// The resolver to look up the addresses of encrypted nameservers with,
+    /// if they’re given as host names.
+    pub bootstrap: Option<Nameserver>,
 }
 
 /// Which things the user has specified they want queried.
@@ -43,12 +59,33 @@ pub struct Inputs {
 
 /// Weird protocol options that are allowed by the spec but are not common.
 #[derive(PartialEq, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ProtocolTweaks {
 
     /// Set the `AD` flag (Authentic Data) in the header of each request.
     pub set_authentic_flag: bool,
+
+    /// The UDP payload size to advertise in the OPT record, if not the
+    /// default of 512 bytes.
+    pub udp_payload_size: Option<u16>,
+
+    /// Send a request again over TCP if sending it over UDP fails, rather
+    /// than only when the UDP response is truncated.
+    pub retry_over_tcp: bool,
+
+    /// Set the `DO` flag (DNSSEC OK) in the OPT record of each request, so
+    /// that signatures get sent back along with the records.
+    pub dnssec_ok: bool,
+
+    /// Clear the `RD` flag (Recursion Desired) in the header of each
+    /// request, so the nameserver only answers from its own data.
+    pub no_recursion: bool,
 }
 
+/// The largest UDP payload size recommended by DNS Flag Day 2020, which
+/// avoids IP fragmentation on almost every path.
+pub const FLAG_DAY_PAYLOAD_SIZE: u16 = 1232;
+
 /// Whether to send or display OPT packets.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum UseEDNS {
@@ -68,9 +105,9 @@ pub enum UseEDNS {
 
 impl RequestGenerator {
 
-    /// Iterate through the inputs matrix, returning pairs of DNS requests and
-    /// the details of the transport to send them down.
-    pub fn generate(self) -> Vec<(dns::Request, Box<dyn dns_transport::Transport>)> {
+    /// Iterate through the inputs matrix, returning DNS requests along with
+    /// the details of the nameserver and transport to send them down.
+    pub fn generate(self) -> Vec<(dns::Request, Nameserver, TransportType)> {
         let nameservers = self.inputs.resolvers.into_iter()
                               .map(|e| e.lookup().expect("Failed to get nameserver").expect("No nameserver found"))
                               .collect::<Vec<_>>();
@@ -80,27 +117,46 @@ impl RequestGenerator {
             for qtype in self.inputs.types.iter().copied() {
                 for qclass in self.inputs.classes.iter().copied() {
                     for nameserver in &nameservers {
-                        for transport_type in &self.inputs.transport_types {
+                        let endpoints = match TransportType::from_uri(nameserver) {
+                            Some(endpoint) => vec![ endpoint ],
+                            None => self.inputs.transport_types.iter().map(|t| (*t, nameserver.clone())).collect(),
+                        };
+
+                        for (transport_type, nameserver) in endpoints {
 
                             let transaction_id = self.txid_generator.generate();
                             let mut flags = dns::Flags::query();
                             if self.protocol_tweaks.set_authentic_flag {
                                 flags.authentic_data = true;
                             }
+                            if self.protocol_tweaks.no_recursion {
+                                flags.recursion_desired = false;
+                            }
 
                             let mut additional = None;
                             if self.edns.should_send() {
-                                additional = Some(dns::Request::additional_record());
+                                let mut opt = dns::Request::additional_record();
+                                if let Some(size) = self.protocol_tweaks.udp_payload_size {
+                                    opt.udp_payload_size = size;
+                                }
+
+                                if self.protocol_tweaks.dnssec_ok {
+                                    opt.flags |= dns::record::OPT::DNSSEC_OK;
+                                }
+
+                                additional = Some(opt);
                             }
 
                             let queries = vec![
                                 dns::Query { qname: domain.clone(), qtype, qclass },
                             ];
 
-                            let request = dns::Request { transaction_id, flags, queries, additional };
+                            let mut request = dns::Request { transaction_id, flags, queries, additional, signature: None };
+                            if let Some(key) = &self.sig0_key {
+                                key.sign(&mut request);
+                            }
 
-                            let transport = transport_type.make_transport(nameserver.clone());
-                            requests.push((request, transport));
+                            requests.push((request, nameserver, transport_type));
                         }
                     }
                 }
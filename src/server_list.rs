@@ -0,0 +1,363 @@
+//! Loading lists of nameservers from files, so a curated set of resolvers
+//! can be queried without giving each one on the command-line.
+//!
+//! Three formats are understood:
+//!
+//! - the Markdown lists published for dnscrypt-proxy, such as
+//!   `public-resolvers.md`, where each resolver has a `##` heading followed
+//!   by one or more `sdns://` stamps;
+//! - TOML, with a `[[server]]` table for each nameserver, holding its
+//!   `address` and an optional `name`;
+//! - CSV, with a nameserver on each line as `name,address`, or just the
+//!   address.
+//!
+//! Addresses in TOML and CSV files can be anything that can come after an
+//! `@` on the command-line, or a stamp.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use log::*;
+
+use crate::resolve::Nameserver;
+
+
+/// One nameserver from a list.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Server {
+
+    /// The name the list gives the nameserver, or its address if it
+    /// doesn’t give one.
+    pub name: String,
+
+    /// Where to send requests to, as it would be given after an `@`.
+    pub nameserver: Nameserver,
+}
+
+/// Something wrong with a list of nameservers.
+#[derive(PartialEq, Debug)]
+pub enum ServerListError {
+
+    /// There was an error reading the file.
+    IO(String),
+
+    /// The file was not valid TOML.
+    InvalidToml(String),
+
+    /// A `[[server]]` table had no `address` string, at this position in
+    /// the list, counting from one.
+    MissingAddress(usize),
+
+    /// A stamp was not valid base64, or was cut short.
+    InvalidStamp(String),
+
+    /// There were no nameservers in the list that dog can query.
+    Empty,
+}
+
+
+/// Reads a list of nameservers from a file, working out its format from its
+/// extension, or from whether it contains any stamps if it has none that
+/// dog knows about.
+pub fn load(path: &Path) -> Result<Vec<Server>, ServerListError> {
+    let contents = fs::read_to_string(path).map_err(|e| ServerListError::IO(e.to_string()))?;
+
+    let servers = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml")  => parse_toml(&contents)?,
+        Some("csv")   => parse_csv(&contents)?,
+        Some("md")    => parse_markdown(&contents)?,
+        _ if contents.lines().any(|l| l.trim().starts_with("sdns://")) => parse_markdown(&contents)?,
+        _             => parse_csv(&contents)?,
+    };
+
+    debug!("Loaded {} nameservers from {}", servers.len(), path.display());
+    if servers.is_empty() {
+        return Err(ServerListError::Empty);
+    }
+
+    Ok(servers)
+}
+
+/// Reads the stamps in a dnscrypt-proxy list, naming each after the heading
+/// above it.
+fn parse_markdown(contents: &str) -> Result<Vec<Server>, ServerListError> {
+    let mut servers = Vec::new();
+    let mut heading = None;
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("## ") {
+            heading = Some(title.trim().to_owned());
+        }
+        else if line.starts_with("sdns://") {
+            if let Some(nameserver) = parse_stamp(line)? {
+                let name = heading.clone().unwrap_or_else(|| nameserver.clone());
+                servers.push(Server { name, nameserver });
+            }
+        }
+    }
+
+    Ok(servers)
+}
+
+/// Reads the `[[server]]` tables in a TOML file.
+fn parse_toml(contents: &str) -> Result<Vec<Server>, ServerListError> {
+    let value = contents.parse::<toml::Value>().map_err(|e| ServerListError::InvalidToml(e.to_string()))?;
+
+    let mut servers = Vec::new();
+    let tables = value.get("server").and_then(toml::Value::as_array).map(Vec::as_slice).unwrap_or_default();
+    for (index, table) in tables.iter().enumerate() {
+        let address = table.get("address").and_then(toml::Value::as_str).ok_or(ServerListError::MissingAddress(index + 1))?;
+        let name = table.get("name").and_then(toml::Value::as_str);
+
+        if let Some(server) = server(name, address)? {
+            servers.push(server);
+        }
+    }
+
+    Ok(servers)
+}
+
+/// Reads the lines of a CSV file, skipping blank lines, comments, and a
+/// header line.
+fn parse_csv(contents: &str) -> Result<Vec<Server>, ServerListError> {
+    let mut servers = Vec::new();
+
+    for (index, line) in contents.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split(',').map(|f| f.trim().trim_matches('"')).collect::<Vec<_>>();
+        if index == 0 && fields.iter().any(|f| f.eq_ignore_ascii_case("address")) {
+            continue;
+        }
+
+        let server = match fields.as_slice() {
+            [ address ]             => server(None, address)?,
+            [ name, address, .. ]   => server(Some(name), address)?,
+            []                      => None,
+        };
+
+        servers.extend(server);
+    }
+
+    Ok(servers)
+}
+
+/// Makes a server out of a name and an address, which could be a stamp.
+/// Returns `None` for stamps of protocols dog can’t speak.
+fn server(name: Option<&str>, address: &str) -> Result<Option<Server>, ServerListError> {
+    let nameserver = if address.starts_with("sdns://") {
+        match parse_stamp(address)? {
+            Some(nameserver)  => nameserver,
+            None              => return Ok(None),
+        }
+    }
+    else {
+        address.to_owned()
+    };
+
+    let name = name.filter(|n| ! n.is_empty()).map_or_else(|| nameserver.clone(), str::to_owned);
+    Ok(Some(Server { name, nameserver }))
+}
+
+
+/// Decodes a DNS stamp, as used by dnscrypt-proxy, into a nameserver.
+/// Stamps for plain DNS, DNS-over-HTTPS, and DNS-over-TLS are understood;
+/// the others, such as those for encrypted relays, give `None`.
+///
+/// A stamp is a protocol byte, eight bytes of properties, then fields that
+/// depend on the protocol, each prefixed with its length, all encoded in
+/// URL-safe base64 without padding.
+pub fn parse_stamp(stamp: &str) -> Result<Option<Nameserver>, ServerListError> {
+    let invalid = || ServerListError::InvalidStamp(stamp.to_owned());
+
+    let encoded = stamp.strip_prefix("sdns://").ok_or_else(invalid)?;
+    let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+    let (&protocol, rest) = bytes.split_first().ok_or_else(invalid)?;
+    let mut fields = Fields { bytes: rest.get(8 ..).ok_or_else(invalid)? };
+
+    match protocol {
+        0x00 => {
+            let address = fields.string().ok_or_else(invalid)?;
+            Ok(Some(address))
+        }
+        0x02 => {
+            let _address = fields.string().ok_or_else(invalid)?;
+            fields.skip_set().ok_or_else(invalid)?;
+            let host = fields.string().ok_or_else(invalid)?;
+            let path = fields.string().ok_or_else(invalid)?;
+            Ok(Some(format!("https://{}{}", host, path)))
+        }
+        0x03 => {
+            let address = fields.string().ok_or_else(invalid)?;
+            fields.skip_set().ok_or_else(invalid)?;
+            let host = fields.string().ok_or_else(invalid)?;
+
+            // The certificate is for the host name, so it gets sent as the
+            // SNI when connecting to the address
+            let sni = host.rsplit_once(':').map_or(host.as_str(), |(name, _)| name);
+            if address.is_empty() { Ok(Some(format!("tls://{}", host))) }
+                             else { Ok(Some(format!("tls://{}#{}", address, sni))) }
+        }
+        other => {
+            debug!("Skipping stamp for unsupported protocol {:#04x}", other);
+            Ok(None)
+        }
+    }
+}
+
+/// The length-prefixed fields of a stamp, read from the front.
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+impl Fields<'_> {
+
+    /// Reads one field as a string.
+    fn string(&mut self) -> Option<String> {
+        let bytes = self.field(0xFF)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    /// Skips over a set of fields, such as certificate hashes, where the
+    /// high bit of each length says whether another one follows.
+    fn skip_set(&mut self) -> Option<()> {
+        loop {
+            let more = self.bytes.first()? & 0x80 != 0;
+            let _ = self.field(0x7F)?;
+            if ! more {
+                return Some(());
+            }
+        }
+    }
+
+    /// Reads one field, taking its length from the bits of its first byte
+    /// given by the mask.
+    fn field(&mut self, mask: u8) -> Option<&[u8]> {
+        let (&length, rest) = self.bytes.split_first()?;
+        let length = usize::from(length & mask);
+        let field = rest.get(.. length)?;
+        self.bytes = &rest[length ..];
+        Some(field)
+    }
+}
+
+
+impl fmt::Display for ServerListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IO(e)                  => write!(f, "{}", e),
+            Self::InvalidToml(e)         => write!(f, "invalid TOML: {}", e),
+            Self::MissingAddress(index)  => write!(f, "server number {} has no address", index),
+            Self::InvalidStamp(stamp)    => write!(f, "invalid stamp {:?}", stamp),
+            Self::Empty                  => write!(f, "no nameservers that can be queried"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server(name: &str, nameserver: &str) -> Server {
+        Server { name: name.into(), nameserver: nameserver.into() }
+    }
+
+    #[test]
+    fn plain_stamp() {
+        assert_eq!(parse_stamp("sdns://AAcAAAAAAAAABzEuMS4xLjE"),
+                   Ok(Some("1.1.1.1".into())));
+    }
+
+    #[test]
+    fn https_stamp() {
+        assert_eq!(parse_stamp("sdns://AgMAAAAAAAAABzkuOS45LjkADWRucy5xdWFkOS5uZXQKL2Rucy1xdWVyeQ"),
+                   Ok(Some("https://dns.quad9.net/dns-query".into())));
+    }
+
+    #[test]
+    fn tls_stamp() {
+        assert_eq!(parse_stamp("sdns://AwMAAAAAAAAABzkuOS45LjkAEWRucy5xdWFkOS5uZXQ6ODUz"),
+                   Ok(Some("tls://9.9.9.9#dns.quad9.net".into())));
+    }
+
+    #[test]
+    fn dnscrypt_stamp_skipped() {
+        assert_eq!(parse_stamp("sdns://AQMAAAAAAAAABzkuOS45Ljk"), Ok(None));
+    }
+
+    #[test]
+    fn invalid_stamps() {
+        assert_eq!(parse_stamp("sdns://!!!"), Err(ServerListError::InvalidStamp("sdns://!!!".into())));
+        assert_eq!(parse_stamp("sdns://AgMAAAAAAAAABzkuOS45"), Err(ServerListError::InvalidStamp("sdns://AgMAAAAAAAAABzkuOS45".into())));
+    }
+
+    #[test]
+    fn markdown() {
+        let contents = "\
+# public-resolvers
+
+## cloudflare
+
+Cloudflare DNS, over plain DNS.
+
+sdns://AAcAAAAAAAAABzEuMS4xLjE
+
+## quad9-dnscrypt
+
+sdns://AQMAAAAAAAAABzkuOS45Ljk
+
+## quad9-doh
+
+sdns://AgMAAAAAAAAABzkuOS45LjkADWRucy5xdWFkOS5uZXQKL2Rucy1xdWVyeQ
+";
+
+        assert_eq!(parse_markdown(contents), Ok(vec![
+            server("cloudflare", "1.1.1.1"),
+            server("quad9-doh", "https://dns.quad9.net/dns-query"),
+        ]));
+    }
+
+    #[test]
+    fn toml() {
+        let contents = r#"
+[[server]]
+name = "Cloudflare"
+address = "1.1.1.1"
+
+[[server]]
+address = "tls://9.9.9.9#dns.quad9.net"
+"#;
+
+        assert_eq!(parse_toml(contents), Ok(vec![
+            server("Cloudflare", "1.1.1.1"),
+            server("tls://9.9.9.9#dns.quad9.net", "tls://9.9.9.9#dns.quad9.net"),
+        ]));
+    }
+
+    #[test]
+    fn toml_without_address() {
+        assert_eq!(parse_toml("[[server]]\nname = \"Cloudflare\"\n"), Err(ServerListError::MissingAddress(1)));
+    }
+
+    #[test]
+    fn csv() {
+        let contents = "\
+name,address
+# the big ones
+Cloudflare,1.1.1.1
+Quad9, sdns://AgMAAAAAAAAABzkuOS45LjkADWRucy5xdWFkOS5uZXQKL2Rucy1xdWVyeQ
+
+8.8.8.8
+";
+
+        assert_eq!(parse_csv(contents), Ok(vec![
+            server("Cloudflare", "1.1.1.1"),
+            server("Quad9", "https://dns.quad9.net/dns-query"),
+            server("8.8.8.8", "8.8.8.8"),
+        ]));
+    }
+}
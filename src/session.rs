@@ -0,0 +1,307 @@
+//! Recording every message sent to and received from nameservers, so that
+//! the same run can be replayed later without any network access.
+//!
+//! A session is a directory with one JSON file per exchange, numbered in
+//! the order the responses arrived. When replaying, each request is matched
+//! to a recorded one by nameserver, transport, and bytes, ignoring the
+//! transaction ID, which is different every time. Matched exchanges are
+//! used up, so a request sent twice gets its two responses in order.
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use log::*;
+use serde_json::{json, Value as JsonValue};
+
+use dns::{Request, Response};
+use dns_transport::{Transport, Error, Details};
+
+use crate::connect::TransportType;
+use crate::resolve::Nameserver;
+
+
+/// Whether to record a session or replay one, and the directory it’s in.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Mode {
+
+    /// Send requests over the network as usual, and write each exchange to
+    /// a file in the directory, creating it if necessary.
+    Record(PathBuf),
+
+    /// Answer requests from the exchanges in the directory, without
+    /// sending anything over the network.
+    Replay(PathBuf),
+}
+
+/// One request sent to a nameserver, and the response that came back.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Exchange {
+
+    /// The nameserver the request was sent to.
+    pub nameserver: Nameserver,
+
+    /// The transport the request was sent with.
+    pub transport: TransportType,
+
+    /// Whether the response arrived over UDP.
+    pub over_udp: bool,
+
+    /// The request, as bytes.
+    pub request: Vec<u8>,
+
+    /// The response, as bytes.
+    pub response: Vec<u8>,
+}
+
+/// The session running for this process, if there is one.
+enum Session {
+    Record { directory: PathBuf, count: AtomicUsize },
+    Replay { exchanges: Mutex<Vec<Exchange>> },
+}
+
+/// The session gets started after the command-line options have been
+/// parsed, and every transport made after that goes through it.
+static SESSION: OnceLock<Session> = OnceLock::new();
+
+
+/// Starts recording to or replaying from a directory. For replays, every
+/// exchange in the directory gets read in now, so any problems with it are
+/// found before any requests are made.
+pub fn start(mode: &Mode) -> io::Result<()> {
+    let session = match mode {
+        Mode::Record(directory) => {
+            fs::create_dir_all(directory)?;
+            let count = fs::read_dir(directory)?.count();
+            Session::Record { directory: directory.clone(), count: AtomicUsize::new(count) }
+        }
+        Mode::Replay(directory) => {
+            Session::Replay { exchanges: Mutex::new(read(directory)?) }
+        }
+    };
+
+    if SESSION.set(session).is_err() {
+        warn!("A session has already been started");
+    }
+
+    Ok(())
+}
+
+/// Wraps a transport so it records or replays its exchanges, if a session
+/// has been started.
+pub fn wrap(transport: Box<dyn Transport>, nameserver: Nameserver, transport_type: TransportType) -> Box<dyn Transport> {
+    match SESSION.get() {
+        Some(session)  => Box::new(SessionTransport { inner: transport, session, nameserver, transport_type }),
+        None           => transport,
+    }
+}
+
+
+/// A transport that writes down what another transport sends and receives,
+/// or stands in for it entirely.
+struct SessionTransport {
+    inner: Box<dyn Transport>,
+    session: &'static Session,
+    nameserver: Nameserver,
+    transport_type: TransportType,
+}
+
+#[async_trait]
+impl Transport for SessionTransport {
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let sent = request.to_bytes().expect("failed to serialise request");
+
+        match self.session {
+            Session::Record { directory, count } => {
+                let (response, details) = self.inner.send_with_details(request).await?;
+
+                let exchange = Exchange {
+                    nameserver: self.nameserver.clone(),
+                    transport: self.transport_type,
+                    over_udp: details.over_udp,
+                    request: sent,
+                    response: details.message.clone(),
+                };
+
+                let path = directory.join(format!("{:04}.json", count.fetch_add(1, Ordering::SeqCst) + 1));
+                debug!("Recording exchange with {} to {}", self.nameserver, path.display());
+                fs::write(&path, exchange.to_json().to_string())?;
+
+                Ok((response, details))
+            }
+
+            Session::Replay { exchanges } => {
+                let exchange = take(&mut exchanges.lock().unwrap(), &self.nameserver, self.transport_type, &sent);
+                let Some(exchange) = exchange else {
+                    warn!("No recorded exchange with {} matches the request", self.nameserver);
+                    let message = format!("no recorded response from {} to this request", self.nameserver);
+                    return Err(Error::NetworkError(io::Error::new(io::ErrorKind::NotFound, message)));
+                };
+
+                // The response needs the new transaction ID to look like
+                // the answer to this request
+                let mut message = exchange.response;
+                if message.len() >= 2 && sent.len() >= 2 {
+                    message[.. 2].copy_from_slice(&sent[.. 2]);
+                }
+
+                let response = Response::from_bytes(&message)?;
+                Ok((response, Details { size: message.len(), over_udp: exchange.over_udp, http: None, message, round_trip: None, address: None, ip_ttl: None }))
+            }
+        }
+    }
+}
+
+
+/// Removes and returns the first exchange that was with the same
+/// nameserver, over the same transport, with the same request, apart from
+/// its transaction ID.
+fn take(exchanges: &mut Vec<Exchange>, nameserver: &str, transport: TransportType, request: &[u8]) -> Option<Exchange> {
+    let index = exchanges.iter().position(|exchange| {
+        exchange.nameserver == nameserver
+            && exchange.transport == transport
+            && exchange.request.get(2 ..) == request.get(2 ..)
+    })?;
+
+    Some(exchanges.remove(index))
+}
+
+/// Reads every exchange in a session directory, in the order of their
+/// file names.
+fn read(directory: &Path) -> io::Result<Vec<Exchange>> {
+    let mut paths = fs::read_dir(directory)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    paths.iter().map(|path| {
+        let json = serde_json::from_slice(&fs::read(path)?)?;
+        Exchange::from_json(&json).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a recorded exchange", path.display()))
+        })
+    }).collect()
+}
+
+
+impl Exchange {
+
+    /// Writes the exchange as JSON, with the messages in hex.
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "nameserver": self.nameserver,
+            "transport": format!("{:?}", self.transport).to_lowercase(),
+            "over_udp": self.over_udp,
+            "request": hex(&self.request),
+            "response": hex(&self.response),
+        })
+    }
+
+    /// Reads an exchange back from JSON, returning `None` if any of its
+    /// fields are missing or invalid.
+    fn from_json(json: &JsonValue) -> Option<Self> {
+        let transport = match json["transport"].as_str()? {
+            "automatic"  => TransportType::Automatic,
+            "udp"        => TransportType::UDP,
+            "tcp"        => TransportType::TCP,
+            "tls"        => TransportType::TLS,
+            "https"      => TransportType::HTTPS,
+            "local"      => TransportType::Local,
+            _            => return None,
+        };
+
+        Some(Self {
+            nameserver: json["nameserver"].as_str()?.into(),
+            transport,
+            over_udp: json["over_udp"].as_bool()?,
+            request: unhex(json["request"].as_str()?)?,
+            response: unhex(json["response"].as_str()?)?,
+        })
+    }
+}
+
+/// Writes bytes as a string of lowercase hexadecimal digits.
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Reads bytes back from a string of hexadecimal digits.
+fn unhex(input: &str) -> Option<Vec<u8>> {
+    if ! input.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0 .. input.len()).step_by(2)
+        .map(|i| input.get(i .. i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exchange(txid: u8, last: u8) -> Exchange {
+        Exchange {
+            nameserver: "192.0.2.53".into(),
+            transport: TransportType::UDP,
+            over_udp: true,
+            request: vec![ 0x12, txid, 0x01, 0x00, last ],
+            response: vec![ 0x12, txid, 0x81, 0x80, last ],
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let exchange = exchange(0x34, 0x01);
+        assert_eq!(Exchange::from_json(&exchange.to_json()), Some(exchange));
+    }
+
+    #[test]
+    fn invalid_json() {
+        let mut json = exchange(0x34, 0x01).to_json();
+        json["request"] = json!("12x4");
+        assert_eq!(Exchange::from_json(&json), None);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(hex(&[ 0x00, 0xAB, 0x7F ]), "00ab7f");
+        assert_eq!(unhex("00ab7f"), Some(vec![ 0x00, 0xAB, 0x7F ]));
+        assert_eq!(unhex("00a"), None);
+    }
+
+    #[test]
+    fn matches_ignoring_transaction_id() {
+        let mut exchanges = vec![ exchange(0x34, 0x01), exchange(0x35, 0x02) ];
+        let found = take(&mut exchanges, "192.0.2.53", TransportType::UDP, &[ 0xAB, 0xCD, 0x01, 0x00, 0x02 ]);
+        assert_eq!(found, Some(exchange(0x35, 0x02)));
+        assert_eq!(exchanges, vec![ exchange(0x34, 0x01) ]);
+    }
+
+    #[test]
+    fn used_up_in_order() {
+        let mut exchanges = vec![ exchange(0x34, 0x01), exchange(0x35, 0x01) ];
+        let request = [ 0xAB, 0xCD, 0x01, 0x00, 0x01 ];
+        assert_eq!(take(&mut exchanges, "192.0.2.53", TransportType::UDP, &request), Some(exchange(0x34, 0x01)));
+        assert_eq!(take(&mut exchanges, "192.0.2.53", TransportType::UDP, &request), Some(exchange(0x35, 0x01)));
+        assert_eq!(take(&mut exchanges, "192.0.2.53", TransportType::UDP, &request), None);
+    }
+
+    #[test]
+    fn different_nameserver_or_transport() {
+        let mut exchanges = vec![ exchange(0x34, 0x01) ];
+        let request = [ 0xAB, 0xCD, 0x01, 0x00, 0x01 ];
+        assert_eq!(take(&mut exchanges, "192.0.2.54", TransportType::UDP, &request), None);
+        assert_eq!(take(&mut exchanges, "192.0.2.53", TransportType::TCP, &request), None);
+    }
+}
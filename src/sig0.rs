@@ -0,0 +1,263 @@
+//! Signing requests with SIG(0) transaction signatures.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::*;
+use ring::signature::Ed25519KeyPair;
+
+use dns::record::SIG;
+
+
+/// A **SIG(0) key** is the private half of a key pair that gets used to sign
+/// every request, so that a server holding the public half in a KEY record
+/// can authenticate them.
+///
+/// Keys are read from the `.private` files produced by BIND’s
+/// `dnssec-keygen`, which are named after the key’s owner, algorithm, and key
+/// tag, such as `Kdog.example.+015+12345.private`. Only Ed25519 keys are
+/// currently supported.
+#[derive(PartialEq)]
+pub struct Sig0Key {
+
+    /// The domain name of the key’s owner.
+    signer_name: String,
+
+    /// The key tag, as given in the file name.
+    key_tag: u16,
+
+    /// The 32-byte Ed25519 private key seed.
+    seed: Vec<u8>,
+}
+
+/// The algorithm number for Ed25519 (RFC 8080).
+const ED25519: u8 = 15;
+
+/// How far either side of the current time a signature should be valid for,
+/// to allow for clock skew between us and the server.
+const VALIDITY_SECONDS: u32 = 5 * 60;
+
+
+impl Sig0Key {
+
+    /// Reads a private key from the given path, which should be the `.private`
+    /// file for the key (or the matching `.key` file next to it).
+    pub fn load(path: &Path) -> Result<Self, Sig0KeyError> {
+        let path = if path.extension() == Some(OsStr::new("key")) {
+                path.with_extension("private")
+            }
+            else {
+                path.to_path_buf()
+            };
+
+        let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+        let (signer_name, algorithm, key_tag) = parse_file_name(file_name).ok_or(Sig0KeyError::FileName)?;
+        debug!("Loading SIG(0) key for {:?} (algorithm {}, key tag {})", signer_name, algorithm, key_tag);
+
+        if algorithm != ED25519 {
+            return Err(Sig0KeyError::UnsupportedAlgorithm(algorithm));
+        }
+
+        let contents = fs::read_to_string(&path).map_err(|e| Sig0KeyError::IO(e.to_string()))?;
+        let seed = parse_private_key(&contents)?;
+        Ok(Self { signer_name, key_tag, seed })
+    }
+
+    /// Signs the given request, adding a SIG(0) record to it. This has to be
+    /// done last, after every other part of the request has been filled in.
+    pub fn sign(&self, request: &mut dns::Request) {
+        let now = unix_time();
+
+        let mut sig = SIG {
+            type_covered: 0,
+            algorithm: ED25519,
+            labels: 0,
+            original_ttl: 0,
+            signature_expiration: now.wrapping_add(VALIDITY_SECONDS),
+            signature_inception: now.wrapping_sub(VALIDITY_SECONDS),
+            key_tag: self.key_tag,
+            signer_name: self.signer_name.clone(),
+            signature: Vec::new(),
+        };
+
+        let data = request.sig0_signed_data(&sig).expect("failed to serialise request");
+
+        // The seed was checked when the key was loaded, so this should never fail
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&self.seed).expect("invalid Ed25519 seed");
+        sig.signature = key_pair.sign(&data).as_ref().to_vec();
+
+        info!("Signing request {:#06x} with SIG(0) key {}", request.transaction_id, self.key_tag);
+        request.signature = Some(sig);
+    }
+}
+
+impl fmt::Debug for Sig0Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the private key out of the debug logs
+        f.debug_struct("Sig0Key")
+         .field("signer_name", &self.signer_name)
+         .field("key_tag", &self.key_tag)
+         .finish_non_exhaustive()
+    }
+}
+
+
+/// Parses a BIND key file name, in the form `K<name>+<alg>+<tag>.private`,
+/// into its name, algorithm number, and key tag.
+fn parse_file_name(file_name: &str) -> Option<(String, u8, u16)> {
+    let stem = file_name.strip_prefix('K')?;
+    let stem = stem.strip_suffix(".private").unwrap_or(stem);
+
+    let mut parts = stem.rsplitn(3, '+');
+    let key_tag = parts.next()?.parse().ok()?;
+    let algorithm = parts.next()?.parse().ok()?;
+    let signer_name = parts.next().filter(|n| ! n.is_empty())?;
+
+    Some((signer_name.into(), algorithm, key_tag))
+}
+
+/// Parses the contents of a BIND `.private` file, returning the private key
+/// seed from its `PrivateKey` field.
+fn parse_private_key(contents: &str) -> Result<Vec<u8>, Sig0KeyError> {
+    for line in contents.lines() {
+        if let Some(value) = line.strip_prefix("Algorithm:") {
+            let algorithm = value.split_whitespace().next().and_then(|a| a.parse().ok());
+            if algorithm != Some(ED25519) {
+                return Err(Sig0KeyError::UnsupportedAlgorithm(algorithm.unwrap_or_default()));
+            }
+        }
+        else if let Some(value) = line.strip_prefix("PrivateKey:") {
+            let seed = base64::decode(value.trim()).map_err(|_| Sig0KeyError::InvalidPrivateKey)?;
+            if Ed25519KeyPair::from_seed_unchecked(&seed).is_err() {
+                return Err(Sig0KeyError::InvalidPrivateKey);
+            }
+
+            return Ok(seed);
+        }
+    }
+
+    Err(Sig0KeyError::MissingPrivateKey)
+}
+
+/// Returns the current time as a 32-bit number of seconds since the Unix
+/// epoch. SIG records compare times using serial number arithmetic, so the
+/// number is allowed to wrap around.
+#[allow(clippy::cast_possible_truncation)]
+fn unix_time() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+                     .map_or(0, |d| d.as_secs() as u32)
+}
+
+
+/// Something wrong with a SIG(0) key file.
+#[derive(PartialEq, Debug)]
+pub enum Sig0KeyError {
+
+    /// The file name was not in the form BIND uses for keys, so we don’t
+    /// know the signer name or key tag.
+    FileName,
+
+    /// There was an error reading the file.
+    IO(String),
+
+    /// The key uses an algorithm we can’t sign with.
+    UnsupportedAlgorithm(u8),
+
+    /// The file had no `PrivateKey` field.
+    MissingPrivateKey,
+
+    /// The `PrivateKey` field was not a valid key.
+    InvalidPrivateKey,
+}
+
+impl fmt::Display for Sig0KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileName                   => write!(f, "file name should be in the form K<name>+<alg>+<tag>.private"),
+            Self::IO(e)                      => write!(f, "{}", e),
+            Self::UnsupportedAlgorithm(alg)  => write!(f, "algorithm {} is not supported (only Ed25519 is)", alg),
+            Self::MissingPrivateKey          => write!(f, "no private key found"),
+            Self::InvalidPrivateKey          => write!(f, "private key is invalid"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ring::signature::{KeyPair, UnparsedPublicKey, ED25519 as ED25519_VERIFY};
+
+    const SEED: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+    #[test]
+    fn file_names() {
+        assert_eq!(parse_file_name("Kdog.example.+015+12345.private"),
+                   Some((String::from("dog.example."), 15, 12345)));
+
+        assert_eq!(parse_file_name("Kdog.example.+015+12345"),
+                   Some((String::from("dog.example."), 15, 12345)));
+    }
+
+    #[test]
+    fn bad_file_names() {
+        assert_eq!(parse_file_name("dog.example.+015+12345.private"), None);
+        assert_eq!(parse_file_name("Kdog.example.+015.private"),      None);
+        assert_eq!(parse_file_name("K+015+12345.private"),            None);
+        assert_eq!(parse_file_name("Kdog.example.+015+99999.private"), None);
+    }
+
+    #[test]
+    fn private_key() {
+        let contents = format!("Private-key-format: v1.3\nAlgorithm: 15 (ED25519)\nPrivateKey: {}\n", SEED);
+        assert_eq!(parse_private_key(&contents), Ok((0 .. 32).collect()));
+    }
+
+    #[test]
+    fn wrong_algorithm() {
+        let contents = "Private-key-format: v1.3\nAlgorithm: 13 (ECDSAP256SHA256)\nPrivateKey: AAAA\n";
+        assert_eq!(parse_private_key(contents), Err(Sig0KeyError::UnsupportedAlgorithm(13)));
+    }
+
+    #[test]
+    fn missing_private_key() {
+        let contents = "Private-key-format: v1.3\nAlgorithm: 15 (ED25519)\n";
+        assert_eq!(parse_private_key(contents), Err(Sig0KeyError::MissingPrivateKey));
+    }
+
+    #[test]
+    fn invalid_private_key() {
+        let contents = "Private-key-format: v1.3\nAlgorithm: 15 (ED25519)\nPrivateKey: AAAA\n";
+        assert_eq!(parse_private_key(contents), Err(Sig0KeyError::InvalidPrivateKey));
+    }
+
+    #[test]
+    fn signature_verifies() {
+        let key = Sig0Key {
+            signer_name: String::from("dog.example."),
+            key_tag: 12345,
+            seed: (0 .. 32).collect(),
+        };
+
+        let mut request = dns::Request {
+            transaction_id: 0xABCD,
+            flags: dns::Flags::query(),
+            queries: vec![ dns::Query { qname: "lookup.dog".into(), qclass: dns::QClass::IN, qtype: 1 } ],
+            additional: None,
+            signature: None,
+        };
+
+        key.sign(&mut request);
+        let sig = request.signature.clone().unwrap();
+        assert_eq!(sig.key_tag, 12345);
+        assert_eq!(sig.signer_name, "dog.example.");
+
+        let data = request.sig0_signed_data(&sig).unwrap();
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&key.seed).unwrap();
+        let public_key = UnparsedPublicKey::new(&ED25519_VERIFY, key_pair.public_key().as_ref());
+        assert!(public_key.verify(&data, &sig.signature).is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+//! Detecting answers that point at sinkholes rather than real hosts.
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use dns::record::Record;
+
+
+/// A **sinkhole** is an address that a filtering resolver hands out instead
+/// of the real one, so that connections to a blocked domain go nowhere (or
+/// somewhere that explains why it was blocked). Seeing one of these in an
+/// answer means the name is being filtered, not that it genuinely resolves
+/// there.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Sinkhole {
+
+    /// The unspecified address, `0.0.0.0` or `::`, which is what most
+    /// ad-blocking and malware-filtering resolvers return.
+    Unspecified,
+
+    /// A loopback address, such as `127.0.0.1` or `::1`.
+    Loopback,
+
+    /// One of the addresses of OpenDNS’s block pages, which it hands out
+    /// for blocked names instead of the unspecified address.
+    OpenDnsBlockPage,
+}
+
+impl Sinkhole {
+
+    /// Checks whether the given record is an address record pointing at a
+    /// known sinkhole, returning which kind if so.
+    pub fn detect(record: &Record) -> Option<Self> {
+        match record {
+            Record::A(a)        => Self::detect_ipv4(a.address),
+            Record::AAAA(aaaa)  => Self::detect_ipv6(aaaa.address),
+            _                   => None,
+        }
+    }
+
+    fn detect_ipv4(address: Ipv4Addr) -> Option<Self> {
+        if address.is_unspecified() {
+            Some(Self::Unspecified)
+        }
+        else if address.is_loopback() {
+            Some(Self::Loopback)
+        }
+        else if OPENDNS_BLOCK_PAGES.0 <= address && address <= OPENDNS_BLOCK_PAGES.1 {
+            Some(Self::OpenDnsBlockPage)
+        }
+        else {
+            None
+        }
+    }
+
+    fn detect_ipv6(address: Ipv6Addr) -> Option<Self> {
+        if address.is_unspecified() {
+            Some(Self::Unspecified)
+        }
+        else if address.is_loopback() {
+            Some(Self::Loopback)
+        }
+        else if let Some(ipv4) = address.to_ipv4_mapped() {
+            Self::detect_ipv4(ipv4)
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Sinkhole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unspecified         => write!(f, "unspecified address"),
+            Self::Loopback            => write!(f, "loopback address"),
+            Self::OpenDnsBlockPage    => write!(f, "OpenDNS block page"),
+        }
+    }
+}
+
+
+/// The first and last addresses of OpenDNS’s block pages, one for each
+/// reason a name can be blocked, as listed in the Cisco Umbrella
+/// documentation under “Umbrella Block Page IP Addresses”.
+///
+/// The other common filtering resolvers don’t have block pages to look for.
+/// Cloudflare for Families answers blocked names with `0.0.0.0` and `::`,
+/// which count as unspecified addresses, and Quad9 answers with `NXDOMAIN`,
+/// which has no address to detect.
+static OPENDNS_BLOCK_PAGES: (Ipv4Addr, Ipv4Addr) = (Ipv4Addr::new(146, 112, 61, 104), Ipv4Addr::new(146, 112, 61, 110));
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::{A, AAAA, CNAME};
+
+    fn a(address: [u8; 4]) -> Record {
+        Record::A(A { address: Ipv4Addr::from(address) })
+    }
+
+    fn aaaa(address: &str) -> Record {
+        Record::AAAA(AAAA { address: address.parse().unwrap() })
+    }
+
+    #[test]
+    fn unspecified() {
+        assert_eq!(Sinkhole::detect(&a([0, 0, 0, 0])),  Some(Sinkhole::Unspecified));
+        assert_eq!(Sinkhole::detect(&aaaa("::")),       Some(Sinkhole::Unspecified));
+    }
+
+    #[test]
+    fn loopback() {
+        assert_eq!(Sinkhole::detect(&a([127, 0, 0, 1])),   Some(Sinkhole::Loopback));
+        assert_eq!(Sinkhole::detect(&a([127, 0, 53, 53])), Some(Sinkhole::Loopback));
+        assert_eq!(Sinkhole::detect(&aaaa("::1")),         Some(Sinkhole::Loopback));
+    }
+
+    #[test]
+    fn opendns_block_page() {
+        assert_eq!(Sinkhole::detect(&a([146, 112, 61, 106])),        Some(Sinkhole::OpenDnsBlockPage));
+        assert_eq!(Sinkhole::detect(&aaaa("::ffff:146.112.61.104")), Some(Sinkhole::OpenDnsBlockPage));
+        assert_eq!(Sinkhole::detect(&a([146, 112, 61, 110])),        Some(Sinkhole::OpenDnsBlockPage));
+    }
+
+    #[test]
+    fn genuine_addresses() {
+        assert_eq!(Sinkhole::detect(&a([146, 112, 61, 111])),  None);
+        assert_eq!(Sinkhole::detect(&a([93, 184, 216, 34])),   None);
+        assert_eq!(Sinkhole::detect(&aaaa("2606:2800:220:1::")), None);
+    }
+
+    #[test]
+    fn other_record_types() {
+        let cname = Record::CNAME(CNAME { domain: "localhost.".into() });
+        assert_eq!(Sinkhole::detect(&cname), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! Suggesting what the user might have meant when they typo something.
+
+
+/// Returns the candidates closest to the given input, for suggesting valid
+/// alternatives when the input isn’t recognised. Case is ignored, and
+/// candidates have to be within a few edits of the input to count, so that
+/// nonsense input doesn’t get nonsense suggestions.
+pub fn closest<'a>(input: &str, candidates: impl IntoIterator<Item=&'a str>) -> Vec<&'a str> {
+    let input = input.to_lowercase();
+    let threshold = (input.chars().count() / 3).max(1);
+
+    let mut matches = candidates.into_iter()
+        .map(|candidate| (edit_distance(&input, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .collect::<Vec<_>>();
+
+    matches.sort_by_key(|&(distance, _)| distance);
+    matches.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+}
+
+/// Formats a list of suggestions as a question to put after an error
+/// message, or returns an empty string if there are none.
+pub fn did_you_mean(suggestions: &[impl AsRef<str>]) -> String {
+    match suggestions {
+        []                 => String::new(),
+        [one]              => format!(" (did you mean {}?)", one.as_ref()),
+        [rest @ .., last]  => {
+            let rest = rest.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+            format!(" (did you mean {} or {}?)", rest.join(", "), last.as_ref())
+        }
+    }
+}
+
+/// Counts how many single-character insertions, deletions, substitutions,
+/// or transpositions of adjacent characters it takes to turn one string
+/// into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // distances[i][j] is the distance between the first i characters of a
+    // and the first j characters of b
+    let mut distances = vec![ vec![0; b.len() + 1]; a.len() + 1 ];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distances[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1 ..= a.len() {
+        for j in 1 ..= b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("aaaa", "aaaa"), 0);
+        assert_eq!(edit_distance("aaa", "aaaa"),  1);
+        assert_eq!(edit_distance("mx", "txt"),    2);
+        assert_eq!(edit_distance("tpye", "type"), 1);
+        assert_eq!(edit_distance("", "soa"),      3);
+    }
+
+    #[test]
+    fn closest_type() {
+        assert_eq!(closest("AAA", vec![ "A", "AAAA", "CAA", "MX" ]), vec![ "AAAA", "CAA" ]);
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert_eq!(closest("cnmae", vec![ "CNAME", "MX" ]), vec![ "CNAME" ]);
+    }
+
+    #[test]
+    fn nothing_close() {
+        assert_eq!(closest("tubes", vec![ "A", "AAAA", "MX" ]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn questions() {
+        assert_eq!(did_you_mean(&Vec::<&str>::new()),      "");
+        assert_eq!(did_you_mean(&[ "AAAA" ]),               " (did you mean AAAA?)");
+        assert_eq!(did_you_mean(&[ "AAAA", "CAA", "A" ]),   " (did you mean AAAA, CAA or A?)");
+    }
+}
@@ -1,14 +1,15 @@
 //! Tables of DNS response results.
 
-use std::time::Duration;
-
-use ansi_term::ANSIString;
+use ansi_term::{ANSIString, Style};
 
 use dns::Answer;
 use dns::record::Record;
 
+use crate::annotations::annotate;
 use crate::colours::Colours;
+use crate::explain::{explain, Field};
 use crate::output::TextFormat;
+use crate::sinkhole::Sinkhole;
 
 
 /// A **table** is built up from all the response records present in a DNS
@@ -17,6 +18,7 @@ use crate::output::TextFormat;
 pub struct Table {
     colours: Colours,
     text_format: TextFormat,
+    columns: Vec<Column>,
     rows: Vec<Row>,
 }
 
@@ -25,9 +27,51 @@ pub struct Table {
 pub struct Row {
     qtype: ANSIString<'static>,
     qname: String,
+    qclass: Option<String>,
     ttl: Option<String>,
     section: Section,
     summary: String,
+    notes: Vec<Note>,
+    explanation: Vec<Field>,
+}
+
+/// A note that follows the record data, kept apart from the style it gets
+/// painted in so its length can be measured before it is.
+#[derive(Debug)]
+struct Note {
+    text: String,
+    style: Style,
+}
+
+/// A column of the table, which the user can pick and choose between.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Column {
+
+    /// The record type.
+    Type,
+
+    /// The domain name the record is for.
+    Name,
+
+    /// The record’s class.
+    Class,
+
+    /// The record’s time-to-live.
+    TTL,
+
+    /// A symbol indicating which section the record was found in.
+    Section,
+
+    /// The record’s data.
+    Data,
+}
+
+impl Column {
+
+    /// The columns that get displayed when the user has not picked any.
+    pub fn defaults() -> Vec<Self> {
+        vec![ Self::Type, Self::Name, Self::TTL, Self::Section, Self::Data ]
+    }
 }
 
 /// The section of the DNS response that a record was read from.
@@ -45,71 +89,109 @@ pub enum Section {
 }
 
 
+/// The narrowest that record data gets truncated to, no matter how little
+/// room there is left in the terminal.
+const MIN_DATA_WIDTH: usize = 16;
+
+
 impl Table {
 
-    /// Create a new table with no rows.
-    pub fn new(colours: Colours, text_format: TextFormat) -> Self {
-        Self { colours, text_format, rows: Vec::new() }
+    /// Create a new table with no rows, that displays the given columns.
+    pub fn new(colours: Colours, text_format: TextFormat, columns: Vec<Column>) -> Self {
+        Self { colours, text_format, columns, rows: Vec::new() }
     }
 
     /// Adds a row to the table, containing the data in the given answer in
     /// the right section.
     pub fn add_row(&mut self, answer: Answer, section: Section) {
         match answer {
-            Answer::Standard { record, qname, ttl, .. } => {
+            Answer::Standard { record, qname, qclass, ttl } => {
                 let qtype = self.coloured_record_type(&record);
                 let summary = self.text_format.record_payload_summary(&record);
+                let mut notes = Vec::new();
+                if let Some(sinkhole) = Sinkhole::detect(&record).filter(|_| ! self.text_format.zone_format) {
+                    notes.push(Note { text: format!("(sinkhole: {})", sinkhole), style: self.colours.sinkhole });
+                }
+
+                if let Some(note) = annotate(&qname, &record).filter(|_| self.text_format.annotate) {
+                    notes.push(Note { text: format!("; {}", note), style: self.colours.annotation });
+                }
+
+                let explanation = if self.text_format.explain { explain(&record, self.text_format) } else { Vec::new() };
+                let qname = self.text_format.display_name(&qname);
+                let qclass = Some(format!("{:?}", qclass));
                 let ttl = Some(self.text_format.format_duration(ttl));
-                self.rows.push(Row { qtype, qname, ttl, summary, section });
+                self.rows.push(Row { qtype, qname, qclass, ttl, section, summary, notes, explanation });
             }
             Answer::Pseudo { qname, opt } => {
+                let qname = self.text_format.display_name(&qname);
                 let qtype = self.colours.opt.paint("OPT");
                 let summary = self.text_format.pseudo_record_payload_summary(&opt);
-                self.rows.push(Row { qtype, qname, ttl: None, summary, section });
+                self.rows.push(Row { qtype, qname, qclass: None, ttl: None, summary, notes: Vec::new(), section, explanation: Vec::new() });
             }
         }
     }
 
     /// Prints the formatted table to stdout.
-    pub fn print(self, duration: Option<Duration>) {
+    pub fn print(self) {
         if self.rows.is_empty() {
             println!("No results");
         }
         else {
-            let qtype_len = self.max_qtype_len();
-            let qname_len = self.max_qname_len();
-            let ttl_len   = self.max_ttl_len();
+            let widths = self.columns.iter().map(|c| self.max_len(*c)).collect::<Vec<_>>();
+            let data_width = self.available_data_width(&widths);
 
             for r in &self.rows {
-                for _ in 0 .. qtype_len - r.qtype.len() {
-                    print!(" ");
-                }
-
-                print!("{} {} ", r.qtype, self.colours.qname.paint(&r.qname));
-
-                for _ in 0 .. qname_len - r.qname.len() {
-                    print!(" ");
-                }
-
-                if let Some(ttl) = &r.ttl {
-                    for _ in 0 .. ttl_len - ttl.len() {
+                for (index, (column, width)) in self.columns.iter().zip(&widths).enumerate() {
+                    let is_last = index + 1 == self.columns.len();
+                    if index > 0 {
                         print!(" ");
                     }
 
-                    print!("{}", ttl);
-                }
-                else {
-                    for _ in 0 .. ttl_len {
-                        print!(" ");
+                    let cell_len = r.cell_len(*column);
+                    match column {
+                        Column::Type => {
+                            print!("{}{}", " ".repeat(width - cell_len), r.qtype);
+                        }
+                        Column::Name => {
+                            print!("{}", self.colours.qname.paint(&r.qname));
+                        }
+                        Column::Class => {
+                            print!("{}", r.qclass.as_deref().unwrap_or_default());
+                        }
+                        Column::TTL => {
+                            print!("{}{}", " ".repeat(width - cell_len), self.colours.ttl.paint(r.ttl.as_deref().unwrap_or_default()));
+                        }
+                        Column::Section => {
+                            print!("{}", self.format_section(r.section));
+                        }
+                        Column::Data => {
+                            print!("{}", r.data(data_width));
+                        }
+                    }
+
+                    if ! is_last && matches!(column, Column::Name | Column::Class | Column::Data) {
+                        print!("{}", " ".repeat(width - cell_len));
                     }
                 }
 
-                println!(" {} {}", self.format_section(r.section), r.summary);
+                println!();
+                self.print_explanation(r, &widths);
             }
         }
+    }
 
-        if let Some(dur) = duration {
-            println!("Ran in {}ms", dur.as_millis());
+    /// Prints each labelled field of a row’s record on its own line, lined
+    /// up underneath the record data.
+    fn print_explanation(&self, row: &Row, widths: &[usize]) {
+        let indent = match self.columns.iter().position(|c| *c == Column::Data) {
+            Some(index)  => widths[.. index].iter().map(|w| w + 1).sum(),
+            None         => 2,
+        };
+
+        for field in &row.explanation {
+            let meaning = self.colours.annotation.paint(format!("; {}", field.meaning));
+            println!("{}{}: {} {}", " ".repeat(indent), field.label, field.value, meaning);
         }
     }
 
@@ -122,6 +204,8 @@ impl Table {
             Record::MX(_)     => self.colours.mx.paint("MX"),
             Record::NS(_)     => self.colours.ns.paint("NS"),
             Record::PTR(_)    => self.colours.ptr.paint("PTR"),
+            Record::RRSIG(_)  => self.colours.rrsig.paint("RRSIG"),
+            Record::SIG(_)    => self.colours.sig.paint("SIG"),
             Record::SOA(_)    => self.colours.soa.paint("SOA"),
             Record::SRV(_)    => self.colours.srv.paint("SRV"),
             Record::TXT(_)    => self.colours.txt.paint("TXT"),
@@ -130,16 +214,21 @@ impl Table {
         }
     }
 
-    fn max_qtype_len(&self) -> usize {
-        self.rows.iter().map(|r| r.qtype.len()).max().unwrap()
-    }
+    /// Works out how many characters the record data can take up without
+    /// any lines overflowing the terminal, if the data should be truncated
+    /// at all. Only data in the last column gets truncated.
+    fn available_data_width(&self, widths: &[usize]) -> Option<usize> {
+        if ! self.text_format.truncate_data || self.columns.last() != Some(&Column::Data) {
+            return None;
+        }
 
-    fn max_qname_len(&self) -> usize {
-        self.rows.iter().map(|r| r.qname.len()).max().unwrap()
+        let (terminal_width, _) = term_size::dimensions_stdout()?;
+        let others_width = widths.iter().rev().skip(1).map(|w| w + 1).sum::<usize>();
+        Some(terminal_width.saturating_sub(others_width).max(MIN_DATA_WIDTH))
     }
 
-    fn max_ttl_len(&self) -> usize {
-        self.rows.iter().map(|r| r.ttl.as_ref().map_or(0, String::len)).max().unwrap()
+    fn max_len(&self, column: Column) -> usize {
+        self.rows.iter().map(|r| r.cell_len(column)).max().unwrap()
     }
 
     fn format_section(&self, section: Section) -> ANSIString<'static> {
@@ -150,3 +239,86 @@ impl Table {
         }
     }
 }
+
+
+impl Row {
+
+    /// The number of characters the given column takes up in this row,
+    /// ignoring any colours.
+    fn cell_len(&self, column: Column) -> usize {
+        match column {
+            Column::Type     => self.qtype.len(),
+            Column::Name     => self.qname.chars().count(),
+            Column::Class    => self.qclass.as_ref().map_or(0, String::len),
+            Column::TTL      => self.ttl.as_ref().map_or(0, String::len),
+            Column::Section  => 1,
+            Column::Data     => self.summary.chars().count() + self.notes.iter().map(|n| n.text.chars().count() + 1).sum::<usize>(),
+        }
+    }
+
+    /// The record data followed by its notes, cut short with an ellipsis
+    /// if it would take up more than the given width. The plain text gets
+    /// cut first, and only then painted, so an escape code never gets cut
+    /// in half or counted towards the width.
+    fn data(&self, width: Option<usize>) -> String {
+        let overflows = width.filter(|w| self.cell_len(Column::Data) > *w);
+        let mut room = overflows.map_or(usize::MAX, |w| w - 1);
+
+        let mut data = take(&self.summary, Style::default(), &mut room);
+        for note in &self.notes {
+            data.push_str(&take(" ", Style::default(), &mut room));
+            data.push_str(&take(&note.text, note.style, &mut room));
+        }
+
+        if overflows.is_some() {
+            data.push('…');
+        }
+
+        data
+    }
+}
+
+/// Paints as much of the text as there is room left for, and takes its
+/// length away from the room.
+fn take(text: &str, style: Style, room: &mut usize) -> String {
+    let taken = text.chars().take(*room).collect::<String>();
+    *room -= taken.chars().count();
+
+    if taken.is_empty() {
+        taken
+    }
+    else {
+        style.paint(taken).to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ansi_term::Colour::Red;
+
+    fn row(summary: &str, notes: Vec<Note>) -> Row {
+        let qtype = Style::default().paint("A");
+        Row { qtype, qname: "dog.".into(), qclass: None, ttl: None, section: Section::Answer, summary: summary.into(), notes, explanation: Vec::new() }
+    }
+
+    #[test]
+    fn painted_note_fits() {
+        let row = row("0.0.0.0", vec![ Note { text: "(sinkhole: null address)".into(), style: Red.bold() } ]);
+        assert_eq!(row.cell_len(Column::Data), 32);
+        assert_eq!(row.data(Some(40)), format!("0.0.0.0 {}", Red.bold().paint("(sinkhole: null address)")));
+    }
+
+    #[test]
+    fn painted_note_overflows() {
+        let row = row("0.0.0.0", vec![ Note { text: "(sinkhole: null address)".into(), style: Red.bold() } ]);
+        assert_eq!(row.data(Some(20)), format!("0.0.0.0 {}…", Red.bold().paint("(sinkhole: ")));
+    }
+
+    #[test]
+    fn summary_overflows_before_note() {
+        let row = row("a long text record", vec![ Note { text: "; note".into(), style: Red.bold() } ]);
+        assert_eq!(row.data(Some(8)), "a long …");
+    }
+}
@@ -0,0 +1,277 @@
+//! Writing every request sent to a nameserver, and whatever came back, to a
+//! database, so long measurement runs can be analysed with SQL.
+//!
+//! Each run of dog adds a row to the `runs` table, with the arguments it was
+//! run with, and each exchange during it adds a row to the `exchanges`
+//! table, with the fields of the query and response picked out, along with
+//! both messages as they were on the wire. Databases get added to, rather
+//! than replaced, so one can hold a whole campaign of runs.
+
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use log::*;
+use rusqlite::{Connection, params};
+
+use dns::{Request, Response};
+use dns_transport::{Transport, Error, Details};
+
+use crate::connect::TransportType;
+use crate::output::{rcode_name, erroneous_phase, error_message};
+use crate::resolve::Nameserver;
+use crate::zone;
+
+
+/// The tables that get created in a new database.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id           INTEGER PRIMARY KEY,
+        started_at   REAL NOT NULL,
+        arguments    TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS exchanges (
+        id              INTEGER PRIMARY KEY,
+        run_id          INTEGER NOT NULL REFERENCES runs (id),
+        sent_at         REAL NOT NULL,
+        nameserver      TEXT NOT NULL,
+        transport       TEXT NOT NULL,
+        qname           TEXT,
+        qtype           TEXT,
+        qclass          TEXT,
+        transaction_id  INTEGER NOT NULL,
+        duration        REAL NOT NULL,
+        over_udp        INTEGER,
+        status          TEXT,
+        answers         INTEGER,
+        authorities     INTEGER,
+        additionals     INTEGER,
+        error_phase     TEXT,
+        error_message   TEXT,
+        request         BLOB NOT NULL,
+        response        BLOB
+    );
+";
+
+/// The database being written to for this process, if there is one.
+struct Log {
+    connection: Mutex<Connection>,
+    run_id: i64,
+}
+
+/// The log gets opened after the command-line options have been parsed, and
+/// every transport made after that writes to it.
+static LOG: OnceLock<Log> = OnceLock::new();
+
+
+/// Opens the database, creating it and its tables if necessary, and adds a
+/// row for this run.
+pub fn start(path: &Path, args: &[String]) -> rusqlite::Result<()> {
+    let connection = Connection::open(path)?;
+
+    // Floods send thousands of queries, and waiting for each row to be
+    // synced to disk would slow them down
+    connection.pragma_update(None, "journal_mode", "WAL")?;
+    connection.pragma_update(None, "synchronous", "NORMAL")?;
+
+    let run_id = begin_run(&connection, args)?;
+    debug!("Logging exchanges to {} as run {}", path.display(), run_id);
+
+    if LOG.set(Log { connection: Mutex::new(connection), run_id }).is_err() {
+        warn!("A transaction log has already been started");
+    }
+
+    Ok(())
+}
+
+/// Wraps a transport so its exchanges get written to the database, if one
+/// has been opened.
+pub fn wrap(transport: Box<dyn Transport>, nameserver: Nameserver, transport_type: TransportType) -> Box<dyn Transport> {
+    match LOG.get() {
+        Some(log)  => Box::new(LoggedTransport { inner: transport, log, nameserver, transport_type }),
+        None       => transport,
+    }
+}
+
+
+/// A transport that writes down what another transport sends and receives.
+struct LoggedTransport {
+    inner: Box<dyn Transport>,
+    log: &'static Log,
+    nameserver: Nameserver,
+    transport_type: TransportType,
+}
+
+#[async_trait]
+impl Transport for LoggedTransport {
+    async fn send_with_details(&self, request: &Request) -> Result<(Response, Details), Error> {
+        let sent_at = unix_time();
+        let start = Instant::now();
+        let result = self.inner.send_with_details(request).await;
+
+        // A round trip timed by the kernel is more accurate than this one
+        let duration = match &result {
+            Ok((_, Details { round_trip: Some(round_trip), .. }))  => round_trip.duration,
+            _                                                      => start.elapsed(),
+        };
+
+        let exchange = Exchange {
+            sent_at,
+            nameserver: &self.nameserver,
+            transport: self.transport_type,
+            request,
+            duration: duration.as_secs_f64(),
+            result: &result,
+        };
+
+        let connection = self.log.connection.lock().unwrap();
+        if let Err(e) = insert(&connection, self.log.run_id, &exchange) {
+            warn!("Failed to log exchange with {} -> {}", self.nameserver, e);
+        }
+
+        result
+    }
+}
+
+
+/// One request, and what happened to it, about to be written.
+struct Exchange<'a> {
+    sent_at: f64,
+    nameserver: &'a str,
+    transport: TransportType,
+    request: &'a Request,
+    duration: f64,
+    result: &'a Result<(Response, Details), Error>,
+}
+
+/// Creates the tables if they aren’t there yet, and adds a row for this
+/// run, returning its ID.
+fn begin_run(connection: &Connection, args: &[String]) -> rusqlite::Result<i64> {
+    connection.execute_batch(SCHEMA)?;
+    connection.execute("INSERT INTO runs (started_at, arguments) VALUES (?1, ?2)", params![ unix_time(), args.join(" ") ])?;
+    Ok(connection.last_insert_rowid())
+}
+
+/// Adds a row for the exchange.
+fn insert(connection: &Connection, run_id: i64, exchange: &Exchange<'_>) -> rusqlite::Result<()> {
+    let request = exchange.request;
+    let query = request.queries.first();
+    let request_bytes = request.to_bytes().unwrap_or_default();
+
+    let (over_udp, status, counts, response_bytes) = match exchange.result {
+        Ok((response, details)) => {
+            let counts = [ response.answers.len(), response.authorities.len(), response.additionals.len() ];
+            (Some(details.over_udp), Some(rcode_name(response.flags.error_code)), Some(counts), Some(&details.message))
+        }
+        Err(_) => (None, None, None, None),
+    };
+
+    let (error_phase, error_message) = match exchange.result {
+        Ok(_)   => (None, None),
+        Err(e)  => (Some(erroneous_phase(e)), Some(error_message(e))),
+    };
+
+    connection.execute(
+        "INSERT INTO exchanges (
+            run_id, sent_at, nameserver, transport, qname, qtype, qclass, transaction_id, duration, over_udp,
+            status, answers, authorities, additionals, error_phase, error_message, request, response
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            run_id,
+            exchange.sent_at,
+            exchange.nameserver,
+            format!("{:?}", exchange.transport).to_lowercase(),
+            query.map(|q| q.qname.as_str()),
+            query.map(|q| zone::type_name(q.qtype)),
+            query.map(|q| format!("{:?}", q.qclass)),
+            request.transaction_id,
+            exchange.duration,
+            over_udp,
+            status,
+            counts.map(|c| c[0]),
+            counts.map(|c| c[1]),
+            counts.map(|c| c[2]),
+            error_phase,
+            error_message,
+            request_bytes,
+            response_bytes,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Returns the current time as fractional seconds since the Unix epoch,
+/// which SQLite’s date functions can read with the `unixepoch` modifier.
+fn unix_time() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or_default()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+    use dns_transport::Phase;
+
+    fn request() -> Request {
+        Request {
+            transaction_id: 0xABCD,
+            flags: Flags::query(),
+            queries: vec![ Query { qname: "lookup.dog.".into(), qclass: QClass::IN, qtype: 1 } ],
+            additional: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn runs_are_numbered() {
+        let connection = Connection::open_in_memory().unwrap();
+        assert_eq!(begin_run(&connection, &[ "lookup.dog".into() ]), Ok(1));
+        assert_eq!(begin_run(&connection, &[ "lookup.dog".into(), "MX".into() ]), Ok(2));
+
+        let arguments: String = connection.query_row("SELECT arguments FROM runs WHERE id = 2", [], |row| row.get(0)).unwrap();
+        assert_eq!(arguments, "lookup.dog MX");
+    }
+
+    #[test]
+    fn response_logged() {
+        let connection = Connection::open_in_memory().unwrap();
+        let run_id = begin_run(&connection, &[]).unwrap();
+
+        let message = vec![ 0xAB, 0xCD, 0x81, 0x83, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ];
+        let response = Response::from_bytes(&message).unwrap();
+        let details = Details { size: message.len(), over_udp: true, http: None, message: message.clone(), round_trip: None, address: None, ip_ttl: None };
+        let request = request();
+
+        let result = Ok((response, details));
+        let exchange = Exchange { sent_at: 1.5, nameserver: "192.0.2.53", transport: TransportType::UDP, request: &request, duration: 0.25, result: &result };
+        insert(&connection, run_id, &exchange).unwrap();
+
+        let row = connection.query_row("SELECT nameserver, transport, qname, qtype, qclass, transaction_id, status, answers, response FROM exchanges", [], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?, row.get::<_, u16>(5)?, row.get::<_, String>(6)?, row.get::<_, i64>(7)?, row.get::<_, Vec<u8>>(8)?))
+        }).unwrap();
+
+        assert_eq!(row, ("192.0.2.53".into(), "udp".into(), "lookup.dog.".into(), "A".into(), "IN".into(), 0xABCD, "NXDOMAIN".into(), 0, message));
+    }
+
+    #[test]
+    fn error_logged() {
+        let connection = Connection::open_in_memory().unwrap();
+        let run_id = begin_run(&connection, &[]).unwrap();
+        let request = request();
+
+        let result = Err(Error::Timeout(Phase::Response));
+        let exchange = Exchange { sent_at: 1.5, nameserver: "192.0.2.53", transport: TransportType::TCP, request: &request, duration: 5.0, result: &result };
+        insert(&connection, run_id, &exchange).unwrap();
+
+        let row = connection.query_row("SELECT status, error_phase, response IS NULL, length(request) FROM exchanges", [], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?, row.get::<_, usize>(3)?))
+        }).unwrap();
+
+        assert_eq!(row, (None, "timeout".into(), true, request.to_bytes().unwrap().len()));
+    }
+}
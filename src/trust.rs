@@ -0,0 +1,221 @@
+//! Summing up how far a response can be trusted, from whether the resolver
+//! says it validated it and how it got from the resolver to here.
+//!
+//! The AD bit in a response is only the resolver’s word that it checked the
+//! DNSSEC signatures, and nothing stops anything on the path from setting
+//! it, so it’s only worth believing when the path is protected as well (RFC
+//! 4035 §4.9.3). dog doesn’t validate signatures itself, or check TSIG or
+//! DANE, so those don’t count towards the verdict.
+
+use std::fmt;
+
+use dns::Response;
+use dns_transport::Details;
+
+use crate::connect::TransportType;
+use crate::provenance::Source;
+
+
+/// How much of the path from the resolver was protected.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Channel {
+
+    /// The response came over the network without encryption, so anything
+    /// on the path could have changed it.
+    Plain,
+
+    /// The response came over DNS-over-TLS.
+    TLS,
+
+    /// The response came over DNS-over-HTTPS.
+    HTTPS,
+
+    /// The response came from this machine, over a local socket or a
+    /// loopback address, so it never crossed the network.
+    Local,
+}
+
+/// The verdict on a response.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Verdict {
+
+    /// The resolver validated the response, and the path from it was
+    /// protected, so the answer is as good as the resolver.
+    Authenticated,
+
+    /// The resolver says it validated the response, but the path from it
+    /// wasn’t protected, so the AD bit could have been set by anyone.
+    Unverified,
+
+    /// The response arrived over a protected path, so it is what the
+    /// resolver sent, but the resolver didn’t validate it.
+    Unvalidated,
+
+    /// The response wasn’t validated, and could have been changed on the
+    /// way.
+    Unauthenticated,
+}
+
+/// Everything that went into a verdict.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Assessment {
+
+    /// Whether the response had the AD bit set.
+    pub validated: bool,
+
+    /// How the response got here.
+    pub channel: Channel,
+
+    /// The verdict that follows from the two.
+    pub verdict: Verdict,
+}
+
+
+/// Assesses a response, given where it came from and how it arrived.
+pub fn assess(response: &Response, source: &Source, details: &Details) -> Assessment {
+    let validated = response.flags.authentic_data;
+    let channel = Channel::of(source, details);
+
+    let verdict = match (validated, channel) {
+        (true,  Channel::Plain)  => Verdict::Unverified,
+        (true,  _)               => Verdict::Authenticated,
+        (false, Channel::Plain)  => Verdict::Unauthenticated,
+        (false, _)               => Verdict::Unvalidated,
+    };
+
+    Assessment { validated, channel, verdict }
+}
+
+
+impl Channel {
+
+    /// Works out the channel a response came over. A DNS-over-HTTPS
+    /// nameserver given with an `http://` URL isn’t encrypted at all, and
+    /// plain DNS to a loopback address never leaves the machine.
+    fn of(source: &Source, details: &Details) -> Self {
+        match source.transport {
+            TransportType::HTTPS if source.nameserver.starts_with("http://") => Self::Plain,
+            TransportType::HTTPS  => Self::HTTPS,
+            TransportType::TLS    => Self::TLS,
+            TransportType::Local  => Self::Local,
+            _ if details.address.is_some_and(|a| a.ip().is_loopback()) => Self::Local,
+            _                     => Self::Plain,
+        }
+    }
+
+    /// The name of the channel, for JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Plain  => "plain",
+            Self::TLS    => "tls",
+            Self::HTTPS  => "https",
+            Self::Local  => "local",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Plain  => "unencrypted DNS",
+            Self::TLS    => "DNS-over-TLS",
+            Self::HTTPS  => "DNS-over-HTTPS",
+            Self::Local  => "this machine",
+        }
+    }
+}
+
+impl Verdict {
+
+    /// The name of the verdict, for output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Authenticated    => "authenticated",
+            Self::Unverified       => "unverified",
+            Self::Unvalidated      => "unvalidated",
+            Self::Unauthenticated  => "unauthenticated",
+        }
+    }
+}
+
+impl fmt::Display for Assessment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channel = self.channel.description();
+        let from = if self.channel == Channel::Local { "from" } else { "over" };
+
+        match self.verdict {
+            Verdict::Authenticated => {
+                write!(f, "{} (validated with DNSSEC by the resolver, and arrived {} {})", self.verdict.name(), from, channel)
+            }
+            Verdict::Unverified => {
+                write!(f, "{} (the resolver says it validated it with DNSSEC, but that arrived over {} so could be forged)", self.verdict.name(), channel)
+            }
+            Verdict::Unvalidated => {
+                write!(f, "{} (arrived intact {} {}, but the resolver did not validate it with DNSSEC)", self.verdict.name(), from, channel)
+            }
+            Verdict::Unauthenticated => {
+                write!(f, "{} (not validated with DNSSEC, and arrived over {} so could have been changed)", self.verdict.name(), channel)
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+    use dns::Flags;
+
+    fn response(authentic_data: bool) -> Response {
+        let mut flags = Flags::from_u16(0x8180);
+        flags.authentic_data = authentic_data;
+        Response { transaction_id: 0x1234, flags, queries: vec![], answers: vec![], authorities: vec![], additionals: vec![] }
+    }
+
+    fn source(nameserver: &str, transport: TransportType) -> Source {
+        Source { nameserver: nameserver.into(), transport, received: UNIX_EPOCH }
+    }
+
+    fn details(address: &str) -> Details {
+        Details { size: 64, over_udp: true, http: None, message: vec![], round_trip: None, address: address.parse().ok(), ip_ttl: None }
+    }
+
+    #[test]
+    fn validated_over_tls() {
+        let assessment = assess(&response(true), &source("192.0.2.1", TransportType::TLS), &details("192.0.2.1:853"));
+        assert_eq!(assessment.verdict, Verdict::Authenticated);
+        assert_eq!(assessment.to_string(), "authenticated (validated with DNSSEC by the resolver, and arrived over DNS-over-TLS)");
+    }
+
+    #[test]
+    fn validated_over_plain_dns() {
+        let assessment = assess(&response(true), &source("192.0.2.1", TransportType::UDP), &details("192.0.2.1:53"));
+        assert_eq!(assessment.verdict, Verdict::Unverified);
+    }
+
+    #[test]
+    fn validated_on_loopback() {
+        let assessment = assess(&response(true), &source("127.0.0.53", TransportType::UDP), &details("127.0.0.53:53"));
+        assert_eq!(assessment.channel, Channel::Local);
+        assert_eq!(assessment.verdict, Verdict::Authenticated);
+    }
+
+    #[test]
+    fn unvalidated_over_https() {
+        let assessment = assess(&response(false), &source("https://dns.example/dns-query", TransportType::HTTPS), &details(""));
+        assert_eq!(assessment.verdict, Verdict::Unvalidated);
+    }
+
+    #[test]
+    fn unencrypted_http() {
+        let assessment = assess(&response(true), &source("http://dns.example/dns-query", TransportType::HTTPS), &details(""));
+        assert_eq!(assessment.channel, Channel::Plain);
+        assert_eq!(assessment.verdict, Verdict::Unverified);
+    }
+
+    #[test]
+    fn nothing_to_go_on() {
+        let assessment = assess(&response(false), &source("192.0.2.1", TransportType::TCP), &details("192.0.2.1:53"));
+        assert_eq!(assessment.verdict, Verdict::Unauthenticated);
+        assert_eq!(assessment.to_string(), "unauthenticated (not validated with DNSSEC, and arrived over unencrypted DNS so could have been changed)");
+    }
+}
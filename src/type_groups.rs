@@ -0,0 +1,122 @@
+//! Named groups of record types, which expand into a query for each type
+//! in the group.
+
+use std::env;
+
+use log::*;
+
+use dns::find_qtype_number;
+use dns::record::find_other_qtype_number;
+
+
+/// The groups that are always available, and the types in each.
+const BUILT_IN: &[(&str, &[&str])] = &[
+    ("mail",    &[ "MX", "TXT" ]),
+    ("dnssec",  &[ "DNSKEY", "DS", "RRSIG", "NSEC" ]),
+];
+
+
+/// Looks up the record types in the group with the given name, checking the
+/// groups defined in the `DOG_TYPE_GROUPS` environment variable before the
+/// built-in ones. Group names are case-insensitive.
+pub fn find(name: &str) -> Option<Vec<String>> {
+    let user_groups = env::var("DOG_TYPE_GROUPS").unwrap_or_default();
+    find_in(&user_groups, name)
+}
+
+/// Looks up a group in the given user-defined groups, falling back to the
+/// built-in ones.
+fn find_in(spec: &str, name: &str) -> Option<Vec<String>> {
+    if let Some(types) = parse(spec).into_iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, ts)| ts) {
+        return Some(types);
+    }
+
+    BUILT_IN.iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, ts)| ts.iter().map(|t| (*t).to_string()).collect())
+}
+
+/// Parses user-defined groups from a colon-separated list of `name=types`
+/// pairs, where the types are separated by commas, such as
+/// `web=A,AAAA,CNAME:mail=MX,TXT`. Pairs that aren’t understood are ignored,
+/// as are groups named after a record type or class, which would otherwise
+/// change what that name queries.
+fn parse(spec: &str) -> Vec<(String, Vec<String>)> {
+    let mut groups = Vec::new();
+
+    for pair in spec.split(':').filter(|p| ! p.is_empty()) {
+        let Some((name, types)) = pair.split_once('=') else {
+            warn!("Type group {:?} has no ‘=’", pair);
+            continue;
+        };
+
+        if is_mnemonic(name) {
+            warn!("Type group {:?} has the same name as a record type or class", name);
+            continue;
+        }
+
+        let types = types.split(',').filter(|t| ! t.is_empty()).map(str::to_uppercase).collect::<Vec<_>>();
+        if types.is_empty() {
+            warn!("Type group {:?} has no types", name);
+            continue;
+        }
+
+        groups.push((name.to_string(), types));
+    }
+
+    groups
+}
+
+/// Whether the given name, in any case, is the name of a record type or a
+/// query class.
+fn is_mnemonic(name: &str) -> bool {
+    let name = name.to_uppercase();
+    find_qtype_number(&name).is_some() || find_other_qtype_number(&name).is_some() || matches!(&*name, "IN" | "CH" | "HS")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn built_in() {
+        assert_eq!(find_in("", "dnssec"),
+                   Some(vec![ "DNSKEY".into(), "DS".into(), "RRSIG".into(), "NSEC".into() ]));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        assert_eq!(find_in("", "MAIL"),
+                   Some(vec![ "MX".into(), "TXT".into() ]));
+    }
+
+    #[test]
+    fn user_defined() {
+        assert_eq!(find_in("web=a,AAAA,CNAME:broken:empty=", "web"),
+                   Some(vec![ "A".into(), "AAAA".into(), "CNAME".into() ]));
+    }
+
+    #[test]
+    fn user_defined_overrides_built_in() {
+        assert_eq!(find_in("mail=MX", "mail"),
+                   Some(vec![ "MX".into() ]));
+    }
+
+    #[test]
+    fn type_names_are_not_groups() {
+        assert_eq!(find_in("A=MX:caa=TXT:nsec3param=A", "A"),           None);
+        assert_eq!(find_in("A=MX:caa=TXT:nsec3param=A", "CAA"),         None);
+        assert_eq!(find_in("A=MX:caa=TXT:nsec3param=A", "NSEC3PARAM"),  None);
+    }
+
+    #[test]
+    fn class_names_are_not_groups() {
+        assert_eq!(find_in("ch=TXT", "CH"), None);
+    }
+
+    #[test]
+    fn no_such_group() {
+        assert_eq!(find_in("web=A", "nonsense"), None);
+    }
+}
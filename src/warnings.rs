@@ -0,0 +1,411 @@
+//! Noticing things about a response that are worth pointing out, even though
+//! nothing went wrong.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use dns::{Request, Response, Answer};
+use dns_transport::Details;
+
+use crate::paging::thousands;
+use crate::zone;
+
+
+/// A **warning** is something odd about a response that doesn’t stop it from
+/// being displayed, but that the user should know about.
+#[derive(PartialEq, Debug)]
+pub enum Warning {
+
+    /// The response had its truncated flag set, so some of the records that
+    /// should have been in it are missing.
+    Truncated(String),
+
+    /// The response had the reserved Z flag set, which should always be zero.
+    ReservedFlag(String),
+
+    /// A record has a TTL of zero, so it won’t get cached anywhere.
+    ZeroTTL(String),
+
+    /// The name in the response’s question section has the same letters as
+    /// the name that was sent, but in a different case, which usually means
+    /// something on the path has rewritten it.
+    MismatchedCase {
+        sent: String,
+        received: String,
+    },
+
+    /// A response that arrived over UDP was larger than the buffer size the
+    /// request advertised, which the nameserver should never send.
+    ExceededBuffer {
+        qname: String,
+        size: usize,
+        advertised: usize,
+    },
+
+    /// A response that arrived over UDP was large enough that it was likely
+    /// to have been fragmented on the way, and fragments often get dropped.
+    LikelyFragmented {
+        qname: String,
+        size: usize,
+    },
+
+    /// Only one page of a response’s records is being shown, so the rest
+    /// of them are hidden. The positions of the first and last records
+    /// shown count from 1.
+    Paged {
+        qname: String,
+        first: usize,
+        last: usize,
+        total: usize,
+        next_page: Option<usize>,
+    },
+
+    /// The glue addresses for a nameserver in a referral aren’t the ones
+    /// the nameserver gives for itself, so the parent zone has stale glue.
+    StaleGlue {
+        nameserver: String,
+        glue: Vec<IpAddr>,
+        authoritative: Vec<IpAddr>,
+    },
+
+    /// The DNSSEC signature that expires the soonest does so within the
+    /// `--expiry-alert` threshold. The remaining time is `None` if it has
+    /// already expired.
+    ExpiringSignature {
+        qname: String,
+        type_covered: u16,
+        remaining: Option<Duration>,
+    },
+
+    /// `--expiry-alert` was given, but none of the responses had any DNSSEC
+    /// signatures in them to check.
+    NoSignatures,
+
+    /// `--authoritative` was given, but a response didn’t have the
+    /// authoritative flag set, so its records were thrown away.
+    NotAuthoritative {
+        qname: String,
+        nameserver: String,
+    },
+
+    /// A response from a public address arrived with an IP TTL that says
+    /// it came from only a hop or so away, which usually means something on
+    /// the local network answered in the nameserver’s place.
+    CloseHop {
+        qname: String,
+        address: IpAddr,
+        hops: u8,
+    },
+}
+
+
+/// The largest UDP message that should make it across any path without
+/// being fragmented: the minimum IPv6 MTU of 1280 bytes, minus 48 bytes of
+/// IPv6 and UDP headers. This is the value DNS Flag Day 2020 settled on.
+pub const UNFRAGMENTED_SIZE: usize = 1232;
+
+/// The buffer size a request without an OPT record implicitly advertises.
+const DEFAULT_BUFFER_SIZE: usize = 512;
+
+/// The IP TTLs that operating systems start packets with. Linux and macOS
+/// use 64, Windows uses 128, and some routers and Solaris use 255.
+const INITIAL_TTLS: [u8; 3] = [ 64, 128, 255 ];
+
+/// The most hops away a response from a public address can come from before
+/// it looks like it was answered locally.
+const CLOSE_HOPS: u8 = 1;
+
+
+/// Checks a response, the request that it was for, and the details of how
+/// it arrived, for anything worth warning about.
+pub fn check(request: &Request, response: &Response, details: &Details) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let qname = response.queries.first().or_else(|| request.queries.first())
+                        .map(|q| q.qname.clone()).unwrap_or_default();
+
+    if response.flags.truncated {
+        warnings.push(Warning::Truncated(qname.clone()));
+    }
+
+    if response.flags.reserved {
+        warnings.push(Warning::ReservedFlag(qname.clone()));
+    }
+
+    if details.over_udp {
+        let advertised = request.additional.as_ref().map_or(DEFAULT_BUFFER_SIZE, |opt| usize::from(opt.udp_payload_size))
+                                .max(DEFAULT_BUFFER_SIZE);
+
+        if details.size > advertised {
+            warnings.push(Warning::ExceededBuffer { qname: qname.clone(), size: details.size, advertised });
+        }
+
+        if details.size > UNFRAGMENTED_SIZE {
+            warnings.push(Warning::LikelyFragmented { qname: qname.clone(), size: details.size });
+        }
+    }
+
+    if let (Some(address), Some(ttl)) = (details.address, details.ip_ttl) {
+        let hops = hops_away(ttl);
+        if hops <= CLOSE_HOPS && is_public(address.ip()) {
+            warnings.push(Warning::CloseHop { qname, address: address.ip(), hops });
+        }
+    }
+
+    for (sent, received) in request.queries.iter().zip(&response.queries) {
+        if sent.qname != received.qname && sent.qname.eq_ignore_ascii_case(&received.qname) {
+            warnings.push(Warning::MismatchedCase { sent: sent.qname.clone(), received: received.qname.clone() });
+        }
+    }
+
+    let records = response.answers.iter().chain(&response.authorities).chain(&response.additionals);
+    for answer in records {
+        if let Answer::Standard { qname, ttl: 0, .. } = answer {
+            let warning = Warning::ZeroTTL(qname.clone());
+
+            // Only warn once per name, rather than once per record
+            if ! warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    warnings
+}
+
+
+impl Warning {
+
+    /// A short name for this kind of warning, for use in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Truncated(_)             => "truncated",
+            Self::ReservedFlag(_)          => "reserved-flag",
+            Self::ZeroTTL(_)               => "zero-ttl",
+            Self::MismatchedCase { .. }    => "mismatched-case",
+            Self::ExceededBuffer { .. }    => "exceeded-buffer",
+            Self::LikelyFragmented { .. }  => "likely-fragmented",
+            Self::Paged { .. }             => "paged",
+            Self::StaleGlue { .. }         => "stale-glue",
+            Self::ExpiringSignature { .. } => "expiring-signature",
+            Self::NoSignatures             => "no-signatures",
+            Self::NotAuthoritative { .. }  => "not-authoritative",
+            Self::CloseHop { .. }          => "close-hop",
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated(qname)                   => write!(f, "Response for {} was truncated, so records may be missing", qname),
+            Self::ReservedFlag(qname)                => write!(f, "Response for {} has the reserved Z flag set", qname),
+            Self::ZeroTTL(qname)                     => write!(f, "Records for {} have a TTL of 0, so will not be cached", qname),
+            Self::MismatchedCase { sent, received }  => write!(f, "Response changed the case of {} to {}", sent, received),
+            Self::ExceededBuffer { qname, size, advertised } => {
+                write!(f, "Response for {} was {} bytes, more than the advertised buffer of {} bytes", qname, size, advertised)
+            }
+            Self::LikelyFragmented { qname, size }   => write!(f, "Response for {} was {} bytes over UDP, so was likely fragmented", qname, size),
+            Self::Paged { qname, first, last, total, next_page } => {
+                write!(f, "Showing records {} to {} of {} for {}", thousands(*first), thousands(*last), thousands(*total), qname)?;
+                match next_page {
+                    Some(page)  => write!(f, " (use --page {} to see more)", page),
+                    None        => Ok(()),
+                }
+            }
+            Self::StaleGlue { nameserver, glue, authoritative } => {
+                write!(f, "Glue for {} is {}, but it says its addresses are {}", nameserver, address_list(glue), address_list(authoritative))
+            }
+            Self::ExpiringSignature { qname, type_covered, remaining: Some(remaining) } => {
+                write!(f, "Signature over the {} records for {} expires in {}", zone::type_name(*type_covered), qname, humantime::format_duration(*remaining))
+            }
+            Self::ExpiringSignature { qname, type_covered, remaining: None } => {
+                write!(f, "Signature over the {} records for {} has expired", zone::type_name(*type_covered), qname)
+            }
+            Self::NoSignatures                       => write!(f, "No DNSSEC signatures were found to check"),
+            Self::NotAuthoritative { qname, nameserver } => {
+                write!(f, "Response for {} from {} was not authoritative, so its records were refused", qname, nameserver)
+            }
+            Self::CloseHop { qname, address, hops } => {
+                write!(f, "Response for {} from {} arrived from {} hop{} away, so may have come from a transparent proxy", qname, address, hops, if *hops == 1 { "" } else { "s" })
+            }
+        }
+    }
+}
+
+
+/// Works out roughly how many hops away a packet came from, given the TTL
+/// it arrived with, by assuming it started with the smallest common initial
+/// TTL that isn’t lower.
+pub fn hops_away(ttl: u8) -> u8 {
+    let initial = INITIAL_TTLS.iter().copied().find(|initial| *initial >= ttl).unwrap_or(u8::MAX);
+    initial - ttl
+}
+
+/// Whether an address is one that can be reached across the Internet,
+/// rather than one for this machine or a private network.
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0b_1100_0000) == 64;
+            ! (ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || shared)
+        }
+        IpAddr::V6(ip) => {
+            let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+            let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+            ! (ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+        }
+    }
+}
+
+/// Writes out a list of addresses, separated by commas.
+fn address_list(addresses: &[IpAddr]) -> String {
+    if addresses.is_empty() {
+        return "nothing".into();
+    }
+
+    addresses.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(", ")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::{Flags, Query, QClass};
+    use dns::record::{Record, A, OPT};
+
+    fn request(qname: &str) -> Request {
+        Request {
+            transaction_id: 0xABCD,
+            flags: Flags::query(),
+            queries: vec![ Query { qname: qname.into(), qclass: QClass::IN, qtype: 1 } ],
+            additional: None,
+            signature: None,
+        }
+    }
+
+    fn response(qname: &str, flags: u16, ttl: u32) -> Response {
+        let record = Record::A(A { address: "127.0.0.1".parse().unwrap() });
+
+        Response {
+            transaction_id: 0xABCD,
+            flags: Flags::from_u16(flags),
+            queries: vec![ Query { qname: qname.into(), qclass: QClass::IN, qtype: 1 } ],
+            answers: vec![ Answer::Standard { qname: qname.into(), qclass: QClass::IN, ttl, record } ],
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        }
+    }
+
+    fn udp(size: usize) -> Details {
+        Details { size, over_udp: true, http: None, message: vec![], round_trip: None, address: None, ip_ttl: None }
+    }
+
+    #[test]
+    fn nothing_wrong() {
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &udp(100)),
+                   Vec::new());
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8380, 300), &udp(100)),
+                   vec![ Warning::Truncated("lookup.dog.".into()) ]);
+    }
+
+    #[test]
+    fn reserved_flag() {
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x81C0, 300), &udp(100)),
+                   vec![ Warning::ReservedFlag("lookup.dog.".into()) ]);
+    }
+
+    #[test]
+    fn zero_ttl() {
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 0), &udp(100)),
+                   vec![ Warning::ZeroTTL("lookup.dog.".into()) ]);
+    }
+
+    #[test]
+    fn mismatched_case() {
+        assert_eq!(check(&request("lookup.dog."), &response("LookUp.dog.", 0x8180, 300), &udp(100)),
+                   vec![ Warning::MismatchedCase { sent: "lookup.dog.".into(), received: "LookUp.dog.".into() } ]);
+    }
+
+    #[test]
+    fn exceeded_buffer() {
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &udp(600)),
+                   vec![ Warning::ExceededBuffer { qname: "lookup.dog.".into(), size: 600, advertised: 512 } ]);
+    }
+
+    #[test]
+    fn likely_fragmented() {
+        let mut request = request("lookup.dog.");
+        request.additional = Some(OPT { udp_payload_size: 4096, ..Request::additional_record() });
+
+        assert_eq!(check(&request, &response("lookup.dog.", 0x8180, 300), &udp(1500)),
+                   vec![ Warning::LikelyFragmented { qname: "lookup.dog.".into(), size: 1500 } ]);
+    }
+
+    #[test]
+    fn large_over_tcp() {
+        let details = Details { size: 1500, over_udp: false, http: None, message: vec![], round_trip: None, address: None, ip_ttl: None };
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &details),
+                   Vec::new());
+    }
+
+    #[test]
+    fn close_hop() {
+        let mut details = udp(100);
+        details.address = Some("8.8.8.8:53".parse().unwrap());
+        details.ip_ttl = Some(63);
+
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &details),
+                   vec![ Warning::CloseHop { qname: "lookup.dog.".into(), address: "8.8.8.8".parse().unwrap(), hops: 1 } ]);
+    }
+
+    #[test]
+    fn far_hop() {
+        let mut details = udp(100);
+        details.address = Some("8.8.8.8:53".parse().unwrap());
+        details.ip_ttl = Some(117);
+
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &details),
+                   Vec::new());
+    }
+
+    #[test]
+    fn close_private_hop() {
+        let mut details = udp(100);
+        details.address = Some("192.168.1.1:53".parse().unwrap());
+        details.ip_ttl = Some(64);
+
+        assert_eq!(check(&request("lookup.dog."), &response("lookup.dog.", 0x8180, 300), &details),
+                   Vec::new());
+    }
+
+    #[test]
+    fn hop_counts() {
+        assert_eq!(hops_away(64), 0);
+        assert_eq!(hops_away(57), 7);
+        assert_eq!(hops_away(117), 11);
+        assert_eq!(hops_away(250), 5);
+    }
+
+    #[test]
+    fn paged_message() {
+        let warning = Warning::Paged { qname: "lookup.dog.".into(), first: 101, last: 200, total: 15_023, next_page: Some(3) };
+        assert_eq!(warning.to_string(), "Showing records 101 to 200 of 15,023 for lookup.dog. (use --page 3 to see more)");
+    }
+
+    #[test]
+    fn stale_glue_message() {
+        let warning = Warning::StaleGlue { nameserver: "ns1.lookup.dog.".into(), glue: vec![ "192.0.2.1".parse().unwrap() ], authoritative: vec![] };
+        assert_eq!(warning.to_string(), "Glue for ns1.lookup.dog. is 192.0.2.1, but it says its addresses are nothing");
+    }
+
+    #[test]
+    fn expiring_signature_message() {
+        let warning = Warning::ExpiringSignature { qname: "lookup.dog.".into(), type_covered: 1, remaining: Some(Duration::from_hours(25)) };
+        assert_eq!(warning.to_string(), "Signature over the A records for lookup.dog. expires in 1day 1h");
+    }
+}
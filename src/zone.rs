@@ -0,0 +1,216 @@
+//! Rendering record data in the presentation format used by zone files, so
+//! that it can be copied out of dog’s output and pasted into one.
+
+use std::fmt::Write;
+use std::time::{Duration, UNIX_EPOCH};
+
+use dns::{find_qtype_number, qtype_names};
+use dns::record::{Record, UnknownQtype};
+
+
+/// Renders the data of a record exactly as it would be written in a zone
+/// file (RFC 1035 §5.1), with domain names fully qualified, text quoted,
+/// and any characters that mean something special escaped.
+pub fn presentation(record: &Record) -> String {
+    match record {
+        Record::A(a)          => a.address.to_string(),
+        Record::AAAA(aaaa)    => aaaa.address.to_string(),
+        Record::CAA(caa)      => format!("{} {} {}", if caa.critical { 128 } else { 0 }, caa.tag, character_string(caa.value.as_bytes())),
+        Record::CNAME(cname)  => name(&cname.domain),
+        Record::MX(mx)        => format!("{} {}", mx.preference, name(&mx.exchange)),
+        Record::NS(ns)        => name(&ns.nameserver),
+        Record::PTR(ptr)      => name(&ptr.cname),
+        Record::RRSIG(rrsig)  => {
+            format!("{} {} {} {} {} {} {} {} {}",
+                type_name(rrsig.type_covered), rrsig.algorithm, rrsig.labels, rrsig.original_ttl,
+                timestamp(rrsig.signature_expiration), timestamp(rrsig.signature_inception),
+                rrsig.key_tag, name(&rrsig.signer_name), base64::encode(&rrsig.signature))
+        }
+        Record::SIG(sig)      => {
+            format!("{} {} {} {} {} {} {} {} {}",
+                type_name(sig.type_covered), sig.algorithm, sig.labels, sig.original_ttl,
+                timestamp(sig.signature_expiration), timestamp(sig.signature_inception),
+                sig.key_tag, name(&sig.signer_name), base64::encode(&sig.signature))
+        }
+        Record::SOA(soa)      => {
+            format!("{} {} {} {} {} {} {}",
+                name(&soa.mname), name(&soa.rname), soa.serial, soa.refresh_interval,
+                soa.retry_interval, soa.expire_limit, soa.minimum_ttl)
+        }
+        Record::SRV(srv)      => format!("{} {} {} {}", srv.priority, srv.weight, srv.port, name(&srv.target)),
+        Record::TXT(txt)      => text(&txt.message),
+        Record::Other { bytes, .. } => {
+            // The generic format for records of unknown types (RFC 3597 §5)
+            let mut output = format!("\\# {}", bytes.len());
+            if ! bytes.is_empty() {
+                output.push(' ');
+                for b in bytes {
+                    let _ = write!(output, "{:02x}", b);
+                }
+            }
+
+            output
+        }
+    }
+}
+
+
+/// Renders a domain name, which has already had any dots and backslashes
+/// within its labels escaped, escaping any other characters that a zone file
+/// would treat specially.
+fn name(input: &str) -> String {
+    if input.is_empty() || input == "." {
+        return String::from(".");
+    }
+
+    let mut output = String::new();
+    let mut bytes = input.bytes();
+    let mut fully_qualified = false;
+
+    while let Some(b) = bytes.next() {
+        fully_qualified = b == b'.';
+
+        match b {
+            b'\\' => {
+                output.push('\\');
+                if let Some(escaped) = bytes.next() {
+                    output.push(char::from(escaped));
+                }
+            }
+            b'"' | b'(' | b')' | b';' | b'@' | b'$' => {
+                output.push('\\');
+                output.push(char::from(b));
+            }
+            _ if b.is_ascii_graphic() => output.push(char::from(b)),
+            _ => { let _ = write!(output, "\\{:03}", b); }
+        }
+    }
+
+    if ! fully_qualified {
+        output.push('.');
+    }
+
+    output
+}
+
+/// Renders text as one or more quoted character-strings, splitting it into
+/// the chunks of at most 255 bytes that it would have been sent as.
+fn text(message: &str) -> String {
+    if message.is_empty() {
+        return String::from("\"\"");
+    }
+
+    message.as_bytes().chunks(255)
+           .map(character_string)
+           .collect::<Vec<_>>()
+           .join(" ")
+}
+
+/// Renders bytes as a quoted character-string, escaping quotes and
+/// backslashes, and writing anything unprintable as a decimal escape.
+fn character_string(bytes: &[u8]) -> String {
+    let mut output = String::from("\"");
+
+    for &b in bytes {
+        match b {
+            b'"' | b'\\'                            => { output.push('\\'); output.push(char::from(b)); }
+            _ if b == b' ' || b.is_ascii_graphic()  => output.push(char::from(b)),
+            _                                       => { let _ = write!(output, "\\{:03}", b); }
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+/// Renders a signature timestamp in the `YYYYMMDDHHmmSS` form (RFC 4034 §3.2).
+fn timestamp(seconds: u32) -> String {
+    let time = UNIX_EPOCH + Duration::from_secs(u64::from(seconds));
+    humantime::format_rfc3339_seconds(time).to_string()
+              .chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Renders a record type number as its name, or in the generic `TYPE123`
+/// form if it doesn’t have one (RFC 3597 §5).
+pub fn type_name(number: u16) -> String {
+    if let Some(name) = qtype_names().into_iter().find(|n| find_qtype_number(n) == Some(number)) {
+        return name.into();
+    }
+
+    match UnknownQtype::from(number) {
+        UnknownQtype::HeardOf(name)   => name.into(),
+        UnknownQtype::UnheardOf(num)  => format!("TYPE{}", num),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dns::record::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(name("lookup.dog."),       "lookup.dog.");
+        assert_eq!(name("lookup.dog"),        "lookup.dog.");
+        assert_eq!(name(""),                  ".");
+        assert_eq!(name("a\\.b.dog."),        "a\\.b.dog.");
+        assert_eq!(name("a;b c.dog."),        "a\\;b\\032c.dog.");
+        assert_eq!(name("dot\\."),            "dot\\..");
+        assert_eq!(name("slash\\\\."),        "slash\\\\.");
+    }
+
+    #[test]
+    fn txt_quoting() {
+        let record = Record::TXT(TXT { message: String::from("say \"hi\" \\ bye\n") });
+        assert_eq!(presentation(&record), "\"say \\\"hi\\\" \\\\ bye\\010\"");
+    }
+
+    #[test]
+    fn long_txt() {
+        let record = Record::TXT(TXT { message: "x".repeat(300) });
+        assert_eq!(presentation(&record), format!("\"{}\" \"{}\"", "x".repeat(255), "x".repeat(45)));
+    }
+
+    #[test]
+    fn mx() {
+        let record = Record::MX(MX { preference: 10, exchange: String::from("mail.lookup.dog.") });
+        assert_eq!(presentation(&record), "10 mail.lookup.dog.");
+    }
+
+    #[test]
+    fn caa() {
+        let record = Record::CAA(CAA { critical: true, tag: String::from("issue"), value: String::from("ca.dog") });
+        assert_eq!(presentation(&record), "128 issue \"ca.dog\"");
+    }
+
+    #[test]
+    fn rrsig() {
+        let record = Record::RRSIG(RRSIG {
+            type_covered: 1,
+            algorithm: 13,
+            labels: 2,
+            original_ttl: 3600,
+            signature_expiration: 1_590_493_484,
+            signature_inception: 1_590_492_928,
+            key_tag: 12345,
+            signer_name: String::from("dog."),
+            signature: vec![ 0xAB, 0xCD, 0xEF ],
+        });
+
+        assert_eq!(presentation(&record), "A 13 2 3600 20200526114444 20200526113528 12345 dog. q83v");
+    }
+
+    #[test]
+    fn unknown() {
+        let record = Record::Other { type_number: UnknownQtype::UnheardOf(1234), bytes: vec![ 0x12, 0x34 ] };
+        assert_eq!(presentation(&record), "\\# 2 1234");
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(type_name(1),     "A");
+        assert_eq!(type_name(43),    "DS");
+        assert_eq!(type_name(1234),  "TYPE1234");
+    }
+}